@@ -674,5 +674,52 @@ pub type ZkSyncSnarkWrapperSetup =
 pub type ZkSyncSnarkWrapperVK =
     ZkSyncCompressionLayerStorage<SnarkVK<Bn256, ZkSyncSnarkWrapperCircuit>>;
 
+use fflonk::{FflonkProof, FflonkSetup, FflonkVerificationKey};
+
+pub type FflonkSnarkVerifierCircuit = ZkSyncSnarkWrapperCircuitNoLookupCustomGate;
+pub type FflonkSnarkVerifierCircuitProof = FflonkProof<Bn256, FflonkSnarkVerifierCircuit>;
+pub type FflonkSnarkVerifierCircuitSetup = FflonkSetup<Bn256, FflonkSnarkVerifierCircuit>;
+pub type FflonkSnarkVerifierCircuitVK = FflonkVerificationKey<Bn256, FflonkSnarkVerifierCircuit>;
+
+/// Same as `ZkSyncCompressionLayerStorage`, but for the FFLONK wrapper artifacts,
+/// that don't implement `Debug` (and the setup doesn't implement serde traits either).
+#[derive(derivative::Derivative, serde::Serialize, serde::Deserialize)]
+#[derivative(Clone(bound = "T: Clone"))]
+pub struct ZkSyncFflonkWrapperStorage<T> {
+    wrapper_type: u8,
+    inner: T,
+}
+
+impl<T> ZkSyncFflonkWrapperStorage<T> {
+    pub fn numeric_circuit_type(&self) -> u8 {
+        self.wrapper_type
+    }
+
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+
+    pub fn from_inner(wrapper_type: u8, inner: T) -> Self {
+        Self {
+            wrapper_type,
+            inner,
+        }
+    }
+}
+
+impl<T> std::fmt::Debug for ZkSyncFflonkWrapperStorage<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ZkSyncFflonkWrapperStorage")
+            .field("wrapper_type", &self.wrapper_type)
+            .finish_non_exhaustive()
+    }
+}
+
+pub type ZkSyncFflonkSnarkWrapperProof =
+    ZkSyncFflonkWrapperStorage<FflonkSnarkVerifierCircuitProof>;
+pub type ZkSyncFflonkSnarkWrapperSetup =
+    ZkSyncFflonkWrapperStorage<Arc<FflonkSnarkVerifierCircuitSetup>>;
+pub type ZkSyncFflonkSnarkWrapperVK = ZkSyncFflonkWrapperStorage<FflonkSnarkVerifierCircuitVK>;
+
 pub type EIP4844VerificationKey =
     VerificationKey<GoldilocksField, GoldilocksPoseidon2Sponge<AbsorptionModeOverwrite>>;
@@ -0,0 +1,192 @@
+use crate::boojum::cs::implementations::pow::NoPow;
+use derivative::*;
+
+use crate::boojum::cs::implementations::transcript::GoldilocksPoisedon2Transcript;
+use crate::boojum::cs::implementations::transcript::Transcript;
+use crate::boojum::gadgets::recursion::circuit_pow::*;
+use crate::boojum::gadgets::tables::*;
+use crate::circuit_definitions::base_layer::TARGET_CIRCUIT_TRACE_LENGTH;
+use crate::zkevm_circuits::{
+    base_structures::recursion_query::RecursionQuery,
+    fsm_input_output::circuit_inputs::INPUT_OUTPUT_COMMITMENT_LENGTH,
+    recursion::interblock::{keccak_aggregator::KeccakPublicInputAggregator, *},
+};
+
+use super::*;
+
+type F = GoldilocksField;
+type TR = GoldilocksPoisedon2Transcript;
+type R = Poseidon2Goldilocks;
+type CTR = CircuitAlgebraicSpongeBasedTranscript<GoldilocksField, 8, 12, 4, R>;
+type EXT = GoldilocksExt2;
+type H = GoldilocksPoseidon2Sponge<AbsorptionModeOverwrite>;
+type RH = CircuitGoldilocksPoseidon2Sponge;
+
+// Maximum amount of scheduler proofs (batches) that are aggregated into a single proof.
+// Unused slots are padded, and their public inputs are replaced with the masking value.
+pub const INTERBLOCK_AGGREGATION_CAPACITY: usize = 16;
+pub const INTERBLOCK_AGGREGATION_MASKING_VALUE: u8 = 0;
+
+/// Aggregates up to N scheduler proofs (of the same scheduler verification key) into one.
+/// Its public input is a keccak of the concatenated scheduler public inputs (treated as BE 7-byte chunks),
+/// packed in the same way as the scheduler does it for a single block.
+/// The circuit logic is in ``interblock_recursion_function`` method (in era-zkevm_circuits repo).
+///
+/// The circuit uses exactly the same geometry and gates as the scheduler, so the proof
+/// can be fed into the compression circuits instead of the scheduler one (with the aggregation VK).
+#[derive(Derivative, serde::Serialize, serde::Deserialize)]
+#[derivative(Clone, Debug(bound = ""))]
+#[serde(bound = "")]
+pub struct InterblockAggregationCircuit<POW: RecursivePoWRunner<F>, const N: usize> {
+    pub witness: InterblockRecursionCircuitInstanceWitness<F, RH, EXT>,
+    pub config: InterblockRecursionConfig<F, H, EXT>,
+    pub transcript_params: <TR as Transcript<F>>::TransciptParameters,
+    pub _marker: std::marker::PhantomData<(R, POW)>,
+}
+
+impl<POW: RecursivePoWRunner<F>, const N: usize>
+    crate::boojum::cs::traits::circuit::CircuitBuilder<F> for InterblockAggregationCircuit<POW, N>
+where
+    [(); <LogQuery<F> as CSAllocatableExt<F>>::INTERNAL_STRUCT_LEN]:,
+    [(); <MemoryQuery<F> as CSAllocatableExt<F>>::INTERNAL_STRUCT_LEN]:,
+    [(); <DecommitQuery<F> as CSAllocatableExt<F>>::INTERNAL_STRUCT_LEN]:,
+    [(); <UInt256<F> as CSAllocatableExt<F>>::INTERNAL_STRUCT_LEN]:,
+    [(); <UInt256<F> as CSAllocatableExt<F>>::INTERNAL_STRUCT_LEN + 1]:,
+    [(); <ExecutionContextRecord<F> as CSAllocatableExt<F>>::INTERNAL_STRUCT_LEN]:,
+    [(); <TimestampedStorageLogRecord<F> as CSAllocatableExt<F>>::INTERNAL_STRUCT_LEN]:,
+    [(); <RecursionQuery<F> as CSAllocatableExt<F>>::INTERNAL_STRUCT_LEN]:,
+{
+    fn geometry() -> CSGeometry {
+        <SchedulerCircuit<POW, USE_4844> as crate::boojum::cs::traits::circuit::CircuitBuilder<
+            F,
+        >>::geometry()
+    }
+
+    fn lookup_parameters() -> LookupParameters {
+        <SchedulerCircuit<POW, USE_4844> as crate::boojum::cs::traits::circuit::CircuitBuilder<
+            F,
+        >>::lookup_parameters()
+    }
+
+    fn configure_builder<
+        T: CsBuilderImpl<F, T>,
+        GC: GateConfigurationHolder<F>,
+        TB: StaticToolboxHolder,
+    >(
+        builder: CsBuilder<T, F, GC, TB>,
+    ) -> CsBuilder<T, F, impl GateConfigurationHolder<F>, impl StaticToolboxHolder> {
+        <SchedulerCircuit<POW, USE_4844> as crate::boojum::cs::traits::circuit::CircuitBuilder<
+            F,
+        >>::configure_builder(builder)
+    }
+}
+
+impl<POW: RecursivePoWRunner<F>, const N: usize> InterblockAggregationCircuit<POW, N>
+where
+    [(); <LogQuery<F> as CSAllocatableExt<F>>::INTERNAL_STRUCT_LEN]:,
+    [(); <MemoryQuery<F> as CSAllocatableExt<F>>::INTERNAL_STRUCT_LEN]:,
+    [(); <DecommitQuery<F> as CSAllocatableExt<F>>::INTERNAL_STRUCT_LEN]:,
+    [(); <UInt256<F> as CSAllocatableExt<F>>::INTERNAL_STRUCT_LEN]:,
+    [(); <UInt256<F> as CSAllocatableExt<F>>::INTERNAL_STRUCT_LEN + 1]:,
+    [(); <ExecutionContextRecord<F> as CSAllocatableExt<F>>::INTERNAL_STRUCT_LEN]:,
+    [(); <TimestampedStorageLogRecord<F> as CSAllocatableExt<F>>::INTERNAL_STRUCT_LEN]:,
+    [(); <RecursionQuery<F> as CSAllocatableExt<F>>::INTERNAL_STRUCT_LEN]:,
+{
+    pub fn description() -> String {
+        format!("Interblock aggregation circuit for {} batches", N)
+    }
+
+    pub fn geometry(&self) -> CSGeometry {
+        <Self as crate::boojum::cs::traits::circuit::CircuitBuilder<F>>::geometry()
+    }
+
+    pub fn size_hint(&self) -> (Option<usize>, Option<usize>) {
+        (
+            Some(TARGET_CIRCUIT_TRACE_LENGTH),
+            Some((1 << 26) + (1 << 25)),
+        )
+    }
+
+    pub fn configure_builder_proxy<
+        T: CsBuilderImpl<F, T>,
+        GC: GateConfigurationHolder<F>,
+        TB: StaticToolboxHolder,
+    >(
+        &self,
+        builder: CsBuilder<T, F, GC, TB>,
+    ) -> CsBuilder<T, F, impl GateConfigurationHolder<F>, impl StaticToolboxHolder> {
+        <Self as crate::boojum::cs::traits::circuit::CircuitBuilder<F>>::configure_builder(builder)
+    }
+
+    // same tables as in the scheduler, keccak needs all of them
+    pub fn add_tables<CS: ConstraintSystem<F>>(&self, cs: &mut CS) {
+        let table = create_xor8_table();
+        cs.add_lookup_table::<Xor8Table, 3>(table);
+
+        let table = create_and8_table();
+        cs.add_lookup_table::<And8Table, 3>(table);
+
+        let table = create_byte_split_table::<F, 1>();
+        cs.add_lookup_table::<ByteSplitTable<1>, 3>(table);
+        let table = create_byte_split_table::<F, 2>();
+        cs.add_lookup_table::<ByteSplitTable<2>, 3>(table);
+        let table = create_byte_split_table::<F, 3>();
+        cs.add_lookup_table::<ByteSplitTable<3>, 3>(table);
+        let table = create_byte_split_table::<F, 4>();
+        cs.add_lookup_table::<ByteSplitTable<4>, 3>(table);
+    }
+
+    pub fn synthesize_into_cs<CS: ConstraintSystem<F> + 'static>(self, cs: &mut CS) {
+        let Self {
+            witness,
+            config,
+            transcript_params,
+            ..
+        } = self;
+
+        assert_eq!(config.capacity, N);
+
+        let verifier_builder =
+            SchedulerCircuitBuilder::<POW>::dyn_recursive_verifier_builder::<EXT, CS>();
+
+        interblock_recursion_function::<
+            F,
+            CS,
+            RH,
+            EXT,
+            TR,
+            CTR,
+            POW,
+            KeccakPublicInputAggregator<F, N, true, INPUT_OUTPUT_COMMITMENT_LENGTH>,
+        >(
+            cs,
+            witness,
+            config,
+            verifier_builder,
+            transcript_params,
+            INTERBLOCK_AGGREGATION_MASKING_VALUE,
+        )
+    }
+
+    pub fn into_dyn_verifier_builder(
+        &self,
+    ) -> Box<dyn crate::boojum::cs::traits::circuit::ErasedBuilderForVerifier<F, EXT>> {
+        InterblockAggregationCircuitBuilder::<POW, N>::dyn_verifier_builder::<EXT>()
+    }
+}
+
+pub type ZkSyncInterblockAggregationCircuit =
+    InterblockAggregationCircuit<NoPow, INTERBLOCK_AGGREGATION_CAPACITY>;
+
+// Proofs and keys of the aggregation circuit are stored separately from the recursion layer ones,
+// so that they never get confused with the scheduler ones (even though the shape is the same).
+pub type ZkSyncInterblockAggregationProof = ZkSyncRecursionProof;
+pub type ZkSyncInterblockAggregationVerificationKey = ZkSyncRecursionVerificationKey;
+pub type ZkSyncInterblockAggregationFinalizationHint = FinalizationHintsForProver;
+
+use crate::boojum::cs::traits::circuit::CircuitBuilderProxy;
+
+pub type InterblockAggregationCircuitBuilder<POW, const N: usize> =
+    CircuitBuilderProxy<F, InterblockAggregationCircuit<POW, N>>;
+pub type ConcreteInterblockAggregationCircuitBuilder =
+    InterblockAggregationCircuitBuilder<NoPow, INTERBLOCK_AGGREGATION_CAPACITY>;
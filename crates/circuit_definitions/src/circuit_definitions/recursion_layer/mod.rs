@@ -16,6 +16,7 @@ use snark_wrapper::boojum::config::CSConfig;
 use snark_wrapper::boojum::dag::{CircuitResolver, StCircuitResolver};
 
 pub mod circuit_def;
+pub mod interblock;
 pub mod leaf_layer;
 pub mod node_layer;
 pub mod recursion_tip;
@@ -37,6 +38,56 @@ pub const RECURSION_ARITY: usize = 32;
 // And with the current scheduler code, and SCHEDULER_CAPACITY set to 34100, the value is 1043851.
 pub const SCHEDULER_CAPACITY: usize = 34100;
 
+/// Aggregation capacities of the leaf and node circuits. It's a setup time parameter:
+/// leaf and node verification keys depend on it, so it's committed to through the leaf vk commitment
+/// in the leaf parameters and the node vk commitment, and the scheduler vk changes too.
+/// The default one builds the same circuits as the fixed RECURSION_ARITY did.
+/// Wider circuits take more memory to prove, but produce less proofs.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub struct RecursionTreeArity {
+    /// How many basic circuit proofs a leaf aggregates
+    pub leaf_layer_capacity: usize,
+    /// How many leaf or node proofs a node aggregates
+    pub node_layer_capacity: usize,
+}
+
+impl Default for RecursionTreeArity {
+    fn default() -> Self {
+        Self {
+            leaf_layer_capacity: RECURSION_ARITY,
+            node_layer_capacity: RECURSION_ARITY,
+        }
+    }
+}
+
+impl RecursionTreeArity {
+    pub fn new(leaf_layer_capacity: usize, node_layer_capacity: usize) -> Result<Self, String> {
+        let arity = Self {
+            leaf_layer_capacity,
+            node_layer_capacity,
+        };
+        arity.validate()?;
+
+        Ok(arity)
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.leaf_layer_capacity == 0 {
+            return Err("leaf capacity must be positive".to_owned());
+        }
+        // otherwise nodes would never reduce the number of proofs
+        if self.node_layer_capacity < 2 {
+            return Err("node capacity must be at least 2".to_owned());
+        }
+        // node circuit compares queue length against their product as u32
+        if (self.leaf_layer_capacity as u64) * (self.node_layer_capacity as u64) > u32::MAX as u64 {
+            return Err("recursion tree is too wide".to_owned());
+        }
+
+        Ok(())
+    }
+}
+
 pub use crate::zkevm_circuits::recursion::recursion_tip::input::RECURSION_TIP_ARITY;
 
 #[derive(derivative::Derivative, serde::Serialize, serde::Deserialize)]
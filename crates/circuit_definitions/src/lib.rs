@@ -21,6 +21,7 @@ pub const L1_SECURITY_BITS: usize = 80;
 
 pub const EIP4844_CYCLE_LIMIT: usize = 4096;
 
+pub use fflonk;
 pub use snark_wrapper;
 use snark_wrapper::boojum::field::goldilocks::GoldilocksField;
 
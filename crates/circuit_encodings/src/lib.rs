@@ -798,6 +798,7 @@ mod tests {
     //use franklin_crypto::boojum::field::goldilocks::GoldilocksField;
     use crate::boojum::field::goldilocks::GoldilocksField;
 
+    use crate::ethereum_types::U256;
     use crate::ZkSyncDefaultRoundFunction;
 
     use super::{recursion_request::RecursionRequest, *};
@@ -868,4 +869,88 @@ mod tests {
         let (element, _) = parts[2].pop_and_output_intermediate_data(&round_function);
         assert_eq!(element.circuit_type, 7);
     }
+
+    fn create_state_diff_record(
+        enumeration_index: u64,
+        initial_value: U256,
+        final_value: U256,
+    ) -> state_diff_record::StateDiffRecord {
+        state_diff_record::StateDiffRecord {
+            address: ethereum_types::Address::repeat_byte(0x11),
+            key: U256::from(enumeration_index + 0x1234),
+            derived_key: [enumeration_index as u8; 32],
+            enumeration_index,
+            initial_value,
+            final_value,
+        }
+    }
+
+    #[test]
+    fn state_diff_record_encoding_roundtrip() {
+        let record = create_state_diff_record(42, U256::from(7), U256::MAX);
+        let decoded = state_diff_record::StateDiffRecord::decode(&record.encode());
+        assert_eq!(decoded.encode(), record.encode());
+        assert_eq!(decoded.enumeration_index, 42);
+        assert_eq!(decoded.final_value, U256::MAX);
+    }
+
+    #[test]
+    fn compressed_value_strategy() {
+        use state_diff_record::{CompressedValue, CompressionOperation};
+
+        let value = CompressedValue::new_with_best_strategy(U256::from(1000), U256::from(1001));
+        assert_eq!(value.operation, CompressionOperation::Add);
+        assert_eq!(value.encode(), vec![(1 << 3) | 1, 1]);
+
+        let value = CompressedValue::new_with_best_strategy(U256::MAX, U256::from(1));
+        assert_eq!(value.operation, CompressionOperation::Add);
+        assert_eq!(value.value, U256::from(2));
+
+        let value = CompressedValue::new_with_best_strategy(U256::from(1 << 20), U256::from(1));
+        assert_eq!(value.operation, CompressionOperation::Transform);
+
+        let value = CompressedValue::new_with_best_strategy(U256::from(1 << 20), U256::MAX);
+        assert_eq!(value.operation, CompressionOperation::Sub);
+        assert_eq!(value.apply(U256::from(1 << 20)), U256::MAX);
+
+        let value = CompressedValue::new_with_best_strategy(U256::zero(), U256::MAX >> 1);
+        assert_eq!(value.operation, CompressionOperation::Nothing);
+        assert_eq!(value.encode().len(), 33);
+        assert_eq!(value.encode()[0], 0);
+    }
+
+    #[test]
+    fn state_diffs_compression_roundtrip() {
+        let records = vec![
+            create_state_diff_record(5, U256::from(100), U256::from(50)),
+            create_state_diff_record(0, U256::zero(), U256::MAX),
+            create_state_diff_record(1 << 20, U256::MAX, U256::zero()),
+            create_state_diff_record(0, U256::zero(), U256::from(12345)),
+        ];
+
+        let compressed = state_diff_record::compress_state_diffs(&records);
+        assert_eq!(
+            compressed[0],
+            state_diff_record::STATE_DIFF_COMPRESSION_VERSION_NUMBER
+        );
+        let decompressed = state_diff_record::decompress_state_diffs(&compressed).unwrap();
+
+        // initial writes go first
+        let expected_order = [1, 3, 0, 2];
+        assert_eq!(decompressed.len(), expected_order.len());
+        for (diff, idx) in decompressed.iter().zip(expected_order) {
+            assert!(diff.matches(&records[idx]));
+        }
+
+        let mut truncated = compressed.clone();
+        truncated.pop();
+        assert!(state_diff_record::decompress_state_diffs(&truncated).is_err());
+
+        let mut wrong_version = compressed;
+        wrong_version[0] = 2;
+        assert_eq!(
+            state_diff_record::decompress_state_diffs(&wrong_version),
+            Err(state_diff_record::StateDiffDecompressionError::UnsupportedVersion(2))
+        );
+    }
 }
@@ -1,4 +1,5 @@
 use zk_evm::ethereum_types::Address;
+use zk_evm::sha3::{Digest, Keccak256};
 
 use super::*;
 use crate::ethereum_types::U256;
@@ -14,7 +15,10 @@ pub struct StateDiffRecord {
     pub final_value: U256,
 }
 
-use zkevm_circuits::base_structures::state_diff_record::STATE_DIFF_RECORD_BYTE_ENCODING_LEN;
+use zkevm_circuits::base_structures::state_diff_record::{
+    NUM_KECCAK256_ROUNDS_PER_RECORD_ACCUMULATION, STATE_DIFF_RECORD_BYTE_ENCODING_LEN,
+};
+use zkevm_circuits::boojum::gadgets::keccak256::KECCAK_RATE_BYTES;
 
 impl StateDiffRecord {
     // the only thing we need is byte encoding
@@ -51,4 +55,389 @@ impl StateDiffRecord {
 
         encoding
     }
+
+    // inverse of `encode`
+    pub fn decode(encoding: &[u8; STATE_DIFF_RECORD_BYTE_ENCODING_LEN]) -> Self {
+        let mut offset = 0;
+        let mut end = 0;
+
+        end += 20;
+        let address = Address::from_slice(&encoding[offset..end]);
+        offset = end;
+
+        end += 32;
+        let key = U256::from_big_endian(&encoding[offset..end]);
+        offset = end;
+
+        end += 32;
+        let mut derived_key = [0u8; 32];
+        derived_key.copy_from_slice(&encoding[offset..end]);
+        offset = end;
+
+        end += 8;
+        let enumeration_index = u64::from_be_bytes(encoding[offset..end].try_into().unwrap());
+        offset = end;
+
+        end += 32;
+        let initial_value = U256::from_big_endian(&encoding[offset..end]);
+        offset = end;
+
+        end += 32;
+        let final_value = U256::from_big_endian(&encoding[offset..end]);
+        offset = end;
+
+        debug_assert_eq!(offset, encoding.len());
+
+        Self {
+            address,
+            key,
+            derived_key,
+            enumeration_index,
+            initial_value,
+            final_value,
+        }
+    }
+
+    /// Writes into slots that were never written before have no enumeration index yet
+    pub fn is_write_initial(&self) -> bool {
+        self.enumeration_index == 0
+    }
+
+    /// Compressed form of the record as it goes into pubdata
+    pub fn compress(&self) -> CompressedStateDiff {
+        let key = if self.is_write_initial() {
+            StateDiffKey::InitialWrite {
+                derived_key: self.derived_key,
+            }
+        } else {
+            StateDiffKey::RepeatedWrite {
+                enumeration_index: self.enumeration_index,
+            }
+        };
+
+        CompressedStateDiff {
+            key,
+            value: CompressedValue::new_with_best_strategy(self.initial_value, self.final_value),
+        }
+    }
+}
+
+/// Version byte of the compressed state diffs.
+pub const STATE_DIFF_COMPRESSION_VERSION_NUMBER: u8 = 1;
+/// Number of bytes used for the enumeration index of repeated writes.
+pub const BYTES_PER_ENUMERATION_INDEX: u8 = 4;
+/// version (1 byte) || length of compressed state diffs (3 bytes) || enumeration index size (1 byte)
+pub const STATE_DIFF_COMPRESSION_HEADER_LEN: usize = 5;
+
+// metadata byte is `length << LENGTH_BITS_OFFSET | operation`
+const LENGTH_BITS_OFFSET: u8 = 3;
+const OPERATION_BITMASK: u8 = (1 << LENGTH_BITS_OFFSET) - 1;
+// largest length that fits into the metadata byte
+const MAX_COMPRESSED_VALUE_LEN: usize = 31;
+
+/// The way a final value is derived from the initial one.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[repr(u8)]
+pub enum CompressionOperation {
+    /// full 32 byte final value
+    Nothing = 0,
+    /// final = initial + value (mod 2^256)
+    Add = 1,
+    /// final = initial - value (mod 2^256)
+    Sub = 2,
+    /// final = value
+    Transform = 3,
+}
+
+impl CompressionOperation {
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(Self::Nothing),
+            1 => Some(Self::Add),
+            2 => Some(Self::Sub),
+            3 => Some(Self::Transform),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct CompressedValue {
+    pub operation: CompressionOperation,
+    pub value: U256,
+}
+
+impl CompressedValue {
+    /// Picks the operation with the shortest encoding. On ties the first one in the order
+    /// `Nothing`, `Add`, `Sub`, `Transform` is used.
+    pub fn new_with_best_strategy(initial_value: U256, final_value: U256) -> Self {
+        let candidates = [
+            (
+                CompressionOperation::Add,
+                final_value.overflowing_sub(initial_value).0,
+            ),
+            (
+                CompressionOperation::Sub,
+                initial_value.overflowing_sub(final_value).0,
+            ),
+            (CompressionOperation::Transform, final_value),
+        ];
+
+        candidates
+            .into_iter()
+            .map(|(operation, value)| Self { operation, value })
+            .filter(|el| el.value_len() <= MAX_COMPRESSED_VALUE_LEN)
+            .min_by_key(|el| el.value_len())
+            .unwrap_or(Self {
+                operation: CompressionOperation::Nothing,
+                value: final_value,
+            })
+    }
+
+    pub fn apply(&self, initial_value: U256) -> U256 {
+        match self.operation {
+            CompressionOperation::Nothing | CompressionOperation::Transform => self.value,
+            CompressionOperation::Add => initial_value.overflowing_add(self.value).0,
+            CompressionOperation::Sub => initial_value.overflowing_sub(self.value).0,
+        }
+    }
+
+    fn value_len(&self) -> usize {
+        match self.operation {
+            CompressionOperation::Nothing => 32,
+            _ => self.value.bits().div_ceil(8),
+        }
+    }
+
+    /// metadata (1 byte) || value (BE, metadata length or 32 bytes for `Nothing`)
+    pub fn encode(&self) -> Vec<u8> {
+        let len = self.value_len();
+        let metadata = match self.operation {
+            CompressionOperation::Nothing => 0,
+            operation => ((len as u8) << LENGTH_BITS_OFFSET) | operation as u8,
+        };
+
+        let mut buffer = [0u8; 32];
+        self.value.to_big_endian(&mut buffer);
+
+        let mut result = Vec::with_capacity(1 + len);
+        result.push(metadata);
+        result.extend_from_slice(&buffer[32 - len..]);
+        result
+    }
+
+    /// Decodes the value from the beginning of the data, returning the rest of the data
+    pub fn decode(data: &[u8]) -> Result<(Self, &[u8]), StateDiffDecompressionError> {
+        let (metadata, data) = data
+            .split_first()
+            .ok_or(StateDiffDecompressionError::UnexpectedEnd)?;
+        let operation = CompressionOperation::from_u8(metadata & OPERATION_BITMASK)
+            .ok_or(StateDiffDecompressionError::InvalidMetadata(*metadata))?;
+        let len = match operation {
+            CompressionOperation::Nothing if *metadata != 0 => {
+                return Err(StateDiffDecompressionError::InvalidMetadata(*metadata))
+            }
+            CompressionOperation::Nothing => 32,
+            _ => (metadata >> LENGTH_BITS_OFFSET) as usize,
+        };
+        if data.len() < len {
+            return Err(StateDiffDecompressionError::UnexpectedEnd);
+        }
+        let (value, data) = data.split_at(len);
+
+        Ok((
+            Self {
+                operation,
+                value: U256::from_big_endian(value),
+            },
+            data,
+        ))
+    }
+}
+
+/// Initial writes are keyed by the derived key, repeated writes by the enumeration index.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum StateDiffKey {
+    InitialWrite { derived_key: [u8; 32] },
+    RepeatedWrite { enumeration_index: u64 },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct CompressedStateDiff {
+    pub key: StateDiffKey,
+    pub value: CompressedValue,
+}
+
+impl CompressedStateDiff {
+    /// Checks that the compressed diff describes the given record
+    pub fn matches(&self, record: &StateDiffRecord) -> bool {
+        self.key == record.compress().key
+            && self.value.apply(record.initial_value) == record.final_value
+    }
+
+    fn encode(&self, enumeration_index_size: usize) -> Vec<u8> {
+        let mut result = match self.key {
+            StateDiffKey::InitialWrite { derived_key } => derived_key.to_vec(),
+            StateDiffKey::RepeatedWrite { enumeration_index } => {
+                assert!(
+                    enumeration_index < 1u64 << (enumeration_index_size * 8),
+                    "enumeration index {} doesn't fit into {} bytes",
+                    enumeration_index,
+                    enumeration_index_size
+                );
+                enumeration_index.to_be_bytes()[8 - enumeration_index_size..].to_vec()
+            }
+        };
+        result.extend(self.value.encode());
+        result
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum StateDiffDecompressionError {
+    UnsupportedVersion(u8),
+    InvalidEnumerationIndexSize(u8),
+    LengthMismatch { expected: usize, actual: usize },
+    InvalidMetadata(u8),
+    UnexpectedEnd,
+}
+
+impl std::fmt::Display for StateDiffDecompressionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::UnsupportedVersion(version) => {
+                write!(f, "unsupported compression version {version}")
+            }
+            Self::InvalidEnumerationIndexSize(size) => {
+                write!(f, "invalid enumeration index size {size}")
+            }
+            Self::LengthMismatch { expected, actual } => write!(
+                f,
+                "compressed state diffs length mismatch: header says {expected}, got {actual}"
+            ),
+            Self::InvalidMetadata(metadata) => {
+                write!(f, "invalid compressed value metadata {metadata:#04x}")
+            }
+            Self::UnexpectedEnd => write!(f, "unexpected end of compressed state diffs"),
+        }
+    }
+}
+
+impl std::error::Error for StateDiffDecompressionError {}
+
+/// Compresses the state diffs into the form that goes into pubdata:
+/// header || number of initial writes (2 bytes) || initial writes || repeated writes.
+/// Within the initial and repeated writes the records keep the order they are given in, which
+/// should be the order the storage application circuit processes them in.
+pub fn compress_state_diffs(state_diffs: &[StateDiffRecord]) -> Vec<u8> {
+    let (initial_writes, repeated_writes): (Vec<&StateDiffRecord>, Vec<_>) = state_diffs
+        .iter()
+        .partition(|record| record.is_write_initial());
+
+    let mut body = vec![];
+    body.extend(
+        u16::try_from(initial_writes.len())
+            .expect("too many initial writes")
+            .to_be_bytes(),
+    );
+    for record in initial_writes.into_iter().chain(repeated_writes) {
+        body.extend(
+            record
+                .compress()
+                .encode(BYTES_PER_ENUMERATION_INDEX as usize),
+        );
+    }
+
+    assert!(body.len() < 1 << 24, "compressed state diffs are too long");
+    let mut result = Vec::with_capacity(STATE_DIFF_COMPRESSION_HEADER_LEN + body.len());
+    result.push(STATE_DIFF_COMPRESSION_VERSION_NUMBER);
+    result.extend_from_slice(&(body.len() as u32).to_be_bytes()[1..]);
+    result.push(BYTES_PER_ENUMERATION_INDEX);
+    result.extend(body);
+    result
+}
+
+/// Inverse of `compress_state_diffs`. Initial writes come first in the result.
+pub fn decompress_state_diffs(
+    data: &[u8],
+) -> Result<Vec<CompressedStateDiff>, StateDiffDecompressionError> {
+    if data.len() < STATE_DIFF_COMPRESSION_HEADER_LEN {
+        return Err(StateDiffDecompressionError::UnexpectedEnd);
+    }
+    let (header, body) = data.split_at(STATE_DIFF_COMPRESSION_HEADER_LEN);
+
+    if header[0] != STATE_DIFF_COMPRESSION_VERSION_NUMBER {
+        return Err(StateDiffDecompressionError::UnsupportedVersion(header[0]));
+    }
+    let expected_len = u32::from_be_bytes([0, header[1], header[2], header[3]]) as usize;
+    if expected_len != body.len() {
+        return Err(StateDiffDecompressionError::LengthMismatch {
+            expected: expected_len,
+            actual: body.len(),
+        });
+    }
+    let enumeration_index_size = header[4] as usize;
+    if enumeration_index_size == 0 || enumeration_index_size > 8 {
+        return Err(StateDiffDecompressionError::InvalidEnumerationIndexSize(
+            header[4],
+        ));
+    }
+
+    if body.len() < 2 {
+        return Err(StateDiffDecompressionError::UnexpectedEnd);
+    }
+    let (num_initial_writes, mut body) = body.split_at(2);
+    let num_initial_writes = u16::from_be_bytes([num_initial_writes[0], num_initial_writes[1]]);
+
+    let mut result = vec![];
+    while !body.is_empty() {
+        let key = if result.len() < num_initial_writes as usize {
+            if body.len() < 32 {
+                return Err(StateDiffDecompressionError::UnexpectedEnd);
+            }
+            let (derived_key, rest) = body.split_at(32);
+            body = rest;
+            StateDiffKey::InitialWrite {
+                derived_key: derived_key.try_into().unwrap(),
+            }
+        } else {
+            if body.len() < enumeration_index_size {
+                return Err(StateDiffDecompressionError::UnexpectedEnd);
+            }
+            let (enumeration_index, rest) = body.split_at(enumeration_index_size);
+            body = rest;
+            let mut buffer = [0u8; 8];
+            buffer[8 - enumeration_index_size..].copy_from_slice(enumeration_index);
+            StateDiffKey::RepeatedWrite {
+                enumeration_index: u64::from_be_bytes(buffer),
+            }
+        };
+
+        let (value, rest) = CompressedValue::decode(body)?;
+        body = rest;
+        result.push(CompressedStateDiff { key, value });
+    }
+
+    if result.len() < num_initial_writes as usize {
+        return Err(StateDiffDecompressionError::UnexpectedEnd);
+    }
+
+    Ok(result)
+}
+
+/// Same hash as `state_diffs_keccak256_hash` output of the storage application circuit: every
+/// record's encoding is padded to a whole number of keccak rounds.
+pub fn state_diffs_keccak256_hash(state_diffs: &[StateDiffRecord]) -> [u8; 32] {
+    let mut hasher = Keccak256::new();
+    for record in state_diffs.iter() {
+        let mut extended_state_diff_encoding =
+            [0u8; KECCAK_RATE_BYTES * NUM_KECCAK256_ROUNDS_PER_RECORD_ACCUMULATION];
+        extended_state_diff_encoding[..STATE_DIFF_RECORD_BYTE_ENCODING_LEN]
+            .copy_from_slice(&record.encode());
+        hasher.update(extended_state_diff_encoding);
+    }
+
+    let mut result = [0u8; 32];
+    result.copy_from_slice(&hasher.finalize());
+    result
 }
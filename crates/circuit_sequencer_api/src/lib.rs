@@ -2,6 +2,7 @@
 
 pub mod geometry_config;
 pub mod proof;
+pub mod public_input;
 pub mod sort_storage_access;
 
 // IMPORTANT! This constant should never be just changed, since it's used in multiple versions
@@ -17,6 +18,11 @@ pub const BLOB_CHUNK_SIZE: usize = 31;
 pub const ELEMENTS_PER_4844_BLOCK: usize = 4096;
 pub const ENCODABLE_BYTES_PER_BLOB: usize = BLOB_CHUNK_SIZE * ELEMENTS_PER_4844_BLOCK;
 
+// Same for `zkevm_circuits::scheduler` constants.
+pub const NUM_SHARDS: usize = 2;
+pub const MAX_4844_BLOBS_PER_BLOCK: usize = 16;
+pub const NUM_SCHEDULER_PUBLIC_INPUTS: usize = 4;
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -31,5 +37,13 @@ mod tests {
         const _: () = assert!(
             ENCODABLE_BYTES_PER_BLOB == zkevm_circuits::eip_4844::input::ENCODABLE_BYTES_PER_BLOB
         );
+        const _: () = assert!(NUM_SHARDS == zkevm_circuits::scheduler::block_header::NUM_SHARDS);
+        const _: () = assert!(
+            MAX_4844_BLOBS_PER_BLOCK
+                == zkevm_circuits::scheduler::block_header::MAX_4844_BLOBS_PER_BLOCK
+        );
+        const _: () = assert!(
+            NUM_SCHEDULER_PUBLIC_INPUTS == zkevm_circuits::scheduler::NUM_SCHEDULER_PUBLIC_INPUTS
+        );
     }
 }
@@ -1,22 +1,36 @@
 use bellman::{
-    bn256::Bn256,
-    plonk::better_better_cs::{
-        cs::{Circuit, Width4MainGateWithDNext},
-        proof::Proof,
+    bn256::{Bn256, Fr},
+    plonk::{
+        better_better_cs::{
+            cs::{Circuit, Gate, GateInternal},
+            gates::selector_optimized_with_d_next::SelectorOptimizedWidth4MainGateWithDNext,
+            proof::Proof,
+            setup::VerificationKey,
+            verifier::verify,
+        },
+        commitments::transcript::keccak_transcript::RollingKeccakTranscript,
     },
+    PrimeField, PrimeFieldRepr, SynthesisError,
 };
+use franklin_crypto::plonk::circuit::custom_rescue_gate::Rescue5CustomGate;
+use zk_evm::sha3::{Digest, Keccak256};
 
 // Wrapper for the final scheduler proof.
 // We use generic circuit here, as this is used only for serializing & deserializing in sequencer.
 // The exact circuti type does not change the rules of (de)serialization, so we use a very lightweight
 // circuit in places that only pass proofs around to avoid unnecessary heavy compilation in most places.
+// The declared gates are the ones of the snark wrapper circuit, so that the final proof can also be
+// verified without the wrapper circuit itself.
 pub type FinalProof = Proof<Bn256, GenericCircuit>;
 
+// Verification key of the snark wrapper, deserialized from the same data as the wrapper one.
+pub type FinalProofVerificationKey = VerificationKey<Bn256, GenericCircuit>;
+
 #[derive(Clone)]
 pub struct GenericCircuit {}
 
 impl Circuit<Bn256> for GenericCircuit {
-    type MainGate = Width4MainGateWithDNext;
+    type MainGate = SelectorOptimizedWidth4MainGateWithDNext;
 
     fn synthesize<CS: bellman::plonk::better_better_cs::cs::ConstraintSystem<Bn256>>(
         &self,
@@ -24,4 +38,126 @@ impl Circuit<Bn256> for GenericCircuit {
     ) -> Result<(), bellman::SynthesisError> {
         Ok(())
     }
+
+    fn declare_used_gates() -> Result<Vec<Box<dyn GateInternal<Bn256>>>, SynthesisError> {
+        Ok(vec![
+            Self::MainGate::default().into_internal(),
+            Rescue5CustomGate::default().into_internal(),
+        ])
+    }
+}
+
+// The final proof has a single public input: the first 28 bytes of
+// keccak256(previous batch commitment || current batch commitment), read as a big endian number.
+// Same as the public input that L1 computes for the batch.
+pub const FINAL_PROOF_PUBLIC_INPUT_BYTES: usize = 28;
+
+pub fn final_proof_public_input(
+    previous_batch_commitment: [u8; 32],
+    current_batch_commitment: [u8; 32],
+) -> Fr {
+    let mut hasher = Keccak256::new();
+    hasher.update(previous_batch_commitment);
+    hasher.update(current_batch_commitment);
+    let hash = hasher.finalize();
+
+    let mut buffer = [0u8; 32];
+    buffer[(32 - FINAL_PROOF_PUBLIC_INPUT_BYTES)..]
+        .copy_from_slice(&hash[..FINAL_PROOF_PUBLIC_INPUT_BYTES]);
+    let mut repr = <Fr as PrimeField>::Repr::default();
+    repr.read_be(&buffer[..]).unwrap();
+
+    Fr::from_repr(repr).expect("224 bits always fit into the field")
+}
+
+#[derive(Debug)]
+pub enum ProofVerificationError {
+    DomainSizeMismatch { vk: usize, proof: usize },
+    PublicInputMismatch { expected: Fr, actual: Vec<Fr> },
+    InvalidProof,
+    Synthesis(SynthesisError),
+}
+
+impl std::fmt::Display for ProofVerificationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::DomainSizeMismatch { vk, proof } => write!(
+                f,
+                "proof domain size {} doesn't match the verification key domain size {}",
+                proof, vk
+            ),
+            Self::PublicInputMismatch { expected, actual } => write!(
+                f,
+                "proof public inputs {:?} don't match the expected public input {}",
+                actual, expected
+            ),
+            Self::InvalidProof => write!(f, "proof is invalid"),
+            Self::Synthesis(error) => write!(f, "proof verification failed: {}", error),
+        }
+    }
+}
+
+impl std::error::Error for ProofVerificationError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Synthesis(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+/// Verifies final (snark wrapper) proofs against a fixed wrapper verification key.
+pub struct ProofVerifier {
+    vk: FinalProofVerificationKey,
+}
+
+impl ProofVerifier {
+    pub fn new(vk: FinalProofVerificationKey) -> Self {
+        Self { vk }
+    }
+
+    pub fn vk(&self) -> &FinalProofVerificationKey {
+        &self.vk
+    }
+
+    pub fn verify(
+        &self,
+        proof: &FinalProof,
+        expected_public_input: Fr,
+    ) -> Result<(), ProofVerificationError> {
+        if proof.n != self.vk.n {
+            return Err(ProofVerificationError::DomainSizeMismatch {
+                vk: self.vk.n,
+                proof: proof.n,
+            });
+        }
+        if proof.inputs.as_slice() != [expected_public_input] {
+            return Err(ProofVerificationError::PublicInputMismatch {
+                expected: expected_public_input,
+                actual: proof.inputs.clone(),
+            });
+        }
+
+        let is_valid =
+            verify::<Bn256, GenericCircuit, RollingKeccakTranscript<Fr>>(&self.vk, proof, None)
+                .map_err(ProofVerificationError::Synthesis)?;
+
+        if is_valid {
+            Ok(())
+        } else {
+            Err(ProofVerificationError::InvalidProof)
+        }
+    }
+
+    pub fn verify_for_batch_commitments(
+        &self,
+        proof: &FinalProof,
+        previous_batch_commitment: [u8; 32],
+        current_batch_commitment: [u8; 32],
+    ) -> Result<(), ProofVerificationError> {
+        self.verify(
+            proof,
+            final_proof_public_input(previous_batch_commitment, current_batch_commitment),
+        )
+    }
 }
@@ -0,0 +1,372 @@
+// Out-of-circuit version of the block header hashing done by the scheduler
+// (see `zkevm_circuits::scheduler::block_header`), and of the compression of the scheduler
+// public input into the single public input of the snark wrapper.
+// Everything is encoded as BE, same as in circuit.
+
+use bellman::{bn256::Fr, Field, PrimeField, PrimeFieldRepr};
+use zk_evm::sha3::{Digest, Keccak256};
+
+use crate::{MAX_4844_BLOBS_PER_BLOCK, NUM_SCHEDULER_PUBLIC_INPUTS, NUM_SHARDS};
+
+// Scheduler public inputs are Goldilocks elements, each holding 7 bytes of the public input hash
+pub const SCHEDULER_PUBLIC_INPUT_CHUNK_BYTES: usize = 7;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PerShardState {
+    pub enumeration_counter: u64,
+    pub state_root: [u8; 32],
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct BlockPassthroughData {
+    pub per_shard_states: [PerShardState; NUM_SHARDS],
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct BlockMetaParameters {
+    pub zkporter_is_available: bool,
+    pub bootloader_code_hash: [u8; 32],
+    pub default_aa_code_hash: [u8; 32],
+    pub evm_simulator_code_hash: [u8; 32],
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BlockAuxilaryOutput {
+    pub l1_messages_linear_hash: [u8; 32],
+    pub rollup_state_diff_for_compression: [u8; 32],
+    pub bootloader_heap_initial_content: [u8; 32],
+    pub events_queue_state: [u8; 32],
+    pub eip4844_linear_hashes: [[u8; 32]; MAX_4844_BLOBS_PER_BLOCK],
+    pub eip4844_output_commitment_hashes: [[u8; 32]; MAX_4844_BLOBS_PER_BLOCK],
+}
+
+impl Default for BlockAuxilaryOutput {
+    fn default() -> Self {
+        Self {
+            l1_messages_linear_hash: [0u8; 32],
+            rollup_state_diff_for_compression: [0u8; 32],
+            bootloader_heap_initial_content: [0u8; 32],
+            events_queue_state: [0u8; 32],
+            eip4844_linear_hashes: [[0u8; 32]; MAX_4844_BLOBS_PER_BLOCK],
+            eip4844_output_commitment_hashes: [[0u8; 32]; MAX_4844_BLOBS_PER_BLOCK],
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct BlockContentHeader {
+    pub block_data: BlockPassthroughData,
+    pub block_meta: BlockMetaParameters,
+    pub auxilary_output: BlockAuxilaryOutput,
+}
+
+fn keccak256(data: &[u8]) -> [u8; 32] {
+    let mut result = [0u8; 32];
+    result.copy_from_slice(&Keccak256::digest(data));
+
+    result
+}
+
+impl PerShardState {
+    pub fn into_flattened_bytes(&self) -> Vec<u8> {
+        let mut result = vec![];
+        result.extend_from_slice(&self.enumeration_counter.to_be_bytes());
+        result.extend_from_slice(&self.state_root);
+
+        result
+    }
+}
+
+impl BlockPassthroughData {
+    pub fn into_flattened_bytes(&self) -> Vec<u8> {
+        let mut result = vec![];
+        for el in self.per_shard_states.iter() {
+            result.extend(el.into_flattened_bytes());
+        }
+
+        result
+    }
+
+    pub fn hash(&self) -> [u8; 32] {
+        keccak256(&self.into_flattened_bytes())
+    }
+}
+
+impl BlockMetaParameters {
+    pub fn into_flattened_bytes(&self) -> Vec<u8> {
+        let mut result = vec![];
+        result.push(self.zkporter_is_available as u8);
+        result.extend_from_slice(&self.bootloader_code_hash);
+        result.extend_from_slice(&self.default_aa_code_hash);
+        result.extend_from_slice(&self.evm_simulator_code_hash);
+
+        result
+    }
+
+    pub fn hash(&self) -> [u8; 32] {
+        keccak256(&self.into_flattened_bytes())
+    }
+}
+
+impl BlockAuxilaryOutput {
+    pub fn into_flattened_bytes(&self) -> Vec<u8> {
+        let mut result = vec![];
+        result.extend_from_slice(&self.l1_messages_linear_hash);
+        result.extend_from_slice(&self.rollup_state_diff_for_compression);
+        result.extend_from_slice(&self.bootloader_heap_initial_content);
+        result.extend_from_slice(&self.events_queue_state);
+        for (linear_hash, blob_opening_commitment) in self
+            .eip4844_linear_hashes
+            .iter()
+            .zip(self.eip4844_output_commitment_hashes.iter())
+        {
+            result.extend_from_slice(linear_hash);
+            result.extend_from_slice(blob_opening_commitment);
+        }
+
+        result
+    }
+
+    pub fn hash(&self) -> [u8; 32] {
+        keccak256(&self.into_flattened_bytes())
+    }
+}
+
+impl BlockContentHeader {
+    /// Returns the formal block hash together with the partial hashes of
+    /// (block data, block meta, auxilary output) it is made of.
+    pub fn into_formal_block_hash(self) -> ([u8; 32], ([u8; 32], [u8; 32], [u8; 32])) {
+        let block_data_hash = self.block_data.hash();
+        let block_meta_hash = self.block_meta.hash();
+        let auxilary_output_hash = self.auxilary_output.hash();
+
+        let block_hash = Self::formal_block_hash_from_partial_hashes(
+            block_data_hash,
+            block_meta_hash,
+            auxilary_output_hash,
+        );
+
+        (
+            block_hash,
+            (block_data_hash, block_meta_hash, auxilary_output_hash),
+        )
+    }
+
+    pub fn formal_block_hash_from_partial_hashes(
+        block_data_hash: [u8; 32],
+        block_meta_hash: [u8; 32],
+        auxilary_output_hash: [u8; 32],
+    ) -> [u8; 32] {
+        let mut concatenated = vec![];
+        concatenated.extend_from_slice(&block_data_hash);
+        concatenated.extend_from_slice(&block_meta_hash);
+        concatenated.extend_from_slice(&auxilary_output_hash);
+
+        keccak256(&concatenated)
+    }
+}
+
+/// Hash of the concatenation of previous and new formal block hashes, that the scheduler
+/// exposes as its public input.
+pub fn scheduler_public_input_hash(
+    previous_block_content_hash: [u8; 32],
+    new_block_content_hash: [u8; 32],
+) -> [u8; 32] {
+    let mut concatenated = vec![];
+    concatenated.extend_from_slice(&previous_block_content_hash);
+    concatenated.extend_from_slice(&new_block_content_hash);
+
+    keccak256(&concatenated)
+}
+
+/// Values of the scheduler (Goldilocks) public inputs, every one is 7 BE bytes of the hash.
+pub fn scheduler_public_inputs(public_input_hash: [u8; 32]) -> [u64; NUM_SCHEDULER_PUBLIC_INPUTS] {
+    let mut result = [0u64; NUM_SCHEDULER_PUBLIC_INPUTS];
+    for (dst, chunk) in result
+        .iter_mut()
+        .zip(public_input_hash.chunks_exact(SCHEDULER_PUBLIC_INPUT_CHUNK_BYTES))
+    {
+        let mut buffer = [0u8; 8];
+        buffer[(8 - SCHEDULER_PUBLIC_INPUT_CHUNK_BYTES)..].copy_from_slice(chunk);
+        *dst = u64::from_be_bytes(buffer);
+    }
+
+    result
+}
+
+/// Computes wrapper public input from stark one, same as
+/// `zkevm_test_harness::proof_wrapper_utils::compress_stark_pi_to_snark_pi`.
+/// Stark PI consist of 4 7-byte elements and we just want to concatenate them
+pub fn compress_stark_pi_to_snark_pi(stark_pi: [u64; NUM_SCHEDULER_PUBLIC_INPUTS]) -> Fr {
+    let chunk_bit_size = SCHEDULER_PUBLIC_INPUT_CHUNK_BYTES * 8;
+    assert!(
+        stark_pi.len() * chunk_bit_size <= Fr::CAPACITY as usize,
+        "scalar field capacity is not enough to fit all public inputs"
+    );
+
+    let mut coeff = Fr::one();
+    let mut shift = <Fr as PrimeField>::Repr::from(1);
+    shift.shl(chunk_bit_size as u32);
+    let shift = Fr::from_repr(shift).unwrap();
+
+    let mut result = Fr::zero();
+    for chunk in stark_pi.iter().rev() {
+        assert!(*chunk < 1u64 << chunk_bit_size);
+        let mut chunk_fr = Fr::from_repr(<Fr as PrimeField>::Repr::from(*chunk)).unwrap();
+        chunk_fr.mul_assign(&coeff);
+        result.add_assign(&chunk_fr);
+        coeff.mul_assign(&shift);
+    }
+
+    result
+}
+
+/// The exact public input of the final (snark wrapper) proof for the new block.
+/// Same as the scheduler, the previous block is given by its state roots and enumeration counters,
+/// while its meta parameters and auxilary output are only committed by their hashes
+/// (`previous_block_meta_hash` and `previous_block_aux_hash` of the scheduler witness).
+pub fn compute_snark_public_input(
+    previous_block_data: BlockPassthroughData,
+    previous_block_meta_hash: [u8; 32],
+    previous_block_aux_hash: [u8; 32],
+    new_block: BlockContentHeader,
+) -> Fr {
+    let previous_block_content_hash = BlockContentHeader::formal_block_hash_from_partial_hashes(
+        previous_block_data.hash(),
+        previous_block_meta_hash,
+        previous_block_aux_hash,
+    );
+    let (new_block_content_hash, _) = new_block.into_formal_block_hash();
+    let public_input_hash =
+        scheduler_public_input_hash(previous_block_content_hash, new_block_content_hash);
+
+    compress_stark_pi_to_snark_pi(scheduler_public_inputs(public_input_hash))
+}
+
+/// Public inputs of the interblock aggregation circuit, that aggregates up to `capacity` scheduler proofs.
+/// Every scheduler public input contributes 7 BE bytes to the keccak preimage, while missing (padding)
+/// batches contribute `masking_value` bytes instead. The hash is then split the same way as for a single block.
+pub fn interblock_aggregated_public_inputs(
+    batches_public_inputs: &[[u64; NUM_SCHEDULER_PUBLIC_INPUTS]],
+    capacity: usize,
+    masking_value: u8,
+) -> [u64; NUM_SCHEDULER_PUBLIC_INPUTS] {
+    assert!(
+        batches_public_inputs.len() <= capacity,
+        "can aggregate at most {} batches, got {}",
+        capacity,
+        batches_public_inputs.len()
+    );
+
+    let mut concatenated = Vec::with_capacity(
+        capacity * NUM_SCHEDULER_PUBLIC_INPUTS * SCHEDULER_PUBLIC_INPUT_CHUNK_BYTES,
+    );
+    for public_inputs in batches_public_inputs {
+        for input in public_inputs.iter() {
+            let bytes = input.to_be_bytes();
+            assert!(
+                bytes[..(8 - SCHEDULER_PUBLIC_INPUT_CHUNK_BYTES)]
+                    .iter()
+                    .all(|el| *el == 0),
+                "scheduler public input doesn't fit into {} bytes",
+                SCHEDULER_PUBLIC_INPUT_CHUNK_BYTES
+            );
+            concatenated.extend_from_slice(&bytes[(8 - SCHEDULER_PUBLIC_INPUT_CHUNK_BYTES)..]);
+        }
+    }
+    concatenated.resize(
+        capacity * NUM_SCHEDULER_PUBLIC_INPUTS * SCHEDULER_PUBLIC_INPUT_CHUNK_BYTES,
+        masking_value,
+    );
+
+    scheduler_public_inputs(keccak256(&concatenated))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proof::final_proof_public_input;
+
+    fn testing_block(seed: u8) -> BlockContentHeader {
+        let mut block = BlockContentHeader::default();
+        for (idx, state) in block.block_data.per_shard_states.iter_mut().enumerate() {
+            state.enumeration_counter = 1000 * seed as u64 + idx as u64;
+            state.state_root = [seed + idx as u8; 32];
+        }
+        block.block_meta.bootloader_code_hash = [seed ^ 1; 32];
+        block.block_meta.default_aa_code_hash = [seed ^ 2; 32];
+        block.block_meta.evm_simulator_code_hash = [seed ^ 3; 32];
+        block.auxilary_output.l1_messages_linear_hash = [seed ^ 4; 32];
+        block.auxilary_output.events_queue_state = [seed ^ 5; 32];
+        block.auxilary_output.eip4844_linear_hashes[0] = [seed ^ 6; 32];
+        block.auxilary_output.eip4844_output_commitment_hashes[0] = [seed ^ 7; 32];
+
+        block
+    }
+
+    #[test]
+    fn test_flattened_lengths() {
+        let block = testing_block(1);
+        assert_eq!(
+            block.block_data.into_flattened_bytes().len(),
+            NUM_SHARDS * 40
+        );
+        assert_eq!(block.block_meta.into_flattened_bytes().len(), 1 + 3 * 32);
+        assert_eq!(
+            block.auxilary_output.into_flattened_bytes().len(),
+            4 * 32 + 2 * 32 * MAX_4844_BLOBS_PER_BLOCK
+        );
+    }
+
+    #[test]
+    fn test_snark_public_input_is_truncated_hash() {
+        let previous_block = testing_block(1);
+        let (previous_block_content_hash, (_, previous_block_meta_hash, previous_block_aux_hash)) =
+            previous_block.into_formal_block_hash();
+        let new_block = testing_block(2);
+        let (new_block_content_hash, _) = new_block.into_formal_block_hash();
+
+        assert_eq!(
+            compute_snark_public_input(
+                previous_block.block_data,
+                previous_block_meta_hash,
+                previous_block_aux_hash,
+                new_block
+            ),
+            final_proof_public_input(previous_block_content_hash, new_block_content_hash)
+        );
+    }
+
+    #[test]
+    fn test_interblock_aggregation_public_inputs() {
+        let hashes: Vec<_> = (1..4u8)
+            .map(|seed| {
+                let (previous, _) = testing_block(seed).into_formal_block_hash();
+                let (new, _) = testing_block(seed + 1).into_formal_block_hash();
+                scheduler_public_inputs(scheduler_public_input_hash(previous, new))
+            })
+            .collect();
+
+        let mut expected_preimage = vec![];
+        for hash in hashes.iter() {
+            for input in hash.iter() {
+                expected_preimage.extend_from_slice(&input.to_be_bytes()[1..]);
+            }
+        }
+        expected_preimage.extend([0xff; 28]);
+
+        assert_eq!(
+            interblock_aggregated_public_inputs(&hashes, 4, 0xff),
+            scheduler_public_inputs(keccak256(&expected_preimage))
+        );
+        // padding is a part of the commitment
+        assert_ne!(
+            interblock_aggregated_public_inputs(&hashes, 4, 0xff),
+            interblock_aggregated_public_inputs(&hashes, 4, 0)
+        );
+        assert_ne!(
+            interblock_aggregated_public_inputs(&hashes, 4, 0),
+            interblock_aggregated_public_inputs(&hashes, 3, 0)
+        );
+    }
+}
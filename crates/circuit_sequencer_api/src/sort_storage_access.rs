@@ -1,5 +1,9 @@
 use rayon::prelude::*;
 use std::cmp::Ordering;
+use std::collections::{BinaryHeap, VecDeque};
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::PathBuf;
 use zk_evm::{
     aux_structures::{LogQuery, LogQueryWithExtendedEnumeration, Timestamp},
     ethereum_types::{H160, U256},
@@ -13,185 +17,121 @@ pub struct StorageSlotHistoryKeeper {
     pub did_read_at_depth_zero: bool,
 }
 
-// IMPORTANT! This function is being used by all the protocol versions in MultiVM, so changing it
-// may cause a change in behavior for existing protocol versions.
-pub fn sort_storage_access_queries(
-    unsorted_storage_queries: impl IntoIterator<Item = LogQuery>,
-) -> (Vec<LogQueryWithExtendedEnumeration>, Vec<LogQuery>) {
-    let mut sorted_storage_queries_with_extra_timestamp: Vec<_> = unsorted_storage_queries
-        .into_iter()
-        .enumerate()
-        .map(|(i, el)| LogQueryWithExtendedEnumeration {
-            raw_query: el,
-            extended_timestamp: i as u32,
-        })
-        .collect();
-
-    sorted_storage_queries_with_extra_timestamp.par_sort_by(|a, b| {
-        match a.raw_query.shard_id.cmp(&b.raw_query.shard_id) {
-            Ordering::Equal => match a.raw_query.address.cmp(&b.raw_query.address) {
-                Ordering::Equal => match a.raw_query.key.cmp(&b.raw_query.key) {
-                    Ordering::Equal => a.extended_timestamp.cmp(&b.extended_timestamp),
-                    r => r,
-                },
-                r => r,
-            },
-            r => r,
+impl StorageSlotHistoryKeeper {
+    fn apply_query(&mut self, el: &LogQueryWithExtendedEnumeration) {
+        if self.current_value.is_none() {
+            assert!(self.initial_value.is_none(), "invalid for query {:?}", el);
+            // first read potentially
+            if el.raw_query.rw_flag == false {
+                self.did_read_at_depth_zero = true;
+            }
+        } else {
+            // explicit read at zero
+            if el.raw_query.rw_flag == false && self.changes_stack.is_empty() {
+                self.did_read_at_depth_zero = true;
+            }
         }
-    });
 
-    let mut deduplicated_storage_queries = vec![];
-
-    // now just implement the logic to sort and deduplicate
-    let mut it = sorted_storage_queries_with_extra_timestamp
-        .iter()
-        .peekable();
-
-    loop {
-        if it.peek().is_none() {
-            break;
+        if self.current_value.is_none() {
+            assert!(self.initial_value.is_none(), "invalid for query {:?}", el);
+            if el.raw_query.rw_flag == false {
+                self.initial_value = Some(el.raw_query.read_value);
+                self.current_value = Some(el.raw_query.read_value);
+            } else {
+                assert!(el.raw_query.rollback == false);
+                self.initial_value = Some(el.raw_query.read_value);
+                self.current_value = Some(el.raw_query.read_value);
+                // note: We apply updates few lines later
+            }
         }
 
-        // need it to remove "peek"'s mutable borrow
-        #[allow(suspicious_double_ref_op)]
-        let candidate = it.peek().unwrap().clone();
-
-        let subit = it.clone().take_while(|el| {
-            el.raw_query.shard_id == candidate.raw_query.shard_id
-                && el.raw_query.address == candidate.raw_query.address
-                && el.raw_query.key == candidate.raw_query.key
-        });
-
-        let mut current_element_history = StorageSlotHistoryKeeper::default();
-
-        for el in subit {
-            let _ = it.next().unwrap();
-
-            if current_element_history.current_value.is_none() {
-                assert!(
-                    current_element_history.initial_value.is_none(),
+        if el.raw_query.rw_flag == false {
+            assert_eq!(
+                &el.raw_query.read_value,
+                self.current_value.as_ref().unwrap(),
+                "invalid for query {:?}",
+                el
+            );
+            // and do not place reads into the stack
+        } else {
+            // write-like things manipulate the stack
+            if el.raw_query.rollback == false {
+                // write and push to the stack
+                assert_eq!(
+                    &el.raw_query.read_value,
+                    self.current_value.as_ref().unwrap(),
                     "invalid for query {:?}",
                     el
                 );
-                // first read potentially
-                if el.raw_query.rw_flag == false {
-                    current_element_history.did_read_at_depth_zero = true;
-                }
+                self.current_value = Some(el.raw_query.written_value);
+                self.changes_stack.push(el.clone());
             } else {
-                // explicit read at zero
-                if el.raw_query.rw_flag == false && current_element_history.changes_stack.is_empty()
-                {
-                    current_element_history.did_read_at_depth_zero = true;
-                }
-            }
-
-            if current_element_history.current_value.is_none() {
-                assert!(
-                    current_element_history.initial_value.is_none(),
+                // pop from stack and self-check
+                let popped_change = self.changes_stack.pop().unwrap();
+                // we do not explicitly swap values, and use rollback flag instead, so compare this way
+                assert_eq!(
+                    el.raw_query.read_value, popped_change.raw_query.read_value,
                     "invalid for query {:?}",
                     el
                 );
-                if el.raw_query.rw_flag == false {
-                    current_element_history.initial_value = Some(el.raw_query.read_value);
-                    current_element_history.current_value = Some(el.raw_query.read_value);
-                } else {
-                    assert!(el.raw_query.rollback == false);
-                    current_element_history.initial_value = Some(el.raw_query.read_value);
-                    current_element_history.current_value = Some(el.raw_query.read_value);
-                    // note: We apply updates few lines later
-                }
-            }
-
-            if el.raw_query.rw_flag == false {
                 assert_eq!(
-                    &el.raw_query.read_value,
-                    current_element_history.current_value.as_ref().unwrap(),
+                    el.raw_query.written_value, popped_change.raw_query.written_value,
                     "invalid for query {:?}",
                     el
                 );
-                // and do not place reads into the stack
-            } else {
-                // write-like things manipulate the stack
-                if el.raw_query.rollback == false {
-                    // write and push to the stack
-                    assert_eq!(
-                        &el.raw_query.read_value,
-                        current_element_history.current_value.as_ref().unwrap(),
-                        "invalid for query {:?}",
-                        el
-                    );
-                    current_element_history.current_value = Some(el.raw_query.written_value);
-                    current_element_history.changes_stack.push(el.clone());
-                } else {
-                    // pop from stack and self-check
-                    let popped_change = current_element_history.changes_stack.pop().unwrap();
-                    // we do not explicitly swap values, and use rollback flag instead, so compare this way
-                    assert_eq!(
-                        el.raw_query.read_value, popped_change.raw_query.read_value,
-                        "invalid for query {:?}",
-                        el
-                    );
-                    assert_eq!(
-                        el.raw_query.written_value, popped_change.raw_query.written_value,
-                        "invalid for query {:?}",
-                        el
-                    );
-                    assert_eq!(
-                        &el.raw_query.written_value,
-                        current_element_history.current_value.as_ref().unwrap(),
-                        "invalid for query {:?}",
-                        el
-                    );
-                    // check that we properly apply rollbacks
-                    assert_eq!(
-                        el.raw_query.shard_id, popped_change.raw_query.shard_id,
-                        "invalid for query {:?}",
-                        el
-                    );
-                    assert_eq!(
-                        el.raw_query.address, popped_change.raw_query.address,
-                        "invalid for query {:?}",
-                        el
-                    );
-                    assert_eq!(
-                        el.raw_query.key, popped_change.raw_query.key,
-                        "invalid for query {:?}",
-                        el
-                    );
-                    // apply rollback
-                    current_element_history.current_value = Some(el.raw_query.read_value);
-                    // our convension
-                }
+                assert_eq!(
+                    &el.raw_query.written_value,
+                    self.current_value.as_ref().unwrap(),
+                    "invalid for query {:?}",
+                    el
+                );
+                // check that we properly apply rollbacks
+                assert_eq!(
+                    el.raw_query.shard_id, popped_change.raw_query.shard_id,
+                    "invalid for query {:?}",
+                    el
+                );
+                assert_eq!(
+                    el.raw_query.address, popped_change.raw_query.address,
+                    "invalid for query {:?}",
+                    el
+                );
+                assert_eq!(
+                    el.raw_query.key, popped_change.raw_query.key,
+                    "invalid for query {:?}",
+                    el
+                );
+                // apply rollback
+                self.current_value = Some(el.raw_query.read_value);
+                // our convension
             }
         }
+    }
 
-        if current_element_history.did_read_at_depth_zero == false
-            && current_element_history.changes_stack.is_empty()
-        {
+    fn into_deduplicated_query(
+        self,
+        candidate: &LogQueryWithExtendedEnumeration,
+    ) -> Option<LogQuery> {
+        if self.did_read_at_depth_zero == false && self.changes_stack.is_empty() {
             // whatever happened there didn't produce any final changes
-            assert_eq!(
-                current_element_history.initial_value.unwrap(),
-                current_element_history.current_value.unwrap()
-            );
+            assert_eq!(self.initial_value.unwrap(), self.current_value.unwrap());
             // here we know that last write was a rollback, and there we no reads after it (otherwise "did_read_at_depth_zero" == true),
             // so whatever was an initial value in storage slot it's not ever observed, and we do not need to issue even read here
-            continue;
-        } else if current_element_history.initial_value.unwrap()
-            == current_element_history.current_value.unwrap()
-        {
+            None
+        } else if self.initial_value.unwrap() == self.current_value.unwrap() {
             // no change, but we may need protective read
-            if current_element_history.did_read_at_depth_zero {
+            if self.did_read_at_depth_zero {
                 // protective read
                 let sorted_log_query = create_partially_filled_from_fields(
                     candidate.raw_query.shard_id,
                     candidate.raw_query.address,
                     candidate.raw_query.key,
-                    current_element_history.initial_value.unwrap(),
-                    current_element_history.current_value.unwrap(),
+                    self.initial_value.unwrap(),
+                    self.current_value.unwrap(),
                     false,
                 );
 
-                deduplicated_storage_queries.push(sorted_log_query);
+                Some(sorted_log_query)
             } else {
                 // we didn't read at depth zero, so it's something like
                 // - write cell from a into b
@@ -207,7 +147,7 @@ pub fn sort_storage_access_queries(
                 // protects us in case of write - rollback - read, so we only need to degrade write into
                 // read here if the latest write wasn't a rollback
 
-                if current_element_history.changes_stack.is_empty() == false {
+                if self.changes_stack.is_empty() == false {
                     // it means that we did accumlate some changes, even though in NET result
                     // it CLAIMS that it didn't change a value
                     // degrade to protective read
@@ -215,18 +155,19 @@ pub fn sort_storage_access_queries(
                         candidate.raw_query.shard_id,
                         candidate.raw_query.address,
                         candidate.raw_query.key,
-                        current_element_history.initial_value.unwrap(),
-                        current_element_history.current_value.unwrap(),
+                        self.initial_value.unwrap(),
+                        self.current_value.unwrap(),
                         false,
                     );
 
-                    deduplicated_storage_queries.push(sorted_log_query);
+                    Some(sorted_log_query)
                 } else {
                     // Whatever has happened we rolled it back completely, so unless
                     // there was a need for protective read at depth 0, we do not need
                     // to go into storage and check or change any value
 
                     // we just do nothing!
+                    None
                 }
             }
         } else {
@@ -235,11 +176,79 @@ pub fn sort_storage_access_queries(
                 candidate.raw_query.shard_id,
                 candidate.raw_query.address,
                 candidate.raw_query.key,
-                current_element_history.initial_value.unwrap(),
-                current_element_history.current_value.unwrap(),
+                self.initial_value.unwrap(),
+                self.current_value.unwrap(),
                 true,
             );
 
+            Some(sorted_log_query)
+        }
+    }
+}
+
+fn compare_storage_queries(
+    a: &LogQueryWithExtendedEnumeration,
+    b: &LogQueryWithExtendedEnumeration,
+) -> Ordering {
+    match a.raw_query.shard_id.cmp(&b.raw_query.shard_id) {
+        Ordering::Equal => match a.raw_query.address.cmp(&b.raw_query.address) {
+            Ordering::Equal => match a.raw_query.key.cmp(&b.raw_query.key) {
+                Ordering::Equal => a.extended_timestamp.cmp(&b.extended_timestamp),
+                r => r,
+            },
+            r => r,
+        },
+        r => r,
+    }
+}
+
+// IMPORTANT! This function is being used by all the protocol versions in MultiVM, so changing it
+// may cause a change in behavior for existing protocol versions.
+pub fn sort_storage_access_queries(
+    unsorted_storage_queries: impl IntoIterator<Item = LogQuery>,
+) -> (Vec<LogQueryWithExtendedEnumeration>, Vec<LogQuery>) {
+    let mut sorted_storage_queries_with_extra_timestamp: Vec<_> = unsorted_storage_queries
+        .into_iter()
+        .enumerate()
+        .map(|(i, el)| LogQueryWithExtendedEnumeration {
+            raw_query: el,
+            extended_timestamp: i as u32,
+        })
+        .collect();
+
+    sorted_storage_queries_with_extra_timestamp.par_sort_by(compare_storage_queries);
+
+    let mut deduplicated_storage_queries = vec![];
+
+    // now just implement the logic to sort and deduplicate
+    let mut it = sorted_storage_queries_with_extra_timestamp
+        .iter()
+        .peekable();
+
+    loop {
+        if it.peek().is_none() {
+            break;
+        }
+
+        // need it to remove "peek"'s mutable borrow
+        #[allow(suspicious_double_ref_op)]
+        let candidate = it.peek().unwrap().clone();
+
+        let subit = it.clone().take_while(|el| {
+            el.raw_query.shard_id == candidate.raw_query.shard_id
+                && el.raw_query.address == candidate.raw_query.address
+                && el.raw_query.key == candidate.raw_query.key
+        });
+
+        let mut current_element_history = StorageSlotHistoryKeeper::default();
+
+        for el in subit {
+            let _ = it.next().unwrap();
+
+            current_element_history.apply_query(el);
+        }
+
+        if let Some(sorted_log_query) = current_element_history.into_deduplicated_query(candidate) {
             deduplicated_storage_queries.push(sorted_log_query);
         }
     }
@@ -250,6 +259,304 @@ pub fn sort_storage_access_queries(
     )
 }
 
+/// Limits for [`sort_storage_access_queries_streaming`].
+#[derive(Clone, Debug)]
+pub struct ExternalSortConfig {
+    /// Directory where the sorted runs are spilled. A fresh subdirectory is created in it and
+    /// removed once sorting is done.
+    pub temp_dir: PathBuf,
+    /// Maximum number of queries that are sorted in memory at once.
+    pub max_queries_in_memory: usize,
+    /// Maximum number of sorted runs that are merged at once, each of them holds an open file and
+    /// a read buffer. If there are more runs, they are merged into larger ones in several passes.
+    pub max_runs_per_merge: usize,
+}
+
+/// Bounded-memory version of [`sort_storage_access_queries`]. Queries are sorted in runs of at most
+/// `max_queries_in_memory` elements that are spilled to disk, and then merged, at most
+/// `max_runs_per_merge` runs at once. Instead of being collected, the sorted queries and the
+/// deduplicated queries are passed to the sinks in the same order as they appear in the vectors
+/// returned by [`sort_storage_access_queries`].
+pub fn sort_storage_access_queries_streaming(
+    unsorted_storage_queries: impl IntoIterator<Item = LogQuery>,
+    config: &ExternalSortConfig,
+    mut sorted_queries_sink: impl FnMut(LogQueryWithExtendedEnumeration),
+    mut deduplicated_queries_sink: impl FnMut(LogQuery),
+) -> std::io::Result<()> {
+    assert!(config.max_queries_in_memory > 0);
+    assert!(config.max_runs_per_merge > 1);
+
+    let spill_dir = tempfile::Builder::new()
+        .prefix("storage_sort")
+        .tempdir_in(&config.temp_dir)?;
+    let mut spilled_runs_count = 0;
+    let mut next_run_path = || {
+        spilled_runs_count += 1;
+        spill_dir
+            .path()
+            .join(format!("run_{}", spilled_runs_count - 1))
+    };
+
+    let mut spilled_runs = VecDeque::new();
+    let mut current_run = Vec::with_capacity(config.max_queries_in_memory);
+    for (i, el) in unsorted_storage_queries.into_iter().enumerate() {
+        current_run.push(LogQueryWithExtendedEnumeration {
+            raw_query: el,
+            extended_timestamp: i as u32,
+        });
+
+        if current_run.len() == config.max_queries_in_memory {
+            current_run.par_sort_by(compare_storage_queries);
+            let mut writer = SpilledRunWriter::create(next_run_path())?;
+            for query in current_run.iter() {
+                writer.write(query)?;
+            }
+            spilled_runs.push_back(writer.finish()?);
+            current_run.clear();
+        }
+    }
+
+    // the last run is never spilled and takes part in the final merge
+    while spilled_runs.len() + 1 > config.max_runs_per_merge {
+        let merged_runs = spilled_runs
+            .drain(..config.max_runs_per_merge)
+            .collect::<Vec<_>>();
+        let mut writer = SpilledRunWriter::create(next_run_path())?;
+        merge_sorted_runs(
+            merged_runs
+                .iter()
+                .map(SpilledRun::open)
+                .collect::<std::io::Result<_>>()?,
+            |query| writer.write(&query),
+        )?;
+        spilled_runs.push_back(writer.finish()?);
+        for run in merged_runs.into_iter() {
+            std::fs::remove_file(run.path)?;
+        }
+    }
+
+    current_run.par_sort_by(compare_storage_queries);
+    let mut runs = spilled_runs
+        .iter()
+        .map(SpilledRun::open)
+        .collect::<std::io::Result<Vec<_>>>()?;
+    runs.push(SortedRun::InMemory(current_run.into_iter()));
+
+    let mut current_slot: Option<(LogQueryWithExtendedEnumeration, StorageSlotHistoryKeeper)> =
+        None;
+    merge_sorted_runs(runs, |query| {
+        match current_slot.as_mut() {
+            Some((candidate, history))
+                if query.raw_query.shard_id == candidate.raw_query.shard_id
+                    && query.raw_query.address == candidate.raw_query.address
+                    && query.raw_query.key == candidate.raw_query.key =>
+            {
+                history.apply_query(&query);
+            }
+            _ => {
+                if let Some((candidate, history)) = current_slot.take() {
+                    if let Some(sorted_log_query) = history.into_deduplicated_query(&candidate) {
+                        deduplicated_queries_sink(sorted_log_query);
+                    }
+                }
+                let mut history = StorageSlotHistoryKeeper::default();
+                history.apply_query(&query);
+                current_slot = Some((query.clone(), history));
+            }
+        }
+
+        sorted_queries_sink(query);
+        Ok(())
+    })?;
+
+    if let Some((candidate, history)) = current_slot.take() {
+        if let Some(sorted_log_query) = history.into_deduplicated_query(&candidate) {
+            deduplicated_queries_sink(sorted_log_query);
+        }
+    }
+
+    spill_dir.close()
+}
+
+/// k-way merge of the sorted runs, the queries are passed to the sink in the sorted order
+fn merge_sorted_runs(
+    mut runs: Vec<SortedRun>,
+    mut sink: impl FnMut(LogQueryWithExtendedEnumeration) -> std::io::Result<()>,
+) -> std::io::Result<()> {
+    let mut merge_heap = BinaryHeap::with_capacity(runs.len());
+    for (run_idx, run) in runs.iter_mut().enumerate() {
+        if let Some(query) = run.next()? {
+            merge_heap.push(MergeCandidate { query, run_idx });
+        }
+    }
+
+    while let Some(MergeCandidate { query, run_idx }) = merge_heap.pop() {
+        if let Some(next) = runs[run_idx].next()? {
+            merge_heap.push(MergeCandidate {
+                query: next,
+                run_idx,
+            });
+        }
+        sink(query)?;
+    }
+
+    Ok(())
+}
+
+/// Sorted run on disk, it's opened only for the merge
+struct SpilledRun {
+    path: PathBuf,
+    len: usize,
+}
+
+impl SpilledRun {
+    fn open(&self) -> std::io::Result<SortedRun> {
+        Ok(SortedRun::Spilled {
+            reader: BufReader::new(File::open(&self.path)?),
+            remaining: self.len,
+        })
+    }
+}
+
+struct SpilledRunWriter {
+    writer: BufWriter<File>,
+    path: PathBuf,
+    len: usize,
+}
+
+impl SpilledRunWriter {
+    fn create(path: PathBuf) -> std::io::Result<Self> {
+        Ok(Self {
+            writer: BufWriter::new(File::create(&path)?),
+            path,
+            len: 0,
+        })
+    }
+
+    fn write(&mut self, query: &LogQueryWithExtendedEnumeration) -> std::io::Result<()> {
+        self.len += 1;
+        self.writer.write_all(&encode_spilled_query(query))
+    }
+
+    fn finish(mut self) -> std::io::Result<SpilledRun> {
+        self.writer.flush()?;
+
+        Ok(SpilledRun {
+            path: self.path,
+            len: self.len,
+        })
+    }
+}
+
+enum SortedRun {
+    InMemory(std::vec::IntoIter<LogQueryWithExtendedEnumeration>),
+    Spilled {
+        reader: BufReader<File>,
+        remaining: usize,
+    },
+}
+
+// timestamp || tx number in block || aux byte || shard id || address || key || read value ||
+// written value || flags || extended timestamp
+const SPILLED_QUERY_ENCODING_LEN: usize = 4 + 2 + 1 + 1 + 20 + 32 * 3 + 1 + 4;
+
+impl SortedRun {
+    fn next(&mut self) -> std::io::Result<Option<LogQueryWithExtendedEnumeration>> {
+        match self {
+            Self::InMemory(it) => Ok(it.next()),
+            Self::Spilled { reader, remaining } => {
+                if *remaining == 0 {
+                    return Ok(None);
+                }
+                *remaining -= 1;
+                let mut buffer = [0u8; SPILLED_QUERY_ENCODING_LEN];
+                reader.read_exact(&mut buffer)?;
+
+                Ok(Some(decode_spilled_query(&buffer)))
+            }
+        }
+    }
+}
+
+fn encode_spilled_query(
+    query: &LogQueryWithExtendedEnumeration,
+) -> [u8; SPILLED_QUERY_ENCODING_LEN] {
+    let LogQuery {
+        timestamp,
+        tx_number_in_block,
+        aux_byte,
+        shard_id,
+        address,
+        key,
+        read_value,
+        written_value,
+        rw_flag,
+        rollback,
+        is_service,
+    } = query.raw_query;
+
+    let mut encoding = [0u8; SPILLED_QUERY_ENCODING_LEN];
+    encoding[0..4].copy_from_slice(&timestamp.0.to_le_bytes());
+    encoding[4..6].copy_from_slice(&tx_number_in_block.to_le_bytes());
+    encoding[6] = aux_byte;
+    encoding[7] = shard_id;
+    encoding[8..28].copy_from_slice(address.as_bytes());
+    key.to_big_endian(&mut encoding[28..60]);
+    read_value.to_big_endian(&mut encoding[60..92]);
+    written_value.to_big_endian(&mut encoding[92..124]);
+    encoding[124] = rw_flag as u8 | (rollback as u8) << 1 | (is_service as u8) << 2;
+    encoding[125..129].copy_from_slice(&query.extended_timestamp.to_le_bytes());
+
+    encoding
+}
+
+fn decode_spilled_query(
+    encoding: &[u8; SPILLED_QUERY_ENCODING_LEN],
+) -> LogQueryWithExtendedEnumeration {
+    LogQueryWithExtendedEnumeration {
+        raw_query: LogQuery {
+            timestamp: Timestamp(u32::from_le_bytes(encoding[0..4].try_into().unwrap())),
+            tx_number_in_block: u16::from_le_bytes(encoding[4..6].try_into().unwrap()),
+            aux_byte: encoding[6],
+            shard_id: encoding[7],
+            address: H160::from_slice(&encoding[8..28]),
+            key: U256::from_big_endian(&encoding[28..60]),
+            read_value: U256::from_big_endian(&encoding[60..92]),
+            written_value: U256::from_big_endian(&encoding[92..124]),
+            rw_flag: encoding[124] & 1 != 0,
+            rollback: encoding[124] & 2 != 0,
+            is_service: encoding[124] & 4 != 0,
+        },
+        extended_timestamp: u32::from_le_bytes(encoding[125..129].try_into().unwrap()),
+    }
+}
+
+// `BinaryHeap` is a max-heap, so the ordering is reversed to pop the smallest query first
+struct MergeCandidate {
+    query: LogQueryWithExtendedEnumeration,
+    run_idx: usize,
+}
+
+impl PartialEq for MergeCandidate {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for MergeCandidate {}
+
+impl PartialOrd for MergeCandidate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for MergeCandidate {
+    fn cmp(&self, other: &Self) -> Ordering {
+        compare_storage_queries(&other.query, &self.query)
+    }
+}
+
 pub fn sort_transient_storage_access_queries(
     unsorted_storage_queries: impl IntoIterator<Item = LogQuery>,
 ) -> Vec<LogQueryWithExtendedEnumeration> {
@@ -308,3 +615,93 @@ fn create_partially_filled_from_fields(
         is_service: false,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{Rng, SeedableRng, XorShiftRng};
+    use std::collections::HashMap;
+
+    // produces a consistent history of reads, writes and rollbacks over a small set of slots
+    fn generate_storage_queries(rng: &mut XorShiftRng, num_frames: usize) -> Vec<LogQuery> {
+        let mut state = HashMap::new();
+        let mut queries = vec![];
+
+        for frame in 0..num_frames {
+            let mut frame_writes = vec![];
+            for _ in 0..rng.gen_range(1, 10) {
+                let shard_id = rng.gen_range(0, 2);
+                let address = H160::from_low_u64_be(rng.gen_range(0, 3));
+                let key = U256::from(rng.gen_range(0u64, 4));
+                let current_value = *state
+                    .get(&(shard_id, address, key))
+                    .unwrap_or(&U256::zero());
+
+                let mut query = create_partially_filled_from_fields(
+                    shard_id,
+                    address,
+                    key,
+                    current_value,
+                    current_value,
+                    false,
+                );
+                query.timestamp = Timestamp(queries.len() as u32);
+                query.tx_number_in_block = frame as u16;
+                if rng.gen() {
+                    query.rw_flag = true;
+                    query.written_value = U256::from(rng.gen_range(0u64, 4));
+                    state.insert((shard_id, address, key), query.written_value);
+                    frame_writes.push(query);
+                }
+                queries.push(query);
+            }
+
+            if rng.gen_weighted_bool(3) {
+                for mut query in frame_writes.into_iter().rev() {
+                    query.rollback = true;
+                    query.timestamp = Timestamp(queries.len() as u32);
+                    state.insert((query.shard_id, query.address, query.key), query.read_value);
+                    queries.push(query);
+                }
+            }
+        }
+
+        queries
+    }
+
+    #[test]
+    fn streaming_sort_matches_in_memory_sort() {
+        let mut rng = XorShiftRng::from_seed([0x1234, 0x5678, 0x9abc, 0xdef0]);
+        let queries = generate_storage_queries(&mut rng, 500);
+
+        let (expected_sorted, expected_deduplicated) =
+            sort_storage_access_queries(queries.iter().copied());
+
+        // from many small runs merged in several passes to a single run in memory
+        for (max_queries_in_memory, max_runs_per_merge) in
+            [(64, 4), (100, 8), (500, 64), (queries.len() + 1, 64)]
+        {
+            let config = ExternalSortConfig {
+                temp_dir: std::env::temp_dir(),
+                max_queries_in_memory,
+                max_runs_per_merge,
+            };
+            let mut sorted = vec![];
+            let mut deduplicated = vec![];
+            sort_storage_access_queries_streaming(
+                queries.iter().copied(),
+                &config,
+                |el| sorted.push(el),
+                |el| deduplicated.push(el),
+            )
+            .unwrap();
+
+            assert_eq!(sorted.len(), expected_sorted.len());
+            for (a, b) in sorted.iter().zip(expected_sorted.iter()) {
+                assert_eq!(a.raw_query, b.raw_query);
+                assert_eq!(a.extended_timestamp, b.extended_timestamp);
+            }
+            assert_eq!(deduplicated, expected_deduplicated);
+        }
+    }
+}
@@ -0,0 +1,425 @@
+//! Cell proofs for EIP-7594 (PeerDAS).
+//!
+//! Implementation based on https://github.com/ethereum/consensus-specs/blob/dev/specs/fulu/polynomial-commitments-sampling.md
+//! and the FK20 multiproof algorithm from https://eprint.iacr.org/2023/033.pdf.
+use boojum::pairing::{
+    bls12_381::{
+        fq12::Fq12,
+        fr::{Fr, FrRepr},
+        Bls12, G1Affine, G2Affine, G1,
+    },
+    ff::{Field, PrimeField, PrimeFieldRepr},
+    CurveAffine, CurveProjective, Engine,
+};
+use rayon::prelude::*;
+use zkevm_circuits::eip_4844::{bitreverse, ifft};
+
+use crate::{hash_to_bls_field, multiscalar_mul, KzgSettings, FIELD_ELEMENTS_PER_BLOB};
+
+/// Number of field elements in a single cell.
+pub const FIELD_ELEMENTS_PER_CELL: usize = 64;
+/// Number of evaluations of the blob polynomial on the extended (2x) domain.
+pub(crate) const FIELD_ELEMENTS_PER_EXT_BLOB: usize = 2 * FIELD_ELEMENTS_PER_BLOB;
+/// Number of cells the original blob is split into.
+pub(crate) const CELLS_PER_BLOB: usize = FIELD_ELEMENTS_PER_BLOB / FIELD_ELEMENTS_PER_CELL;
+/// Number of cells (and cell proofs) per blob on the extended domain.
+pub const CELLS_PER_EXT_BLOB: usize = FIELD_ELEMENTS_PER_EXT_BLOB / FIELD_ELEMENTS_PER_CELL;
+
+const RANDOM_CHALLENGE_DOMAIN_VERIFY_CELL_KZG_PROOF_BATCH: &[u8; 16] = b"RCKZGCBATCH__V1_";
+
+// 32731401973776920074999878620293785439674386180695720638377027142500196583783
+// 2^13 root of unity for BLS12-381, its square is the 2^12 root used for the blobs
+const EXT_ROOT_OF_UNITY: FrRepr = FrRepr([
+    0x6fdd00bfc78c8967,
+    0x146b58bc434906ac,
+    0x2ccddea2972e89ed,
+    0x485d512737b1da3d,
+]);
+
+/// Evaluations of the blob polynomial on one coset of the extended domain.
+pub type Cell = [Fr; FIELD_ELEMENTS_PER_CELL];
+
+/// Elements that can be transformed by the radix-2 FFT over the scalar field.
+trait FftElement: Copy {
+    fn butterfly(low: &mut Self, high: &mut Self, twiddle: &Fr);
+}
+
+impl FftElement for Fr {
+    fn butterfly(low: &mut Self, high: &mut Self, twiddle: &Fr) {
+        high.mul_assign(twiddle);
+        let mut neg = *low;
+        neg.sub_assign(high);
+        low.add_assign(high);
+        *high = neg;
+    }
+}
+
+impl FftElement for G1 {
+    fn butterfly(low: &mut Self, high: &mut Self, twiddle: &Fr) {
+        high.mul_assign(*twiddle);
+        let mut neg = *low;
+        neg.sub_assign(high);
+        low.add_assign(high);
+        *high = neg;
+    }
+}
+
+/// Returns the primitive `n`-th root of unity, consistent with the one used for the blobs.
+pub(crate) fn root_of_unity(n: usize) -> Fr {
+    assert!(n.is_power_of_two() && n <= FIELD_ELEMENTS_PER_EXT_BLOB);
+    Fr::from_repr(EXT_ROOT_OF_UNITY)
+        .unwrap()
+        .pow([(FIELD_ELEMENTS_PER_EXT_BLOB / n) as u64])
+}
+
+fn compute_powers(base: &Fr, count: usize) -> Vec<Fr> {
+    let mut current = Fr::one();
+    (0..count)
+        .map(|_| {
+            let power = current;
+            current.mul_assign(base);
+            power
+        })
+        .collect()
+}
+
+fn fr_from_u64(value: u64) -> Fr {
+    Fr::from_repr(FrRepr([value, 0, 0, 0])).unwrap()
+}
+
+// reverse bit order of given number assuming an order of `n`
+fn reverse_bits_limited(n: usize, value: usize) -> usize {
+    value.reverse_bits() >> (usize::BITS - n.trailing_zeros())
+}
+
+/// In-place radix-2 FFT, `root` must be a primitive root of unity of order `values.len()`.
+/// The inverse transform is obtained by passing the inverse root, without the `1/n` scaling.
+fn fft_in_place<T: FftElement>(values: &mut [T], root: &Fr) {
+    let n = values.len();
+    assert!(n.is_power_of_two());
+    let twiddles = compute_powers(root, n / 2);
+
+    bitreverse(values);
+
+    let mut split = 1;
+    while split < n {
+        values.chunks_mut(split * 2).for_each(|chunk| {
+            let (low, high) = chunk.split_at_mut(split);
+            low.iter_mut()
+                .zip(high)
+                .zip(twiddles.iter().step_by(n / (split * 2)))
+                .for_each(|((low, high), twiddle)| T::butterfly(low, high, twiddle));
+        });
+
+        split *= 2;
+    }
+}
+
+/// Coset shift `h_k` of the cell with the given index.
+fn coset_shift_for_cell(cell_index: usize) -> Fr {
+    let cell_index_rbl = reverse_bits_limited(CELLS_PER_EXT_BLOB, cell_index);
+    root_of_unity(FIELD_ELEMENTS_PER_EXT_BLOB).pow([cell_index_rbl as u64])
+}
+
+impl KzgSettings {
+    fn fk20_columns(&self) -> &[Vec<G1Affine>] {
+        self.fk20_columns
+            .get_or_init(|| compute_fk20_columns(self.setup_g1_monomial.as_slice()))
+    }
+}
+
+/// Precomputes the FFTs of the extended setup vectors `s_i` used by the Toeplitz matrix
+/// multiplication in FK20, transposed into columns.
+fn compute_fk20_columns(setup_g1_monomial: &[G1Affine]) -> Vec<Vec<G1Affine>> {
+    // the circulant matrix is twice the size of the Toeplitz one
+    let circulant_domain_size = 2 * CELLS_PER_BLOB;
+    let root = root_of_unity(circulant_domain_size);
+
+    let rows = (0..FIELD_ELEMENTS_PER_CELL)
+        .into_par_iter()
+        .map(|offset| {
+            let start = FIELD_ELEMENTS_PER_BLOB - FIELD_ELEMENTS_PER_CELL - 1 - offset;
+            let mut x_ext = vec![G1::zero(); circulant_domain_size];
+            x_ext
+                .iter_mut()
+                .take(CELLS_PER_BLOB - 1)
+                .enumerate()
+                .for_each(|(i, point)| {
+                    *point =
+                        setup_g1_monomial[start - i * FIELD_ELEMENTS_PER_CELL].into_projective()
+                });
+            fft_in_place(&mut x_ext, &root);
+            x_ext
+        })
+        .collect::<Vec<Vec<G1>>>();
+
+    let mut columns = (0..circulant_domain_size)
+        .map(|_| Vec::with_capacity(FIELD_ELEMENTS_PER_CELL))
+        .collect::<Vec<_>>();
+    for x_ext in rows {
+        for (column, point) in columns.iter_mut().zip(x_ext) {
+            column.push(point.into_affine());
+        }
+    }
+
+    columns
+}
+
+/// Selects the first column of the `offset`-th circulant matrix out of the polynomial coefficients.
+fn circulant_coeffs_stride(poly: &[Fr], offset: usize) -> Vec<Fr> {
+    let r = CELLS_PER_BLOB;
+    let l = FIELD_ELEMENTS_PER_CELL;
+    let d_minus_i = FIELD_ELEMENTS_PER_BLOB - 1 - offset;
+
+    let mut out = vec![Fr::zero(); 2 * r];
+    out[0] = poly[d_minus_i];
+    (1..r - 1).for_each(|j| out[2 * r - j] = poly[d_minus_i - j * l]);
+    out
+}
+
+/// Computes the cell proofs for a polynomial in monomial form with FK20. The proofs are returned in
+/// natural order of the cosets.
+fn compute_fk20_cell_proofs(settings: &KzgSettings, poly: &[Fr]) -> Vec<G1> {
+    let circulant_domain_size = 2 * CELLS_PER_BLOB;
+    let root = root_of_unity(circulant_domain_size);
+
+    // we prescale the scalars by 1/n so that the inverse group FFT below doesn't have to
+    let inv_domain_size = fr_from_u64(circulant_domain_size as u64).inverse().unwrap();
+    let mut coeffs = vec![vec![Fr::zero(); FIELD_ELEMENTS_PER_CELL]; circulant_domain_size];
+    for offset in 0..FIELD_ELEMENTS_PER_CELL {
+        let mut circulant_coeffs = circulant_coeffs_stride(poly, offset);
+        fft_in_place(&mut circulant_coeffs, &root);
+        for (row, mut coeff) in coeffs.iter_mut().zip(circulant_coeffs) {
+            coeff.mul_assign(&inv_domain_size);
+            row[offset] = coeff;
+        }
+    }
+
+    let mut v = settings
+        .fk20_columns()
+        .par_iter()
+        .zip(coeffs.par_iter())
+        .map(|(points, scalars)| multiscalar_mul(points, scalars).into_projective())
+        .collect::<Vec<G1>>();
+    fft_in_place(&mut v, &root.inverse().unwrap());
+
+    // the upper half are commitments to zero coefficients, which makes `v` of degree r - 1
+    v.truncate(CELLS_PER_BLOB);
+    v.resize(CELLS_PER_EXT_BLOB, G1::zero());
+    fft_in_place(&mut v, &root_of_unity(CELLS_PER_EXT_BLOB));
+
+    v
+}
+
+/// Computes the cells of the extended blob and a KZG multiproof for each of them.
+///
+/// The blob is expected in the same (bit-reversed evaluation) form as for `compute_commitment`.
+/// Both the cells and the proofs are returned in bit-reversed order, as expected by EIP-7594.
+pub fn compute_cells_and_kzg_proofs(
+    settings: &KzgSettings,
+    blob: &[Fr],
+) -> (Vec<Cell>, Vec<G1Affine>) {
+    assert_eq!(blob.len(), FIELD_ELEMENTS_PER_BLOB);
+
+    let mut poly = blob.to_vec();
+    bitreverse(&mut poly);
+    ifft(&mut poly);
+
+    let mut evaluations = poly.clone();
+    evaluations.resize(FIELD_ELEMENTS_PER_EXT_BLOB, Fr::zero());
+    fft_in_place(
+        &mut evaluations,
+        &root_of_unity(FIELD_ELEMENTS_PER_EXT_BLOB),
+    );
+    bitreverse(&mut evaluations);
+    let cells = evaluations
+        .chunks(FIELD_ELEMENTS_PER_CELL)
+        .map(|cell| Cell::try_from(cell).unwrap())
+        .collect::<Vec<Cell>>();
+
+    let mut proofs = compute_fk20_cell_proofs(settings, &poly);
+    bitreverse(&mut proofs);
+    let proofs = proofs
+        .into_iter()
+        .map(|proof| proof.into_affine())
+        .collect::<Vec<G1Affine>>();
+
+    (cells, proofs)
+}
+
+fn compute_verify_cell_kzg_proof_batch_challenge(
+    commitments: &[G1Affine],
+    commitment_indices: &[u64],
+    cell_indices: &[u64],
+    cells: &[Cell],
+    proofs: &[G1Affine],
+) -> Fr {
+    let mut data = RANDOM_CHALLENGE_DOMAIN_VERIFY_CELL_KZG_PROOF_BATCH.to_vec();
+    data.extend((FIELD_ELEMENTS_PER_BLOB as u64).to_be_bytes());
+    data.extend((FIELD_ELEMENTS_PER_CELL as u64).to_be_bytes());
+    data.extend((commitments.len() as u64).to_be_bytes());
+    data.extend((cells.len() as u64).to_be_bytes());
+    commitments
+        .iter()
+        .for_each(|commitment| data.extend(commitment.into_compressed().as_ref()));
+    commitment_indices
+        .iter()
+        .zip(cell_indices)
+        .zip(cells.iter().zip(proofs))
+        .for_each(|((commitment_index, cell_index), (cell, proof))| {
+            data.extend(commitment_index.to_be_bytes());
+            data.extend(cell_index.to_be_bytes());
+            cell.iter().for_each(|el| {
+                el.into_repr()
+                    .write_be(&mut data)
+                    .expect("should be able to write to data vector");
+            });
+            data.extend(proof.into_compressed().as_ref());
+        });
+
+    hash_to_bls_field(&data)
+}
+
+/// Aggregates the cells of every column with the powers of `r`, interpolates them over their cosets
+/// and commits to the sum of the interpolation polynomials.
+fn compute_commitment_to_aggregated_interpolation_poly(
+    settings: &KzgSettings,
+    r_powers: &[Fr],
+    cell_indices: &[u64],
+    cells: &[Cell],
+) -> G1Affine {
+    let mut aggregated_columns: Vec<Option<Cell>> = vec![None; CELLS_PER_EXT_BLOB];
+    cells
+        .iter()
+        .zip(cell_indices)
+        .zip(r_powers)
+        .for_each(|((cell, cell_index), r_power)| {
+            let column = aggregated_columns[*cell_index as usize]
+                .get_or_insert([Fr::zero(); FIELD_ELEMENTS_PER_CELL]);
+            column.iter_mut().zip(cell).for_each(|(acc, el)| {
+                let mut el = *el;
+                el.mul_assign(r_power);
+                acc.add_assign(&el);
+            });
+        });
+
+    let inv_root = root_of_unity(FIELD_ELEMENTS_PER_CELL).inverse().unwrap();
+    let inv_width = fr_from_u64(FIELD_ELEMENTS_PER_CELL as u64)
+        .inverse()
+        .unwrap();
+    let mut aggregated_poly = [Fr::zero(); FIELD_ELEMENTS_PER_CELL];
+    aggregated_columns
+        .into_iter()
+        .enumerate()
+        .filter_map(|(cell_index, column)| column.map(|column| (cell_index, column)))
+        .for_each(|(cell_index, mut column)| {
+            // interpolate over the roots of unity and then shift the result onto the coset, since
+            // we can't do an inverse FFT over the coset directly
+            bitreverse(&mut column);
+            fft_in_place(&mut column, &inv_root);
+            let inv_shift = coset_shift_for_cell(cell_index).inverse().unwrap();
+            let mut factor = inv_width;
+            aggregated_poly
+                .iter_mut()
+                .zip(column)
+                .for_each(|(acc, mut coeff)| {
+                    coeff.mul_assign(&factor);
+                    acc.add_assign(&coeff);
+                    factor.mul_assign(&inv_shift);
+                });
+        });
+
+    multiscalar_mul(
+        &settings.setup_g1_monomial[..FIELD_ELEMENTS_PER_CELL],
+        &aggregated_poly,
+    )
+}
+
+/// Verifies a batch of cells against their commitments and cell proofs. Every cell is given together
+/// with the commitment of the blob it belongs to and its index in the extended blob.
+/// Batches with inputs of different lengths or with cell indices out of the extended blob are not valid.
+pub fn verify_cell_kzg_proof_batch(
+    settings: &KzgSettings,
+    commitments: &[G1Affine],
+    cell_indices: &[u64],
+    cells: &[Cell],
+    proofs: &[G1Affine],
+) -> bool {
+    if commitments.len() != cells.len()
+        || cell_indices.len() != cells.len()
+        || proofs.len() != cells.len()
+    {
+        return false;
+    }
+    if cell_indices
+        .iter()
+        .any(|index| *index >= CELLS_PER_EXT_BLOB as u64)
+    {
+        return false;
+    }
+
+    if cells.is_empty() {
+        return true;
+    }
+
+    let mut unique_commitments = Vec::with_capacity(commitments.len());
+    let commitment_indices = commitments
+        .iter()
+        .map(|commitment| {
+            let index = unique_commitments
+                .iter()
+                .position(|unique| unique == commitment)
+                .unwrap_or_else(|| {
+                    unique_commitments.push(*commitment);
+                    unique_commitments.len() - 1
+                });
+            index as u64
+        })
+        .collect::<Vec<u64>>();
+
+    let r = compute_verify_cell_kzg_proof_batch_challenge(
+        &unique_commitments,
+        &commitment_indices,
+        cell_indices,
+        cells,
+        proofs,
+    );
+    let r_powers = compute_powers(&r, cells.len());
+
+    let proof_lincomb = multiscalar_mul(proofs, &r_powers);
+
+    let mut commitment_weights = vec![Fr::zero(); unique_commitments.len()];
+    commitment_indices
+        .iter()
+        .zip(r_powers.iter())
+        .for_each(|(index, r_power)| commitment_weights[*index as usize].add_assign(r_power));
+    let mut final_g1_sum =
+        multiscalar_mul(&unique_commitments, &commitment_weights).into_projective();
+
+    let interpolation_poly_commitment = compute_commitment_to_aggregated_interpolation_poly(
+        settings,
+        &r_powers,
+        cell_indices,
+        cells,
+    );
+    final_g1_sum.sub_assign(&interpolation_poly_commitment.into_projective());
+
+    let weighted_r_powers = r_powers
+        .iter()
+        .zip(cell_indices)
+        .map(|(r_power, cell_index)| {
+            let mut weight =
+                coset_shift_for_cell(*cell_index as usize).pow([FIELD_ELEMENTS_PER_CELL as u64]);
+            weight.mul_assign(r_power);
+            weight
+        })
+        .collect::<Vec<Fr>>();
+    final_g1_sum.add_assign(&multiscalar_mul(proofs, &weighted_r_powers).into_projective());
+
+    let mut g2_neg = G2Affine::one().into_projective();
+    g2_neg.negate();
+
+    let mut p1 = Bls12::pairing(final_g1_sum, g2_neg);
+    p1.mul_assign(&Bls12::pairing(proof_lincomb, settings.setup_g2_64));
+    p1 == Fq12::one()
+}
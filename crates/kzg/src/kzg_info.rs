@@ -2,7 +2,10 @@ use std::convert::TryInto;
 
 use crate::trusted_setup::KZG_SETTINGS;
 
-use super::{compute_commitment, compute_proof, compute_proof_poly};
+use super::{
+    compute_cells_and_kzg_proofs, compute_commitment, compute_proof, compute_proof_poly,
+    CELLS_PER_EXT_BLOB,
+};
 use zkevm_circuits::{
     boojum::pairing::{
         bls12_381::{Fr, FrRepr, G1Affine},
@@ -10,7 +13,7 @@ use zkevm_circuits::{
         CurveAffine,
     },
     eip_4844::{
-        bitreverse, fft,
+        bitreverse, ethereum_4844_pubdata_into_bitreversed_lagrange_form_poly, fft,
         input::{BLOB_CHUNK_SIZE, ELEMENTS_PER_4844_BLOCK},
         zksync_pubdata_into_ethereum_4844_data, zksync_pubdata_into_monomial_form_poly,
     },
@@ -263,6 +266,77 @@ impl KzgInfo {
     }
 }
 
+/// `KzgInfo` extended with the EIP-7594 cell proofs. After PeerDAS the blob sidecar carries a proof for
+/// every cell of the extended blob instead of the single blob proof.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct KzgInfoWithCellProofs {
+    /// All the info needed for `commitBatches`, same as without cell proofs
+    pub kzg_info: KzgInfo,
+    /// Proofs for all the cells of the extended blob, in the order expected by the sidecar
+    pub cell_proofs: [[u8; 48]; CELLS_PER_EXT_BLOB],
+}
+
+impl KzgInfoWithCellProofs {
+    /// Size of `KzgInfoWithCellProofs` is equal to size(`KzgInfo`) + `CELLS_PER_EXT_BLOB` * size(`kzg_proof`)
+    pub const SERIALIZED_SIZE: usize = KzgInfo::SERIALIZED_SIZE + CELLS_PER_EXT_BLOB * 48;
+
+    /// Deserializes `Self::SERIALIZED_SIZE` bytes into `KzgInfoWithCellProofs` struct
+    pub fn from_slice(data: &[u8]) -> Self {
+        assert_eq!(data.len(), Self::SERIALIZED_SIZE);
+
+        let (kzg_info, mut data) = data.split_at(KzgInfo::SERIALIZED_SIZE);
+        let kzg_info = KzgInfo::from_slice(kzg_info);
+
+        let mut cell_proofs = [[0u8; 48]; CELLS_PER_EXT_BLOB];
+        for cell_proof in cell_proofs.iter_mut() {
+            data = copy_n_bytes_return_rest(cell_proof, data, 48);
+        }
+
+        assert_eq!(data.len(), 0);
+
+        Self {
+            kzg_info,
+            cell_proofs,
+        }
+    }
+
+    /// Converts `KzgInfoWithCellProofs` struct into a byte array
+    pub fn to_bytes(&self) -> [u8; Self::SERIALIZED_SIZE] {
+        let mut res = [0u8; Self::SERIALIZED_SIZE];
+
+        res[..KzgInfo::SERIALIZED_SIZE].copy_from_slice(&self.kzg_info.to_bytes());
+        let mut ptr = KzgInfo::SERIALIZED_SIZE;
+
+        for cell_proof in self.cell_proofs.iter() {
+            res[ptr..ptr + 48].copy_from_slice(cell_proof.as_slice());
+            ptr += 48;
+        }
+
+        assert_eq!(ptr, Self::SERIALIZED_SIZE);
+
+        res
+    }
+
+    /// Construct all the KZG info for a piece of zksync pubdata, same as `KzgInfo::new`, and additionally
+    /// compute the cell proofs of the 4844 blob with `compute_cells_and_kzg_proofs`.
+    pub fn new(pubdata: &[u8]) -> Self {
+        let kzg_info = KzgInfo::new(pubdata);
+
+        let poly = ethereum_4844_pubdata_into_bitreversed_lagrange_form_poly(&kzg_info.blob);
+        let (_, proofs) = compute_cells_and_kzg_proofs(&KZG_SETTINGS, &poly);
+
+        let mut cell_proofs = [[0u8; 48]; CELLS_PER_EXT_BLOB];
+        for (cell_proof, proof) in cell_proofs.iter_mut().zip(proofs) {
+            cell_proof.copy_from_slice(proof.into_compressed().as_ref());
+        }
+
+        Self {
+            kzg_info,
+            cell_proofs,
+        }
+    }
+}
+
 pub fn pubdata_to_blob_commitments(num_blobs: usize, pubdata_input: &[u8]) -> Vec<H256> {
     assert!(
         pubdata_input.len() <= num_blobs * ZK_SYNC_BYTES_PER_BLOB,
@@ -15,6 +15,7 @@ use boojum::{
     sha2::Sha256,
 };
 
+use once_cell::sync::OnceCell;
 use rayon::prelude::*;
 
 // These are the 3 things that are exposed to the public and used by sequencer.
@@ -22,6 +23,14 @@ pub use kzg_info::pubdata_to_blob_commitments;
 pub use kzg_info::KzgInfo;
 pub use kzg_info::ZK_SYNC_BYTES_PER_BLOB;
 
+// EIP-7594 (PeerDAS) cell proofs.
+pub use eip7594::{
+    compute_cells_and_kzg_proofs, verify_cell_kzg_proof_batch, Cell, CELLS_PER_EXT_BLOB,
+    FIELD_ELEMENTS_PER_CELL,
+};
+pub use kzg_info::KzgInfoWithCellProofs;
+
+mod eip7594;
 mod kzg_info;
 #[cfg(test)]
 mod tests;
@@ -36,7 +45,13 @@ pub struct TrustedSetup {
 pub struct KzgSettings {
     pub roots_of_unity_brp: Box<[Fr; FIELD_ELEMENTS_PER_BLOB]>,
     pub setup_g2_1: G2,
+    /// `[tau^FIELD_ELEMENTS_PER_CELL]G2`, needed to verify cell proofs.
+    pub setup_g2_64: G2,
+    pub setup_g1_monomial: Box<[G1Affine; FIELD_ELEMENTS_PER_BLOB]>,
     pub lagrange_setup_brp: Box<[G1Affine; FIELD_ELEMENTS_PER_BLOB]>,
+    /// FFTs of the shifted monomial setup used by FK20, computed on the first cell proof request
+    /// since most users never need them.
+    fk20_columns: OnceCell<Vec<Vec<G1Affine>>>,
 }
 
 impl KzgSettings {
@@ -75,26 +90,29 @@ impl KzgSettings {
             reversed_roots
         };
 
-        let setup_g2_1 = {
-            let point = "b5bfd7dd8cdeb128843bc287230af38926187075cbfbefa81009a2ce615ac53d2914e5870cb452d2afaaab24f3499f72185cbfee53492714734429b7b38608e23926c911cceceac9a36851477ba4c60b087041de621000edc98edada20c1def2";
-            let bytes = hex_to_bytes(point);
-            let mut point = G2Compressed::empty();
-            let v = point.as_mut();
-            v.copy_from_slice(bytes.as_slice());
-            point.into_affine().unwrap().into_projective()
-        };
+        let setup_g2_1 = g2_from_hex("b5bfd7dd8cdeb128843bc287230af38926187075cbfbefa81009a2ce615ac53d2914e5870cb452d2afaaab24f3499f72185cbfee53492714734429b7b38608e23926c911cceceac9a36851477ba4c60b087041de621000edc98edada20c1def2");
+        let setup_g2_64 = g2_from_hex("92dcc5a1c8c3e1b28b1524e3dd6dbecd63017c9201da9dbe077f1b82adc08c50169f56fc7b5a3b28ec6b89254de3e2fd12838a761053437883c3e01ba616670cea843754548ef84bcc397de2369adcca2ab54cd73c55dc68d87aec3fc2fe4f10");
+
+        // the points in the setup file are the powers of tau in monomial form
+        let setup_g1_monomial: Box<[G1Affine; FIELD_ELEMENTS_PER_BLOB]> = setup
+            .g1_lagrange
+            .iter()
+            .map(|hex| {
+                let bytes = hex_to_bytes(&hex[2..]);
+                let mut point = G1Compressed::empty();
+                let v = point.as_mut();
+                v.copy_from_slice(bytes.as_slice());
+                point.into_affine().unwrap()
+            })
+            .collect::<Vec<G1Affine>>()
+            .into_boxed_slice()
+            .try_into()
+            .expect("trusted setup should contain 4096 G1 points");
 
         let lagrange_setup_brp = {
-            let mut base_setup: Vec<G1> = setup
-                .g1_lagrange
+            let mut base_setup: Vec<G1> = setup_g1_monomial
                 .iter()
-                .map(|hex| {
-                    let bytes = hex_to_bytes(&hex[2..]);
-                    let mut point = G1Compressed::empty();
-                    let v = point.as_mut();
-                    v.copy_from_slice(bytes.as_slice());
-                    point.into_affine().unwrap().into_projective()
-                })
+                .map(|point| point.into_projective())
                 .collect::<Vec<G1>>();
 
             // radix-2 ifft
@@ -156,10 +174,31 @@ impl KzgSettings {
             Box::new(lagrange_setup_brp)
         };
 
+        Self::from_parts(
+            roots_of_unity_brp,
+            setup_g2_1,
+            setup_g2_64,
+            setup_g1_monomial,
+            lagrange_setup_brp,
+        )
+    }
+
+    /// Settings from already processed setup values, the ones needed for the cell proofs are
+    /// computed lazily.
+    pub fn from_parts(
+        roots_of_unity_brp: Box<[Fr; FIELD_ELEMENTS_PER_BLOB]>,
+        setup_g2_1: G2,
+        setup_g2_64: G2,
+        setup_g1_monomial: Box<[G1Affine; FIELD_ELEMENTS_PER_BLOB]>,
+        lagrange_setup_brp: Box<[G1Affine; FIELD_ELEMENTS_PER_BLOB]>,
+    ) -> Self {
         Self {
             roots_of_unity_brp,
             setup_g2_1,
+            setup_g2_64,
+            setup_g1_monomial,
             lagrange_setup_brp,
+            fk20_columns: OnceCell::new(),
         }
     }
 }
@@ -193,6 +232,14 @@ fn hex_to_bytes(hex_string: &str) -> Vec<u8> {
         .collect::<Vec<u8>>()
 }
 
+fn g2_from_hex(hex_string: &str) -> G2 {
+    let bytes = hex_to_bytes(hex_string);
+    let mut point = G2Compressed::empty();
+    let v = point.as_mut();
+    v.copy_from_slice(bytes.as_slice());
+    point.into_affine().unwrap().into_projective()
+}
+
 /// Computes a KZG commitment to a EIP4844 blob.
 pub fn compute_commitment(settings: &KzgSettings, blob: &[Fr]) -> G1Affine {
     assert!(blob.len() <= FIELD_ELEMENTS_PER_BLOB);
@@ -378,6 +425,11 @@ fn compute_challenge(blob: &[Fr], commitment: &G1Affine) -> Fr {
     });
     data.extend(commitment.into_compressed().as_ref());
 
+    hash_to_bls_field(&data)
+}
+
+/// Hashes the data with sha256 and reduces the digest into the BLS scalar field.
+fn hash_to_bls_field(data: &[u8]) -> Fr {
     let mut result = [0u8; 32];
     let digest = Sha256::digest(data);
     result.copy_from_slice(&digest);
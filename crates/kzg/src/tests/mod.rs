@@ -1,17 +1,21 @@
 //! Tests for KZG commitments.
 
+use crate::eip7594::{root_of_unity, CELLS_PER_BLOB, FIELD_ELEMENTS_PER_EXT_BLOB};
 use crate::trusted_setup::KZG_SETTINGS;
 
 use super::{verify_kzg_proof, verify_proof_poly};
 use boojum::pairing::{bls12_381::G1Compressed, EncodedPoint};
 use serde::{Deserialize, Serialize};
 use zkevm_circuits::eip_4844::{
-    bitreverse, ethereum_4844_data_into_zksync_pubdata, fft, zksync_pubdata_into_monomial_form_poly,
+    bitreverse, ethereum_4844_data_into_zksync_pubdata,
+    ethereum_4844_pubdata_into_bitreversed_lagrange_form_poly, fft,
+    zksync_pubdata_into_monomial_form_poly,
 };
 
 use super::*;
 
 const KZG_TEST_JSON: &str = include_str!("kzg_test_0.json");
+const KZG_CELL_PROOFS_TEST_JSON: &str = include_str!("kzg_cell_proofs_test_0.json");
 
 #[serde_with::serde_as]
 #[derive(Debug, Serialize, Deserialize)]
@@ -40,6 +44,46 @@ struct KzgTest {
     expected_outputs: ExpectedOutputs,
 }
 
+/// Outputs of c-kzg 2.1.8 with the Ethereum trusted setup for the blob from `cell_proofs_test_blob`:
+/// commitment, the proofs of all cells and a few of the cells themselves.
+#[serde_with::serde_as]
+#[derive(Debug, Serialize, Deserialize)]
+struct KzgCellProofsTest {
+    #[serde_as(as = "serde_with::hex::Hex")]
+    commitment: Vec<u8>,
+    cell_indices: Vec<u64>,
+    #[serde_as(as = "Vec<serde_with::hex::Hex>")]
+    cells: Vec<Vec<u8>>,
+    #[serde_as(as = "Vec<serde_with::hex::Hex>")]
+    proofs: Vec<Vec<u8>>,
+}
+
+impl KzgCellProofsTest {
+    fn cells(&self) -> Vec<Cell> {
+        self.cells
+            .iter()
+            .map(|cell| {
+                let cell = cell.chunks(32).map(u8_repr_to_fr).collect::<Vec<Fr>>();
+                Cell::try_from(cell).unwrap()
+            })
+            .collect()
+    }
+
+    fn proofs_for_cells(&self) -> Vec<G1Affine> {
+        self.cell_indices
+            .iter()
+            .map(|index| bytes_to_g1(&self.proofs[*index as usize]))
+            .collect()
+    }
+}
+
+/// Blob with elements `i^3 + 7i + 1`, same as in the c-kzg generated test
+fn cell_proofs_test_blob() -> Vec<Fr> {
+    (0..FIELD_ELEMENTS_PER_BLOB as u64)
+        .map(|i| Fr::from_repr(FrRepr([i * i * i + 7 * i + 1, 0, 0, 0])).unwrap())
+        .collect()
+}
+
 /// Copy of function from https://github.com/matter-labs/era-zkevm_test_harness/blob/99956050a7705e26e0e5aa0729348896a27846c7/src/kzg/mod.rs#L339
 fn u8_repr_to_fr(bytes: &[u8]) -> Fr {
     assert_eq!(bytes.len(), 32);
@@ -135,3 +179,142 @@ fn bytes_test() {
     let decoded_kzg_info = KzgInfo::from_slice(&encoded_info);
     assert_eq!(kzg_info, decoded_kzg_info);
 }
+
+#[test]
+#[ignore = "slow in debug builds, cell proofs are covered by the known answer tests"]
+fn cell_proofs_test() {
+    let kzg_test: KzgTest = serde_json::from_str(KZG_TEST_JSON).unwrap();
+    let kzg_info = KzgInfoWithCellProofs::new(&kzg_test.pubdata);
+
+    // Cell proofs don't change the rest of the info
+    assert_eq!(kzg_info.kzg_info, KzgInfo::new(&kzg_test.pubdata));
+
+    let encoded_info = kzg_info.to_bytes();
+    assert_eq!(KzgInfoWithCellProofs::SERIALIZED_SIZE, encoded_info.len());
+    assert_eq!(kzg_info, KzgInfoWithCellProofs::from_slice(&encoded_info));
+
+    // Verify that all the cells of the blob match the commitment
+    let poly = ethereum_4844_pubdata_into_bitreversed_lagrange_form_poly(&kzg_info.kzg_info.blob);
+    let (cells, _) = compute_cells_and_kzg_proofs(&KZG_SETTINGS, &poly);
+
+    let commitment = bytes_to_g1(&kzg_info.kzg_info.kzg_commitment);
+    let commitments = vec![commitment; CELLS_PER_EXT_BLOB];
+    let cell_indices = (0..CELLS_PER_EXT_BLOB as u64).collect::<Vec<u64>>();
+    let proofs = kzg_info
+        .cell_proofs
+        .iter()
+        .map(|proof| bytes_to_g1(proof))
+        .collect::<Vec<G1Affine>>();
+
+    assert!(verify_cell_kzg_proof_batch(
+        &KZG_SETTINGS,
+        &commitments,
+        &cell_indices,
+        &cells,
+        &proofs
+    ));
+
+    let mut cells = cells;
+    cells[3][7].add_assign(&Fr::one());
+    assert!(!verify_cell_kzg_proof_batch(
+        &KZG_SETTINGS,
+        &commitments,
+        &cell_indices,
+        &cells,
+        &proofs
+    ));
+}
+
+#[test]
+fn extended_root_of_unity_test() {
+    let mut value = root_of_unity(FIELD_ELEMENTS_PER_EXT_BLOB);
+    value.square();
+    assert_eq!(
+        value,
+        KZG_SETTINGS.roots_of_unity_brp[FIELD_ELEMENTS_PER_BLOB / 2]
+    );
+}
+
+#[test]
+fn cell_proofs_known_answer_test() {
+    let kzg_test: KzgCellProofsTest = serde_json::from_str(KZG_CELL_PROOFS_TEST_JSON).unwrap();
+    let blob = cell_proofs_test_blob();
+
+    let commitment = compute_commitment(&KZG_SETTINGS, &blob);
+    assert_eq!(
+        hex::encode(commitment.into_compressed()),
+        hex::encode(&kzg_test.commitment)
+    );
+
+    let (cells, proofs) = compute_cells_and_kzg_proofs(&KZG_SETTINGS, &blob);
+    assert_eq!(cells.len(), CELLS_PER_EXT_BLOB);
+    assert_eq!(proofs.len(), CELLS_PER_EXT_BLOB);
+    // the first half of the extended blob in bit-reversed order is the blob itself
+    let first_half = cells[..CELLS_PER_BLOB]
+        .iter()
+        .flatten()
+        .copied()
+        .collect::<Vec<Fr>>();
+    assert_eq!(first_half, blob);
+
+    for (proof, expected) in proofs.iter().zip(kzg_test.proofs.iter()) {
+        assert_eq!(hex::encode(proof.into_compressed()), hex::encode(expected));
+    }
+    for (index, expected) in kzg_test.cell_indices.iter().zip(kzg_test.cells()) {
+        assert_eq!(cells[*index as usize], expected);
+    }
+}
+
+#[test]
+fn verify_cell_proofs_known_answer_test() {
+    let kzg_test: KzgCellProofsTest = serde_json::from_str(KZG_CELL_PROOFS_TEST_JSON).unwrap();
+    let commitments = vec![bytes_to_g1(&kzg_test.commitment); kzg_test.cell_indices.len()];
+    let cells = kzg_test.cells();
+    let proofs = kzg_test.proofs_for_cells();
+
+    assert!(verify_cell_kzg_proof_batch(
+        &KZG_SETTINGS,
+        &commitments,
+        &kzg_test.cell_indices,
+        &cells,
+        &proofs
+    ));
+
+    // proof of another cell
+    let mut wrong_proofs = proofs.clone();
+    wrong_proofs.swap(0, 1);
+    assert!(!verify_cell_kzg_proof_batch(
+        &KZG_SETTINGS,
+        &commitments,
+        &kzg_test.cell_indices,
+        &cells,
+        &wrong_proofs
+    ));
+
+    // inputs of different lengths
+    assert!(!verify_cell_kzg_proof_batch(
+        &KZG_SETTINGS,
+        &commitments[1..],
+        &kzg_test.cell_indices,
+        &cells,
+        &proofs
+    ));
+    assert!(!verify_cell_kzg_proof_batch(
+        &KZG_SETTINGS,
+        &commitments,
+        &kzg_test.cell_indices,
+        &cells,
+        &proofs[1..]
+    ));
+
+    // cell index out of the extended blob
+    let mut cell_indices = kzg_test.cell_indices.clone();
+    cell_indices[0] = CELLS_PER_EXT_BLOB as u64;
+    assert!(!verify_cell_kzg_proof_batch(
+        &KZG_SETTINGS,
+        &commitments,
+        &cell_indices,
+        &cells,
+        &proofs
+    ));
+}
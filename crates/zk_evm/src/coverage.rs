@@ -0,0 +1,126 @@
+//! Tracer that records the executed instructions and the outcomes of the conditional instructions
+//! (branches) per code address, to build instruction and branch coverage reports.
+
+use std::collections::{BTreeMap, HashMap};
+
+use crate::reference_impls::memory::SimpleMemory;
+use crate::tracing::*;
+use crate::vm_state::VmLocalState;
+use crate::zkevm_opcode_defs::decoding::{AllowedPcOrImm, VmEncodingMode};
+use crate::zkevm_opcode_defs::Condition;
+
+use super::*;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct BranchCoverage {
+    /// Number of times the condition was satisfied and the instruction was executed
+    pub taken: u64,
+    /// Number of times the instruction was skipped as a NOP
+    pub not_taken: u64,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ContractCoverage {
+    /// Number of times the instruction at the pc was decoded
+    pub hits: BTreeMap<u16, u64>,
+    /// Outcomes of the instructions with a condition other than `Always`
+    pub branches: BTreeMap<u16, BranchCoverage>,
+}
+
+/// Coverage tracer, keyed by the code address of the frame, so the delegate calls are attributed
+/// to the code that was executed. Cycles that handle a pending exception or happen after the end
+/// of the execution don't execute any instruction of the code and are not recorded.
+/// Can be passed as the out-of-circuit tracer to `run_vms`, or embedded into another tracer
+/// with `record_decoded_opcode`.
+#[derive(Clone, Debug, Default)]
+pub struct CoverageTracer {
+    contracts: HashMap<Address, ContractCoverage>,
+}
+
+impl CoverageTracer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Coverage of the code deployed at the address, `None` if it was never executed
+    pub fn contract(&self, address: &Address) -> Option<&ContractCoverage> {
+        self.contracts.get(address)
+    }
+
+    pub fn contracts(&self) -> impl Iterator<Item = (&Address, &ContractCoverage)> {
+        self.contracts.iter()
+    }
+
+    /// Should be called from `Tracer::after_decoding`
+    pub fn record_decoded_opcode<const N: usize, E: VmEncodingMode<N>>(
+        &mut self,
+        state: &VmLocalState<N, E>,
+        data: &AfterDecodingData<N, E>,
+    ) {
+        if state.pending_exception || state.execution_has_ended() {
+            return;
+        }
+
+        let frame = state.callstack.get_current_stack();
+        let pc = frame.pc.as_u64() as u16;
+        let coverage = self.contracts.entry(frame.code_address).or_default();
+        *coverage.hits.entry(pc).or_default() += 1;
+
+        // opcodes that failed the pre-execution checks are masked into a panic without resolving
+        // their own condition
+        if !data.error_flags_accumulated.is_empty() {
+            return;
+        }
+        let (unmasked, _) =
+            E::parse_preliminary_variant_and_absolute_number(data.raw_opcode_unmasked);
+        if unmasked.condition != Condition::Always {
+            let branch = coverage.branches.entry(pc).or_default();
+            if data.resolved_condition {
+                branch.taken += 1;
+            } else {
+                branch.not_taken += 1;
+            }
+        }
+    }
+}
+
+impl<const N: usize, E: VmEncodingMode<N>> Tracer<N, E> for CoverageTracer {
+    const CALL_BEFORE_DECODING: bool = false;
+    const CALL_AFTER_DECODING: bool = true;
+    const CALL_BEFORE_EXECUTION: bool = false;
+    const CALL_AFTER_EXECUTION: bool = false;
+
+    type SupportedMemory = SimpleMemory;
+
+    fn before_decoding(
+        &mut self,
+        _state: VmLocalStateData<'_, N, E>,
+        _memory: &Self::SupportedMemory,
+    ) {
+    }
+
+    fn after_decoding(
+        &mut self,
+        state: VmLocalStateData<'_, N, E>,
+        data: AfterDecodingData<N, E>,
+        _memory: &Self::SupportedMemory,
+    ) {
+        self.record_decoded_opcode(state.vm_local_state, &data);
+    }
+
+    fn before_execution(
+        &mut self,
+        _state: VmLocalStateData<'_, N, E>,
+        _data: BeforeExecutionData<N, E>,
+        _memory: &Self::SupportedMemory,
+    ) {
+    }
+
+    fn after_execution(
+        &mut self,
+        _state: VmLocalStateData<'_, N, E>,
+        _data: AfterExecutionData<N, E>,
+        _memory: &Self::SupportedMemory,
+    ) {
+    }
+}
@@ -0,0 +1,416 @@
+//! Step debugger over the `VmState`: stepping by cycle, near call or far call,
+//! breakpoints (pc, opcode, address, function label) and watchpoints (registers, memory, storage).
+
+use std::collections::HashMap;
+
+use crate::opcodes::DecodedOpcode;
+use crate::reference_impls::memory::SimpleMemory;
+use crate::tracing::*;
+use crate::vm_state::{CallStackEntry, PrimitiveValue, VmState};
+use crate::witness_trace::VmWitnessTracer;
+use crate::zkevm_opcode_defs::decoding::{EncodingModeProduction, VmEncodingMode};
+use crate::zkevm_opcode_defs::{FatPointer, LogOpcode, Opcode};
+use zk_evm_abstractions::aux::{MemoryIndex, MemoryLocation};
+use zk_evm_abstractions::vm::{
+    DecommittmentProcessor, EventSink, MemoryType, PrecompilesProcessor, Storage,
+};
+
+use super::*;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Breakpoint {
+    /// Before executing the instruction at pc, in any contract if address is not set
+    Pc { address: Option<Address>, pc: u16 },
+    /// Before executing the opcode, matched against the prefix of its debug representation,
+    /// e.g. `FarCall` matches all far calls, while `Log(StorageWrite)` only the storage writes
+    Opcode(String),
+    /// On entering the code of the address
+    Address(Address),
+    /// Before executing the first instruction after the label, labels have to be set with `set_function_labels`
+    Function { address: Address, label: String },
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Watchpoint {
+    /// Index of the register, from 1 to 15
+    Register(usize),
+    Memory(MemoryLocation),
+    /// Any read or write of the persistent storage slot
+    Storage {
+        address: Address,
+        key: U256,
+    },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WatchpointChange {
+    Value {
+        old: PrimitiveValue,
+        new: PrimitiveValue,
+    },
+    StorageRead,
+    StorageWrite(U256),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StepMode {
+    Cycle,
+    /// Until the next call or return (near or far)
+    NearCall,
+    /// Until the next far call or return from the far call
+    FarCall,
+    /// Until the breakpoint, watchpoint or the end of execution
+    Continue,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum StopReason {
+    /// Step of the requested mode is finished
+    Step,
+    Breakpoint(usize),
+    Watchpoint {
+        id: usize,
+        change: WatchpointChange,
+    },
+    ExecutionEnded,
+    CycleLimit,
+    Error(String),
+}
+
+#[derive(Clone, Copy, Debug)]
+struct StorageAccess {
+    address: Address,
+    key: U256,
+    written_value: Option<U256>,
+}
+
+/// Collects the storage accesses of the cycle, other watchpoints are checked by comparing the state
+#[derive(Clone, Debug, Default)]
+struct DebuggerTracer {
+    storage_accesses: Vec<StorageAccess>,
+}
+
+impl Tracer for DebuggerTracer {
+    const CALL_BEFORE_EXECUTION: bool = true;
+
+    type SupportedMemory = SimpleMemory;
+
+    fn before_decoding(&mut self, _state: VmLocalStateData<'_>, _memory: &Self::SupportedMemory) {}
+
+    fn after_decoding(
+        &mut self,
+        _state: VmLocalStateData<'_>,
+        _data: AfterDecodingData,
+        _memory: &Self::SupportedMemory,
+    ) {
+    }
+
+    fn before_execution(
+        &mut self,
+        state: VmLocalStateData<'_>,
+        data: BeforeExecutionData,
+        _memory: &Self::SupportedMemory,
+    ) {
+        let written_value = match data.opcode.variant.opcode {
+            Opcode::Log(LogOpcode::StorageRead) => None,
+            Opcode::Log(LogOpcode::StorageWrite) => Some(data.src1_value.value),
+            _ => return,
+        };
+        self.storage_accesses.push(StorageAccess {
+            address: state.vm_local_state.callstack.current.this_address,
+            key: data.src0_value.value,
+            written_value,
+        });
+    }
+
+    fn after_execution(
+        &mut self,
+        _state: VmLocalStateData<'_>,
+        _data: AfterExecutionData,
+        _memory: &Self::SupportedMemory,
+    ) {
+    }
+}
+
+pub struct Debugger<
+    S: Storage,
+    EV: EventSink,
+    PP: PrecompilesProcessor,
+    DP: DecommittmentProcessor,
+    WT: VmWitnessTracer<8, EncodingModeProduction>,
+> {
+    vm: VmState<S, SimpleMemory, EV, PP, DP, WT>,
+    tracer: DebuggerTracer,
+    breakpoints: HashMap<usize, Breakpoint>,
+    watchpoints: HashMap<usize, Watchpoint>,
+    next_id: usize,
+    /// Function labels sorted by pc, per code address
+    function_labels: HashMap<Address, Vec<(u16, String)>>,
+    cycles: u64,
+}
+
+impl<
+        S: Storage,
+        EV: EventSink,
+        PP: PrecompilesProcessor,
+        DP: DecommittmentProcessor,
+        WT: VmWitnessTracer<8, EncodingModeProduction>,
+    > Debugger<S, EV, PP, DP, WT>
+{
+    pub fn new(vm: VmState<S, SimpleMemory, EV, PP, DP, WT>) -> Self {
+        Self {
+            vm,
+            tracer: DebuggerTracer::default(),
+            breakpoints: HashMap::new(),
+            watchpoints: HashMap::new(),
+            next_id: 0,
+            function_labels: HashMap::new(),
+            cycles: 0,
+        }
+    }
+
+    pub fn vm(&self) -> &VmState<S, SimpleMemory, EV, PP, DP, WT> {
+        &self.vm
+    }
+
+    pub fn into_vm(self) -> VmState<S, SimpleMemory, EV, PP, DP, WT> {
+        self.vm
+    }
+
+    /// Number of cycles executed by the debugger
+    pub fn cycles(&self) -> u64 {
+        self.cycles
+    }
+
+    /// Function labels of the code deployed at the address, `function_labels` of `zkevm_assembly::Assembly`
+    /// can be passed as is
+    pub fn set_function_labels(
+        &mut self,
+        address: Address,
+        labels: impl IntoIterator<Item = (String, usize)>,
+    ) {
+        let mut labels: Vec<_> = labels
+            .into_iter()
+            .map(|(name, pc)| (pc as u16, name))
+            .collect();
+        labels.sort();
+        self.function_labels.insert(address, labels);
+    }
+
+    pub fn add_breakpoint(&mut self, breakpoint: Breakpoint) -> usize {
+        self.next_id += 1;
+        self.breakpoints.insert(self.next_id, breakpoint);
+        self.next_id
+    }
+
+    pub fn add_watchpoint(&mut self, watchpoint: Watchpoint) -> usize {
+        if let Watchpoint::Register(idx) = &watchpoint {
+            assert!(*idx < 16, "there are only 16 registers");
+        }
+        self.next_id += 1;
+        self.watchpoints.insert(self.next_id, watchpoint);
+        self.next_id
+    }
+
+    /// Removes the breakpoint or watchpoint with the id, returns false if there is no such
+    pub fn remove(&mut self, id: usize) -> bool {
+        self.breakpoints.remove(&id).is_some() || self.watchpoints.remove(&id).is_some()
+    }
+
+    pub fn breakpoints(&self) -> impl Iterator<Item = (&usize, &Breakpoint)> {
+        self.breakpoints.iter()
+    }
+
+    pub fn watchpoints(&self) -> impl Iterator<Item = (&usize, &Watchpoint)> {
+        self.watchpoints.iter()
+    }
+
+    /// Frames from the outermost (bootloader) to the current one
+    pub fn callstack(&self) -> Vec<&CallStackEntry> {
+        let callstack = &self.vm.local_state.callstack;
+        // the first entry is the empty context before the bootloader
+        callstack
+            .inner
+            .iter()
+            .skip(1)
+            .chain(std::iter::once(&callstack.current))
+            .collect()
+    }
+
+    pub fn current_frame(&self) -> &CallStackEntry {
+        self.vm.local_state.callstack.get_current_stack()
+    }
+
+    /// Value of the register, 0 is the zero register
+    pub fn register(&self, idx: usize) -> PrimitiveValue {
+        match idx {
+            0 => PrimitiveValue::empty(),
+            idx => self.vm.local_state.registers[idx - 1],
+        }
+    }
+
+    /// Fat pointer if the value is tagged as a pointer
+    pub fn fat_pointer(value: PrimitiveValue) -> Option<FatPointer> {
+        value.is_pointer.then(|| FatPointer::from_u256(value.value))
+    }
+
+    pub fn memory_word(&self, location: MemoryLocation) -> PrimitiveValue {
+        *self
+            .vm
+            .memory
+            .read_slot(location.page.0 as usize, location.index.0 as usize)
+    }
+
+    /// Opcode that will be executed in the next cycle (before masking due to exceptions or condition)
+    pub fn next_opcode(&self) -> Option<DecodedOpcode> {
+        let state = &self.vm.local_state;
+        if state.execution_has_ended() || state.pending_exception {
+            return None;
+        }
+        let frame = state.callstack.get_current_stack();
+        let (super_pc, sub_pc) = EncodingModeProduction::split_pc(frame.pc);
+        let word = self
+            .memory_word(MemoryLocation {
+                memory_type: MemoryType::Code,
+                page: frame.code_page,
+                index: MemoryIndex(super_pc as u32),
+            })
+            .value;
+        let encoding = EncodingModeProduction::integer_representaiton_from_u256(word, sub_pc);
+        let (inner, _) =
+            EncodingModeProduction::parse_preliminary_variant_and_absolute_number(encoding);
+
+        Some(DecodedOpcode { inner })
+    }
+
+    /// Label of the function that contains the pc of the frame
+    pub fn function_label(&self, frame: &CallStackEntry) -> Option<&str> {
+        let labels = self.function_labels.get(&frame.code_address)?;
+        let idx = labels.partition_point(|(pc, _)| *pc <= frame.pc);
+        idx.checked_sub(1).map(|idx| labels[idx].1.as_str())
+    }
+
+    fn label_pc(&self, address: &Address, label: &str) -> Option<u16> {
+        self.function_labels
+            .get(address)?
+            .iter()
+            .find(|(_, name)| name == label)
+            .map(|(pc, _)| *pc)
+    }
+
+    fn watched_values(&self) -> HashMap<usize, PrimitiveValue> {
+        self.watchpoints
+            .iter()
+            .filter_map(|(id, watchpoint)| match watchpoint {
+                Watchpoint::Register(idx) => Some((*id, self.register(*idx))),
+                Watchpoint::Memory(location) => Some((*id, self.memory_word(*location))),
+                Watchpoint::Storage { .. } => None,
+            })
+            .collect()
+    }
+
+    fn triggered_watchpoint(
+        &self,
+        values_before: HashMap<usize, PrimitiveValue>,
+    ) -> Option<StopReason> {
+        let mut ids: Vec<_> = self.watchpoints.keys().copied().collect();
+        ids.sort();
+        for id in ids {
+            let change = match &self.watchpoints[&id] {
+                Watchpoint::Storage { address, key } => self
+                    .tracer
+                    .storage_accesses
+                    .iter()
+                    .find(|el| el.address == *address && el.key == *key)
+                    .map(|el| match el.written_value {
+                        Some(value) => WatchpointChange::StorageWrite(value),
+                        None => WatchpointChange::StorageRead,
+                    }),
+                Watchpoint::Register(idx) => {
+                    let new = self.register(*idx);
+                    let old = values_before[&id];
+                    (old != new).then_some(WatchpointChange::Value { old, new })
+                }
+                Watchpoint::Memory(location) => {
+                    let new = self.memory_word(*location);
+                    let old = values_before[&id];
+                    (old != new).then_some(WatchpointChange::Value { old, new })
+                }
+            };
+            if let Some(change) = change {
+                return Some(StopReason::Watchpoint { id, change });
+            }
+        }
+
+        None
+    }
+
+    fn triggered_breakpoint(&self, address_before: Address) -> Option<usize> {
+        let frame = self.current_frame();
+        let opcode = self
+            .next_opcode()
+            .map(|el| format!("{:?}", el.variant.opcode));
+        let mut ids: Vec<_> = self.breakpoints.keys().copied().collect();
+        ids.sort();
+        ids.into_iter().find(|id| match &self.breakpoints[id] {
+            Breakpoint::Pc { address, pc } => {
+                frame.pc == *pc && address.map_or(true, |el| el == frame.code_address)
+            }
+            Breakpoint::Opcode(name) => opcode.as_ref().is_some_and(|el| el.starts_with(name)),
+            Breakpoint::Address(address) => {
+                frame.code_address == *address && address_before != *address
+            }
+            Breakpoint::Function { address, label } => {
+                frame.code_address == *address && self.label_pc(address, label) == Some(frame.pc)
+            }
+        })
+    }
+
+    fn far_call_depth(&self) -> usize {
+        self.callstack()
+            .iter()
+            .filter(|el| !el.is_local_frame)
+            .count()
+    }
+
+    /// Runs the VM until the step is finished, or breakpoint or watchpoint is hit, but for at most `max_cycles`
+    pub fn step(&mut self, mode: StepMode, max_cycles: u64) -> StopReason {
+        let depth = self.vm.local_state.callstack.depth();
+        let far_call_depth = self.far_call_depth();
+        for _ in 0..max_cycles {
+            if self.vm.execution_has_ended() {
+                return StopReason::ExecutionEnded;
+            }
+
+            let values_before = self.watched_values();
+            let address_before = self.current_frame().code_address;
+            self.tracer.storage_accesses.clear();
+            if let Err(err) = self.vm.cycle(&mut self.tracer) {
+                return StopReason::Error(err.to_string());
+            }
+            self.cycles += 1;
+
+            if let Some(reason) = self.triggered_watchpoint(values_before) {
+                return reason;
+            }
+            let step_is_finished = match mode {
+                StepMode::Cycle => true,
+                StepMode::NearCall => self.vm.local_state.callstack.depth() != depth,
+                StepMode::FarCall => self.far_call_depth() != far_call_depth,
+                StepMode::Continue => false,
+            };
+            if step_is_finished {
+                return StopReason::Step;
+            }
+            if let Some(id) = self.triggered_breakpoint(address_before) {
+                return StopReason::Breakpoint(id);
+            }
+        }
+
+        if self.vm.execution_has_ended() {
+            StopReason::ExecutionEnded
+        } else {
+            StopReason::CycleLimit
+        }
+    }
+}
@@ -0,0 +1,628 @@
+//! Decoding of the output of `InMemoryEventSink`: event queries are reassembled into the events
+//! (address, topics, data), L1 messages are decoded into (sender, key, value), and the events
+//! with a known ABI get their parameters decoded. The result is serialized to JSON for test assertions.
+//!
+//! The first query of an event has the number of topics in the low 32 bits of the key, the length of the data
+//! in the next 32 bits, and the first word in the value. The remaining words are in the keys and values
+//! of the following queries: topics first, then the data. Events emitted through the `EventWriter` system contract
+//! have the address of the emitter as the first topic, it is used as the address of the event.
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::ethereum_types::H256;
+use crate::reference_impls::event_sink::{EventMessage, InMemoryEventSink};
+use crate::sha3::{Digest, Keccak256};
+use crate::zkevm_opcode_defs::system_params::ADDRESS_EVENT_WRITER;
+
+use super::*;
+
+mod hex_bytes {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        let hex: String = bytes.iter().map(|el| format!("{:02x}", el)).collect();
+        serializer.serialize_str(&format!("0x{}", hex))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        let string = String::deserialize(deserializer)?;
+        let hex = string.strip_prefix("0x").unwrap_or(&string);
+        if hex.len() % 2 != 0 {
+            return Err(serde::de::Error::custom("odd length of hex string"));
+        }
+        (0..hex.len())
+            .step_by(2)
+            .map(|idx| u8::from_str_radix(&hex[idx..idx + 2], 16))
+            .collect::<Result<_, _>>()
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AbiType {
+    Address,
+    Uint256,
+    Bytes32,
+    Bool,
+    Bytes,
+}
+
+impl AbiType {
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Address => "address",
+            Self::Uint256 => "uint256",
+            Self::Bytes32 => "bytes32",
+            Self::Bool => "bool",
+            Self::Bytes => "bytes",
+        }
+    }
+
+    fn decode_word(&self, word: &[u8; 32]) -> AbiValue {
+        match self {
+            Self::Address => AbiValue::Address(Address::from_slice(&word[12..])),
+            Self::Uint256 => AbiValue::Uint(U256::from_big_endian(word)),
+            Self::Bool => AbiValue::Bool(word[31] != 0),
+            // indexed dynamic values are represented by their hash
+            Self::Bytes32 | Self::Bytes => AbiValue::Bytes32(H256(*word)),
+        }
+    }
+}
+
+/// Tagged in JSON, as the hex forms of the values can't be told apart by their length
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", content = "value")]
+pub enum AbiValue {
+    Bool(bool),
+    Address(Address),
+    Bytes32(H256),
+    Uint(U256),
+    Bytes(#[serde(with = "hex_bytes")] Vec<u8>),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EventParam {
+    pub name: String,
+    pub kind: AbiType,
+    pub indexed: bool,
+}
+
+/// ABI of an event, only the types used by the system contracts are supported
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EventAbi {
+    pub name: String,
+    pub inputs: Vec<EventParam>,
+}
+
+impl EventAbi {
+    pub fn new(name: &str, inputs: &[(&str, AbiType, bool)]) -> Self {
+        Self {
+            name: name.to_owned(),
+            inputs: inputs
+                .iter()
+                .map(|(name, kind, indexed)| EventParam {
+                    name: (*name).to_owned(),
+                    kind: *kind,
+                    indexed: *indexed,
+                })
+                .collect(),
+        }
+    }
+
+    pub fn signature(&self) -> String {
+        let types: Vec<_> = self.inputs.iter().map(|el| el.kind.name()).collect();
+        format!("{}({})", self.name, types.join(","))
+    }
+
+    /// First topic of the event
+    pub fn topic(&self) -> H256 {
+        H256::from_slice(&Keccak256::digest(self.signature().as_bytes()))
+    }
+
+    /// Parameters of the event in the order of declaration, `None` if the event doesn't match the ABI
+    pub fn decode(&self, topics: &[H256], data: &[u8]) -> Option<Vec<(String, AbiValue)>> {
+        if topics.first() != Some(&self.topic()) {
+            return None;
+        }
+        let num_indexed = self.inputs.iter().filter(|el| el.indexed).count();
+        if topics.len() != num_indexed + 1 {
+            return None;
+        }
+
+        let word_at = |offset: usize| -> Option<[u8; 32]> {
+            data.get(offset..offset.checked_add(32)?)
+                .map(|el| el.try_into().unwrap())
+        };
+        let mut topics = topics[1..].iter();
+        let mut head_offset = 0;
+        let mut result = Vec::with_capacity(self.inputs.len());
+        for param in self.inputs.iter() {
+            let value = if param.indexed {
+                param.kind.decode_word(&topics.next().unwrap().0)
+            } else {
+                let word = word_at(head_offset)?;
+                head_offset += 32;
+                if param.kind == AbiType::Bytes {
+                    let offset = usize::try_from(U256::from_big_endian(&word)).ok()?;
+                    let length = usize::try_from(U256::from_big_endian(&word_at(offset)?)).ok()?;
+                    let start = offset + 32;
+                    AbiValue::Bytes(data.get(start..start.checked_add(length)?)?.to_vec())
+                } else {
+                    param.kind.decode_word(&word)
+                }
+            };
+            result.push((param.name.clone(), value));
+        }
+
+        Some(result)
+    }
+}
+
+/// Events of the system contracts
+pub fn system_events() -> Vec<EventAbi> {
+    use AbiType::*;
+
+    vec![
+        EventAbi::new(
+            "ContractDeployed",
+            &[
+                ("deployerAddress", Address, true),
+                ("bytecodeHash", Bytes32, true),
+                ("contractAddress", Address, true),
+            ],
+        ),
+        EventAbi::new(
+            "MarkedAsKnown",
+            &[
+                ("bytecodeHash", Bytes32, true),
+                ("sendBytecodeToL1", Bool, true),
+            ],
+        ),
+        EventAbi::new(
+            "L1MessageSent",
+            &[
+                ("_sender", Address, true),
+                ("_hash", Bytes32, true),
+                ("_message", Bytes, false),
+            ],
+        ),
+        EventAbi::new(
+            "BytecodeL1PublicationRequested",
+            &[("_bytecodeHash", Bytes32, false)],
+        ),
+        EventAbi::new(
+            "Transfer",
+            &[
+                ("from", Address, true),
+                ("to", Address, true),
+                ("value", Uint256, false),
+            ],
+        ),
+        EventAbi::new(
+            "Mint",
+            &[("account", Address, true), ("amount", Uint256, false)],
+        ),
+        EventAbi::new(
+            "Withdrawal",
+            &[
+                ("_l2Sender", Address, true),
+                ("_l1Receiver", Address, true),
+                ("_amount", Uint256, false),
+            ],
+        ),
+    ]
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DecodedEventParams {
+    pub name: String,
+    pub params: Vec<(String, AbiValue)>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DecodedEvent {
+    pub tx_number_in_block: u16,
+    pub address: Address,
+    pub topics: Vec<H256>,
+    #[serde(with = "hex_bytes")]
+    pub data: Vec<u8>,
+    /// Set if the ABI of the event is known to the decoder
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub decoded: Option<DecodedEventParams>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct L1Message {
+    pub shard_id: u8,
+    pub is_service: bool,
+    pub tx_number_in_block: u16,
+    pub sender: Address,
+    pub key: H256,
+    pub value: H256,
+}
+
+impl From<&EventMessage> for L1Message {
+    fn from(message: &EventMessage) -> Self {
+        let mut key = H256::zero();
+        let mut value = H256::zero();
+        message.key.to_big_endian(&mut key.0);
+        message.value.to_big_endian(&mut value.0);
+
+        Self {
+            shard_id: message.shard_id,
+            is_service: message.is_first,
+            tx_number_in_block: message.tx_number_in_block,
+            sender: message.address,
+            key,
+            value,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DecodedLogs {
+    pub events: Vec<DecodedEvent>,
+    pub l1_messages: Vec<L1Message>,
+}
+
+impl DecodedLogs {
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap()
+    }
+}
+
+struct PendingEvent {
+    remaining_topics: u32,
+    remaining_data_length: u32,
+    event: DecodedEvent,
+}
+
+impl PendingEvent {
+    fn push_word(&mut self, word: U256) {
+        let mut buffer = [0u8; 32];
+        word.to_big_endian(&mut buffer);
+        if self.remaining_topics != 0 {
+            self.event.topics.push(H256(buffer));
+            self.remaining_topics -= 1;
+        } else if self.remaining_data_length != 0 {
+            let len = self.remaining_data_length.min(32);
+            self.event.data.extend_from_slice(&buffer[..len as usize]);
+            self.remaining_data_length -= len;
+        }
+    }
+
+    fn is_complete(&self) -> bool {
+        self.remaining_topics == 0 && self.remaining_data_length == 0
+    }
+}
+
+/// Reassembles the events from the queries, events that are interrupted by other events are skipped
+pub fn merge_events(messages: &[EventMessage]) -> Vec<DecodedEvent> {
+    let mut result = vec![];
+    let mut current: Option<PendingEvent> = None;
+
+    for message in messages.iter() {
+        if message.is_first {
+            // the previous event is dropped if it's not complete
+            let initializer = message.key.low_u64();
+            let mut pending = PendingEvent {
+                remaining_topics: initializer as u32,
+                remaining_data_length: (initializer >> 32) as u32,
+                event: DecodedEvent {
+                    tx_number_in_block: message.tx_number_in_block,
+                    address: message.address,
+                    topics: vec![],
+                    data: vec![],
+                    decoded: None,
+                },
+            };
+            pending.push_word(message.value);
+            current = Some(pending);
+        } else if let Some(pending) = current.as_mut() {
+            if pending.event.address != message.address {
+                current = None;
+                continue;
+            }
+            pending.push_word(message.key);
+            pending.push_word(message.value);
+        }
+
+        if current.as_ref().is_some_and(|el| el.is_complete()) {
+            result.push(current.take().unwrap().event);
+        }
+    }
+
+    result
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct EventDecoder {
+    known_events: HashMap<H256, EventAbi>,
+}
+
+impl EventDecoder {
+    /// Decoder without known events
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_system_events() -> Self {
+        let mut decoder = Self::new();
+        for abi in system_events() {
+            decoder.add_event(abi);
+        }
+
+        decoder
+    }
+
+    pub fn add_event(&mut self, abi: EventAbi) {
+        self.known_events.insert(abi.topic(), abi);
+    }
+
+    pub fn decode_events(&self, messages: &[EventMessage]) -> Vec<DecodedEvent> {
+        let event_writer = Address::from_low_u64_be(ADDRESS_EVENT_WRITER as u64);
+        let mut events = merge_events(messages);
+        for event in events.iter_mut() {
+            if event.address == event_writer && !event.topics.is_empty() {
+                let emitter = event.topics.remove(0);
+                event.address = Address::from_slice(&emitter.0[12..]);
+            }
+            event.decoded = event
+                .topics
+                .first()
+                .and_then(|el| self.known_events.get(el))
+                .and_then(|abi| {
+                    Some(DecodedEventParams {
+                        name: abi.name.clone(),
+                        params: abi.decode(&event.topics, &event.data)?,
+                    })
+                });
+        }
+
+        events
+    }
+
+    pub fn decode_l1_messages(&self, messages: &[EventMessage]) -> Vec<L1Message> {
+        messages.iter().map(L1Message::from).collect()
+    }
+
+    pub fn decode(&self, event_sink: InMemoryEventSink) -> DecodedLogs {
+        let (_, events, l1_messages) = event_sink.flatten();
+
+        DecodedLogs {
+            events: self.decode_events(&events),
+            l1_messages: self.decode_l1_messages(&l1_messages),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use zk_evm_abstractions::aux::Timestamp;
+    use zk_evm_abstractions::queries::LogQuery;
+    use zk_evm_abstractions::vm::EventSink;
+
+    use crate::zkevm_opcode_defs::system_params::{EVENT_AUX_BYTE, L1_MESSAGE_AUX_BYTE};
+
+    use super::*;
+
+    fn message(is_first: bool, address: u64, key: U256, value: U256) -> EventMessage {
+        EventMessage {
+            shard_id: 0,
+            is_first,
+            tx_number_in_block: 1,
+            address: Address::from_low_u64_be(address),
+            key,
+            value,
+        }
+    }
+
+    fn word(value: H256) -> U256 {
+        U256::from_big_endian(&value.0)
+    }
+
+    #[test]
+    fn test_decode_system_event() {
+        let transfer = system_events()
+            .into_iter()
+            .find(|el| el.name == "Transfer")
+            .unwrap();
+        assert_eq!(
+            transfer.topic(),
+            H256::from_slice(
+                &hex::decode("ddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef")
+                    .unwrap()
+            )
+        );
+
+        let token = H256::from_low_u64_be(0x800a);
+        let from = H256::from_low_u64_be(0x10000);
+        let to = H256::from_low_u64_be(0x10001);
+        let writer = ADDRESS_EVENT_WRITER as u64;
+        // token address and 3 topics, 32 bytes of data
+        let initializer = U256::from(4) + (U256::from(32) << 32);
+        let messages = vec![
+            message(true, writer, initializer, word(token)),
+            message(false, writer, word(transfer.topic()), word(from)),
+            message(false, writer, word(to), U256::from(1000)),
+            // interrupted by the next event
+            message(true, writer, U256::from(3), word(token)),
+            message(true, 0x10000, U256::from(1), word(transfer.topic())),
+        ];
+
+        let events = EventDecoder::with_system_events().decode_events(&messages);
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].address, Address::from_low_u64_be(0x800a));
+        assert_eq!(events[0].topics, vec![transfer.topic(), from, to]);
+        assert_eq!(
+            events[0].decoded,
+            Some(DecodedEventParams {
+                name: "Transfer".to_owned(),
+                params: vec![
+                    (
+                        "from".to_owned(),
+                        AbiValue::Address(Address::from_low_u64_be(0x10000))
+                    ),
+                    (
+                        "to".to_owned(),
+                        AbiValue::Address(Address::from_low_u64_be(0x10001))
+                    ),
+                    ("value".to_owned(), AbiValue::Uint(U256::from(1000))),
+                ],
+            })
+        );
+        // emitted directly, so the topics don't match the ABI
+        assert_eq!(events[1].address, Address::from_low_u64_be(0x10000));
+        assert_eq!(events[1].decoded, None);
+
+        let logs = DecodedLogs {
+            events,
+            l1_messages: EventDecoder::new().decode_l1_messages(&[message(
+                true,
+                0x8008,
+                U256::from(1),
+                U256::from(2),
+            )]),
+        };
+        assert_eq!(logs.l1_messages[0].value, H256::from_low_u64_be(2));
+        let decoded: DecodedLogs = serde_json::from_str(&logs.to_json()).unwrap();
+        assert_eq!(decoded, logs);
+    }
+
+    #[test]
+    fn test_decode_dynamic_data() {
+        let abi = EventAbi::new(
+            "L1MessageSent",
+            &[
+                ("_sender", AbiType::Address, true),
+                ("_hash", AbiType::Bytes32, true),
+                ("_message", AbiType::Bytes, false),
+            ],
+        );
+        assert_eq!(abi.signature(), "L1MessageSent(address,bytes32,bytes)");
+
+        let mut data = vec![0u8; 96];
+        data[31] = 32;
+        data[63] = 3;
+        data[64..67].copy_from_slice(&[1, 2, 3]);
+        let topics = [abi.topic(), H256::from_low_u64_be(1), H256::repeat_byte(2)];
+        let params = abi.decode(&topics, &data).unwrap();
+        assert_eq!(params[2].1, AbiValue::Bytes(vec![1, 2, 3]));
+        assert!(abi.decode(&topics, &data[..64]).is_none());
+        assert!(abi.decode(&topics[..2], &data).is_none());
+    }
+
+    #[test]
+    fn test_abi_value_json_round_trip() {
+        let values = vec![
+            AbiValue::Bool(true),
+            AbiValue::Address(Address::repeat_byte(1)),
+            AbiValue::Bytes32(H256::repeat_byte(2)),
+            AbiValue::Uint(U256::MAX),
+            // 40 and 64 hex digits, as an address and a 32 bytes word
+            AbiValue::Uint(U256::one() << 156),
+            AbiValue::Uint(U256::one() << 252),
+            AbiValue::Bytes(vec![3; 20]),
+            AbiValue::Bytes(vec![4; 32]),
+            AbiValue::Bytes(vec![]),
+        ];
+        let json = serde_json::to_string(&values).unwrap();
+        let decoded: Vec<AbiValue> = serde_json::from_str(&json).unwrap();
+        assert_eq!(decoded, values);
+
+        assert_eq!(
+            serde_json::to_string(&AbiValue::Bytes(vec![1, 2])).unwrap(),
+            r#"{"type":"Bytes","value":"0x0102"}"#
+        );
+    }
+
+    #[test]
+    fn test_decode_event_sink() {
+        let mint = system_events()
+            .into_iter()
+            .find(|el| el.name == "Mint")
+            .unwrap();
+        let writer = Address::from_low_u64_be(ADDRESS_EVENT_WRITER as u64);
+        let mut timestamp = 0;
+        let mut query = |aux_byte: u8, is_service: bool, key: U256, value: U256| {
+            timestamp += 1;
+            LogQuery {
+                timestamp: Timestamp(timestamp),
+                tx_number_in_block: 1,
+                aux_byte,
+                shard_id: 0,
+                address: if aux_byte == EVENT_AUX_BYTE {
+                    writer
+                } else {
+                    Address::from_low_u64_be(0x8008)
+                },
+                key,
+                read_value: U256::zero(),
+                written_value: value,
+                rw_flag: true,
+                rollback: false,
+                is_service,
+            }
+        };
+        // token address and 2 topics, 32 bytes of data
+        let initializer = U256::from(3) + (U256::from(32) << 32);
+        let token = U256::from(0x800a);
+        let account = U256::from(0x10000);
+
+        let mut sink = InMemoryEventSink::new();
+        sink.start_frame(Timestamp(0));
+        for el in [
+            query(EVENT_AUX_BYTE, true, initializer, token),
+            query(EVENT_AUX_BYTE, false, word(mint.topic()), account),
+            query(EVENT_AUX_BYTE, false, U256::from(1000), U256::zero()),
+            query(L1_MESSAGE_AUX_BYTE, true, U256::from(1), U256::from(2)),
+        ] {
+            sink.add_partial_query(0, el);
+        }
+        // the events of a reverted frame are rolled back
+        sink.start_frame(Timestamp(0));
+        sink.add_partial_query(0, query(EVENT_AUX_BYTE, true, initializer, token));
+        sink.add_partial_query(
+            0,
+            query(L1_MESSAGE_AUX_BYTE, true, U256::from(3), U256::from(4)),
+        );
+        sink.finish_frame(true, Timestamp(0));
+        sink.finish_frame(false, Timestamp(0));
+
+        let logs = EventDecoder::with_system_events().decode(sink);
+        assert_eq!(
+            logs.events,
+            vec![DecodedEvent {
+                tx_number_in_block: 1,
+                address: Address::from_low_u64_be(0x800a),
+                topics: vec![mint.topic(), H256::from_low_u64_be(0x10000)],
+                data: H256::from_low_u64_be(1000).0.to_vec(),
+                decoded: Some(DecodedEventParams {
+                    name: "Mint".to_owned(),
+                    params: vec![
+                        (
+                            "account".to_owned(),
+                            AbiValue::Address(Address::from_low_u64_be(0x10000))
+                        ),
+                        ("amount".to_owned(), AbiValue::Uint(U256::from(1000))),
+                    ],
+                }),
+            }]
+        );
+        assert_eq!(
+            logs.l1_messages,
+            vec![L1Message {
+                shard_id: 0,
+                is_service: true,
+                tx_number_in_block: 1,
+                sender: Address::from_low_u64_be(0x8008),
+                key: H256::from_low_u64_be(1),
+                value: H256::from_low_u64_be(2),
+            }]
+        );
+        let decoded: DecodedLogs = serde_json::from_str(&logs.to_json()).unwrap();
+        assert_eq!(decoded, logs);
+    }
+}
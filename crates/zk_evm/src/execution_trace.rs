@@ -0,0 +1,373 @@
+//! Per-cycle execution trace: decoded opcodes, register changes, memory and log queries and decommits.
+//! The trace is recorded by `TraceRecorder` (out-of-circuit tracer) together with `TraceWitnessRecorder`
+//! (witness tracer), and can be replayed against a fresh run of the VM with `replay`
+//! to find the first cycle where the behavior differs.
+
+use std::collections::BTreeMap;
+use std::fmt::{self, Display};
+
+use crate::opcodes::DecodedOpcode;
+use crate::reference_impls::memory::SimpleMemory;
+use crate::tracing::*;
+use crate::vm_state::{PrimitiveValue, VmState};
+use crate::witness_trace::VmWitnessTracer;
+use crate::zkevm_opcode_defs::decoding::{EncodingModeProduction, VmEncodingMode};
+use crate::zkevm_opcode_defs::REGISTERS_COUNT;
+use serde::{Deserialize, Serialize};
+use zk_evm_abstractions::queries::{DecommittmentQuery, LogQuery, MemoryQuery};
+use zk_evm_abstractions::vm::{DecommittmentProcessor, EventSink, PrecompilesProcessor, Storage};
+
+use super::*;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RegisterWrite {
+    /// Index of the register, from 1 to 15
+    pub index: u8,
+    pub value: U256,
+    pub is_pointer: bool,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TraceCycle {
+    pub monotonic_cycle_counter: u32,
+    /// Callstack depth before the cycle
+    pub callstack_depth: u16,
+    pub code_address: Address,
+    pub pc: u16,
+    /// Opcode as executed, after masking into a NOP or a panic, in the production encoding
+    pub opcode: u64,
+    /// Registers that changed during the cycle
+    pub register_writes: Vec<RegisterWrite>,
+    /// Ergs of the current frame after the cycle
+    pub ergs_remaining: u32,
+    pub memory_queries: Vec<MemoryQuery>,
+    pub log_queries: Vec<LogQuery>,
+    pub decommits: Vec<DecommittmentQuery>,
+}
+
+impl TraceCycle {
+    pub fn decoded_opcode(&self) -> DecodedOpcode {
+        let (inner, _) =
+            EncodingModeProduction::parse_preliminary_variant_and_absolute_number(self.opcode);
+        DecodedOpcode { inner }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExecutionTrace {
+    pub cycles: Vec<TraceCycle>,
+}
+
+impl ExecutionTrace {
+    /// First cycle where the traces differ, the trace itself is treated as the expected one
+    pub fn first_divergence(&self, other: &Self) -> Option<TraceDivergence> {
+        let len = self.cycles.len().max(other.cycles.len());
+        (0..len).find_map(|idx| {
+            TraceDivergence::compare(idx, self.cycles.get(idx), other.cycles.get(idx))
+        })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TraceDivergence {
+    /// Index of the cycle in the trace
+    pub cycle: usize,
+    /// Description of the first differing field
+    pub difference: String,
+    pub expected: Option<TraceCycle>,
+    pub actual: Option<TraceCycle>,
+}
+
+impl TraceDivergence {
+    fn compare(
+        cycle: usize,
+        expected: Option<&TraceCycle>,
+        actual: Option<&TraceCycle>,
+    ) -> Option<Self> {
+        let difference = match (expected, actual) {
+            (None, None) => return None,
+            (Some(_), None) => "cycle is missing".to_owned(),
+            (None, Some(_)) => "unexpected cycle".to_owned(),
+            (Some(expected), Some(actual)) => cycle_difference(expected, actual)?,
+        };
+
+        Some(Self {
+            cycle,
+            difference,
+            expected: expected.cloned(),
+            actual: actual.cloned(),
+        })
+    }
+}
+
+impl Display for TraceDivergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "cycle {}: {}", self.cycle, self.difference)?;
+        for (name, cycle) in [("expected", &self.expected), ("actual", &self.actual)] {
+            if let Some(cycle) = cycle {
+                write!(
+                    f,
+                    "\n  {}: {:?} at {:?} pc {}, counter {}",
+                    name,
+                    cycle.decoded_opcode().variant,
+                    cycle.code_address,
+                    cycle.pc,
+                    cycle.monotonic_cycle_counter
+                )?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn list_difference<T: PartialEq + fmt::Debug>(
+    name: &str,
+    expected: &[T],
+    actual: &[T],
+) -> Option<String> {
+    let idx =
+        (0..expected.len().max(actual.len())).find(|idx| expected.get(*idx) != actual.get(*idx))?;
+    Some(format!(
+        "{} {}: expected {:?}, got {:?}",
+        name,
+        idx,
+        expected.get(idx),
+        actual.get(idx)
+    ))
+}
+
+pub(crate) fn cycle_difference(expected: &TraceCycle, actual: &TraceCycle) -> Option<String> {
+    macro_rules! compare_field {
+        ($field: ident) => {
+            if expected.$field != actual.$field {
+                return Some(format!(
+                    "{}: expected {:?}, got {:?}",
+                    stringify!($field),
+                    expected.$field,
+                    actual.$field
+                ));
+            }
+        };
+    }
+
+    compare_field!(monotonic_cycle_counter);
+    compare_field!(callstack_depth);
+    compare_field!(code_address);
+    compare_field!(pc);
+    if expected.opcode != actual.opcode {
+        return Some(format!(
+            "opcode: expected {:?}, got {:?}",
+            expected.decoded_opcode().inner,
+            actual.decoded_opcode().inner
+        ));
+    }
+    let lists = [
+        list_difference(
+            "register write",
+            &expected.register_writes,
+            &actual.register_writes,
+        ),
+        list_difference(
+            "memory query",
+            &expected.memory_queries,
+            &actual.memory_queries,
+        ),
+        list_difference("log query", &expected.log_queries, &actual.log_queries),
+        list_difference("decommit", &expected.decommits, &actual.decommits),
+    ];
+    if let Some(difference) = lists.into_iter().flatten().next() {
+        return Some(difference);
+    }
+    compare_field!(ergs_remaining);
+
+    None
+}
+
+#[derive(Clone, Debug, Default)]
+struct CycleEvents {
+    memory_queries: Vec<MemoryQuery>,
+    log_queries: Vec<LogQuery>,
+    decommits: Vec<DecommittmentQuery>,
+}
+
+/// Witness tracer that collects the queries of every cycle, keyed by the monotonic cycle counter
+#[derive(Clone, Debug, Default)]
+pub struct TraceWitnessRecorder {
+    events: BTreeMap<u32, CycleEvents>,
+}
+
+impl TraceWitnessRecorder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn fill_cycle(&mut self, cycle: &mut TraceCycle) {
+        if let Some(events) = self.events.remove(&cycle.monotonic_cycle_counter) {
+            cycle.memory_queries = events.memory_queries;
+            cycle.log_queries = events.log_queries;
+            cycle.decommits = events.decommits;
+        }
+    }
+}
+
+impl VmWitnessTracer<8, EncodingModeProduction> for TraceWitnessRecorder {
+    fn add_memory_query(&mut self, monotonic_cycle_counter: u32, memory_query: MemoryQuery) {
+        self.events
+            .entry(monotonic_cycle_counter)
+            .or_default()
+            .memory_queries
+            .push(memory_query);
+    }
+
+    fn add_log_query(&mut self, monotonic_cycle_counter: u32, log_query: LogQuery) {
+        self.events
+            .entry(monotonic_cycle_counter)
+            .or_default()
+            .log_queries
+            .push(log_query);
+    }
+
+    fn prepare_for_decommittment(
+        &mut self,
+        monotonic_cycle_counter: u32,
+        decommittment_query: DecommittmentQuery,
+    ) {
+        self.events
+            .entry(monotonic_cycle_counter)
+            .or_default()
+            .decommits
+            .push(decommittment_query);
+    }
+}
+
+/// Records the decoded opcodes and the register changes of every cycle.
+/// The queries are collected separately by `TraceWitnessRecorder`, that should be the witness tracer
+/// of the same VM, and are merged into the trace with `into_trace`.
+#[derive(Clone, Debug)]
+pub struct TraceRecorder {
+    cycles: Vec<TraceCycle>,
+    current: TraceCycle,
+    registers_before: [PrimitiveValue; REGISTERS_COUNT],
+}
+
+impl Default for TraceRecorder {
+    fn default() -> Self {
+        Self {
+            cycles: vec![],
+            current: TraceCycle::default(),
+            registers_before: [PrimitiveValue::empty(); REGISTERS_COUNT],
+        }
+    }
+}
+
+impl TraceRecorder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn into_trace(self, mut witness: TraceWitnessRecorder) -> ExecutionTrace {
+        let mut cycles = self.cycles;
+        for cycle in cycles.iter_mut() {
+            witness.fill_cycle(cycle);
+        }
+
+        ExecutionTrace { cycles }
+    }
+
+    fn take_last_cycle(&mut self, witness: &mut TraceWitnessRecorder) -> Option<TraceCycle> {
+        let mut cycle = self.cycles.pop()?;
+        witness.fill_cycle(&mut cycle);
+        Some(cycle)
+    }
+}
+
+impl Tracer for TraceRecorder {
+    const CALL_BEFORE_DECODING: bool = true;
+    const CALL_AFTER_DECODING: bool = true;
+    const CALL_AFTER_EXECUTION: bool = true;
+
+    type SupportedMemory = SimpleMemory;
+
+    fn before_decoding(&mut self, state: VmLocalStateData<'_>, _memory: &Self::SupportedMemory) {
+        let local_state = state.vm_local_state;
+        let frame = local_state.callstack.get_current_stack();
+        self.current = TraceCycle {
+            monotonic_cycle_counter: local_state.monotonic_cycle_counter,
+            callstack_depth: local_state.callstack.depth() as u16,
+            code_address: frame.code_address,
+            pc: frame.pc,
+            ..TraceCycle::default()
+        };
+        self.registers_before = local_state.registers;
+    }
+
+    fn after_decoding(
+        &mut self,
+        _state: VmLocalStateData<'_>,
+        data: AfterDecodingData,
+        _memory: &Self::SupportedMemory,
+    ) {
+        self.current.opcode = data.opcode_masked.inner.serialize_as_integer();
+    }
+
+    fn before_execution(
+        &mut self,
+        _state: VmLocalStateData<'_>,
+        _data: BeforeExecutionData,
+        _memory: &Self::SupportedMemory,
+    ) {
+    }
+
+    fn after_execution(
+        &mut self,
+        state: VmLocalStateData<'_>,
+        _data: AfterExecutionData,
+        _memory: &Self::SupportedMemory,
+    ) {
+        let local_state = state.vm_local_state;
+        let mut cycle = std::mem::take(&mut self.current);
+        cycle.register_writes = local_state
+            .registers
+            .iter()
+            .zip(self.registers_before.iter())
+            .enumerate()
+            .filter(|(_, (new, old))| new != old)
+            .map(|(idx, (new, _))| RegisterWrite {
+                index: idx as u8 + 1,
+                value: new.value,
+                is_pointer: new.is_pointer,
+            })
+            .collect();
+        cycle.ergs_remaining = local_state.callstack.get_current_stack().ergs_remaining;
+        self.cycles.push(cycle);
+    }
+}
+
+/// Runs the VM cycle by cycle and checks every cycle against the expected trace. The VM should be
+/// in the same initial state as the one the trace was recorded from. Only the cycles of the trace
+/// are executed, so the caller should check whether the execution of the VM has ended if needed.
+pub fn replay<S: Storage, EV: EventSink, PP: PrecompilesProcessor, DP: DecommittmentProcessor>(
+    vm: &mut VmState<S, SimpleMemory, EV, PP, DP, TraceWitnessRecorder>,
+    expected: &ExecutionTrace,
+) -> Result<(), TraceDivergence> {
+    let mut recorder = TraceRecorder::new();
+    for (idx, expected_cycle) in expected.cycles.iter().enumerate() {
+        if let Err(error) = vm.cycle(&mut recorder) {
+            return Err(TraceDivergence {
+                cycle: idx,
+                difference: format!("cycle failed: {}", error),
+                expected: Some(expected_cycle.clone()),
+                actual: None,
+            });
+        }
+        let actual = recorder.take_last_cycle(&mut vm.witness_tracer);
+        if let Some(divergence) =
+            TraceDivergence::compare(idx, Some(expected_cycle), actual.as_ref())
+        {
+            return Err(divergence);
+        }
+    }
+
+    Ok(())
+}
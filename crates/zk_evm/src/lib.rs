@@ -11,11 +11,17 @@
 )]
 
 pub mod block_properties;
+pub mod coverage;
+pub mod debugger;
 pub mod errors;
+pub mod event_decoder;
+pub mod execution_trace;
 pub mod flags;
 pub mod opcodes;
+pub mod profiler;
 pub mod reference_impls;
 pub mod testing;
+pub mod trace_diff;
 pub mod tracing;
 pub mod utils;
 pub mod vm_state;
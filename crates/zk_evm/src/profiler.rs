@@ -0,0 +1,356 @@
+//! Tracer that attributes the execution costs (cycles, ergs, memory growth, storage accesses
+//! and precompile calls) to the far- and near-call stack, and exports them in the collapsed stack
+//! format understood by the flamegraph tools (`inferno-flamegraph`, `flamegraph.pl`, speedscope).
+
+use std::collections::{BTreeMap, HashMap};
+
+use crate::reference_impls::memory::SimpleMemory;
+use crate::tracing::*;
+use crate::vm_state::CallStackEntry;
+use crate::zkevm_opcode_defs::decoding::{AllowedPcOrImm, VmEncodingMode};
+use crate::zkevm_opcode_defs::{LogOpcode, Opcode};
+
+use super::*;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+struct FrameKey {
+    code_address: Address,
+    is_local: bool,
+    /// pc of the function label, if the labels are known for the code address
+    function: Option<u16>,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FrameCosts {
+    pub cycles: u64,
+    pub ergs: u64,
+    /// Growth of the heap and aux heap bounds, in bytes
+    pub memory_growth: u64,
+    /// Reads and writes of both persistent and transient storage
+    pub storage_accesses: u64,
+    pub precompile_calls: u64,
+}
+
+impl FrameCosts {
+    fn add(&mut self, other: &Self) {
+        self.cycles += other.cycles;
+        self.ergs += other.ergs;
+        self.memory_growth += other.memory_growth;
+        self.storage_accesses += other.storage_accesses;
+        self.precompile_calls += other.precompile_calls;
+    }
+
+    pub fn get(&self, metric: ProfileMetric) -> u64 {
+        match metric {
+            ProfileMetric::Cycles => self.cycles,
+            ProfileMetric::Ergs => self.ergs,
+            ProfileMetric::MemoryGrowth => self.memory_growth,
+            ProfileMetric::StorageAccesses => self.storage_accesses,
+            ProfileMetric::PrecompileCalls => self.precompile_calls,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ProfileMetric {
+    Cycles,
+    Ergs,
+    MemoryGrowth,
+    StorageAccesses,
+    PrecompileCalls,
+}
+
+#[derive(Clone, Debug)]
+struct PendingCycle {
+    stack: Vec<FrameKey>,
+    depth: usize,
+    total_ergs: u64,
+    heap_bounds: u64,
+    costs: FrameCosts,
+}
+
+/// Profiling tracer. Costs of every cycle are attributed to the call stack at the start of the cycle,
+/// so the ergs passed to a callee are attributed to the callee, and the ergs burnt by a panic are attributed
+/// to the panicking frame. Can be passed as the out-of-circuit tracer to `run_vms`.
+#[derive(Clone, Debug, Default)]
+pub struct ProfilingTracer {
+    contract_names: HashMap<Address, String>,
+    /// Function labels sorted by pc, per code address
+    function_labels: HashMap<Address, Vec<(u16, String)>>,
+    costs: BTreeMap<Vec<FrameKey>, FrameCosts>,
+    pending: Option<PendingCycle>,
+}
+
+impl ProfilingTracer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Name of the contract in the reports instead of its address
+    pub fn with_contract_name(mut self, address: Address, name: String) -> Self {
+        self.contract_names.insert(address, name);
+        self
+    }
+
+    /// Function labels of the code deployed at the address, `function_labels` of `zkevm_assembly::Assembly`
+    /// can be passed as is. Without them near calls are reported as `near_call`.
+    pub fn with_function_labels(
+        mut self,
+        address: Address,
+        labels: impl IntoIterator<Item = (String, usize)>,
+    ) -> Self {
+        let mut labels: Vec<_> = labels
+            .into_iter()
+            .map(|(name, pc)| (pc as u16, name))
+            .collect();
+        labels.sort();
+        self.function_labels.insert(address, labels);
+        self
+    }
+
+    fn function_for_pc(&self, address: &Address, pc: u16) -> Option<u16> {
+        let labels = self.function_labels.get(address)?;
+        let idx = labels.partition_point(|(label_pc, _)| *label_pc <= pc);
+        idx.checked_sub(1).map(|idx| labels[idx].0)
+    }
+
+    fn frame_key<const N: usize, E: VmEncodingMode<N>>(
+        &self,
+        entry: &CallStackEntry<N, E>,
+    ) -> FrameKey {
+        FrameKey {
+            code_address: entry.code_address,
+            is_local: entry.is_local_frame,
+            function: self.function_for_pc(&entry.code_address, entry.pc.as_u64() as u16),
+        }
+    }
+
+    fn frame_names(&self, frame: &FrameKey) -> Vec<String> {
+        let function = frame.function.map(|pc| {
+            let labels = &self.function_labels[&frame.code_address];
+            let idx = labels.partition_point(|(label_pc, _)| *label_pc < pc);
+            labels[idx].1.clone()
+        });
+
+        if frame.is_local {
+            vec![function.unwrap_or_else(|| "near_call".to_owned())]
+        } else {
+            let contract = self
+                .contract_names
+                .get(&frame.code_address)
+                .cloned()
+                .unwrap_or_else(|| format!("{:?}", frame.code_address));
+            std::iter::once(contract).chain(function).collect()
+        }
+    }
+
+    /// Costs per call stack, outermost frame first
+    pub fn costs_by_stack(&self) -> Vec<(Vec<String>, FrameCosts)> {
+        let mut result: BTreeMap<Vec<String>, FrameCosts> = BTreeMap::new();
+        for (stack, costs) in self.costs.iter() {
+            let names = stack
+                .iter()
+                .flat_map(|frame| self.frame_names(frame))
+                .collect();
+            result.entry(names).or_default().add(costs);
+        }
+
+        result.into_iter().collect()
+    }
+
+    /// Total costs of the execution
+    pub fn total_costs(&self) -> FrameCosts {
+        let mut result = FrameCosts::default();
+        for costs in self.costs.values() {
+            result.add(costs);
+        }
+
+        result
+    }
+
+    /// Collapsed stacks (`frame;frame;frame value` per line), stacks with zero value are skipped
+    pub fn collapsed_stacks(&self, metric: ProfileMetric) -> String {
+        let mut result = String::new();
+        for (stack, costs) in self.costs_by_stack() {
+            let value = costs.get(metric);
+            if value == 0 {
+                continue;
+            }
+            // `;` separates the frames and the last space separates the value
+            let stack: Vec<_> = stack.iter().map(|el| el.replace([';', ' '], "_")).collect();
+            result.push_str(&format!("{} {}\n", stack.join(";"), value));
+        }
+
+        result
+    }
+}
+
+fn heap_bounds<const N: usize, E: VmEncodingMode<N>>(entry: &CallStackEntry<N, E>) -> u64 {
+    entry.heap_bound as u64 + entry.aux_heap_bound as u64
+}
+
+impl<const N: usize, E: VmEncodingMode<N>> Tracer<N, E> for ProfilingTracer {
+    const CALL_BEFORE_DECODING: bool = true;
+    const CALL_AFTER_DECODING: bool = false;
+    const CALL_BEFORE_EXECUTION: bool = true;
+    const CALL_AFTER_EXECUTION: bool = true;
+
+    type SupportedMemory = SimpleMemory;
+
+    fn before_decoding(
+        &mut self,
+        state: VmLocalStateData<'_, N, E>,
+        _memory: &Self::SupportedMemory,
+    ) {
+        let callstack = &state.vm_local_state.callstack;
+        // the first entry is the empty context before the bootloader
+        let stack = callstack
+            .inner
+            .iter()
+            .skip(1)
+            .chain(std::iter::once(&callstack.current))
+            .map(|entry| self.frame_key(entry))
+            .collect();
+        let total_ergs = callstack
+            .inner
+            .iter()
+            .chain(std::iter::once(&callstack.current))
+            .map(|entry| entry.ergs_remaining as u64)
+            .sum();
+
+        self.pending = Some(PendingCycle {
+            stack,
+            depth: callstack.depth(),
+            total_ergs,
+            heap_bounds: heap_bounds(&callstack.current),
+            costs: FrameCosts {
+                cycles: 1,
+                ..Default::default()
+            },
+        });
+    }
+
+    fn after_decoding(
+        &mut self,
+        _state: VmLocalStateData<'_, N, E>,
+        _data: AfterDecodingData<N, E>,
+        _memory: &Self::SupportedMemory,
+    ) {
+    }
+
+    fn before_execution(
+        &mut self,
+        _state: VmLocalStateData<'_, N, E>,
+        data: BeforeExecutionData<N, E>,
+        _memory: &Self::SupportedMemory,
+    ) {
+        let Some(pending) = self.pending.as_mut() else {
+            return;
+        };
+        match data.opcode.variant.opcode {
+            Opcode::Log(LogOpcode::StorageRead)
+            | Opcode::Log(LogOpcode::StorageWrite)
+            | Opcode::Log(LogOpcode::TransientStorageRead)
+            | Opcode::Log(LogOpcode::TransientStorageWrite) => pending.costs.storage_accesses += 1,
+            Opcode::Log(LogOpcode::PrecompileCall) => pending.costs.precompile_calls += 1,
+            _ => {}
+        }
+    }
+
+    fn after_execution(
+        &mut self,
+        state: VmLocalStateData<'_, N, E>,
+        _data: AfterExecutionData<N, E>,
+        _memory: &Self::SupportedMemory,
+    ) {
+        let Some(mut pending) = self.pending.take() else {
+            return;
+        };
+        let callstack = &state.vm_local_state.callstack;
+        let total_ergs: u64 = callstack
+            .inner
+            .iter()
+            .chain(std::iter::once(&callstack.current))
+            .map(|entry| entry.ergs_remaining as u64)
+            .sum();
+        // far calls may add the stipend, so it can grow
+        pending.costs.ergs = pending.total_ergs.saturating_sub(total_ergs);
+        if callstack.depth() == pending.depth {
+            pending.costs.memory_growth =
+                heap_bounds(&callstack.current).saturating_sub(pending.heap_bounds);
+        }
+
+        self.costs
+            .entry(pending.stack)
+            .or_default()
+            .add(&pending.costs);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_collapsed_stacks() {
+        let contract = Address::from_low_u64_be(0x8001);
+        let mut tracer = ProfilingTracer::new()
+            .with_contract_name(contract, "Token contract".to_owned())
+            .with_function_labels(
+                contract,
+                vec![("__entry".to_owned(), 0), ("transfer".to_owned(), 10)],
+            );
+        assert_eq!(tracer.function_for_pc(&contract, 5), Some(0));
+        assert_eq!(tracer.function_for_pc(&contract, 10), Some(10));
+        assert_eq!(tracer.function_for_pc(&Address::zero(), 10), None);
+
+        let far = FrameKey {
+            code_address: contract,
+            is_local: false,
+            function: Some(0),
+        };
+        let near = FrameKey {
+            code_address: contract,
+            is_local: true,
+            function: Some(10),
+        };
+        let unknown = FrameKey {
+            code_address: Address::zero(),
+            is_local: true,
+            function: None,
+        };
+        tracer.costs.insert(
+            vec![far],
+            FrameCosts {
+                cycles: 3,
+                ergs: 20,
+                ..Default::default()
+            },
+        );
+        tracer.costs.insert(
+            vec![far, near],
+            FrameCosts {
+                cycles: 2,
+                storage_accesses: 1,
+                ..Default::default()
+            },
+        );
+        tracer.costs.insert(
+            vec![far, unknown],
+            FrameCosts {
+                cycles: 1,
+                ..Default::default()
+            },
+        );
+
+        assert_eq!(
+            tracer.collapsed_stacks(ProfileMetric::Cycles),
+            "Token_contract;__entry 3\nToken_contract;__entry;near_call 1\nToken_contract;__entry;transfer 2\n"
+        );
+        assert_eq!(
+            tracer.collapsed_stacks(ProfileMetric::Ergs),
+            "Token_contract;__entry 20\n"
+        );
+        assert_eq!(tracer.total_costs().cycles, 6);
+    }
+}
@@ -11,10 +11,15 @@ use super::*;
 
 const PRIMITIVE_VALUE_EMPTY: PrimitiveValue = PrimitiveValue::empty();
 const PAGE_SUBDIVISION_LEN: usize = 64;
+const PAGE_SUBDIVISION_SIZE: usize = PAGE_SUBDIVISION_LEN * std::mem::size_of::<PrimitiveValue>();
+const PAGE_ROOT_ENTRY_SIZE: usize =
+    std::mem::size_of::<Option<Box<[PrimitiveValue; PAGE_SUBDIVISION_LEN]>>>();
+const PAGE_TABLE_ENTRY_SIZE: usize = std::mem::size_of::<SparseMemoryPage>();
 
 #[derive(Debug, Default, Clone)]
 struct SparseMemoryPage {
     root: Vec<Option<Box<[PrimitiveValue; PAGE_SUBDIVISION_LEN]>>>,
+    allocated_subdivisions: usize,
 }
 
 impl SparseMemoryPage {
@@ -42,14 +47,15 @@ impl SparseMemoryPage {
             let mut leaf = [PrimitiveValue::empty(); PAGE_SUBDIVISION_LEN];
             leaf[leaf_index] = value;
             self.root[root_index] = Some(Box::new(leaf));
+            self.allocated_subdivisions += 1;
             PrimitiveValue::empty()
         }
     }
 
+    /// Size of the allocated subdivisions and of the index pointing to them
     fn get_size(&self) -> usize {
-        self.root.iter().filter_map(|x| x.as_ref()).count()
-            * PAGE_SUBDIVISION_LEN
-            * std::mem::size_of::<PrimitiveValue>()
+        self.allocated_subdivisions * PAGE_SUBDIVISION_SIZE
+            + self.root.capacity() * PAGE_ROOT_ENTRY_SIZE
     }
 }
 
@@ -67,6 +73,8 @@ impl PartialEq for SparseMemoryPage {
 #[derive(Debug, Default, Clone)]
 pub struct MemoryWrapper {
     memory: Vec<SparseMemoryPage>,
+    /// Sum of the page sizes, the page table itself is not included
+    pages_size: usize,
 }
 
 impl PartialEq for MemoryWrapper {
@@ -114,19 +122,43 @@ impl MemoryWrapper {
             .unwrap_or(&PRIMITIVE_VALUE_EMPTY)
     }
 
+    /// Size of the pages and of the page table, which grows up to the highest page
+    /// number written and is never shrunk
     pub fn get_size(&self) -> usize {
-        self.memory.iter().map(|page| page.get_size()).sum()
+        self.pages_size + self.memory.capacity() * PAGE_TABLE_ENTRY_SIZE
     }
 
-    pub(crate) fn write_to_memory(&mut self, page: usize, slot: usize, value: PrimitiveValue) {
+    pub fn get_page_size(&self, page: usize) -> usize {
+        self.memory.get(page).map_or(0, |page| page.get_size())
+    }
+
+    /// Returns the number of bytes allocated by the write
+    pub(crate) fn write_to_memory(
+        &mut self,
+        page: usize,
+        slot: usize,
+        value: PrimitiveValue,
+    ) -> usize {
+        let size_before = self.get_size();
         self.ensure_page_exists(page);
         let page_handle = self.memory.get_mut(page).unwrap();
+        let page_size_before = page_handle.get_size();
         page_handle.set(slot, value);
+        self.pages_size += page_handle.get_size() - page_size_before;
+
+        self.get_size() - size_before
     }
 
-    fn clear_page(&mut self, page: usize) {
+    /// Returns the number of released bytes
+    fn clear_page(&mut self, page: usize) -> usize {
         if let Some(page_handle) = self.memory.get_mut(page) {
+            let released = page_handle.get_size();
             *page_handle = SparseMemoryPage::default();
+            self.pages_size -= released;
+
+            released
+        } else {
+            0
         }
     }
 }
@@ -179,10 +211,87 @@ impl<T> FramedStack<T> {
     }
 }
 
-#[derive(Debug, Default)]
+/// Budgets of `SimpleMemory`, in bytes of the allocated memory, including the page indices and
+/// the page table. Allocation happens in chunks of `PAGE_SUBDIVISION_LEN` words, so a budget can be
+/// exceeded by one chunk and the index growth of the write before it is reported.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MemoryLimits {
+    pub max_total_size: Option<usize>,
+    pub max_page_size: Option<usize>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MemoryLimitExceeded {
+    Total {
+        size: usize,
+        limit: usize,
+    },
+    Page {
+        page: u32,
+        size: usize,
+        limit: usize,
+    },
+}
+
+impl std::fmt::Display for MemoryLimitExceeded {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> Result<(), std::fmt::Error> {
+        match self {
+            Self::Total { size, limit } => write!(
+                f,
+                "memory uses {} bytes, more than the limit of {} bytes",
+                size, limit
+            ),
+            Self::Page { page, size, limit } => write!(
+                f,
+                "memory page {} uses {} bytes, more than the limit of {} bytes",
+                page, size, limit
+            ),
+        }
+    }
+}
+
+impl std::error::Error for MemoryLimitExceeded {}
+
+/// Memory of a far call frame
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FrameMemoryUsage {
+    /// Bytes allocated while the frame or its finished child frames were executed
+    pub allocated: usize,
+    /// Bytes of the pages released when the child frames finished
+    pub released: usize,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MemoryMetrics {
+    pub current_size: usize,
+    pub peak_size: usize,
+    pub allocated_size: usize,
+    pub released_size: usize,
+    pub released_pages: usize,
+}
+
+#[derive(Debug)]
 pub struct SimpleMemory {
     memory: MemoryWrapper,
     observable_pages: FramedStack<u32>,
+    limits: MemoryLimits,
+    limit_exceeded: Option<MemoryLimitExceeded>,
+    metrics: MemoryMetrics,
+    frames_usage: Vec<FrameMemoryUsage>,
+}
+
+impl Default for SimpleMemory {
+    fn default() -> Self {
+        Self {
+            memory: MemoryWrapper::default(),
+            observable_pages: FramedStack::default(),
+            limits: MemoryLimits::default(),
+            limit_exceeded: None,
+            metrics: MemoryMetrics::default(),
+            // same as for the observable pages
+            frames_usage: vec![FrameMemoryUsage::default()],
+        }
+    }
 }
 
 impl SimpleMemory {
@@ -193,6 +302,64 @@ impl SimpleMemory {
     pub fn new_without_preallocations() -> Self {
         Self::default()
     }
+
+    pub fn with_limits(limits: MemoryLimits) -> Self {
+        Self {
+            limits,
+            ..Self::default()
+        }
+    }
+
+    pub fn set_limits(&mut self, limits: MemoryLimits) {
+        self.limits = limits;
+    }
+
+    pub fn limits(&self) -> MemoryLimits {
+        self.limits
+    }
+
+    /// First exceeded budget, the VM stops with an error at the end of the cycle that exceeded it
+    pub fn limit_exceeded(&self) -> Option<MemoryLimitExceeded> {
+        self.limit_exceeded
+    }
+
+    pub fn metrics(&self) -> MemoryMetrics {
+        MemoryMetrics {
+            current_size: self.memory.get_size(),
+            ..self.metrics
+        }
+    }
+
+    /// Usage of the far call frames, from the root frame to the current one
+    pub fn frames_memory_usage(&self) -> &[FrameMemoryUsage] {
+        &self.frames_usage
+    }
+
+    fn write_to_memory(&mut self, page: usize, slot: usize, value: PrimitiveValue) {
+        let allocated = self.memory.write_to_memory(page, slot, value);
+        if allocated == 0 {
+            return;
+        }
+
+        let size = self.memory.get_size();
+        self.metrics.allocated_size += allocated;
+        self.metrics.peak_size = self.metrics.peak_size.max(size);
+        self.frames_usage.last_mut().unwrap().allocated += allocated;
+
+        if self.limit_exceeded.is_some() {
+            return;
+        }
+        let page_size = self.memory.get_page_size(page);
+        if let Some(limit) = self.limits.max_page_size.filter(|el| page_size > *el) {
+            self.limit_exceeded = Some(MemoryLimitExceeded::Page {
+                page: page as u32,
+                size: page_size,
+                limit,
+            });
+        } else if let Some(limit) = self.limits.max_total_size.filter(|el| size > *el) {
+            self.limit_exceeded = Some(MemoryLimitExceeded::Total { size, limit });
+        }
+    }
 }
 
 impl SimpleMemory {
@@ -203,7 +370,7 @@ impl SimpleMemory {
                     value,
                     is_pointer: false,
                 };
-                self.memory.write_to_memory(page as usize, i, value);
+                self.write_to_memory(page as usize, i, value);
             }
         }
     }
@@ -288,7 +455,7 @@ impl Memory for SimpleMemory {
         let slot = query.location.index.0 as usize;
 
         if query.rw_flag {
-            self.memory.write_to_memory(
+            self.write_to_memory(
                 page,
                 slot,
                 PrimitiveValue {
@@ -320,7 +487,7 @@ impl Memory for SimpleMemory {
         let slot = query.location.index.0 as usize;
 
         if query.rw_flag {
-            self.memory.write_to_memory(
+            self.write_to_memory(
                 page,
                 slot,
                 PrimitiveValue {
@@ -359,6 +526,13 @@ impl Memory for SimpleMemory {
         query
     }
 
+    fn check_limits(&self) -> anyhow::Result<()> {
+        match self.limit_exceeded {
+            Some(error) => Err(error.into()),
+            None => Ok(()),
+        }
+    }
+
     fn start_global_frame(
         &mut self,
         _current_base_page: MemoryPage,
@@ -370,6 +544,7 @@ impl Memory for SimpleMemory {
         // page, heap page and aux heap page.
         // The code page will be always left observable, so we don't include it here.
         self.observable_pages.push_frame();
+        self.frames_usage.push(FrameMemoryUsage::default());
         self.observable_pages.extend_frame(vec![
             calldata_fat_pointer.memory_page,
             stack_page_from_base(new_base_page).0,
@@ -394,15 +569,24 @@ impl Memory for SimpleMemory {
         let is_returndata_page_static =
             last_callstack_this == *CODE_ORACLE_ADDRESS && returndata_fat_pointer.length > 0;
 
+        let mut frame_usage = self.frames_usage.pop().unwrap();
         for &page in current_observable_pages {
             // If the page's number is greater than or equal to the `base_page`,
             // it means that it was created by the internal calls of this contract.
             // We need to add this check as the calldata pointer is also part of the
             // observable pages.
             if page >= base_page.0 && page != returndata_page {
-                self.memory.clear_page(page as usize);
+                let released = self.memory.clear_page(page as usize);
+                if released > 0 {
+                    frame_usage.released += released;
+                    self.metrics.released_size += released;
+                    self.metrics.released_pages += 1;
+                }
             }
         }
+        let parent_usage = self.frames_usage.last_mut().unwrap();
+        parent_usage.allocated += frame_usage.allocated;
+        parent_usage.released += frame_usage.released;
 
         self.observable_pages.clear_frame();
         self.observable_pages.merge_frame();
@@ -556,7 +740,7 @@ mod tests {
 
             Self {
                 memory: memory,
-                base_page_counter: 0,
+                base_page_counter: BOOTLOADER_BASE_PAGE,
                 base_pages: vec![
                     (Address::zero(), MemoryPage(0)),
                     (*BOOTLOADER_FORMAL_ADDRESS, MemoryPage(BOOTLOADER_BASE_PAGE)),
@@ -622,4 +806,136 @@ mod tests {
 
         assert_eq!(read_value, U256::from(42));
     }
+
+    #[test]
+    fn test_memory_limits() {
+        let mut tester = MemoryTester::new();
+        let page = tester.get_heap_location(0).page.0;
+        tester.write_query(tester.get_heap_location(0), U256::from(1));
+        tester.write_query(
+            tester.get_heap_location(PAGE_SUBDIVISION_LEN as u32),
+            U256::from(1),
+        );
+        let limit = tester.memory.memory.get_page_size(page as usize);
+        tester.memory.set_limits(MemoryLimits {
+            max_total_size: None,
+            max_page_size: Some(limit),
+        });
+        assert!(tester.memory.check_limits().is_ok());
+
+        tester.write_query(
+            tester.get_heap_location(2 * PAGE_SUBDIVISION_LEN as u32),
+            U256::from(1),
+        );
+        assert_eq!(
+            tester.memory.limit_exceeded(),
+            Some(MemoryLimitExceeded::Page {
+                page,
+                size: tester.memory.memory.get_page_size(page as usize),
+                limit,
+            })
+        );
+        assert!(tester.memory.check_limits().is_err());
+
+        let mut tester = MemoryTester::new();
+        tester.write_query(tester.get_heap_location(0), U256::from(1));
+        tester.memory.set_limits(MemoryLimits {
+            max_total_size: Some(tester.memory.metrics().current_size),
+            max_page_size: None,
+        });
+        assert!(tester.memory.check_limits().is_ok());
+
+        tester.start_frame_with_code(Address::zero(), vec![]);
+        tester.write_query(tester.get_heap_location(0), U256::from(1));
+        assert!(matches!(
+            tester.memory.limit_exceeded(),
+            Some(MemoryLimitExceeded::Total { .. })
+        ));
+    }
+
+    #[test]
+    fn test_memory_limits_count_page_indices() {
+        let limits = MemoryLimits {
+            max_total_size: Some(1 << 16),
+            max_page_size: None,
+        };
+
+        // a single chunk at a high offset grows the index of the page
+        let mut tester = MemoryTester::new();
+        tester.memory.set_limits(limits);
+        let location = tester.get_heap_location(1 << 20);
+        tester.write_query(location, U256::from(1));
+        assert!(
+            tester.memory.memory.get_page_size(location.page.0 as usize)
+                >= PAGE_SUBDIVISION_SIZE
+                    + ((1 << 20) / PAGE_SUBDIVISION_LEN + 1) * PAGE_ROOT_ENTRY_SIZE
+        );
+        assert!(matches!(
+            tester.memory.limit_exceeded(),
+            Some(MemoryLimitExceeded::Total { .. })
+        ));
+
+        // a single chunk in a high page grows the page table
+        let mut tester = MemoryTester::new();
+        tester.memory.set_limits(limits);
+        tester
+            .memory
+            .populate_page(vec![(1 << 16, vec![U256::from(1)])]);
+        let metrics = tester.memory.metrics();
+        assert!(metrics.current_size >= ((1 << 16) + 1) * PAGE_TABLE_ENTRY_SIZE);
+        assert_eq!(metrics.allocated_size, metrics.current_size);
+        assert!(matches!(
+            tester.memory.limit_exceeded(),
+            Some(MemoryLimitExceeded::Total { .. })
+        ));
+    }
+
+    #[test]
+    fn test_frame_memory_usage() {
+        let mut tester = MemoryTester::new();
+        tester.write_query(tester.get_heap_location(0), U256::from(1));
+        let root_allocated = tester.memory.metrics().current_size;
+
+        let base_page = tester.start_frame_with_code(Address::zero(), vec![U256::from(42)]);
+        tester.write_query(tester.get_heap_location(0), U256::from(1));
+        tester.write_query(
+            tester.get_heap_location(PAGE_SUBDIVISION_LEN as u32),
+            U256::from(1),
+        );
+        let frame_allocated = tester.memory.metrics().current_size - root_allocated;
+        let heap_size = tester
+            .memory
+            .memory
+            .get_page_size(heap_page_from_base(base_page).0 as usize);
+        assert!(heap_size > 2 * PAGE_SUBDIVISION_SIZE);
+        assert!(frame_allocated > heap_size + PAGE_SUBDIVISION_SIZE);
+        assert_eq!(
+            tester.memory.frames_memory_usage().last(),
+            Some(&FrameMemoryUsage {
+                allocated: frame_allocated,
+                released: 0,
+            })
+        );
+
+        // the heap is released, the code page is kept
+        tester.finish_frame(FatPointer::empty());
+        assert_eq!(tester.memory.frames_memory_usage().len(), 2);
+        assert_eq!(
+            tester.memory.frames_memory_usage().last(),
+            Some(&FrameMemoryUsage {
+                allocated: root_allocated + frame_allocated,
+                released: heap_size,
+            })
+        );
+        assert_eq!(
+            tester.memory.metrics(),
+            MemoryMetrics {
+                current_size: root_allocated + frame_allocated - heap_size,
+                peak_size: root_allocated + frame_allocated,
+                allocated_size: root_allocated + frame_allocated,
+                released_size: heap_size,
+                released_pages: 1,
+            }
+        );
+    }
 }
@@ -0,0 +1,276 @@
+//! Comparison of two execution traces aligned by call frames. Frames are matched by their position
+//! in the call tree, and the cycles of a frame are compared without the cycles of its child frames,
+//! so the difference in one callee doesn't shift the comparison of the rest of the caller.
+
+use std::fmt::{self, Display};
+
+use crate::execution_trace::{cycle_difference, ExecutionTrace, TraceCycle};
+use zk_evm_abstractions::aux::{MemoryPage, Timestamp};
+
+use super::*;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FrameItem {
+    /// Index of the cycle in the trace
+    Cycle(usize),
+    /// Index of the child frame in the list of frames
+    Call(usize),
+}
+
+/// Call frame (far or near) of the trace
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TraceFrame {
+    /// Number of the child frame on every level of the call tree, empty for the root frame
+    pub path: Vec<usize>,
+    pub code_address: Address,
+    /// Index of the cycle that entered the frame, `None` for the root frame
+    pub call_cycle: Option<usize>,
+    /// Cycles executed in the frame and the entries into the child frames, in the order of execution
+    pub items: Vec<FrameItem>,
+}
+
+/// Splits the trace into the frames by the callstack depth, frames are ordered by the entry.
+/// The root frame is always present, even for an empty trace.
+pub fn split_into_frames(trace: &ExecutionTrace) -> Vec<TraceFrame> {
+    let first = trace.cycles.first();
+    let mut frames = vec![TraceFrame {
+        path: vec![],
+        code_address: first.map_or(Address::zero(), |el| el.code_address),
+        call_cycle: None,
+        items: vec![],
+    }];
+    let mut children = vec![0];
+    let mut stack = vec![0];
+    let mut depth = first.map_or(0, |el| el.callstack_depth);
+
+    for (idx, cycle) in trace.cycles.iter().enumerate() {
+        while cycle.callstack_depth < depth && stack.len() > 1 {
+            stack.pop();
+            depth -= 1;
+        }
+        while cycle.callstack_depth > depth {
+            let parent = *stack.last().unwrap();
+            let mut path = frames[parent].path.clone();
+            path.push(children[parent]);
+            children[parent] += 1;
+            let child = frames.len();
+            frames[parent].items.push(FrameItem::Call(child));
+            stack.push(child);
+            frames.push(TraceFrame {
+                path,
+                code_address: cycle.code_address,
+                call_cycle: idx.checked_sub(1),
+                items: vec![],
+            });
+            children.push(0);
+            depth += 1;
+        }
+        frames[*stack.last().unwrap()]
+            .items
+            .push(FrameItem::Cycle(idx));
+    }
+
+    frames
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FrameContext {
+    pub code_address: Address,
+    /// Pc of the call in the parent frame, `None` for the root frame
+    pub call_pc: Option<u16>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FrameDivergence {
+    /// Path of the frame in the call tree, see `TraceFrame::path`
+    pub path: Vec<usize>,
+    /// Position in the frame, counting the cycles and the calls of the child frames
+    pub position: usize,
+    /// Description of the first differing field
+    pub difference: String,
+    /// Indexes of the differing cycles in the traces
+    pub expected_cycle: Option<usize>,
+    pub actual_cycle: Option<usize>,
+    pub expected: Option<TraceCycle>,
+    pub actual: Option<TraceCycle>,
+    /// Frames from the root to the diverged one, taken from the expected trace if the frame exists there
+    pub callstack: Vec<FrameContext>,
+}
+
+impl Display for FrameDivergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "frame [{}], position {}: {}",
+            self.path
+                .iter()
+                .map(|el| el.to_string())
+                .collect::<Vec<_>>()
+                .join(", "),
+            self.position,
+            self.difference
+        )?;
+        for (name, idx, cycle) in [
+            ("expected", self.expected_cycle, &self.expected),
+            ("actual", self.actual_cycle, &self.actual),
+        ] {
+            if let (Some(idx), Some(cycle)) = (idx, cycle) {
+                writeln!(
+                    f,
+                    "  {}: {:?} at pc {}, ergs {}, trace cycle {}",
+                    name,
+                    cycle.decoded_opcode().variant,
+                    cycle.pc,
+                    cycle.ergs_remaining,
+                    idx
+                )?;
+            }
+        }
+        write!(f, "  callstack:")?;
+        for (depth, frame) in self.callstack.iter().enumerate().rev() {
+            write!(f, "\n    #{} {:?}", depth, frame.code_address)?;
+            if let Some(pc) = frame.call_pc {
+                write!(f, ", called at pc {}", pc)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn frame_callstack(
+    trace: &ExecutionTrace,
+    frames: &[TraceFrame],
+    path: &[usize],
+) -> Option<Vec<FrameContext>> {
+    (0..=path.len())
+        .map(|len| {
+            let frame = frames.iter().find(|el| el.path == path[..len])?;
+            Some(FrameContext {
+                code_address: frame.code_address,
+                call_pc: frame.call_cycle.map(|idx| trace.cycles[idx].pc),
+            })
+        })
+        .collect()
+}
+
+/// Cycle counters, timestamps and memory pages depend on the number of the cycles executed before,
+/// so they are not compared: otherwise every cycle after a frame of a different length would differ
+fn without_counters(cycle: &TraceCycle) -> TraceCycle {
+    let mut cycle = cycle.clone();
+    cycle.monotonic_cycle_counter = 0;
+    for write in cycle.register_writes.iter_mut() {
+        if write.is_pointer {
+            // memory page of the fat pointer
+            write.value.0[0] &= u32::MAX as u64;
+        }
+    }
+    for query in cycle.memory_queries.iter_mut() {
+        query.timestamp = Timestamp(0);
+        query.location.page = MemoryPage(0);
+    }
+    for query in cycle.log_queries.iter_mut() {
+        query.timestamp = Timestamp(0);
+    }
+    for query in cycle.decommits.iter_mut() {
+        query.timestamp = Timestamp(0);
+        query.memory_page = MemoryPage(0);
+    }
+
+    cycle
+}
+
+/// First divergence of the traces aligned by frames: frames are visited in the order of entry
+/// in the expected trace, and the divergence that happens first in the expected trace is reported.
+/// Unlike `ExecutionTrace::first_divergence` the values that depend on the cycle counter are not compared.
+pub fn first_frame_divergence(
+    expected: &ExecutionTrace,
+    actual: &ExecutionTrace,
+) -> Option<FrameDivergence> {
+    let expected_frames = split_into_frames(expected);
+    let actual_frames = split_into_frames(actual);
+
+    // first cycle of the item in the expected trace, to order the divergences by the time of execution
+    let first_cycle = |frame: &TraceFrame, position: usize| -> usize {
+        let mut frame = frame;
+        let mut position = position;
+        loop {
+            match frame.items.get(position) {
+                Some(FrameItem::Cycle(idx)) => return *idx,
+                Some(FrameItem::Call(child)) => {
+                    frame = &expected_frames[*child];
+                    position = 0;
+                }
+                // after the end of the frame, that is the first cycle after the last item
+                None => match frame.items.last() {
+                    Some(FrameItem::Cycle(idx)) => return idx + 1,
+                    Some(FrameItem::Call(child)) => {
+                        frame = &expected_frames[*child];
+                        position = frame.items.len();
+                    }
+                    None => return frame.call_cycle.map_or(0, |el| el + 1),
+                },
+            }
+        }
+    };
+
+    let mut result: Option<(usize, FrameDivergence)> = None;
+    for expected_frame in expected_frames.iter() {
+        // frames that were not entered in the actual trace are reported by the parent
+        let Some(actual_frame) = actual_frames
+            .iter()
+            .find(|el| el.path == expected_frame.path)
+        else {
+            continue;
+        };
+
+        let len = expected_frame.items.len().max(actual_frame.items.len());
+        for position in 0..len {
+            let expected_item = expected_frame.items.get(position).copied();
+            let actual_item = actual_frame.items.get(position).copied();
+            let cycle = |trace: &ExecutionTrace, item| match item {
+                Some(FrameItem::Cycle(idx)) => Some((idx, trace.cycles[idx].clone())),
+                _ => None,
+            };
+            let expected_cycle = cycle(expected, expected_item);
+            let actual_cycle = cycle(actual, actual_item);
+
+            let difference = match (expected_item, actual_item) {
+                (Some(FrameItem::Call(_)), Some(FrameItem::Call(_))) | (None, None) => None,
+                (Some(FrameItem::Cycle(_)), Some(FrameItem::Cycle(_))) => cycle_difference(
+                    &without_counters(&expected_cycle.as_ref().unwrap().1),
+                    &without_counters(&actual_cycle.as_ref().unwrap().1),
+                ),
+                (Some(FrameItem::Call(_)), _) => Some("call is missing".to_owned()),
+                (_, Some(FrameItem::Call(_))) => Some("unexpected call".to_owned()),
+                (Some(FrameItem::Cycle(_)), None) => Some("cycle is missing".to_owned()),
+                (None, Some(FrameItem::Cycle(_))) => Some("unexpected cycle".to_owned()),
+            };
+            let Some(difference) = difference else {
+                continue;
+            };
+
+            let key = first_cycle(expected_frame, position);
+            if result.as_ref().map_or(true, |(el, _)| key < *el) {
+                let callstack = frame_callstack(expected, &expected_frames, &expected_frame.path)
+                    .expect("frame must have all the parents");
+                result = Some((
+                    key,
+                    FrameDivergence {
+                        path: expected_frame.path.clone(),
+                        position,
+                        difference,
+                        expected_cycle: expected_cycle.as_ref().map(|el| el.0),
+                        actual_cycle: actual_cycle.as_ref().map(|el| el.0),
+                        expected: expected_cycle.map(|el| el.1),
+                        actual: actual_cycle.map(|el| el.1),
+                        callstack,
+                    },
+                ));
+            }
+            break;
+        }
+    }
+
+    result.map(|(_, divergence)| divergence)
+}
@@ -461,6 +461,7 @@ impl<
         };
 
         after_masking_decoded.apply(self, prestate)?;
+        self.memory.check_limits()?;
 
         if !skip_cycle {
             self.increment_timestamp_after_cycle();
@@ -110,6 +110,11 @@ pub trait Memory: std::fmt::Debug {
 
     fn read_code_query(&self, monotonic_cycle_counter: u32, query: MemoryQuery) -> MemoryQuery;
 
+    // Report if the memory went over its budget, checked by the VM after every cycle
+    fn check_limits(&self) -> anyhow::Result<()> {
+        Ok(())
+    }
+
     // Notify that a certain page went out of scope and can be discarded
     fn start_global_frame(
         &mut self,
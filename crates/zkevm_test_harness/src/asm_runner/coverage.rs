@@ -0,0 +1,171 @@
+//! Instruction and branch coverage of the assembly tests, exported in the lcov format.
+//! Coverage is collected with `CoverageTracer` from zk_evm and mapped back to the source lines
+//! through `pc_line_mapping` of the assembler.
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+use crate::zk_evm::coverage::{BranchCoverage, ContractCoverage};
+use zkevm_assembly::zkevm_opcode_defs::decoding::EncodingModeProduction;
+use zkevm_assembly::zkevm_opcode_defs::Condition;
+use zkevm_assembly::Assembly;
+
+/// `pc_line_mapping` refers to the lines of the preprocessed source, and the preprocessing
+/// expands the directives and inserts the data sections. So every line of the original source
+/// is marked with its number in a comment, that survives the preprocessing.
+const SOURCE_LINE_MARKER: &str = "; __source_line=";
+
+/// Appends the line number comment to every line, should be done before `preprocess_asm`.
+/// The comment goes right after the line, the assembler doesn't trim the operands in front of it
+pub fn mark_source_lines(asm: &str) -> String {
+    asm.lines()
+        .enumerate()
+        .map(|(idx, line)| format!("{line}{SOURCE_LINE_MARKER}{}\n", idx + 1))
+        .collect()
+}
+
+/// Lines of the original source (1-based) per pc, for the assembly compiled from the marked source
+pub fn pc_source_lines(assembly: &Assembly) -> BTreeMap<usize, usize> {
+    // lines produced by the preprocessing are placed before the marked line they originate from
+    let lines: Vec<_> = assembly.assembly_code.lines().collect();
+    let mut source_lines = vec![None; lines.len()];
+    let mut current = None;
+    for (idx, line) in lines.iter().enumerate().rev() {
+        if let Some(position) = line.rfind(SOURCE_LINE_MARKER) {
+            current = line[position + SOURCE_LINE_MARKER.len()..]
+                .trim()
+                .parse::<usize>()
+                .ok();
+        }
+        source_lines[idx] = current;
+    }
+
+    assembly
+        .pc_line_mapping
+        .iter()
+        .filter_map(|(pc, line)| Some((*pc, source_lines.get(*line).copied().flatten()?)))
+        .collect()
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct FileCoverage {
+    /// Hits per line that has at least one instruction
+    pub lines: BTreeMap<usize, u64>,
+    /// Outcomes per conditional instruction, keyed by the line and the pc.
+    /// `None` if the instruction was never reached.
+    pub branches: BTreeMap<(usize, usize), Option<BranchCoverage>>,
+}
+
+impl FileCoverage {
+    fn merge(&mut self, other: &Self) {
+        for (line, hits) in other.lines.iter() {
+            *self.lines.entry(*line).or_default() += hits;
+        }
+        for (key, branch) in other.branches.iter() {
+            let entry = self.branches.entry(*key).or_default();
+            if let Some(branch) = branch {
+                let entry = entry.get_or_insert_with(BranchCoverage::default);
+                entry.taken += branch.taken;
+                entry.not_taken += branch.not_taken;
+            }
+        }
+    }
+}
+
+/// Coverage of the source files, accumulated over the tests
+#[derive(Clone, Debug, Default)]
+pub struct CoverageReport {
+    pub files: BTreeMap<String, FileCoverage>,
+}
+
+impl CoverageReport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the coverage of the contract compiled from the marked source (see `mark_source_lines`).
+    /// Contracts that were never executed have `None` coverage, and all their lines are reported as missed.
+    pub fn add_contract(
+        &mut self,
+        source_file: &str,
+        assembly: &Assembly,
+        coverage: Option<&ContractCoverage>,
+    ) -> Result<(), String> {
+        let opcodes = assembly
+            .opcodes::<8, EncodingModeProduction>()
+            .map_err(|el| format!("Failed to decode {}: {:?}", source_file, el))?;
+        let empty = ContractCoverage::default();
+        let coverage = coverage.unwrap_or(&empty);
+
+        let mut file = FileCoverage::default();
+        for (pc, line) in pc_source_lines(assembly) {
+            let hits = coverage.hits.get(&(pc as u16)).copied().unwrap_or(0);
+            // several instructions on the same line come from the expanded directives
+            let line_hits = file.lines.entry(line).or_default();
+            *line_hits = (*line_hits).max(hits);
+
+            if opcodes[pc].condition != Condition::Always {
+                let branch = if hits > 0 {
+                    // there is no outcome if the instruction was masked into a panic by an exception
+                    Some(
+                        coverage
+                            .branches
+                            .get(&(pc as u16))
+                            .copied()
+                            .unwrap_or_default(),
+                    )
+                } else {
+                    None
+                };
+                file.branches.insert((line, pc), branch);
+            }
+        }
+
+        self.files
+            .entry(source_file.to_owned())
+            .or_default()
+            .merge(&file);
+
+        Ok(())
+    }
+
+    /// Report in the lcov tracefile format. Every conditional instruction is a block with two branches:
+    /// the taken one (0) and the skipped one (1).
+    pub fn to_lcov(&self) -> String {
+        let mut result = String::new();
+        for (source_file, file) in self.files.iter() {
+            writeln!(result, "TN:").unwrap();
+            writeln!(result, "SF:{}", source_file).unwrap();
+
+            let mut branches_hit = 0;
+            for ((line, pc), branch) in file.branches.iter() {
+                let counts = match branch {
+                    Some(branch) => [branch.taken.to_string(), branch.not_taken.to_string()],
+                    None => ["-".to_owned(), "-".to_owned()],
+                };
+                for (idx, count) in counts.iter().enumerate() {
+                    writeln!(result, "BRDA:{},{},{},{}", line, pc, idx, count).unwrap();
+                }
+                if let Some(branch) = branch {
+                    branches_hit += (branch.taken > 0) as usize + (branch.not_taken > 0) as usize;
+                }
+            }
+            writeln!(result, "BRF:{}", file.branches.len() * 2).unwrap();
+            writeln!(result, "BRH:{}", branches_hit).unwrap();
+
+            for (line, hits) in file.lines.iter() {
+                writeln!(result, "DA:{},{}", line, hits).unwrap();
+            }
+            writeln!(result, "LF:{}", file.lines.len()).unwrap();
+            writeln!(
+                result,
+                "LH:{}",
+                file.lines.values().filter(|hits| **hits > 0).count()
+            )
+            .unwrap();
+            writeln!(result, "end_of_record").unwrap();
+        }
+
+        result
+    }
+}
@@ -0,0 +1,104 @@
+//! JUnit XML report, that is understood by most of the CI systems
+
+use super::AsmTestResult;
+
+fn escape(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => result.push_str("&amp;"),
+            '<' => result.push_str("&lt;"),
+            '>' => result.push_str("&gt;"),
+            '"' => result.push_str("&quot;"),
+            '\'' => result.push_str("&apos;"),
+            _ => result.push(c),
+        }
+    }
+
+    result
+}
+
+/// Report with a single test suite that contains all the results
+pub fn junit_xml(suite_name: &str, results: &[AsmTestResult]) -> String {
+    let failures = results.iter().filter(|el| !el.is_success()).count();
+    let total_time: f64 = results.iter().map(|el| el.duration.as_secs_f64()).sum();
+
+    let mut result = String::new();
+    result.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    result.push_str(&format!(
+        "<testsuites tests=\"{}\" failures=\"{}\" time=\"{:.3}\">\n",
+        results.len(),
+        failures,
+        total_time
+    ));
+    result.push_str(&format!(
+        "  <testsuite name=\"{}\" tests=\"{}\" failures=\"{}\" errors=\"0\" skipped=\"0\" time=\"{:.3}\">\n",
+        escape(suite_name),
+        results.len(),
+        failures,
+        total_time
+    ));
+    for test in results.iter() {
+        let name = escape(&test.name);
+        let time = test.duration.as_secs_f64();
+        match &test.failure {
+            None => result.push_str(&format!(
+                "    <testcase name=\"{}\" classname=\"{}\" time=\"{:.3}\"/>\n",
+                name,
+                escape(suite_name),
+                time
+            )),
+            Some(failure) => {
+                result.push_str(&format!(
+                    "    <testcase name=\"{}\" classname=\"{}\" time=\"{:.3}\">\n",
+                    name,
+                    escape(suite_name),
+                    time
+                ));
+                let message = failure.lines().next().unwrap_or_default();
+                result.push_str(&format!(
+                    "      <failure message=\"{}\">{}</failure>\n",
+                    escape(message),
+                    escape(failure)
+                ));
+                result.push_str("    </testcase>\n");
+            }
+        }
+    }
+    result.push_str("  </testsuite>\n");
+    result.push_str("</testsuites>\n");
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_junit_xml() {
+        let results = vec![
+            AsmTestResult {
+                name: "ok".to_owned(),
+                duration: Duration::from_millis(1500),
+                failure: None,
+            },
+            AsmTestResult {
+                name: "failed<1>".to_owned(),
+                duration: Duration::from_millis(250),
+                failure: Some("Expected panic with \"a\"\ndetails".to_owned()),
+            },
+        ];
+
+        let xml = junit_xml("asm", &results);
+        assert!(xml.contains("<testsuites tests=\"2\" failures=\"1\" time=\"1.750\">"));
+        assert!(xml.contains("<testcase name=\"ok\" classname=\"asm\" time=\"1.500\"/>"));
+        assert!(
+            xml.contains("<testcase name=\"failed&lt;1&gt;\" classname=\"asm\" time=\"0.250\">")
+        );
+        assert!(xml.contains(
+            "<failure message=\"Expected panic with &quot;a&quot;\">Expected panic with &quot;a&quot;\ndetails</failure>"
+        ));
+    }
+}
@@ -0,0 +1,79 @@
+use std::path::PathBuf;
+
+use structopt::StructOpt;
+use zkevm_test_harness::asm_runner::coverage::CoverageReport;
+use zkevm_test_harness::asm_runner::junit::junit_xml;
+use zkevm_test_harness::asm_runner::{run_asm_test, run_asm_test_with_coverage, AsmTest};
+
+#[derive(Debug, StructOpt)]
+#[structopt(
+    name = "EraVM assembly test runner",
+    about = "Runs .asm tests in the out-of-circuit VM and checks the circuits"
+)]
+struct Opt {
+    /// Test directories (with `entry.asm`, `ADDRESS.asm` dependencies and optional `test.json`)
+    /// or single `.asm` files
+    #[structopt(required = true)]
+    tests: Vec<PathBuf>,
+    /// Write the results in JUnit XML format to this file
+    #[structopt(long)]
+    junit: Option<PathBuf>,
+    /// Name of the test suite in the JUnit report
+    #[structopt(long, default_value = "asm_tests")]
+    suite_name: String,
+    /// Write the instruction and branch coverage in lcov format to this file
+    #[structopt(long)]
+    lcov: Option<PathBuf>,
+    /// Only run the out-of-circuit VM, without checking the circuits
+    #[structopt(long)]
+    skip_circuits: bool,
+    /// KZG trusted setup for the EIP4844 circuits
+    #[structopt(long)]
+    trusted_setup_path: Option<String>,
+}
+
+fn main() {
+    let opt = Opt::from_args();
+
+    let mut results = vec![];
+    let mut coverage = CoverageReport::new();
+    for path in opt.tests.iter() {
+        let test = if path.is_dir() {
+            AsmTest::from_dir(path)
+        } else {
+            AsmTest::from_file(path)
+        };
+        let mut test = test.unwrap_or_else(|el| panic!("Unable to load test {:?}: {}", path, el));
+        test.check_circuits = !opt.skip_circuits;
+        if let Some(trusted_setup_path) = opt.trusted_setup_path.as_ref() {
+            test.options.trusted_setup_path = trusted_setup_path.clone();
+        }
+
+        let result = if opt.lcov.is_some() {
+            run_asm_test_with_coverage(&test, &mut coverage)
+        } else {
+            run_asm_test(&test)
+        };
+        match &result.failure {
+            None => println!("{} ... ok ({:?})", result.name, result.duration),
+            Some(failure) => println!("{} ... FAILED\n{}", result.name, failure),
+        }
+        results.push(result);
+    }
+
+    let failed = results.iter().filter(|el| !el.is_success()).count();
+    println!("{} passed, {} failed", results.len() - failed, failed);
+
+    if let Some(path) = opt.junit.as_ref() {
+        std::fs::write(path, junit_xml(&opt.suite_name, &results))
+            .expect("Unable to write JUnit report");
+    }
+
+    if let Some(path) = opt.lcov.as_ref() {
+        std::fs::write(path, coverage.to_lcov()).expect("Unable to write lcov report");
+    }
+
+    if failed > 0 {
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,609 @@
+//! Runner for the EraVM assembly tests.
+//! Tests are compiled from `.asm` files (see `preprocess_asm` for the supported directives and templates),
+//! executed in the out-of-circuit VM, and then all the produced base layer circuits are checked for satisfiability.
+
+pub mod coverage;
+pub mod junit;
+pub mod preprocess_asm;
+pub mod storage;
+pub mod testing_tracer;
+
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::sync_channel;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::ethereum_types::{Address, H160, U256};
+use crate::helper::artifact_utils::save_predeployed_contracts;
+use crate::helper::circuit_utils::base_test_circuit;
+use crate::run_vms::{run_vms, RunVMsResult, RunVmError};
+use crate::toolset::GeometryConfig;
+use crate::witness::oracle::WitnessGenerationArtifact;
+use crate::witness::tree::{BinarySparseStorageTree, ZKSyncTestingTree};
+use crate::zk_evm::coverage::CoverageTracer;
+use crate::zk_evm::utils::bytecode_to_code_hash;
+use crate::zk_evm::zkevm_opcode_defs::system_params::BOOTLOADER_FORMAL_ADDRESS;
+use circuit_definitions::circuit_definitions::base_layer::ZkSyncBaseLayerCircuit;
+use serde::{Deserialize, Serialize};
+use zkevm_assembly::Assembly;
+
+use self::coverage::{mark_source_lines, CoverageReport};
+use self::preprocess_asm::{preprocess_asm, TemplateDictionary};
+use self::storage::InMemoryCustomRefundStorage;
+use self::testing_tracer::TestingTracer;
+
+const DEFAULT_CYCLE_LIMIT: usize = 50;
+const DEFAULT_CYCLES_PER_VM_SNAPSHOT: u32 = 5;
+const DEFAULT_TRUSTED_SETUP_PATH: &str =
+    concat!(env!("CARGO_MANIFEST_DIR"), "/../kzg/src/trusted_setup.json");
+
+#[derive(Clone)]
+pub struct Options {
+    // How many cycles should the main VM run for.
+    // If not set - default is DEFAULT_CYCLE_LIMIT (50).
+    pub cycle_limit: usize,
+    // Additional contracts that should be deployed (pairs 'address, bytecode')
+    pub other_contracts: Vec<(H160, Vec<[u8; 32]>)>,
+    // How many cycles should a single VM handle (default is DEFAULT_CYCLES_PER_VM_SNAPSHOT = 5)
+    pub cycles_per_vm_snapshot: u32,
+    // KZG trusted setup, needed for the EIP4844 circuits (default is the one from the kzg crate)
+    pub trusted_setup_path: String,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            cycle_limit: DEFAULT_CYCLE_LIMIT,
+            other_contracts: Default::default(),
+            cycles_per_vm_snapshot: DEFAULT_CYCLES_PER_VM_SNAPSHOT,
+            trusted_setup_path: DEFAULT_TRUSTED_SETUP_PATH.to_owned(),
+        }
+    }
+}
+
+/// Out-of-circuit execution didn't finish successfully
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExecutionError {
+    InvalidInput(String),
+    /// Root frame panicked or VM didn't finish within the cycle limit.
+    /// `panic_message` is the message of the `revert("...")` directive
+    /// or the name of the VM error that caused the panic.
+    Failed {
+        reason: String,
+        panic_message: Option<String>,
+    },
+}
+
+impl fmt::Display for ExecutionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExecutionError::InvalidInput(msg) => write!(f, "Invalid input error: {msg}"),
+            ExecutionError::Failed { reason, .. } => {
+                write!(f, "Out-of-circuit execution error: {reason}")
+            }
+        }
+    }
+}
+
+/// Small geometry, so even the short tests produce several instances of every circuit
+pub(crate) fn test_geometry(cycles_per_vm_snapshot: u32) -> GeometryConfig {
+    GeometryConfig {
+        cycles_per_vm_snapshot,
+        cycles_code_decommitter_sorter: 16,
+        cycles_per_log_demuxer: 8,
+        cycles_per_storage_sorter: 4,
+        cycles_per_events_or_l1_messages_sorter: 2,
+        cycles_per_ram_permutation: 4,
+        cycles_per_code_decommitter: 4,
+        cycles_per_storage_application: 2,
+        cycles_per_keccak256_circuit: 1,
+        cycles_per_sha256_circuit: 1,
+        cycles_per_ecrecover_circuit: 1,
+        cycles_per_secp256r1_verify_circuit: 1,
+        cycles_per_transient_storage_sorter: 4,
+
+        limit_for_l1_messages_pudata_hasher: 8,
+    }
+}
+
+/// Runs the out-of-circuit VM for the entry point and collects the base layer circuits
+pub fn execute(
+    entry_point_bytecode: Vec<[u8; 32]>,
+    options: Options,
+) -> Result<(Vec<ZkSyncBaseLayerCircuit>, RunVMsResult), ExecutionError> {
+    execute_inner(entry_point_bytecode, options, false).0
+}
+
+/// Coverage is returned even if the execution failed
+fn execute_inner(
+    entry_point_bytecode: Vec<[u8; 32]>,
+    options: Options,
+    collect_coverage: bool,
+) -> (
+    Result<(Vec<ZkSyncBaseLayerCircuit>, RunVMsResult), ExecutionError>,
+    Option<CoverageTracer>,
+) {
+    let geometry = test_geometry(options.cycles_per_vm_snapshot);
+
+    let mut used_bytecodes_and_hashes = HashMap::new();
+    used_bytecodes_and_hashes.extend(options.other_contracts.iter().cloned().map(|(_, code)| {
+        let code_hash = bytecode_to_code_hash(&code).unwrap();
+
+        (U256::from_big_endian(&code_hash), code)
+    }));
+
+    // We must pass a correct empty code hash (with proper version) into the run method.
+    let empty_code_hash = U256::from_big_endian(&bytecode_to_code_hash(&[[0; 32]]).unwrap());
+
+    let mut storage_impl = InMemoryCustomRefundStorage::new();
+
+    let mut tree = ZKSyncTestingTree::empty();
+
+    let mut known_contracts = HashMap::new();
+    known_contracts.extend(options.other_contracts.iter().cloned());
+
+    save_predeployed_contracts(&mut storage_impl.storage, &mut tree, &known_contracts);
+
+    let mut basic_block_circuits = vec![];
+
+    // we are using TestingTracer to track prints and exceptions inside out_of_circuit_vm cycles
+    let mut out_of_circuit_tracer =
+        TestingTracer::new(Some(storage_impl.create_refund_controller()));
+    if collect_coverage {
+        out_of_circuit_tracer = out_of_circuit_tracer.with_coverage();
+    }
+
+    let (sender, receiver) = sync_channel(1);
+
+    let artifacts_receiver_handle = thread::spawn(move || {
+        while let Ok(artifact) = receiver.recv() {
+            if let WitnessGenerationArtifact::BaseLayerCircuit(circuit) = artifact {
+                basic_block_circuits.push(circuit)
+            }
+        }
+
+        basic_block_circuits
+    });
+
+    let result = run_vms(
+        Address::zero(),
+        *BOOTLOADER_FORMAL_ADDRESS,
+        entry_point_bytecode,
+        vec![],
+        false,
+        empty_code_hash,
+        empty_code_hash,
+        used_bytecodes_and_hashes,
+        vec![],
+        options.cycle_limit,
+        geometry,
+        storage_impl,
+        tree,
+        options.trusted_setup_path,
+        std::array::from_fn(|_| None),
+        sender,
+        &mut out_of_circuit_tracer,
+    );
+    let basic_block_circuits = artifacts_receiver_handle.join().unwrap();
+    let coverage = out_of_circuit_tracer.coverage.take();
+
+    let result = result.map_err(|err| match err {
+        RunVmError::InvalidInput(msg) => ExecutionError::InvalidInput(msg),
+        RunVmError::OutOfCircuitExecutionError(msg) => match out_of_circuit_tracer.exception {
+            Some(exception) => ExecutionError::Failed {
+                reason: format!("{msg} {exception}"),
+                panic_message: exception.exception_message.clone(),
+            },
+            None => ExecutionError::Failed {
+                reason: msg,
+                panic_message: None,
+            },
+        },
+    });
+    let result = match result {
+        Ok(result) => result,
+        Err(err) => return (Err(err), coverage),
+    };
+
+    (Ok((basic_block_circuits, result)), coverage)
+}
+
+/// Value of the storage slot after the execution
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StorageValue {
+    pub address: H160,
+    pub key: U256,
+    pub value: U256,
+}
+
+/// Part of the event emitted with `log.event`, events that were rolled back are not included
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EmittedEvent {
+    pub address: H160,
+    pub key: U256,
+    pub value: U256,
+    /// Set for the first part of the event (`log.event.first`)
+    #[serde(default)]
+    pub is_first: bool,
+}
+
+/// Storage slots accessed during the execution, with their final values
+pub fn final_storage_values(circuits: &[ZkSyncBaseLayerCircuit]) -> Vec<StorageValue> {
+    let mut result = vec![];
+    for circuit in circuits.iter() {
+        let ZkSyncBaseLayerCircuit::StorageApplication(inner) = circuit else {
+            continue;
+        };
+        // storage application works with the sorted and deduplicated queue,
+        // so there is exactly one query per slot
+        for (query, _) in inner
+            .clone_witness()
+            .unwrap()
+            .storage_queue_witness
+            .elements
+        {
+            result.push(StorageValue {
+                address: query.address,
+                key: query.key,
+                value: if query.rw_flag {
+                    query.written_value
+                } else {
+                    query.read_value
+                },
+            });
+        }
+    }
+
+    result
+}
+
+/// Events emitted during the execution, in the order of emission
+pub fn emitted_events(circuits: &[ZkSyncBaseLayerCircuit]) -> Vec<EmittedEvent> {
+    let mut result: Vec<(u32, EmittedEvent)> = vec![];
+    for circuit in circuits.iter() {
+        let ZkSyncBaseLayerCircuit::EventsSorter(inner) = circuit else {
+            continue;
+        };
+        // queue is sorted by timestamp, and rollback is placed right after the event it cancels
+        for (query, _) in inner
+            .clone_witness()
+            .unwrap()
+            .intermediate_sorted_queue_witness
+            .elements
+        {
+            if query.rollback {
+                let (timestamp, _) = result.pop().expect("rollback must follow the event");
+                assert_eq!(timestamp, query.timestamp);
+                continue;
+            }
+            result.push((
+                query.timestamp,
+                EmittedEvent {
+                    address: query.address,
+                    key: query.key,
+                    value: query.written_value,
+                    is_first: query.is_service,
+                },
+            ));
+        }
+    }
+
+    result.into_iter().map(|(_, event)| event).collect()
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExpectedOutcome {
+    #[default]
+    Success,
+    /// Root frame should panic, with the given message if it's set
+    Panic(Option<String>),
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AsmTestExpectations {
+    pub outcome: ExpectedOutcome,
+    /// Slots that should have the given values after the execution, other slots are not checked
+    pub storage: Vec<StorageValue>,
+    /// Exact list of the emitted events, not checked if not set
+    pub events: Option<Vec<EmittedEvent>>,
+}
+
+/// Optional `test.json` file in the test directory
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AsmTestManifest {
+    #[serde(flatten)]
+    pub expectations: AsmTestExpectations,
+    pub cycle_limit: Option<usize>,
+    pub cycles_per_vm_snapshot: Option<u32>,
+    /// Values for the `${KEY}` tags in the templates
+    pub dictionary: HashMap<String, String>,
+}
+
+#[derive(Clone)]
+pub struct AsmTest {
+    pub name: String,
+    /// Source of the entry point contract
+    pub entry_point: String,
+    /// Sources of the contracts that are deployed at the given addresses.
+    /// Their code hashes can be used in the entry point as `<ADDRESS.asm>`
+    pub contracts: Vec<(u64, String)>,
+    pub dictionary: HashMap<String, String>,
+    pub expectations: AsmTestExpectations,
+    /// Other contracts in the options are ignored, they are compiled from `contracts`
+    pub options: Options,
+    /// If not set - only the out-of-circuit execution is checked
+    pub check_circuits: bool,
+    /// File of the entry point, the contracts are expected in the same directory.
+    /// Used to name the sources in the coverage reports.
+    pub entry_point_path: Option<PathBuf>,
+}
+
+fn read_source(path: &Path) -> Result<String, String> {
+    std::fs::read_to_string(path).map_err(|el| format!("Unable to read {:?}: {}", path, el))
+}
+
+impl AsmTest {
+    /// Test with the default expectations (successful execution) from a single `.asm` file
+    pub fn from_file(path: &Path) -> Result<Self, String> {
+        Ok(Self {
+            name: path.to_string_lossy().to_string(),
+            entry_point: read_source(path)?,
+            contracts: vec![],
+            dictionary: HashMap::new(),
+            expectations: AsmTestExpectations::default(),
+            options: Options::default(),
+            check_circuits: true,
+            entry_point_path: Some(path.to_owned()),
+        })
+    }
+
+    /// Test from the directory in the same layout as in `simple_tests/testdata`:
+    /// the entry point is in `entry.asm`, additional contracts are in `ADDRESS.asm` files,
+    /// where `ADDRESS` is the numerical address at which they should be deployed.
+    /// Expectations and options are read from `test.json` if it exists, see `AsmTestManifest`.
+    pub fn from_dir(path: &Path) -> Result<Self, String> {
+        let mut contracts = vec![];
+        let entries =
+            std::fs::read_dir(path).map_err(|el| format!("Unable to read {:?}: {}", path, el))?;
+        for entry in entries {
+            let entry_path = entry.map_err(|el| el.to_string())?.path();
+            if entry_path.extension().and_then(|el| el.to_str()) != Some("asm") {
+                continue;
+            }
+            let stem = entry_path.file_stem().unwrap().to_string_lossy();
+            if let Ok(address) = stem.parse::<u64>() {
+                contracts.push((address, read_source(&entry_path)?));
+            }
+        }
+        contracts.sort_by_key(|(address, _)| *address);
+
+        let manifest_path = path.join("test.json");
+        let manifest: AsmTestManifest = if manifest_path.exists() {
+            serde_json::from_str(&read_source(&manifest_path)?)
+                .map_err(|el| format!("Invalid {:?}: {}", manifest_path, el))?
+        } else {
+            AsmTestManifest::default()
+        };
+
+        let mut options = Options::default();
+        if let Some(cycle_limit) = manifest.cycle_limit {
+            options.cycle_limit = cycle_limit;
+        }
+        if let Some(cycles_per_vm_snapshot) = manifest.cycles_per_vm_snapshot {
+            options.cycles_per_vm_snapshot = cycles_per_vm_snapshot;
+        }
+
+        Ok(Self {
+            name: path.to_string_lossy().to_string(),
+            entry_point: read_source(&path.join("entry.asm"))?,
+            contracts,
+            dictionary: manifest.dictionary,
+            expectations: manifest.expectations,
+            options,
+            check_circuits: true,
+            entry_point_path: Some(path.join("entry.asm")),
+        })
+    }
+
+    /// Name of the source in the coverage reports, `contract` is the address of the contract or `None` for the entry point
+    pub(crate) fn source_name(&self, contract: Option<u64>) -> String {
+        match (&self.entry_point_path, contract) {
+            (Some(path), None) => path.to_string_lossy().to_string(),
+            (Some(path), Some(address)) => path
+                .with_file_name(format!("{address}.asm"))
+                .to_string_lossy()
+                .to_string(),
+            (None, None) => self.name.clone(),
+            (None, Some(address)) => format!("{}/{}.asm", self.name, address),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct AsmTestResult {
+    pub name: String,
+    pub duration: Duration,
+    /// Reason of the failure, `None` if the test passed
+    pub failure: Option<String>,
+}
+
+impl AsmTestResult {
+    pub fn is_success(&self) -> bool {
+        self.failure.is_none()
+    }
+}
+
+pub(crate) fn compile(
+    asm: &str,
+    additional_contracts: Option<&Vec<(H160, Vec<[u8; 32]>)>>,
+    dictionary: &TemplateDictionary,
+) -> Result<Vec<[u8; 32]>, String> {
+    assemble(asm, additional_contracts, dictionary).map(|(_, bytecode)| bytecode)
+}
+
+/// Same as `compile`, also returns the assembly with the labels and the pc to line mapping
+pub(crate) fn assemble(
+    asm: &str,
+    additional_contracts: Option<&Vec<(H160, Vec<[u8; 32]>)>>,
+    dictionary: &TemplateDictionary,
+) -> Result<(Assembly, Vec<[u8; 32]>), String> {
+    let asm_preprocessed = preprocess_asm(asm.to_owned(), additional_contracts, Some(dictionary));
+    let mut assembly = Assembly::try_from(asm_preprocessed)
+        .map_err(|el| format!("Failed to parse assembly: {:?}", el))?;
+    let bytecode = assembly
+        .compile_to_bytecode()
+        .map_err(|el| format!("Failed to compile assembly: {:?}", el))?;
+
+    Ok((assembly, bytecode))
+}
+
+fn check_expectations(
+    test: &AsmTest,
+    execution_result: Result<Vec<ZkSyncBaseLayerCircuit>, ExecutionError>,
+) -> Result<(), String> {
+    let circuits = match (&test.expectations.outcome, execution_result) {
+        (ExpectedOutcome::Success, Ok(circuits)) => circuits,
+        (ExpectedOutcome::Success, Err(err)) => return Err(err.to_string()),
+        (ExpectedOutcome::Panic(_), Ok(_)) => {
+            return Err("Expected panic, but execution succeeded".to_owned())
+        }
+        (ExpectedOutcome::Panic(expected), Err(err)) => match err {
+            ExecutionError::Failed { panic_message, .. } => {
+                if expected.is_some() && expected != &panic_message {
+                    return Err(format!(
+                        "Expected panic with {:?}, got {:?}",
+                        expected, panic_message
+                    ));
+                }
+                // no circuits are produced for the failed execution
+                return Ok(());
+            }
+            err => return Err(err.to_string()),
+        },
+    };
+
+    let storage = final_storage_values(&circuits);
+    for expected in test.expectations.storage.iter() {
+        let actual = storage
+            .iter()
+            .find(|el| el.address == expected.address && el.key == expected.key)
+            .map(|el| el.value)
+            .unwrap_or_default();
+        if actual != expected.value {
+            return Err(format!(
+                "Storage slot {:?}:{:#x} has value {:#x}, expected {:#x}",
+                expected.address, expected.key, actual, expected.value
+            ));
+        }
+    }
+
+    if let Some(expected) = test.expectations.events.as_ref() {
+        let events = emitted_events(&circuits);
+        if expected != &events {
+            return Err(format!(
+                "Emitted events {:?}, expected {:?}",
+                events, expected
+            ));
+        }
+    }
+
+    if test.check_circuits {
+        for circuit in circuits {
+            let description = circuit.short_description();
+            std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| base_test_circuit(circuit)))
+                .map_err(|_| format!("{} circuit is not satisfied", description))?;
+        }
+    }
+
+    Ok(())
+}
+
+pub(crate) fn panic_reason(panic: Box<dyn std::any::Any + Send>) -> String {
+    panic
+        .downcast_ref::<&str>()
+        .map(|el| el.to_string())
+        .or_else(|| panic.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "unknown panic".to_string())
+}
+
+/// Compiles and runs the test, panics inside of the VM or the circuits are reported as failures
+pub fn run_asm_test(test: &AsmTest) -> AsmTestResult {
+    run_asm_test_inner(test, None)
+}
+
+/// Same as `run_asm_test`, and adds the coverage of the entry point and the contracts to the report.
+/// Coverage is not added if the test didn't compile or the VM panicked.
+pub fn run_asm_test_with_coverage(test: &AsmTest, report: &mut CoverageReport) -> AsmTestResult {
+    run_asm_test_inner(test, Some(report))
+}
+
+fn run_asm_test_inner(test: &AsmTest, mut report: Option<&mut CoverageReport>) -> AsmTestResult {
+    let start = Instant::now();
+    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        let dictionary: TemplateDictionary = test
+            .dictionary
+            .iter()
+            .map(|(key, value)| (key.as_str(), value.clone()))
+            .collect();
+        let collect_coverage = report.is_some();
+        let source = |asm: &str| {
+            if collect_coverage {
+                mark_source_lines(asm)
+            } else {
+                asm.to_owned()
+            }
+        };
+
+        let compiled_contracts = test
+            .contracts
+            .iter()
+            .map(|(_, asm)| assemble(&source(asm), None, &dictionary))
+            .collect::<Result<Vec<_>, _>>()?;
+        let contracts: Vec<_> = test
+            .contracts
+            .iter()
+            .zip(compiled_contracts.iter())
+            .map(|((address, _), (_, bytecode))| {
+                (Address::from_low_u64_be(*address), bytecode.clone())
+            })
+            .collect();
+        let (entry_point, entry_point_bytecode) =
+            assemble(&source(&test.entry_point), Some(&contracts), &dictionary)?;
+
+        let mut options = test.options.clone();
+        options.other_contracts = contracts;
+        let (execution_result, coverage) =
+            execute_inner(entry_point_bytecode, options, collect_coverage);
+
+        if let (Some(report), Some(coverage)) = (report.as_mut(), coverage) {
+            report.add_contract(
+                &test.source_name(None),
+                &entry_point,
+                coverage.contract(&BOOTLOADER_FORMAL_ADDRESS),
+            )?;
+            for ((address, _), (assembly, _)) in
+                test.contracts.iter().zip(compiled_contracts.iter())
+            {
+                report.add_contract(
+                    &test.source_name(Some(*address)),
+                    assembly,
+                    coverage.contract(&Address::from_low_u64_be(*address)),
+                )?;
+            }
+        }
+
+        check_expectations(test, execution_result.map(|el| el.0))
+    }))
+    .unwrap_or_else(|panic| Err(panic_reason(panic)));
+
+    AsmTestResult {
+        name: test.name.clone(),
+        duration: start.elapsed(),
+        failure: result.err(),
+    }
+}
@@ -8,7 +8,7 @@ use circuit_definitions::zk_evm::{
 
 /// Enum holding the types of storage refunds
 #[derive(Debug, Copy, Clone)]
-pub(crate) enum StorageRefund {
+pub enum StorageRefund {
     Cold,
     Warm,
 }
@@ -64,10 +64,10 @@ impl Storage for InMemoryCustomRefundStorage {
     ) -> StorageAccessRefund {
         let storage_refund = self.slot_refund.borrow();
         match storage_refund.0 {
-            StorageRefund::Cold => dbg!(StorageAccessRefund::Cold),
-            StorageRefund::Warm => dbg!(StorageAccessRefund::Warm {
-                ergs: storage_refund.1
-            }),
+            StorageRefund::Cold => StorageAccessRefund::Cold,
+            StorageRefund::Warm => StorageAccessRefund::Warm {
+                ergs: storage_refund.1,
+            },
         }
     }
 
@@ -15,18 +15,19 @@ use zkevm_assembly::zkevm_opcode_defs::Opcode;
 use zkevm_assembly::zkevm_opcode_defs::PtrOpcode;
 use zkevm_assembly::zkevm_opcode_defs::RetOpcode;
 
+use crate::asm_runner::storage::RefundController;
+use crate::asm_runner::storage::StorageRefund;
 use crate::ethereum_types::U256;
-use crate::tests::storage::RefundController;
-use crate::tests::storage::StorageRefund;
+use crate::zk_evm::coverage::CoverageTracer;
 use crate::zk_evm::reference_impls::memory::SimpleMemory;
 use crate::zk_evm::tracing::*;
 
-use crate::tests::utils::preprocess_asm::EXCEPTION_PREFIX;
-use crate::tests::utils::preprocess_asm::PRINT_PREFIX;
-use crate::tests::utils::preprocess_asm::PRINT_PTR_PREFIX;
-use crate::tests::utils::preprocess_asm::PRINT_REG_PREFIX;
-use crate::tests::utils::preprocess_asm::STORAGE_REFUND_COLD_PREFIX;
-use crate::tests::utils::preprocess_asm::STORAGE_REFUND_WARM_PREFIX;
+use crate::asm_runner::preprocess_asm::EXCEPTION_PREFIX;
+use crate::asm_runner::preprocess_asm::PRINT_PREFIX;
+use crate::asm_runner::preprocess_asm::PRINT_PTR_PREFIX;
+use crate::asm_runner::preprocess_asm::PRINT_REG_PREFIX;
+use crate::asm_runner::preprocess_asm::STORAGE_REFUND_COLD_PREFIX;
+use crate::asm_runner::preprocess_asm::STORAGE_REFUND_WARM_PREFIX;
 
 #[derive(Debug, Clone, PartialEq, Default)]
 enum TracerState {
@@ -95,6 +96,8 @@ pub struct TestingTracer<const N: usize = 8, E: VmEncodingMode<N> = EncodingMode
     message_buffer: Option<String>,
     /// Optional controller to set the type/amount of refund for storage slot access
     storage_refund_controller: Option<RefundController>,
+    /// Executed instructions and branches, collected if set
+    pub coverage: Option<CoverageTracer>,
 }
 
 /// TestingTracer interprets valid x values in `add x r0 r0` and `ptr.add x r0 r0` instructions as commands to execute.
@@ -115,9 +118,15 @@ impl<const N: usize, E: VmEncodingMode<N>> TestingTracer<N, E> {
             tracer_state: TracerState::default(),
             message_buffer: None,
             storage_refund_controller,
+            coverage: None,
         }
     }
 
+    pub fn with_coverage(mut self) -> Self {
+        self.coverage = Some(CoverageTracer::new());
+        self
+    }
+
     fn reset_exception(&mut self) {
         self.exception = None;
     }
@@ -265,6 +274,10 @@ impl<const N: usize, E: VmEncodingMode<N>> Tracer<N, E> for TestingTracer<N, E>
         data: AfterDecodingData<N, E>,
         _memory: &Self::SupportedMemory,
     ) {
+        if let Some(coverage) = self.coverage.as_mut() {
+            coverage.record_decoded_opcode(state.vm_local_state, &data);
+        }
+
         if let Opcode::Ret(RetOpcode::Panic | RetOpcode::Revert) =
             data.opcode_masked.inner.variant.opcode
         {
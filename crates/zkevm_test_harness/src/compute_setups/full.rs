@@ -279,6 +279,8 @@ pub fn recursive_layer_vk_count() -> usize {
 
 /// num_threads control how many VKs are generated in parallel - each one takes around 25GB of RAM.
 /// if not specified, will run them sequencially.
+/// Leaf and node circuits are generated for the recursion tree arity from the source
+/// (see `SetupDataSource::set_recursion_tree_arity`), default one if it's not set.
 pub fn generate_recursive_layer_vks<CB: Fn() + Send + Sync>(
     source: &mut dyn SetupDataSource,
     num_threads: Option<usize>,
@@ -393,6 +395,41 @@ pub fn generate_scheduler_vk(
     Ok(())
 }
 
+/// Generates setup data for the circuit, that aggregates several scheduler proofs into one.
+/// Source must contain the scheduler verification key, the aggregation vk and finalization hint
+/// are written back to it.
+pub fn generate_interblock_aggregation_setup_data(
+    source: &mut dyn SetupDataSource,
+) -> crate::data_source::SourceResult<CircuitSetupData> {
+    use crate::witness::recursive_aggregation::create_interblock_aggregation_circuit;
+
+    let worker = Worker::new();
+    let scheduler_vk =
+        source.get_recursion_layer_vk(ZkSyncRecursionLayerStorageType::SchedulerCircuit as u8)?;
+    let circuit = create_interblock_aggregation_circuit(vec![], scheduler_vk);
+
+    let (setup_base, setup, vk, setup_tree, vars_hint, wits_hint, finalization_hint) =
+        create_interblock_aggregation_setup_data(
+            circuit,
+            &worker,
+            RECURSION_LAYER_FRI_LDE_FACTOR,
+            RECURSION_LAYER_CAP_SIZE,
+        );
+
+    source.set_interblock_aggregation_vk(vk.clone())?;
+    source.set_interblock_aggregation_finalization_hint(finalization_hint.clone())?;
+
+    Ok(CircuitSetupData {
+        setup_base,
+        setup,
+        vk,
+        setup_tree,
+        vars_hint,
+        wits_hint,
+        finalization_hint,
+    })
+}
+
 pub fn compute_leaf_params(
     source: &mut dyn SetupDataSource,
 ) -> crate::data_source::SourceResult<Vec<(u8, RecursionLeafParametersWitness<GoldilocksField>)>> {
@@ -472,4 +509,27 @@ mod test {
 
         generate_scheduler_vk(source).unwrap();
     }
+
+    #[ignore = "too slow"]
+    #[test]
+    fn test_generate_interblock_aggregation() {
+        let mut src = LocalFileDataSource::default();
+        src.create_folders_for_storing_data();
+        let source = &mut src;
+
+        let setup_data = generate_interblock_aggregation_setup_data(source).unwrap();
+        assert_eq!(
+            source.get_interblock_aggregation_vk().unwrap(),
+            setup_data.vk
+        );
+        // must have the same shape as the scheduler, so that compression can consume it
+        let scheduler_vk = source
+            .get_recursion_layer_vk(ZkSyncRecursionLayerStorageType::SchedulerCircuit as u8)
+            .unwrap()
+            .into_inner();
+        assert_eq!(
+            setup_data.vk.fixed_parameters.parameters,
+            scheduler_vk.fixed_parameters.parameters
+        );
+    }
 }
@@ -18,8 +18,7 @@ use circuit_definitions::circuit_definitions::base_layer::ZkSyncBaseLayerCircuit
 use circuit_definitions::circuit_definitions::recursion_layer::leaf_layer::ZkSyncLeafLayerRecursiveCircuit;
 use circuit_definitions::circuit_definitions::recursion_layer::{
     base_circuit_type_into_recursive_leaf_circuit_type, circuit_def,
-    ZkSyncRecursionLayerStorageType, ZkSyncRecursiveLayerCircuit, RECURSION_ARITY,
-    SCHEDULER_CAPACITY,
+    ZkSyncRecursionLayerStorageType, ZkSyncRecursiveLayerCircuit, SCHEDULER_CAPACITY,
 };
 use circuit_definitions::circuit_definitions::ZkSyncUniformCircuitInstance;
 use circuit_definitions::recursion_layer_proof_config;
@@ -282,10 +281,12 @@ fn get_all_recursive_circuits(
     return Ok(result);
 }
 
-/// Returns all the leaf circuits.
+/// Returns all the leaf circuits, for the recursion tree arity from the source.
 fn get_leaf_circuits(
     source: &mut dyn SetupDataSource,
 ) -> crate::data_source::SourceResult<Vec<ZkSyncRecursiveLayerCircuit>> {
+    let arity = source.get_recursion_tree_arity()?;
+    arity.validate()?;
     let mut result = vec![];
 
     for base_circuit_type in ((BaseLayerCircuitType::VM as u8)
@@ -314,7 +315,7 @@ fn get_leaf_circuits(
         let config = LeafLayerRecursionConfig {
             proof_config: recursion_layer_proof_config(),
             vk_fixed_parameters: vk.into_inner().fixed_parameters,
-            capacity: RECURSION_ARITY,
+            capacity: arity.leaf_layer_capacity,
             _marker: std::marker::PhantomData,
         };
 
@@ -335,10 +336,12 @@ fn get_leaf_circuits(
     return Ok(result);
 }
 
-/// Returns the node circuit.
+/// Returns the node circuit, for the recursion tree arity from the source.
 fn get_node_circuit(
     source: &mut dyn SetupDataSource,
 ) -> crate::data_source::SourceResult<ZkSyncRecursiveLayerCircuit> {
+    let arity = source.get_recursion_tree_arity()?;
+    arity.validate()?;
     use crate::zkevm_circuits::recursion::node_layer::input::*;
     let input = RecursionNodeInput::placeholder_witness();
     let vk = source
@@ -348,7 +351,7 @@ fn get_node_circuit(
     use crate::boojum::gadgets::queue::QueueTailState;
     let split_points = vec![
         QueueTailState::<GoldilocksField, FULL_SPONGE_QUEUE_STATE_WIDTH>::placeholder_witness();
-        RECURSION_ARITY - 1
+        arity.node_layer_capacity - 1
     ];
     let witness = RecursionNodeInstanceWitness {
         input,
@@ -362,8 +365,8 @@ fn get_node_circuit(
     let config = NodeLayerRecursionConfig {
         proof_config: recursion_layer_proof_config(),
         vk_fixed_parameters: vk.into_inner().fixed_parameters,
-        leaf_layer_capacity: RECURSION_ARITY,
-        node_layer_capacity: RECURSION_ARITY,
+        leaf_layer_capacity: arity.leaf_layer_capacity,
+        node_layer_capacity: arity.node_layer_capacity,
         _marker: std::marker::PhantomData,
     };
     let circuit = ZkSyncNodeLayerRecursiveCircuit {
@@ -4,14 +4,19 @@ use circuit_definitions::circuit_definitions::aux_layer::{
     EIP4844VerificationKey, ZkSyncCompressionForWrapperFinalizationHint,
     ZkSyncCompressionForWrapperProof, ZkSyncCompressionForWrapperVerificationKey,
     ZkSyncCompressionLayerFinalizationHint, ZkSyncCompressionLayerProof,
-    ZkSyncCompressionLayerVerificationKey, ZkSyncSnarkWrapperProof, ZkSyncSnarkWrapperSetup,
-    ZkSyncSnarkWrapperVK,
+    ZkSyncCompressionLayerVerificationKey, ZkSyncFflonkSnarkWrapperProof,
+    ZkSyncFflonkSnarkWrapperSetup, ZkSyncFflonkSnarkWrapperVK, ZkSyncSnarkWrapperProof,
+    ZkSyncSnarkWrapperSetup, ZkSyncSnarkWrapperVK,
 };
 use circuit_definitions::circuit_definitions::base_layer::{
     ZkSyncBaseLayerFinalizationHint, ZkSyncBaseLayerProof, ZkSyncBaseLayerVerificationKey,
 };
+use circuit_definitions::circuit_definitions::recursion_layer::interblock::{
+    ZkSyncInterblockAggregationFinalizationHint, ZkSyncInterblockAggregationProof,
+    ZkSyncInterblockAggregationVerificationKey,
+};
 use circuit_definitions::circuit_definitions::recursion_layer::{
-    ZkSyncRecursionLayerFinalizationHint, ZkSyncRecursionLayerProof,
+    RecursionTreeArity, ZkSyncRecursionLayerFinalizationHint, ZkSyncRecursionLayerProof,
     ZkSyncRecursionLayerVerificationKey,
 };
 use std::collections::HashMap;
@@ -27,12 +32,17 @@ pub struct InMemoryDataSource {
     recursion_layer_finalization_hint: HashMap<u8, ZkSyncRecursionLayerFinalizationHint>,
     recursion_layer_node_finalization_hint: Option<ZkSyncRecursionLayerFinalizationHint>,
     recursion_tip_finalization_hint: Option<ZkSyncRecursionLayerFinalizationHint>,
+    recursion_tree_arity: Option<RecursionTreeArity>,
+    interblock_aggregation_vk: Option<ZkSyncInterblockAggregationVerificationKey>,
+    interblock_aggregation_finalization_hint: Option<ZkSyncInterblockAggregationFinalizationHint>,
     compression_vk: HashMap<u8, ZkSyncCompressionLayerVerificationKey>,
     compression_hint: HashMap<u8, ZkSyncCompressionLayerFinalizationHint>,
     compression_for_wrapper_vk: HashMap<u8, ZkSyncCompressionForWrapperVerificationKey>,
     compression_for_wrapper_hint: HashMap<u8, ZkSyncCompressionForWrapperFinalizationHint>,
     wrapper_setup: HashMap<u8, ZkSyncSnarkWrapperSetup>,
     wrapper_vk: HashMap<u8, ZkSyncSnarkWrapperVK>,
+    fflonk_wrapper_setup: HashMap<u8, ZkSyncFflonkSnarkWrapperSetup>,
+    fflonk_wrapper_vk: HashMap<u8, ZkSyncFflonkSnarkWrapperVK>,
 
     ///data structures required for holding [`BlockDataSource`] result
     base_layer_proofs: HashMap<(u8, usize), ZkSyncBaseLayerProof>,
@@ -40,9 +50,11 @@ pub struct InMemoryDataSource {
     node_layer_proofs: HashMap<(u8, usize, usize), ZkSyncRecursionLayerProof>,
     recursion_tip_proof: Option<ZkSyncRecursionLayerProof>,
     scheduler_proof: Option<ZkSyncRecursionLayerProof>,
+    interblock_aggregation_proof: Option<ZkSyncInterblockAggregationProof>,
     compression_proof: HashMap<u8, ZkSyncCompressionLayerProof>,
     compression_for_wrapper_proof: HashMap<u8, ZkSyncCompressionForWrapperProof>,
     wrapper_proof: HashMap<u8, ZkSyncSnarkWrapperProof>,
+    fflonk_wrapper_proof: HashMap<u8, ZkSyncFflonkSnarkWrapperProof>,
 }
 
 impl InMemoryDataSource {
@@ -56,20 +68,27 @@ impl InMemoryDataSource {
             recursion_layer_finalization_hint: HashMap::new(),
             recursion_layer_node_finalization_hint: None,
             recursion_tip_finalization_hint: None,
+            recursion_tree_arity: None,
+            interblock_aggregation_vk: None,
+            interblock_aggregation_finalization_hint: None,
             compression_vk: HashMap::new(),
             compression_hint: HashMap::new(),
             compression_for_wrapper_vk: HashMap::new(),
             compression_for_wrapper_hint: HashMap::new(),
             wrapper_setup: HashMap::new(),
             wrapper_vk: HashMap::new(),
+            fflonk_wrapper_setup: HashMap::new(),
+            fflonk_wrapper_vk: HashMap::new(),
             base_layer_proofs: HashMap::new(),
             leaf_layer_proofs: HashMap::new(),
             node_layer_proofs: HashMap::new(),
             scheduler_proof: None,
             recursion_tip_proof: None,
+            interblock_aggregation_proof: None,
             compression_proof: HashMap::new(),
             compression_for_wrapper_proof: HashMap::new(),
             wrapper_proof: HashMap::new(),
+            fflonk_wrapper_proof: HashMap::new(),
         }
     }
 }
@@ -310,6 +329,43 @@ impl SetupDataSource for InMemoryDataSource {
         Ok(())
     }
 
+    fn get_fflonk_wrapper_setup(
+        &self,
+        circuit_type: u8,
+    ) -> SourceResult<ZkSyncFflonkSnarkWrapperSetup> {
+        self.fflonk_wrapper_setup
+            .get(&circuit_type)
+            .cloned()
+            .ok_or(Box::new(Error::new(
+                ErrorKind::Other,
+                format!("no data for circuit type {}", circuit_type),
+            )))
+    }
+
+    fn get_fflonk_wrapper_vk(&self, circuit_type: u8) -> SourceResult<ZkSyncFflonkSnarkWrapperVK> {
+        self.fflonk_wrapper_vk
+            .get(&circuit_type)
+            .cloned()
+            .ok_or(Box::new(Error::new(
+                ErrorKind::Other,
+                format!("no data for circuit type {}", circuit_type),
+            )))
+    }
+
+    fn set_fflonk_wrapper_setup(
+        &mut self,
+        setup: ZkSyncFflonkSnarkWrapperSetup,
+    ) -> SourceResult<()> {
+        self.fflonk_wrapper_setup
+            .insert(setup.numeric_circuit_type(), setup);
+        Ok(())
+    }
+
+    fn set_fflonk_wrapper_vk(&mut self, vk: ZkSyncFflonkSnarkWrapperVK) -> SourceResult<()> {
+        self.fflonk_wrapper_vk.insert(vk.numeric_circuit_type(), vk);
+        Ok(())
+    }
+
     fn get_recursion_tip_vk(&self) -> SourceResult<ZkSyncRecursionLayerVerificationKey> {
         self.recursion_tip_vk.clone().ok_or(Box::new(Error::new(
             ErrorKind::Other,
@@ -340,6 +396,47 @@ impl SetupDataSource for InMemoryDataSource {
         self.recursion_tip_finalization_hint = Some(hint);
         Ok(())
     }
+    fn get_recursion_tree_arity(&self) -> SourceResult<RecursionTreeArity> {
+        Ok(self.recursion_tree_arity.unwrap_or_default())
+    }
+    fn set_recursion_tree_arity(&mut self, arity: RecursionTreeArity) -> SourceResult<()> {
+        self.recursion_tree_arity = Some(arity);
+        Ok(())
+    }
+    fn get_interblock_aggregation_vk(
+        &self,
+    ) -> SourceResult<ZkSyncInterblockAggregationVerificationKey> {
+        self.interblock_aggregation_vk
+            .clone()
+            .ok_or(Box::new(Error::new(
+                ErrorKind::Other,
+                format!("no data for interblock aggregation vk"),
+            )))
+    }
+    fn get_interblock_aggregation_finalization_hint(
+        &self,
+    ) -> SourceResult<ZkSyncInterblockAggregationFinalizationHint> {
+        self.interblock_aggregation_finalization_hint
+            .clone()
+            .ok_or(Box::new(Error::new(
+                ErrorKind::Other,
+                format!("no data for interblock aggregation finalization hint"),
+            )))
+    }
+    fn set_interblock_aggregation_vk(
+        &mut self,
+        vk: ZkSyncInterblockAggregationVerificationKey,
+    ) -> SourceResult<()> {
+        self.interblock_aggregation_vk = Some(vk);
+        Ok(())
+    }
+    fn set_interblock_aggregation_finalization_hint(
+        &mut self,
+        hint: ZkSyncInterblockAggregationFinalizationHint,
+    ) -> SourceResult<()> {
+        self.interblock_aggregation_finalization_hint = Some(hint);
+        Ok(())
+    }
 }
 
 impl BlockDataSource for InMemoryDataSource {
@@ -435,6 +532,19 @@ impl BlockDataSource for InMemoryDataSource {
             )))
     }
 
+    fn get_fflonk_wrapper_proof(
+        &self,
+        circuit_type: u8,
+    ) -> SourceResult<ZkSyncFflonkSnarkWrapperProof> {
+        self.fflonk_wrapper_proof
+            .get(&circuit_type)
+            .cloned()
+            .ok_or(Box::new(Error::new(
+                ErrorKind::Other,
+                format!("no data for circuit type {}", circuit_type),
+            )))
+    }
+
     fn set_base_layer_proof(
         &mut self,
         index: usize,
@@ -493,6 +603,15 @@ impl BlockDataSource for InMemoryDataSource {
         Ok(())
     }
 
+    fn set_fflonk_wrapper_proof(
+        &mut self,
+        proof: ZkSyncFflonkSnarkWrapperProof,
+    ) -> SourceResult<()> {
+        self.fflonk_wrapper_proof
+            .insert(proof.numeric_circuit_type(), proof);
+        Ok(())
+    }
+
     fn set_recursive_tip_proof(&mut self, proof: ZkSyncRecursionLayerProof) -> SourceResult<()> {
         self.recursion_tip_proof = Some(proof);
         Ok(())
@@ -504,4 +623,21 @@ impl BlockDataSource for InMemoryDataSource {
             format!("no recursion tip proof"),
         )))
     }
+
+    fn set_interblock_aggregation_proof(
+        &mut self,
+        proof: ZkSyncInterblockAggregationProof,
+    ) -> SourceResult<()> {
+        self.interblock_aggregation_proof = Some(proof);
+        Ok(())
+    }
+
+    fn get_interblock_aggregation_proof(&self) -> SourceResult<ZkSyncInterblockAggregationProof> {
+        self.interblock_aggregation_proof
+            .clone()
+            .ok_or(Box::new(Error::new(
+                ErrorKind::Other,
+                format!("no interblock aggregation proof"),
+            )))
+    }
 }
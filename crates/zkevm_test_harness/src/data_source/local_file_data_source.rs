@@ -1,17 +1,22 @@
 use super::{BlockDataSource, SetupDataSource, SourceResult};
 use circuit_definitions::boojum::cs::implementations::setup::FinalizationHintsForProver;
 use circuit_definitions::circuit_definitions::aux_layer::{
-    EIP4844VerificationKey, ZkSyncCompressionForWrapperFinalizationHint,
-    ZkSyncCompressionForWrapperProof, ZkSyncCompressionForWrapperVerificationKey,
-    ZkSyncCompressionLayerFinalizationHint, ZkSyncCompressionLayerProof,
-    ZkSyncCompressionLayerVerificationKey, ZkSyncSnarkWrapperProof, ZkSyncSnarkWrapperSetup,
-    ZkSyncSnarkWrapperVK,
+    EIP4844VerificationKey, FflonkSnarkVerifierCircuitSetup,
+    ZkSyncCompressionForWrapperFinalizationHint, ZkSyncCompressionForWrapperProof,
+    ZkSyncCompressionForWrapperVerificationKey, ZkSyncCompressionLayerFinalizationHint,
+    ZkSyncCompressionLayerProof, ZkSyncCompressionLayerVerificationKey,
+    ZkSyncFflonkSnarkWrapperProof, ZkSyncFflonkSnarkWrapperSetup, ZkSyncFflonkSnarkWrapperVK,
+    ZkSyncSnarkWrapperProof, ZkSyncSnarkWrapperSetup, ZkSyncSnarkWrapperVK,
 };
 use circuit_definitions::circuit_definitions::base_layer::{
     ZkSyncBaseLayerFinalizationHint, ZkSyncBaseLayerProof, ZkSyncBaseLayerVerificationKey,
 };
+use circuit_definitions::circuit_definitions::recursion_layer::interblock::{
+    ZkSyncInterblockAggregationFinalizationHint, ZkSyncInterblockAggregationProof,
+    ZkSyncInterblockAggregationVerificationKey,
+};
 use circuit_definitions::circuit_definitions::recursion_layer::{
-    ZkSyncRecursionLayerFinalizationHint, ZkSyncRecursionLayerProof,
+    RecursionTreeArity, ZkSyncRecursionLayerFinalizationHint, ZkSyncRecursionLayerProof,
     ZkSyncRecursionLayerVerificationKey,
 };
 use serde::{Deserialize, Serialize};
@@ -306,6 +311,52 @@ impl SetupDataSource for LocalFileDataSource {
         Ok(())
     }
 
+    fn get_fflonk_wrapper_setup(
+        &self,
+        circuit_type: u8,
+    ) -> SourceResult<ZkSyncFflonkSnarkWrapperSetup> {
+        let file = File::open(format!(
+            "{}/aux_layer/fflonk_wrapper_setup_{}.setup",
+            self.setup_data_location, circuit_type
+        ))
+        .map_err(|el| Box::new(el) as Box<dyn Error>)?;
+
+        let result = Arc::new(
+            FflonkSnarkVerifierCircuitSetup::read(std::io::BufReader::new(file))
+                .map_err(|el| Box::new(el) as Box<dyn Error>)?,
+        );
+
+        Ok(ZkSyncFflonkSnarkWrapperSetup::from_inner(
+            circuit_type,
+            result,
+        ))
+    }
+    fn get_fflonk_wrapper_vk(&self, circuit_type: u8) -> SourceResult<ZkSyncFflonkSnarkWrapperVK> {
+        self.get_setup_data(format!("aux_layer/fflonk_wrapper_vk_{}", circuit_type))
+    }
+    fn set_fflonk_wrapper_setup(
+        &mut self,
+        setup: ZkSyncFflonkSnarkWrapperSetup,
+    ) -> SourceResult<()> {
+        let circuit_type = setup.numeric_circuit_type();
+        let file = File::create(format!(
+            "{}/aux_layer/fflonk_wrapper_setup_{}.setup",
+            self.setup_data_location, circuit_type
+        ))
+        .map_err(|el| Box::new(el) as Box<dyn Error>)?;
+
+        setup
+            .into_inner()
+            .write(std::io::BufWriter::new(file))
+            .map_err(|el| Box::new(el) as Box<dyn Error>)?;
+
+        Ok(())
+    }
+    fn set_fflonk_wrapper_vk(&mut self, vk: ZkSyncFflonkSnarkWrapperVK) -> SourceResult<()> {
+        let circuit_type = vk.numeric_circuit_type();
+        self.set_setup_data(format!("aux_layer/fflonk_wrapper_vk_{}", circuit_type), vk)
+    }
+
     fn get_recursion_tip_vk(&self) -> SourceResult<ZkSyncRecursionLayerVerificationKey> {
         self.get_setup_data("recursion_layer/vk_recursion_tip".to_string())
     }
@@ -329,6 +380,44 @@ impl SetupDataSource for LocalFileDataSource {
             hint,
         )
     }
+    fn get_recursion_tree_arity(&self) -> SourceResult<RecursionTreeArity> {
+        let file_name = "recursion_layer/recursion_tree_arity".to_string();
+        // setups generated before the arity became configurable use the default one
+        if !std::path::Path::new(&format!("{}/{}.json", self.setup_data_location, file_name))
+            .exists()
+        {
+            return Ok(RecursionTreeArity::default());
+        }
+        self.get_setup_data(file_name)
+    }
+    fn set_recursion_tree_arity(&mut self, arity: RecursionTreeArity) -> SourceResult<()> {
+        self.set_setup_data("recursion_layer/recursion_tree_arity".to_string(), arity)
+    }
+    fn get_interblock_aggregation_vk(
+        &self,
+    ) -> SourceResult<ZkSyncInterblockAggregationVerificationKey> {
+        self.get_setup_data("recursion_layer/vk_interblock_aggregation".to_string())
+    }
+    fn get_interblock_aggregation_finalization_hint(
+        &self,
+    ) -> SourceResult<ZkSyncInterblockAggregationFinalizationHint> {
+        self.get_setup_data("recursion_layer/finalization_hint_interblock_aggregation".to_string())
+    }
+    fn set_interblock_aggregation_vk(
+        &mut self,
+        vk: ZkSyncInterblockAggregationVerificationKey,
+    ) -> SourceResult<()> {
+        self.set_setup_data("recursion_layer/vk_interblock_aggregation".to_string(), vk)
+    }
+    fn set_interblock_aggregation_finalization_hint(
+        &mut self,
+        hint: ZkSyncInterblockAggregationFinalizationHint,
+    ) -> SourceResult<()> {
+        self.set_setup_data(
+            "recursion_layer/finalization_hint_interblock_aggregation".to_string(),
+            hint,
+        )
+    }
 }
 
 impl BlockDataSource for LocalFileDataSource {
@@ -396,6 +485,13 @@ impl BlockDataSource for LocalFileDataSource {
         Ok(result)
     }
 
+    fn get_fflonk_wrapper_proof(
+        &self,
+        circuit_type: u8,
+    ) -> SourceResult<ZkSyncFflonkSnarkWrapperProof> {
+        self.get_proof(format!("aux_layer/fflonk_wrapper_proof_{}", circuit_type))
+    }
+
     fn set_base_layer_proof(
         &mut self,
         index: usize,
@@ -472,6 +568,16 @@ impl BlockDataSource for LocalFileDataSource {
 
         Ok(())
     }
+    fn set_fflonk_wrapper_proof(
+        &mut self,
+        proof: ZkSyncFflonkSnarkWrapperProof,
+    ) -> SourceResult<()> {
+        let circuit_type = proof.numeric_circuit_type();
+        self.set_proof(
+            format!("aux_layer/fflonk_wrapper_proof_{}", circuit_type),
+            proof,
+        )
+    }
     fn set_recursive_tip_proof(&mut self, proof: ZkSyncRecursionLayerProof) -> SourceResult<()> {
         self.set_proof("recursion_layer/recursive_tip_proof".to_string(), proof)
     }
@@ -479,4 +585,18 @@ impl BlockDataSource for LocalFileDataSource {
     fn get_recursive_tip_proof(&self) -> SourceResult<ZkSyncRecursionLayerProof> {
         self.get_proof("recursion_layer/recursive_tip_proof".to_string())
     }
+
+    fn set_interblock_aggregation_proof(
+        &mut self,
+        proof: ZkSyncInterblockAggregationProof,
+    ) -> SourceResult<()> {
+        self.set_proof(
+            "recursion_layer/interblock_aggregation_proof".to_string(),
+            proof,
+        )
+    }
+
+    fn get_interblock_aggregation_proof(&self) -> SourceResult<ZkSyncInterblockAggregationProof> {
+        self.get_proof("recursion_layer/interblock_aggregation_proof".to_string())
+    }
 }
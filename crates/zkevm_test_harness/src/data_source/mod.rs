@@ -3,12 +3,30 @@ use std::error::Error;
 use circuit_definitions::boojum::cs::implementations::setup::FinalizationHintsForProver;
 use circuit_definitions::circuit_definitions::aux_layer::*;
 use circuit_definitions::circuit_definitions::base_layer::*;
+use circuit_definitions::circuit_definitions::recursion_layer::interblock::*;
 use circuit_definitions::circuit_definitions::recursion_layer::*;
 
 pub type SourceResult<T> = Result<T, Box<dyn Error>>;
 pub mod in_memory_data_source;
 pub mod local_file_data_source;
 
+/// Returned by the default implementations of the data source methods,
+/// for the artifacts that the source doesn't store.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnsupportedArtifact(pub &'static str);
+
+impl std::fmt::Display for UnsupportedArtifact {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} is not supported by this data source", self.0)
+    }
+}
+
+impl Error for UnsupportedArtifact {}
+
+fn unsupported<T>(artifact: &'static str) -> SourceResult<T> {
+    Err(Box::new(UnsupportedArtifact(artifact)))
+}
+
 // Object save trait to just get things for SYSTEM
 pub trait SetupDataSource {
     fn get_base_layer_vk(&self, circuit_type: u8) -> SourceResult<ZkSyncBaseLayerVerificationKey>;
@@ -32,6 +50,19 @@ pub trait SetupDataSource {
     fn get_recursion_tip_finalization_hint(
         &self,
     ) -> SourceResult<ZkSyncRecursionLayerFinalizationHint>;
+    /// Capacities of the leaf and node circuits that the recursion layer was set up for.
+    /// Sources where it was never set return the default one.
+    fn get_recursion_tree_arity(&self) -> SourceResult<RecursionTreeArity>;
+    fn get_interblock_aggregation_vk(
+        &self,
+    ) -> SourceResult<ZkSyncInterblockAggregationVerificationKey> {
+        unsupported("interblock aggregation vk")
+    }
+    fn get_interblock_aggregation_finalization_hint(
+        &self,
+    ) -> SourceResult<ZkSyncInterblockAggregationFinalizationHint> {
+        unsupported("interblock aggregation finalization hint")
+    }
 
     fn get_compression_vk(
         &self,
@@ -51,6 +82,15 @@ pub trait SetupDataSource {
     ) -> SourceResult<ZkSyncCompressionForWrapperFinalizationHint>;
     fn get_wrapper_setup(&self, circuit_type: u8) -> SourceResult<ZkSyncSnarkWrapperSetup>;
     fn get_wrapper_vk(&self, circuit_type: u8) -> SourceResult<ZkSyncSnarkWrapperVK>;
+    fn get_fflonk_wrapper_setup(
+        &self,
+        _circuit_type: u8,
+    ) -> SourceResult<ZkSyncFflonkSnarkWrapperSetup> {
+        unsupported("FFLONK wrapper setup")
+    }
+    fn get_fflonk_wrapper_vk(&self, _circuit_type: u8) -> SourceResult<ZkSyncFflonkSnarkWrapperVK> {
+        unsupported("FFLONK wrapper vk")
+    }
 
     fn set_base_layer_vk(&mut self, vk: ZkSyncBaseLayerVerificationKey) -> SourceResult<()>;
     fn set_base_layer_finalization_hint(
@@ -79,6 +119,19 @@ pub trait SetupDataSource {
         &mut self,
         hint: ZkSyncRecursionLayerFinalizationHint,
     ) -> SourceResult<()>;
+    fn set_recursion_tree_arity(&mut self, arity: RecursionTreeArity) -> SourceResult<()>;
+    fn set_interblock_aggregation_vk(
+        &mut self,
+        _vk: ZkSyncInterblockAggregationVerificationKey,
+    ) -> SourceResult<()> {
+        unsupported("interblock aggregation vk")
+    }
+    fn set_interblock_aggregation_finalization_hint(
+        &mut self,
+        _hint: ZkSyncInterblockAggregationFinalizationHint,
+    ) -> SourceResult<()> {
+        unsupported("interblock aggregation finalization hint")
+    }
 
     fn set_compression_vk(&mut self, vk: ZkSyncCompressionLayerVerificationKey)
         -> SourceResult<()>;
@@ -96,6 +149,15 @@ pub trait SetupDataSource {
     ) -> SourceResult<()>;
     fn set_wrapper_setup(&mut self, setup: ZkSyncSnarkWrapperSetup) -> SourceResult<()>;
     fn set_wrapper_vk(&mut self, vk: ZkSyncSnarkWrapperVK) -> SourceResult<()>;
+    fn set_fflonk_wrapper_setup(
+        &mut self,
+        _setup: ZkSyncFflonkSnarkWrapperSetup,
+    ) -> SourceResult<()> {
+        unsupported("FFLONK wrapper setup")
+    }
+    fn set_fflonk_wrapper_vk(&mut self, _vk: ZkSyncFflonkSnarkWrapperVK) -> SourceResult<()> {
+        unsupported("FFLONK wrapper vk")
+    }
 }
 
 // Object save trait to just get things for BLOCK
@@ -123,6 +185,12 @@ pub trait BlockDataSource {
         circuit_type: u8,
     ) -> SourceResult<ZkSyncCompressionForWrapperProof>;
     fn get_wrapper_proof(&self, circuit_type: u8) -> SourceResult<ZkSyncSnarkWrapperProof>;
+    fn get_fflonk_wrapper_proof(
+        &self,
+        _circuit_type: u8,
+    ) -> SourceResult<ZkSyncFflonkSnarkWrapperProof> {
+        unsupported("FFLONK wrapper proof")
+    }
 
     fn set_base_layer_proof(
         &mut self,
@@ -148,7 +216,23 @@ pub trait BlockDataSource {
         proof: ZkSyncCompressionForWrapperProof,
     ) -> SourceResult<()>;
     fn set_wrapper_proof(&mut self, proof: ZkSyncSnarkWrapperProof) -> SourceResult<()>;
+    fn set_fflonk_wrapper_proof(
+        &mut self,
+        _proof: ZkSyncFflonkSnarkWrapperProof,
+    ) -> SourceResult<()> {
+        unsupported("FFLONK wrapper proof")
+    }
 
     fn set_recursive_tip_proof(&mut self, proof: ZkSyncRecursionLayerProof) -> SourceResult<()>;
     fn get_recursive_tip_proof(&self) -> SourceResult<ZkSyncRecursionLayerProof>;
+
+    fn set_interblock_aggregation_proof(
+        &mut self,
+        _proof: ZkSyncInterblockAggregationProof,
+    ) -> SourceResult<()> {
+        unsupported("interblock aggregation proof")
+    }
+    fn get_interblock_aggregation_proof(&self) -> SourceResult<ZkSyncInterblockAggregationProof> {
+        unsupported("interblock aggregation proof")
+    }
 }
@@ -0,0 +1,481 @@
+//! Estimation of the ergs and pubdata of a transaction with the out-of-circuit VM, without building the witness.
+//! The execution is repeated from scratch with different ergs limits of the root frame (`VmState` can't be cloned,
+//! so there are no checkpoints), and the minimal limit is found by binary search.
+//! Besides the ergs, the estimate reports the pubdata accumulated by `add_pubdata_cost`, the storage refunds
+//! and the part of the ergs that covers the circuits, by the prices of `zkevm_opcode_defs::circuit_prices`.
+//! The estimated execution is a far call into a deployed contract with calldata (`estimate_far_call`),
+//! a bootloader run (`estimate_bootloader`) or a compiled test entry point (`estimate_entry_point`).
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::asm_runner::storage::InMemoryCustomRefundStorage;
+use crate::asm_runner::test_geometry;
+use crate::entry_point::create_out_of_circuit_global_context;
+use crate::helper::artifact_utils::{save_predeployed_contracts, TestArtifact};
+use crate::toolset::{create_out_of_circuit_vm, create_tools_for_entry_point};
+use crate::utils::calldata_to_aligned_data;
+use crate::vm_debugger::{create_vm, AsmVm, DebugContract};
+use crate::vm_diff::create_bootloader_out_of_circuit_vm;
+use crate::witness::tracer::tracer::WitnessTracer;
+use crate::witness::tree::{BinarySparseStorageTree, ZKSyncTestingTree};
+use crate::zk_evm::abstractions::{PrecompileCyclesWitness, Storage, StorageAccessRefund};
+use crate::zk_evm::aux_structures::{DecommittmentQuery, LogQuery, MemoryQuery};
+use crate::zk_evm::bytecode_to_code_hash;
+use crate::zk_evm::ethereum_types::{Address, U256};
+use crate::zk_evm::reference_impls::decommitter::SimpleDecommitter;
+use crate::zk_evm::reference_impls::event_sink::InMemoryEventSink;
+use crate::zk_evm::reference_impls::memory::{MemoryLimits, SimpleMemory};
+use crate::zk_evm::vm_state::{address_is_kernel, PrimitiveValue, VmLocalState, VmState};
+use crate::zk_evm::witness_trace::VmWitnessTracer;
+use crate::zk_evm::zk_evm_abstractions::precompiles::DefaultPrecompilesProcessor;
+use crate::zk_evm::zkevm_opcode_defs::circuit_prices::*;
+use crate::zk_evm::zkevm_opcode_defs::decoding::EncodingModeProduction;
+use crate::zk_evm::zkevm_opcode_defs::system_params::{
+    EVENT_AUX_BYTE, INITIAL_FRAME_FORMAL_EH_LOCATION, L1_MESSAGE_AUX_BYTE,
+    NEW_FRAME_MEMORY_STIPEND, NEW_KERNEL_FRAME_MEMORY_STIPEND, PRECOMPILE_AUX_BYTE,
+    STORAGE_AUX_BYTE, TRANSIENT_STORAGE_AUX_BYTE,
+};
+use crate::zk_evm::zkevm_opcode_defs::{FatPointer, BOOTLOADER_CALLDATA_PAGE};
+use crate::zk_evm::GenericNoopTracer;
+
+/// Witness is not collected, so the snapshots can be rare
+const CYCLES_PER_VM_SNAPSHOT: u32 = 1 << 16;
+
+/// Counters of the work done by the VM, that is later processed by the circuits
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExecutionCounters {
+    pub cycles: u32,
+    pub memory_queries: u32,
+    pub decommits: u32,
+    /// Words of the fresh decommits, the repeated ones are not decommitted into memory
+    pub decommitted_words: u32,
+    pub storage_reads: u32,
+    pub storage_writes: u32,
+    pub transient_storage_accesses: u32,
+    /// Log queries of the events, an event with many topics or large data takes several of them
+    pub events: u32,
+    pub l1_messages: u32,
+    pub precompile_calls: u32,
+    pub keccak256_rounds: u32,
+    pub sha256_rounds: u32,
+    pub ecrecover_rounds: u32,
+    pub secp256r1_verify_rounds: u32,
+}
+
+impl ExecutionCounters {
+    pub fn log_queries(&self) -> u32 {
+        self.storage_reads
+            + self.storage_writes
+            + self.transient_storage_accesses
+            + self.events
+            + self.l1_messages
+            + self.precompile_calls
+    }
+}
+
+/// Ergs that cover the circuits processing the execution, by the prices of `circuit_prices`.
+/// Pubdata hashers are not included, as their price depends on whether the write is initial,
+/// and pubdata is paid separately
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CircuitCosts {
+    pub main_vm: u64,
+    pub ram_permutation: u64,
+    pub code_decommitter: u64,
+    pub code_decommitments_sorter: u64,
+    pub log_demuxer: u64,
+    pub storage_sorter: u64,
+    pub storage_application: u64,
+    pub transient_storage_checker: u64,
+    pub events_sorter: u64,
+    pub l1_messages_sorter: u64,
+    /// Minimal price of the L1 messages hasher, that is a single-instance circuit
+    pub l1_messages_hasher: u64,
+    pub keccak256: u64,
+    pub sha256: u64,
+    pub ecrecover: u64,
+}
+
+impl CircuitCosts {
+    pub fn from_counters(counters: &ExecutionCounters) -> Self {
+        let cost = |count: u32, price: u32| count as u64 * price as u64;
+
+        Self {
+            main_vm: cost(counters.cycles, VM_CYCLE_COST_IN_ERGS),
+            ram_permutation: cost(counters.memory_queries, RAM_PERMUTATION_COST_IN_ERGS),
+            code_decommitter: cost(
+                counters.decommitted_words,
+                CODE_DECOMMITMENT_COST_PER_WORD_IN_ERGS,
+            ),
+            code_decommitments_sorter: cost(
+                counters.decommits,
+                CODE_DECOMMITTER_SORTER_COST_IN_ERGS,
+            ),
+            log_demuxer: cost(counters.log_queries(), LOG_DEMUXER_COST_IN_ERGS),
+            storage_sorter: cost(
+                counters.storage_reads + counters.storage_writes,
+                STORAGE_SORTER_COST_IN_ERGS,
+            ),
+            storage_application: cost(counters.storage_writes, STORAGE_APPLICATION_COST_IN_ERGS),
+            transient_storage_checker: cost(
+                counters.transient_storage_accesses,
+                TRANSIENT_STORE_CHECKER_COST_IN_ERGS,
+            ),
+            events_sorter: cost(counters.events, EVENTS_OR_L1_MESSAGES_SORTER_COST_IN_ERGS),
+            l1_messages_sorter: cost(
+                counters.l1_messages,
+                EVENTS_OR_L1_MESSAGES_SORTER_COST_IN_ERGS,
+            ),
+            l1_messages_hasher: cost(counters.l1_messages, L1_MESSAGE_MIN_COST_IN_ERGS),
+            keccak256: cost(counters.keccak256_rounds, KECCAK256_CIRCUIT_COST_IN_ERGS),
+            sha256: cost(counters.sha256_rounds, SHA256_CIRCUIT_COST_IN_ERGS),
+            ecrecover: cost(counters.ecrecover_rounds, ECRECOVER_CIRCUIT_COST_IN_ERGS),
+        }
+    }
+
+    pub fn total(&self) -> u64 {
+        self.main_vm
+            + self.ram_permutation
+            + self.code_decommitter
+            + self.code_decommitments_sorter
+            + self.log_demuxer
+            + self.storage_sorter
+            + self.storage_application
+            + self.transient_storage_checker
+            + self.events_sorter
+            + self.l1_messages_sorter
+            + self.l1_messages_hasher
+            + self.keccak256
+            + self.sha256
+            + self.ecrecover
+    }
+}
+
+/// Witness tracer that only counts the queries, to run the VM without collecting the witness
+#[derive(Clone, Debug, Default)]
+pub struct EstimationTracer {
+    pub counters: ExecutionCounters,
+    /// Sum of the ergs refunded for the warm storage accesses
+    pub storage_refunds: u64,
+}
+
+impl VmWitnessTracer<8, EncodingModeProduction> for EstimationTracer {
+    fn end_execution_cycle(&mut self, _current_state: &VmLocalState<8, EncodingModeProduction>) {
+        self.counters.cycles += 1;
+    }
+
+    fn add_memory_query(&mut self, _monotonic_cycle_counter: u32, _memory_query: MemoryQuery) {
+        self.counters.memory_queries += 1;
+    }
+
+    fn record_refund_for_query(
+        &mut self,
+        _monotonic_cycle_counter: u32,
+        _log_query: LogQuery,
+        refund: StorageAccessRefund,
+    ) {
+        self.storage_refunds += refund.refund() as u64;
+    }
+
+    fn add_log_query(&mut self, _monotonic_cycle_counter: u32, log_query: LogQuery) {
+        let counter = match log_query.aux_byte {
+            STORAGE_AUX_BYTE if log_query.rw_flag => &mut self.counters.storage_writes,
+            STORAGE_AUX_BYTE => &mut self.counters.storage_reads,
+            TRANSIENT_STORAGE_AUX_BYTE => &mut self.counters.transient_storage_accesses,
+            EVENT_AUX_BYTE => &mut self.counters.events,
+            L1_MESSAGE_AUX_BYTE => &mut self.counters.l1_messages,
+            PRECOMPILE_AUX_BYTE => &mut self.counters.precompile_calls,
+            _ => return,
+        };
+        *counter += 1;
+    }
+
+    fn execute_decommittment(
+        &mut self,
+        _monotonic_cycle_counter: u32,
+        _decommittment_query: DecommittmentQuery,
+        mem_witness: Vec<U256>,
+    ) {
+        self.counters.decommits += 1;
+        self.counters.decommitted_words += mem_witness.len() as u32;
+    }
+
+    fn add_precompile_call_result(
+        &mut self,
+        _monotonic_cycle_counter: u32,
+        _call_params: LogQuery,
+        _mem_witness_in: Vec<MemoryQuery>,
+        _memory_witness_out: Vec<MemoryQuery>,
+        round_witness: PrecompileCyclesWitness,
+    ) {
+        let (counter, rounds) = match round_witness {
+            PrecompileCyclesWitness::Keccak256(rounds) => {
+                (&mut self.counters.keccak256_rounds, rounds.len())
+            }
+            PrecompileCyclesWitness::Sha256(rounds) => {
+                (&mut self.counters.sha256_rounds, rounds.len())
+            }
+            PrecompileCyclesWitness::ECRecover(rounds) => {
+                (&mut self.counters.ecrecover_rounds, rounds.len())
+            }
+            PrecompileCyclesWitness::Secp256r1Verify(rounds) => {
+                (&mut self.counters.secp256r1_verify_rounds, rounds.len())
+            }
+        };
+        *counter += rounds as u32;
+    }
+}
+
+pub type EstimationVm<S = InMemoryCustomRefundStorage> = VmState<
+    S,
+    SimpleMemory,
+    InMemoryEventSink,
+    DefaultPrecompilesProcessor<true>,
+    SimpleDecommitter<true>,
+    EstimationTracer,
+>;
+
+/// Replaces the witness tracer of the VM created by `create_out_of_circuit_vm`
+pub fn with_estimation_tracer<S: Storage>(
+    vm: VmState<
+        S,
+        SimpleMemory,
+        InMemoryEventSink,
+        DefaultPrecompilesProcessor<true>,
+        SimpleDecommitter<true>,
+        WitnessTracer,
+    >,
+) -> EstimationVm<S> {
+    VmState {
+        local_state: vm.local_state,
+        block_properties: vm.block_properties,
+        storage: vm.storage,
+        memory: vm.memory,
+        event_sink: vm.event_sink,
+        precompiles_processor: vm.precompiles_processor,
+        decommittment_processor: vm.decommittment_processor,
+        witness_tracer: EstimationTracer::default(),
+    }
+}
+
+/// Result of a single execution with the given ergs limit
+#[derive(Clone, Debug)]
+pub struct ExecutionOutcome {
+    pub ergs_limit: u32,
+    /// The root frame has returned without a panic
+    pub succeeded: bool,
+    pub ergs_remaining: u32,
+    /// Net pubdata of the execution, the pubdata of the reverted frames is excluded
+    pub pubdata_cost: i32,
+    pub tracer: EstimationTracer,
+}
+
+/// Runs the VM with the ergs limit of the root frame until the end of execution
+pub fn execute_with_ergs_limit<S: Storage>(
+    mut vm: EstimationVm<S>,
+    ergs_limit: u32,
+    cycle_limit: usize,
+) -> Result<ExecutionOutcome, String> {
+    vm.local_state
+        .callstack
+        .get_current_stack_mut()
+        .ergs_remaining = ergs_limit;
+
+    let mut tracer = GenericNoopTracer::<_>::new();
+    for _ in 0..cycle_limit {
+        if vm.execution_has_ended() {
+            // only the empty base frame is left: a return of the root frame continues it from pc 0,
+            // while a panic jumps to the exception handler of the root frame, that is set to
+            // `INITIAL_FRAME_FORMAL_EH_LOCATION` by `initial_out_of_circuit_context`
+            return Ok(ExecutionOutcome {
+                ergs_limit,
+                succeeded: vm.local_state.callstack.current.pc != INITIAL_FRAME_FORMAL_EH_LOCATION,
+                ergs_remaining: vm.local_state.callstack.current.ergs_remaining,
+                pubdata_cost: vm.local_state.pubdata_revert_counter.0,
+                tracer: vm.witness_tracer,
+            });
+        }
+        vm.cycle(&mut tracer)
+            .map_err(|el| format!("Cycle failed: {}", el))?;
+    }
+
+    Err(format!("Execution hasn't ended in {} cycles", cycle_limit))
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ErgsEstimate {
+    /// Minimal ergs limit of the root frame with which the execution succeeds
+    pub ergs_limit: u32,
+    /// Ergs spent with the minimal limit
+    pub ergs_used: u32,
+    pub pubdata_cost: i32,
+    pub storage_refunds: u64,
+    pub counters: ExecutionCounters,
+    pub circuit_costs: CircuitCosts,
+    /// Number of the executions done by the search
+    pub executions: usize,
+}
+
+impl ErgsEstimate {
+    fn new(outcome: ExecutionOutcome, executions: usize) -> Self {
+        Self {
+            ergs_limit: outcome.ergs_limit,
+            ergs_used: outcome.ergs_limit - outcome.ergs_remaining,
+            pubdata_cost: outcome.pubdata_cost,
+            storage_refunds: outcome.tracer.storage_refunds,
+            counters: outcome.tracer.counters,
+            circuit_costs: CircuitCosts::from_counters(&outcome.tracer.counters),
+            executions,
+        }
+    }
+}
+
+/// Finds the minimal ergs limit of the root frame with which the execution succeeds, `create_vm` should
+/// return the VM in the same initial state on every call.
+/// The search assumes that the success is monotonic in the limit: it is not the case if the code
+/// depends on the remaining ergs, e.g. handles the out-of-ergs panic of a callee in some other way
+pub fn estimate_ergs<S: Storage>(
+    mut create_vm: impl FnMut() -> EstimationVm<S>,
+    max_ergs: u32,
+    cycle_limit: usize,
+) -> Result<ErgsEstimate, String> {
+    let mut best = execute_with_ergs_limit(create_vm(), max_ergs, cycle_limit)?;
+    if !best.succeeded {
+        return Err(format!("Execution fails with {} ergs", max_ergs));
+    }
+    let mut executions = 1;
+
+    // the limit can't be below the ergs spent on the same execution path
+    let mut low = max_ergs - best.ergs_remaining;
+    let mut high = max_ergs;
+    while low < high {
+        let middle = low + (high - low) / 2;
+        let outcome = execute_with_ergs_limit(create_vm(), middle, cycle_limit)?;
+        executions += 1;
+        if outcome.succeeded {
+            high = middle;
+            best = outcome;
+        } else {
+            low = middle + 1;
+        }
+    }
+
+    Ok(ErgsEstimate::new(best, executions))
+}
+
+/// Estimates the compiled test, the entry point is executed at the bootloader address
+/// and does the far calls of the estimated transaction
+pub fn estimate_entry_point(
+    contracts: &[DebugContract],
+    max_ergs: u32,
+    cycle_limit: usize,
+) -> Result<ErgsEstimate, String> {
+    estimate_ergs(
+        || with_estimation_tracer(create_vm(contracts)),
+        max_ergs,
+        cycle_limit,
+    )
+}
+
+/// Estimates the bootloader with the transactions in the initial heap content
+pub fn estimate_bootloader(
+    artifact: &TestArtifact,
+    initial_heap_content: &[u8],
+    max_ergs: u32,
+    cycle_limit: usize,
+) -> Result<ErgsEstimate, String> {
+    estimate_ergs(
+        || {
+            with_estimation_tracer(create_bootloader_out_of_circuit_vm(
+                artifact,
+                initial_heap_content,
+            ))
+        },
+        max_ergs,
+        cycle_limit,
+    )
+}
+
+/// Out-of-circuit VM whose root frame is a far call from `caller` into the deployed contract, with
+/// the calldata in the calldata page. The memory of the frame is bounded by the stipend of a new far call
+/// frame. Panics if the contract is not in `contracts`
+pub fn create_contract_call_vm(
+    contracts: &HashMap<Address, Vec<[u8; 32]>>,
+    caller: Address,
+    address: Address,
+    calldata: &[u8],
+) -> AsmVm {
+    let mut storage_impl = InMemoryCustomRefundStorage::new();
+    let mut tree = ZKSyncTestingTree::empty();
+    save_predeployed_contracts(&mut storage_impl.storage, &mut tree, contracts);
+
+    let hash_of =
+        |bytecode: &[[u8; 32]]| U256::from_big_endian(&bytecode_to_code_hash(bytecode).unwrap());
+    let (tools, _, _) = create_tools_for_entry_point(
+        storage_impl,
+        &test_geometry(CYCLES_PER_VM_SNAPSHOT),
+        MemoryLimits::default(),
+        &contracts[&address],
+        contracts
+            .values()
+            .map(|bytecode| (hash_of(bytecode), bytecode.clone())),
+    );
+
+    // We must pass a correct empty code hash (with proper version) into the run method.
+    let empty_code_hash = hash_of(&[[0; 32]]);
+    let block_properties =
+        create_out_of_circuit_global_context(false, empty_code_hash, empty_code_hash);
+    let mut vm = create_out_of_circuit_vm(tools, block_properties, caller, address);
+
+    let memory_stipend = if address_is_kernel(&address) {
+        NEW_KERNEL_FRAME_MEMORY_STIPEND
+    } else {
+        NEW_FRAME_MEMORY_STIPEND
+    };
+    let frame = vm.local_state.callstack.get_current_stack_mut();
+    frame.heap_bound = memory_stipend;
+    frame.aux_heap_bound = memory_stipend;
+
+    vm.memory.populate_page(vec![(
+        BOOTLOADER_CALLDATA_PAGE,
+        calldata_to_aligned_data(&calldata.to_vec()),
+    )]);
+    let calldata_ptr = FatPointer {
+        offset: 0,
+        memory_page: BOOTLOADER_CALLDATA_PAGE,
+        start: 0,
+        length: calldata.len() as u32,
+    };
+    vm.local_state.registers[0] = PrimitiveValue {
+        value: calldata_ptr.to_u256(),
+        is_pointer: true,
+    };
+
+    vm
+}
+
+/// Estimates a far call from `caller` into the deployed contract with the calldata. The limit is the ergs
+/// passed to the callee, the decommitment and the other costs of the far call itself are paid by the caller
+pub fn estimate_far_call(
+    contracts: &HashMap<Address, Vec<[u8; 32]>>,
+    caller: Address,
+    address: Address,
+    calldata: &[u8],
+    max_ergs: u32,
+    cycle_limit: usize,
+) -> Result<ErgsEstimate, String> {
+    if !contracts.contains_key(&address) {
+        return Err(format!("Contract {:?} is not deployed", address));
+    }
+
+    estimate_ergs(
+        || {
+            with_estimation_tracer(create_contract_call_vm(
+                contracts, caller, address, calldata,
+            ))
+        },
+        max_ergs,
+        cycle_limit,
+    )
+}
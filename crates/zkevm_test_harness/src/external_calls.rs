@@ -2,7 +2,7 @@ use std::sync::mpsc::SyncSender;
 
 use crate::blake2::Blake2s256;
 use crate::ethereum_types::{Address, U256};
-use crate::run_vms::{run_vms, RunVMsResult, RunVmError};
+use crate::run_vms::{run_vms_with_memory_limits, RunVMsResult, RunVmError};
 pub use crate::run_vms::SCHEDULER_TIMESTAMP;
 use crate::snark_wrapper::boojum::field::goldilocks::GoldilocksExt2;
 use crate::snark_wrapper::boojum::gadgets::recursion::recursive_tree_hasher::CircuitGoldilocksPoseidon2Sponge;
@@ -11,6 +11,7 @@ use crate::witness::oracle::WitnessGenerationArtifact;
 use crate::witness::tree::BinarySparseStorageTree;
 use crate::witness::tree::ZkSyncStorageLeaf;
 use crate::zk_evm::abstractions::Storage;
+use crate::zk_evm::reference_impls::memory::MemoryLimits;
 use crate::zk_evm::GenericNoopTracer;
 use crate::zkevm_circuits::scheduler::block_header::BlockAuxilaryOutputWitness;
 use crate::zkevm_circuits::scheduler::{
@@ -54,8 +55,54 @@ pub fn run<S: Storage>(
     SchedulerCircuitInstanceWitness<MainField, CircuitGoldilocksPoseidon2Sponge, GoldilocksExt2>,
     BlockAuxilaryOutputWitness<MainField>,
 ) {
+    let (scheduler_circuit_witness, aux_data, _) = run_with_memory_limits(
+        caller,
+        entry_point_address,
+        entry_point_code,
+        initial_heap_content,
+        zk_porter_is_available,
+        default_aa_code_hash,
+        evm_simulator_code_hash,
+        used_bytecodes,
+        ram_verification_queries,
+        cycle_limit,
+        MemoryLimits::default(),
+        geometry,
+        storage,
+        tree,
+        trusted_setup_path,
+        eip_4844_repack_inputs,
+        artifacts_callback_sender,
+    );
+
+    (scheduler_circuit_witness, aux_data)
+}
+
+/// Same as `run`, but the memory of the out-of-circuit VM is bounded by `memory_limits`,
+/// its memory usage is returned together with the witnesses
+pub fn run_with_memory_limits<S: Storage>(
+    caller: Address,                 // for real block must be zero
+    entry_point_address: Address,    // for real block must be the bootloader
+    entry_point_code: Vec<[u8; 32]>, // for read block must be a bootloader code
+    initial_heap_content: Vec<u8>,   // bootloader starts with non-deterministic heap
+    zk_porter_is_available: bool,
+    default_aa_code_hash: U256,
+    evm_simulator_code_hash: U256,
+    used_bytecodes: std::collections::HashMap<U256, Vec<[u8; 32]>>, // auxilary information to avoid passing a full set of all used codes
+    ram_verification_queries: Vec<(u32, U256)>, // we may need to check that after the bootloader's memory is filled
+    cycle_limit: usize,
+    memory_limits: MemoryLimits,
+    geometry: GeometryConfig,
+    storage: S,
+    tree: impl BinarySparseStorageTree<256, 32, 32, 8, 32, Blake2s256, ZkSyncStorageLeaf>
+        + 'static
+        + std::marker::Send,
+    trusted_setup_path: String,
+    eip_4844_repack_inputs: [Option<Vec<u8>>; MAX_4844_BLOBS_PER_BLOCK],
+    artifacts_callback_sender: SyncSender<WitnessGenerationArtifact>,
+) -> RunVMsResult {
     let mut out_of_circuit_tracer = GenericNoopTracer::<_>::new();
-    match run_vms(
+    match run_vms_with_memory_limits(
         caller,
         entry_point_address,
         entry_point_code,
@@ -66,6 +113,7 @@ pub fn run<S: Storage>(
         used_bytecodes,
         ram_verification_queries,
         cycle_limit,
+        memory_limits,
         geometry,
         storage,
         tree,
@@ -74,7 +122,7 @@ pub fn run<S: Storage>(
         artifacts_callback_sender,
         &mut out_of_circuit_tracer,
     ) {
-        Ok((scheduler_circuit_witness, aux_data)) => (scheduler_circuit_witness, aux_data),
+        Ok(result) => result,
         Err(err) => {
             let error_text = match err {
                 RunVmError::InvalidInput(msg) => {
@@ -0,0 +1,166 @@
+use crate::boojum::field::goldilocks::GoldilocksField;
+use crate::boojum::worker::Worker;
+use circuit_definitions::circuit_definitions::base_layer::ZkSyncBaseLayerCircuit;
+use std::alloc::Global;
+
+/// Synthesizes the circuit from its witness and panics if it's not satisfied
+pub fn base_test_circuit(circuit: ZkSyncBaseLayerCircuit) {
+    use crate::boojum::config::DevCSConfig;
+    use crate::boojum::cs::cs_builder::new_builder;
+    use crate::boojum::cs::cs_builder_reference::CsReferenceImplementationBuilder;
+
+    type P = GoldilocksField;
+    // type P = MixedGL;
+
+    let worker = Worker::new();
+
+    let geometry = circuit.geometry();
+    let (max_trace_len, num_vars) = circuit.size_hint();
+
+    use crate::boojum::config::CSConfig;
+    let builder_impl = CsReferenceImplementationBuilder::<
+        GoldilocksField,
+        P,
+        DevCSConfig,
+        crate::boojum::dag::StCircuitResolver<
+            GoldilocksField,
+            <DevCSConfig as CSConfig>::ResolverConfig,
+        >,
+    >::new(geometry, max_trace_len.unwrap());
+    let arg = num_vars.unwrap();
+    let builder = new_builder::<_, GoldilocksField>(builder_impl);
+
+    let mut cs = match circuit {
+        ZkSyncBaseLayerCircuit::MainVM(inner) => {
+            let builder = inner.configure_builder_proxy(builder);
+            let mut cs = builder.build(arg);
+            inner.add_tables_proxy(&mut cs);
+            inner.synthesize_proxy(&mut cs);
+            let _ = cs.pad_and_shrink();
+            cs.into_assembly::<Global>()
+        }
+        ZkSyncBaseLayerCircuit::CodeDecommittmentsSorter(inner) => {
+            let builder = inner.configure_builder_proxy(builder);
+            let mut cs = builder.build(arg);
+            inner.add_tables_proxy(&mut cs);
+            inner.synthesize_proxy(&mut cs);
+            let _ = cs.pad_and_shrink();
+            cs.into_assembly::<std::alloc::Global>()
+        }
+        ZkSyncBaseLayerCircuit::CodeDecommitter(inner) => {
+            let builder = inner.configure_builder_proxy(builder);
+            let mut cs = builder.build(arg);
+            inner.add_tables_proxy(&mut cs);
+            inner.synthesize_proxy(&mut cs);
+            let _ = cs.pad_and_shrink();
+            cs.into_assembly::<std::alloc::Global>()
+        }
+        ZkSyncBaseLayerCircuit::LogDemuxer(inner) => {
+            let builder = inner.configure_builder_proxy(builder);
+            let mut cs = builder.build(arg);
+            inner.add_tables_proxy(&mut cs);
+            inner.synthesize_proxy(&mut cs);
+            let _ = cs.pad_and_shrink();
+            cs.into_assembly::<std::alloc::Global>()
+        }
+        ZkSyncBaseLayerCircuit::KeccakRoundFunction(inner) => {
+            let builder = inner.configure_builder_proxy(builder);
+            let mut cs = builder.build(arg);
+            inner.add_tables_proxy(&mut cs);
+            inner.synthesize_proxy(&mut cs);
+            let _ = cs.pad_and_shrink();
+            cs.into_assembly::<std::alloc::Global>()
+        }
+        ZkSyncBaseLayerCircuit::Sha256RoundFunction(inner) => {
+            let builder = inner.configure_builder_proxy(builder);
+            let mut cs = builder.build(arg);
+            inner.add_tables_proxy(&mut cs);
+            inner.synthesize_proxy(&mut cs);
+            let _ = cs.pad_and_shrink();
+            cs.into_assembly::<std::alloc::Global>()
+        }
+        ZkSyncBaseLayerCircuit::ECRecover(inner) => {
+            let builder = inner.configure_builder_proxy(builder);
+            let mut cs = builder.build(arg);
+            inner.add_tables_proxy(&mut cs);
+            inner.synthesize_proxy(&mut cs);
+            let _ = cs.pad_and_shrink();
+            cs.into_assembly::<std::alloc::Global>()
+        }
+        ZkSyncBaseLayerCircuit::RAMPermutation(inner) => {
+            let builder = inner.configure_builder_proxy(builder);
+            let mut cs = builder.build(arg);
+            inner.add_tables_proxy(&mut cs);
+            inner.synthesize_proxy(&mut cs);
+            let _ = cs.pad_and_shrink();
+            cs.into_assembly::<std::alloc::Global>()
+        }
+        ZkSyncBaseLayerCircuit::StorageSorter(inner) => {
+            let builder = inner.configure_builder_proxy(builder);
+            let mut cs = builder.build(arg);
+            inner.add_tables_proxy(&mut cs);
+            inner.synthesize_proxy(&mut cs);
+            let _ = cs.pad_and_shrink();
+            cs.into_assembly::<std::alloc::Global>()
+        }
+        ZkSyncBaseLayerCircuit::StorageApplication(inner) => {
+            let builder = inner.configure_builder_proxy(builder);
+            let mut cs = builder.build(arg);
+            inner.add_tables_proxy(&mut cs);
+            inner.synthesize_proxy(&mut cs);
+            let _ = cs.pad_and_shrink();
+            cs.into_assembly::<std::alloc::Global>()
+        }
+        ZkSyncBaseLayerCircuit::EventsSorter(inner) => {
+            let builder = inner.configure_builder_proxy(builder);
+            let mut cs = builder.build(arg);
+            inner.add_tables_proxy(&mut cs);
+            inner.synthesize_proxy(&mut cs);
+            let _ = cs.pad_and_shrink();
+            cs.into_assembly::<std::alloc::Global>()
+        }
+        ZkSyncBaseLayerCircuit::L1MessagesSorter(inner) => {
+            let builder = inner.configure_builder_proxy(builder);
+            let mut cs = builder.build(arg);
+            inner.add_tables_proxy(&mut cs);
+            inner.synthesize_proxy(&mut cs);
+            let _ = cs.pad_and_shrink();
+            cs.into_assembly::<std::alloc::Global>()
+        }
+        ZkSyncBaseLayerCircuit::L1MessagesHasher(inner) => {
+            let builder = inner.configure_builder_proxy(builder);
+            let mut cs = builder.build(num_vars.unwrap());
+            inner.add_tables_proxy(&mut cs);
+            inner.synthesize_proxy(&mut cs);
+            let _ = cs.pad_and_shrink();
+            cs.into_assembly::<std::alloc::Global>()
+        }
+        ZkSyncBaseLayerCircuit::TransientStorageSorter(inner) => {
+            let builder = inner.configure_builder_proxy(builder);
+            let mut cs = builder.build(num_vars.unwrap());
+            inner.add_tables_proxy(&mut cs);
+            inner.synthesize_proxy(&mut cs);
+            let _ = cs.pad_and_shrink();
+            cs.into_assembly::<std::alloc::Global>()
+        }
+        ZkSyncBaseLayerCircuit::Secp256r1Verify(inner) => {
+            let builder = inner.configure_builder_proxy(builder);
+            let mut cs = builder.build(num_vars.unwrap());
+            inner.add_tables_proxy(&mut cs);
+            inner.synthesize_proxy(&mut cs);
+            let _ = cs.pad_and_shrink();
+            cs.into_assembly::<std::alloc::Global>()
+        }
+        ZkSyncBaseLayerCircuit::EIP4844Repack(inner) => {
+            let builder = inner.configure_builder_proxy(builder);
+            let mut cs = builder.build(num_vars.unwrap());
+            inner.add_tables_proxy(&mut cs);
+            inner.synthesize_proxy(&mut cs);
+            let _ = cs.pad_and_shrink();
+            cs.into_assembly::<std::alloc::Global>()
+        }
+    };
+
+    let is_satisfied = cs.check_if_satisfied(&worker);
+    assert!(is_satisfied);
+}
@@ -1,2 +1,3 @@
 pub mod artifact_utils;
+pub mod circuit_utils;
 pub mod serialize_utils;
@@ -29,13 +29,21 @@ pub use crate::zk_evm::blake2;
 pub use crate::zk_evm::sha2;
 pub use crate::zk_evm::sha3;
 
+pub mod asm_runner;
 pub mod data_source;
 pub mod entry_point;
+pub mod ergs_estimator;
+pub mod opcode_fuzzer;
 pub use circuit_sequencer_api::geometry_config;
 pub use kzg;
 pub mod prover_utils;
 pub mod snark_wrapper_test;
+pub mod state_dump;
+pub mod state_provider;
 pub mod utils;
+pub mod vm_debugger;
+pub mod vm_diff;
+pub mod vm_trace;
 pub mod witness;
 
 // Debugging tools (for example for failed proofs).
@@ -58,6 +66,7 @@ pub(crate) mod tests;
 
 pub mod compute_setups;
 pub mod proof_wrapper_utils;
+pub mod protocol_commitments;
 
 pub use tests::complex_tests::utils::empty_node_proof;
 
@@ -0,0 +1,85 @@
+use std::path::PathBuf;
+
+use structopt::StructOpt;
+use zkevm_test_harness::opcode_fuzzer::{run_fuzzer_with_progress, FuzzProgress, FuzzerConfig};
+
+#[derive(Debug, StructOpt)]
+#[structopt(
+    name = "Opcode fuzzer",
+    about = "Runs random programs in the out-of-circuit VM and checks that the MainVM circuit agrees with it"
+)]
+struct Opt {
+    /// Seed of the first program, random if not set
+    #[structopt(long)]
+    seed: Option<u64>,
+    #[structopt(long, default_value = "100")]
+    iterations: usize,
+    /// Maximal number of near call frames in a program
+    #[structopt(long, default_value = "4")]
+    max_blocks: usize,
+    /// Maximal number of random instructions in a near call frame
+    #[structopt(long, default_value = "16")]
+    max_block_len: usize,
+    #[structopt(long, default_value = "4")]
+    cycles_per_vm_snapshot: u32,
+    /// Check all the base layer circuits, not only the MainVM ones
+    #[structopt(long)]
+    all_circuits: bool,
+    /// Directory for the minimized reproductions, they can be run with `asm_test_runner`
+    #[structopt(long, default_value = "./fuzz_failures")]
+    output_dir: PathBuf,
+    /// KZG trusted setup for the EIP4844 circuits
+    #[structopt(long)]
+    trusted_setup_path: Option<String>,
+}
+
+fn main() {
+    let opt = Opt::from_args();
+
+    let mut config = FuzzerConfig {
+        iterations: opt.iterations,
+        max_blocks: opt.max_blocks,
+        max_block_len: opt.max_block_len,
+        check_all_circuits: opt.all_circuits,
+        ..Default::default()
+    };
+    config.seed = opt.seed.unwrap_or_else(|| {
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_nanos() as u64
+    });
+    config.options.cycles_per_vm_snapshot = opt.cycles_per_vm_snapshot;
+    if let Some(trusted_setup_path) = opt.trusted_setup_path {
+        config.options.trusted_setup_path = trusted_setup_path;
+    }
+    println!("Fuzzing with seed {}", config.seed);
+
+    let cases = run_fuzzer_with_progress(&config, &mut |progress| match progress {
+        FuzzProgress::Passed { seed } => println!("seed {} ... ok", seed),
+        FuzzProgress::Failed { seed, failure } => {
+            println!("seed {} ... FAILED, minimizing\n{}", seed, failure)
+        }
+    });
+    for case in cases.iter() {
+        let path = case
+            .save(&opt.output_dir, &config.options)
+            .expect("Unable to save the reproduction");
+        println!(
+            "seed {}: {} ({} instructions) saved to {:?}",
+            case.seed,
+            case.failure,
+            case.program.num_instructions(),
+            path
+        );
+    }
+    println!(
+        "{} passed, {} failed",
+        config.iterations - cases.len(),
+        cases.len()
+    );
+
+    if !cases.is_empty() {
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,632 @@
+//! Differential fuzzing of the out-of-circuit VM (`zk_evm`) against the MainVM circuit.
+//!
+//! Random programs are executed with `run_vms`, and every MainVM circuit that is produced is synthesized
+//! and checked for satisfiability. Synthesis also compares the public input of the circuit against the one
+//! computed from the out-of-circuit state, so any divergence in the opcode semantics shows up
+//! either as an unsatisfied constraint or as a different output state of the VM chunk.
+//!
+//! Every program is a sequence of blocks, each block is executed in its own near call frame with
+//! a random ergs limit and an exception handler. So invalid operands, out of ergs and explicit
+//! panics only unwind the current block, and the root frame always finishes successfully.
+//! Control flow is forward only, so every program terminates.
+//!
+//! Far calls go to the predeployed callees (see `CALLEES`), each of them finishes with its own
+//! far return variant, so both the successful and the reverting far returns are exercised.
+//!
+//! Failing programs are minimized and can be saved as test directories for `asm_test_runner`.
+
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use rand::{Rng, SeedableRng, XorShiftRng};
+
+use crate::asm_runner::preprocess_asm::{asm_with_default_config, TemplateDictionary};
+use crate::asm_runner::{compile, execute, panic_reason, AsmTestManifest, Options};
+use crate::ethereum_types::{Address, H160};
+use crate::helper::circuit_utils::base_test_circuit;
+use circuit_definitions::circuit_definitions::base_layer::ZkSyncBaseLayerCircuit;
+
+/// Placeholder for the label at the end of the current block, the only possible jump target
+pub const BLOCK_END_LABEL: &str = "@block_end";
+
+/// Register that holds the ergs for the near call of the block
+const ERGS_REGISTER: &str = "r15";
+
+/// Contracts that are deployed for the far calls: address and the far return at the end.
+/// The addresses are in the kernel space, so they fit into an immediate
+pub const CALLEES: [(u16, &str); 4] = [
+    (0xff00, "ret.ok r0"),
+    // returns the calldata, interpreted as the heap slice
+    (0xff01, "ret.ok r1"),
+    (0xff02, "ret.revert r1"),
+    (0xff03, "ret.panic r0"),
+];
+
+/// Cycles of a far call on top of the call itself: loading the ABI and the address, and the callee body
+const FAR_CALL_CYCLES: usize = 6;
+
+const CONDITIONS: [&str; 8] = ["gt", "lt", "eq", "ge", "le", "ne", "gtlt", "of"];
+
+const CONTEXT_VARIANTS: [&str; 9] = [
+    "this",
+    "caller",
+    "code_source",
+    "meta",
+    "ergs_left",
+    "sp",
+    "get_context_u128",
+    "set_context_u128",
+    "set_ergs_per_pubdata",
+];
+
+// static memory is not generated: it's not used by the system contracts, so `SimpleMemory`
+// doesn't support it and panics on such queries
+const UMA_VARIANTS: [&str; 5] = [
+    "heap_read",
+    "heap_write",
+    "aux_heap_read",
+    "aux_heap_write",
+    "fat_ptr_read",
+];
+
+// decommit is not generated: random hashes are unknown to the decommitter of the harness,
+// so such programs fail in the witness generation instead of the VM
+const LOG_VARIANTS: [&str; 7] = [
+    "sread",
+    "swrite",
+    "event",
+    "to_l1",
+    "precompile",
+    "tread",
+    "twrite",
+];
+
+#[derive(Clone)]
+pub struct FuzzerConfig {
+    /// Seed of the first program, the next ones use `seed + 1`, `seed + 2`, ...
+    pub seed: u64,
+    pub iterations: usize,
+    /// Maximal number of near call frames in a program
+    pub max_blocks: usize,
+    /// Maximal number of random instructions in a near call frame
+    pub max_block_len: usize,
+    /// Check all the base layer circuits, not only the MainVM ones
+    pub check_all_circuits: bool,
+    /// The cycle limit is computed from the program, other options are used as is
+    pub options: Options,
+}
+
+impl Default for FuzzerConfig {
+    fn default() -> Self {
+        Self {
+            seed: 0,
+            iterations: 100,
+            max_blocks: 4,
+            max_block_len: 16,
+            check_all_circuits: false,
+            options: Options {
+                // small VM chunks, so the state is compared after every few instructions
+                cycles_per_vm_snapshot: 4,
+                ..Default::default()
+            },
+        }
+    }
+}
+
+/// Random instructions that are executed in their own near call frame
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FuzzBlock {
+    /// Ergs that are passed to the near call. Must not be 0: it passes all the available ergs,
+    /// and an exception in the block burns them, so the root frame runs out of ergs
+    pub ergs: u16,
+    /// Instructions in the canonical assembly syntax, may refer to `BLOCK_END_LABEL`.
+    /// A far call is a single entry that also loads the callee address and possibly the ABI
+    pub instructions: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FuzzProgram {
+    pub blocks: Vec<FuzzBlock>,
+}
+
+impl FuzzProgram {
+    pub fn num_instructions(&self) -> usize {
+        self.blocks.iter().map(|el| el.instructions.len()).sum()
+    }
+
+    /// Upper bound on the number of the executed cycles: control flow is forward only,
+    /// and every instruction may additionally execute the `ret` at the end of its block (near call to the block end)
+    pub fn cycle_limit(&self) -> usize {
+        let num_far_calls = self
+            .blocks
+            .iter()
+            .flat_map(|el| el.instructions.iter())
+            .filter(|el| el.contains("far_call"))
+            .count();
+
+        self.num_instructions() * 2 + num_far_calls * FAR_CALL_CYCLES + self.blocks.len() * 3 + 16
+    }
+
+    pub fn to_asm(&self) -> String {
+        let mut main = String::from("__entry:\n.main:\n");
+        let mut blocks = String::new();
+        for (idx, block) in self.blocks.iter().enumerate() {
+            main.push_str(&format!(
+                "    add {}, r0, {ERGS_REGISTER}\n    near_call {ERGS_REGISTER}, @block_{idx}, @block_{idx}_handler\nblock_{idx}_handler:\n",
+                block.ergs
+            ));
+
+            let end_label = format!("@block_{idx}_end");
+            blocks.push_str(&format!("block_{idx}:\n"));
+            for instruction in block.instructions.iter() {
+                blocks.push_str("    ");
+                blocks.push_str(&instruction.replace(BLOCK_END_LABEL, &end_label));
+                blocks.push('\n');
+            }
+            blocks.push_str(&format!("block_{idx}_end:\n    ret.ok r0\n"));
+        }
+        main.push_str("    ret.ok r0\n");
+
+        asm_with_default_config(&(main + &blocks))
+    }
+}
+
+/// Why the program was rejected
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FuzzFailure {
+    /// Generated program is not a valid assembly, that's a bug in the generator
+    Compilation(String),
+    /// Out-of-circuit execution failed, even though all the random code is run in near call frames
+    Execution(String),
+    /// Circuit is not satisfied or its public input differs from the out-of-circuit state
+    Circuit { circuit: String, reason: String },
+}
+
+impl FuzzFailure {
+    /// Failures that are (most likely) caused by the same bug, used to minimize the program
+    pub fn same_kind(&self, other: &Self) -> bool {
+        match (self, other) {
+            (FuzzFailure::Compilation(_), FuzzFailure::Compilation(_)) => true,
+            (FuzzFailure::Execution(_), FuzzFailure::Execution(_)) => true,
+            (
+                FuzzFailure::Circuit { circuit, .. },
+                FuzzFailure::Circuit {
+                    circuit: other_circuit,
+                    ..
+                },
+            ) => circuit == other_circuit,
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for FuzzFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FuzzFailure::Compilation(reason) => write!(f, "Compilation error: {reason}"),
+            FuzzFailure::Execution(reason) => write!(f, "Execution error: {reason}"),
+            FuzzFailure::Circuit { circuit, reason } => {
+                write!(f, "{circuit} circuit is not satisfied: {reason}")
+            }
+        }
+    }
+}
+
+/// Minimized failing program
+#[derive(Debug, Clone)]
+pub struct FuzzCase {
+    /// Seed of the original program
+    pub seed: u64,
+    pub program: FuzzProgram,
+    pub failure: FuzzFailure,
+}
+
+impl FuzzCase {
+    /// Saves the case as `asm_test_runner` test directory (`entry.asm` + `test.json`) in `dir`
+    pub fn save(&self, dir: &Path, options: &Options) -> std::io::Result<PathBuf> {
+        let path = dir.join(format!("seed_{}", self.seed));
+        std::fs::create_dir_all(&path)?;
+
+        let mut asm = String::new();
+        for line in self.failure.to_string().lines() {
+            asm.push_str(&format!("; {line}\n"));
+        }
+        asm.push_str(&self.program.to_asm());
+        std::fs::write(path.join("entry.asm"), asm)?;
+        for (address, source) in callee_sources() {
+            std::fs::write(path.join(format!("{address}.asm")), source)?;
+        }
+
+        let manifest = AsmTestManifest {
+            cycle_limit: Some(self.program.cycle_limit()),
+            cycles_per_vm_snapshot: Some(options.cycles_per_vm_snapshot),
+            ..Default::default()
+        };
+        std::fs::write(
+            path.join("test.json"),
+            serde_json::to_string_pretty(&manifest).unwrap(),
+        )?;
+
+        Ok(path)
+    }
+}
+
+/// Sources of `CALLEES`, the body writes to the storage and emits an event, so the rollbacks are covered too
+pub fn callee_sources() -> Vec<(u16, String)> {
+    CALLEES
+        .iter()
+        .map(|(address, ret)| {
+            let body = format!(
+                "__entry:\n.main:\n    log.swrite r1, r1, r0\n    log.event.first r1, r0, r0\n    {ret}\n"
+            );
+            (*address, asm_with_default_config(&body))
+        })
+        .collect()
+}
+
+/// Compiled `CALLEES`, to be deployed with `Options::other_contracts`
+pub fn callee_contracts() -> Result<Vec<(H160, Vec<[u8; 32]>)>, String> {
+    callee_sources()
+        .into_iter()
+        .map(|(address, source)| {
+            let bytecode = compile(&source, None, &TemplateDictionary::new())?;
+            Ok((Address::from_low_u64_be(address as u64), bytecode))
+        })
+        .collect()
+}
+
+fn rng_for_seed(seed: u64) -> XorShiftRng {
+    // xorshift must not be seeded with zeroes
+    XorShiftRng::from_seed([seed as u32, (seed >> 32) as u32, 0x9e37_79b9, 0x7f4a_7c15])
+}
+
+fn register<R: Rng>(rng: &mut R) -> String {
+    // r1 holds the calldata pointer at the start, so it's a bit more likely to get the valid pointer operations
+    if rng.gen_weighted_bool(4) {
+        "r1".to_owned()
+    } else {
+        format!("r{}", rng.gen_range(0, 16))
+    }
+}
+
+fn immediate<R: Rng>(rng: &mut R) -> String {
+    let value: u16 = match rng.gen_range(0, 4) {
+        0 => *rng.choose(&[0, 1, 31, 32, 255, 256, u16::MAX]).unwrap(),
+        1 => rng.gen_range(0, 64),
+        _ => rng.gen(),
+    };
+
+    value.to_string()
+}
+
+fn stack_operand<R: Rng>(rng: &mut R) -> String {
+    match rng.gen_range(0, 3) {
+        0 => format!("stack[{}]", rng.gen_range(0, 8)),
+        1 => format!("stack-[{}]", rng.gen_range(1, 4)),
+        _ => format!("stack[r{} + {}]", rng.gen_range(0, 16), rng.gen_range(0, 4)),
+    }
+}
+
+/// Full source and destination operands, at most one of them addresses the stack
+fn full_operands<R: Rng>(rng: &mut R) -> (String, String) {
+    match rng.gen_range(0, 10) {
+        0 | 1 => (stack_operand(rng), register(rng)),
+        2 => (register(rng), stack_operand(rng)),
+        3..=5 => (immediate(rng), register(rng)),
+        _ => (register(rng), register(rng)),
+    }
+}
+
+fn modifiers<R: Rng>(rng: &mut R, opcode: &str, swap: bool, set_flags: bool) -> String {
+    let mut result = opcode.to_owned();
+    if rng.gen_weighted_bool(6) {
+        result.push('.');
+        result.push_str(rng.choose(&CONDITIONS).unwrap());
+    }
+    if swap && rng.gen_weighted_bool(3) {
+        result.push_str(".s");
+    }
+    if set_flags && rng.gen_weighted_bool(3) {
+        result.push('!');
+    }
+
+    result
+}
+
+fn random_instruction<R: Rng>(rng: &mut R) -> String {
+    match rng.gen_range(0, 100) {
+        // values to work with: small, large (shifted) and negative
+        0..=9 => format!("add {}, r0, {}", immediate(rng), register(rng)),
+        10..=13 => format!(
+            "shl.s {}, {}, {}",
+            rng.gen_range(0, 256),
+            register(rng),
+            register(rng)
+        ),
+        14..=15 => format!("sub.s {}, r0, {}", immediate(rng), register(rng)),
+        16..=23 => {
+            let (src0, dst0) = full_operands(rng);
+            let opcode = *rng.choose(&["add", "sub"]).unwrap();
+            // only the non-commutative opcodes can swap the operands
+            let opcode = modifiers(rng, opcode, opcode == "sub", true);
+            format!("{opcode} {src0}, {}, {dst0}", register(rng))
+        }
+        24..=31 => {
+            let (src0, dst0) = full_operands(rng);
+            let opcode = *rng.choose(&["mul", "div"]).unwrap();
+            let opcode = modifiers(rng, opcode, opcode == "div", true);
+            format!(
+                "{opcode} {src0}, {}, {dst0}, {}",
+                register(rng),
+                register(rng)
+            )
+        }
+        32..=38 => {
+            let (src0, dst0) = full_operands(rng);
+            let opcode = *rng.choose(&["shl", "shr", "rol", "ror"]).unwrap();
+            let opcode = modifiers(rng, opcode, true, true);
+            format!("{opcode} {src0}, {}, {dst0}", register(rng))
+        }
+        39..=44 => {
+            let (src0, dst0) = full_operands(rng);
+            let opcode = *rng.choose(&["and", "or", "xor"]).unwrap();
+            let opcode = modifiers(rng, opcode, false, true);
+            format!("{opcode} {src0}, {}, {dst0}", register(rng))
+        }
+        45..=52 => {
+            // mostly invalid for the non-pointer operands
+            let variant = *rng.choose(&["add", "sub", "pack", "shrink"]).unwrap();
+            let opcode = modifiers(rng, &format!("ptr.{variant}"), true, false);
+            format!(
+                "{opcode} {}, {}, {}",
+                register(rng),
+                register(rng),
+                register(rng)
+            )
+        }
+        53..=60 => {
+            let variant = *rng.choose(&UMA_VARIANTS).unwrap();
+            let inc = if rng.gen() { ".inc" } else { "" };
+            // the fat pointer can't be an immediate
+            let offset = if variant != "fat_ptr_read" && rng.gen_weighted_bool(3) {
+                immediate(rng)
+            } else {
+                register(rng)
+            };
+            // the circuit writes the encoded dst1 even if the read doesn't update it (no `.inc`,
+            // or an exception), while `zk_evm` keeps the register, so it's always r0 like in
+            // the compiled code
+            format!(
+                "uma.{variant}{inc} {offset}, {}, {}, r0",
+                register(rng),
+                register(rng)
+            )
+        }
+        61..=68 => {
+            let variant = *rng.choose(&LOG_VARIANTS).unwrap();
+            // only the messages and the events can be marked as the first one
+            let first = if matches!(variant, "event" | "to_l1") && rng.gen() {
+                ".first"
+            } else {
+                ""
+            };
+            format!(
+                "log.{variant}{first} {}, {}, {}",
+                register(rng),
+                register(rng),
+                register(rng)
+            )
+        }
+        69..=74 => {
+            if rng.gen_weighted_bool(10) {
+                "context.inc_tx_num".to_owned()
+            } else {
+                format!(
+                    "context.{} {}",
+                    rng.choose(&CONTEXT_VARIANTS).unwrap(),
+                    register(rng)
+                )
+            }
+        }
+        75..=79 => {
+            let opcode = modifiers(rng, "jump", false, false);
+            format!("{opcode} {BLOCK_END_LABEL}")
+        }
+        // nested frame that only executes the `ret.ok` at the end of the block, with the ergs from the register
+        80..=84 => format!(
+            "near_call {}, {BLOCK_END_LABEL}, {BLOCK_END_LABEL}",
+            register(rng)
+        ),
+        85..=87 => format!("ret.{} r0", rng.choose(&["ok", "revert", "panic"]).unwrap()),
+        88..=91 => match rng.gen_range(0, 3) {
+            0 => "nop".to_owned(),
+            1 => format!("nop stack+=[{}]", rng.gen_range(1, 8)),
+            _ => format!("nop stack-=[{}]", rng.gen_range(1, 8)),
+        },
+        92..=93 => "invalid".to_owned(),
+        // far call to one of the callees. Zero ergs in the ABI pass no ergs, so half of the calls
+        // ask for more ergs than available (63/64 of them are passed), the other ones use a random ABI.
+        // Revert and panic of the callee continue at the end of the block
+        94..=97 => {
+            let (address, _) = rng.choose(&CALLEES).unwrap();
+            // r0 can't hold the address
+            let address_idx = rng.gen_range(1, 16);
+            let address_register = format!("r{address_idx}");
+            let (abi_setup, abi_register) = if rng.gen() {
+                let abi_register = format!("r{}", address_idx % 15 + 1);
+                (
+                    format!("add 65535, r0, {abi_register}\n    shl.s 208, {abi_register}, {abi_register}\n    "),
+                    abi_register,
+                )
+            } else {
+                (String::new(), register(rng))
+            };
+            let variant = *rng.choose(&["", ".delegate", ".mimic"]).unwrap();
+            let is_static = if rng.gen_weighted_bool(4) {
+                ".static"
+            } else {
+                ""
+            };
+            format!(
+                "{abi_setup}add {address}, r0, {address_register}\n    far_call{variant}{is_static} {abi_register}, {address_register}, {BLOCK_END_LABEL}"
+            )
+        }
+        _ => format!("add {}, r0, {}", register(rng), register(rng)),
+    }
+}
+
+fn random_ergs<R: Rng>(rng: &mut R) -> u16 {
+    match rng.gen_range(0, 3) {
+        // likely to run out of ergs in the middle of the block
+        0 => rng.gen_range(1, 200),
+        _ => rng.gen_range(200, u16::MAX),
+    }
+}
+
+/// Deterministic random program for the seed
+pub fn generate_program(seed: u64, config: &FuzzerConfig) -> FuzzProgram {
+    let mut rng = rng_for_seed(seed);
+    let num_blocks = rng.gen_range(1, config.max_blocks + 1);
+    let blocks = (0..num_blocks)
+        .map(|_| {
+            let len = rng.gen_range(1, config.max_block_len + 1);
+            FuzzBlock {
+                ergs: random_ergs(&mut rng),
+                instructions: (0..len).map(|_| random_instruction(&mut rng)).collect(),
+            }
+        })
+        .collect();
+
+    FuzzProgram { blocks }
+}
+
+/// Runs the program in the out-of-circuit VM and checks the circuits
+pub fn check_program(program: &FuzzProgram, config: &FuzzerConfig) -> Result<(), FuzzFailure> {
+    let bytecode = compile(&program.to_asm(), None, &TemplateDictionary::new())
+        .map_err(FuzzFailure::Compilation)?;
+
+    let mut options = config.options.clone();
+    options.cycle_limit = program.cycle_limit();
+    options
+        .other_contracts
+        .extend(callee_contracts().map_err(FuzzFailure::Compilation)?);
+    let (circuits, _) =
+        std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| execute(bytecode, options)))
+            .map_err(|panic| FuzzFailure::Execution(panic_reason(panic)))?
+            .map_err(|err| FuzzFailure::Execution(err.to_string()))?;
+
+    for circuit in circuits {
+        if !config.check_all_circuits && !matches!(circuit, ZkSyncBaseLayerCircuit::MainVM(_)) {
+            continue;
+        }
+        let description = circuit.short_description();
+        std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| base_test_circuit(circuit)))
+            .map_err(|panic| FuzzFailure::Circuit {
+                circuit: description.to_owned(),
+                reason: panic_reason(panic),
+            })?;
+    }
+
+    Ok(())
+}
+
+/// Removes blocks and instructions while the program still fails in the same way
+pub fn minimize(
+    program: FuzzProgram,
+    failure: FuzzFailure,
+    config: &FuzzerConfig,
+) -> (FuzzProgram, FuzzFailure) {
+    let mut program = program;
+    let mut failure = failure;
+
+    let try_candidate =
+        |candidate: &FuzzProgram, failure: &mut FuzzFailure| match check_program(candidate, config)
+        {
+            Err(new_failure) if new_failure.same_kind(failure) => {
+                *failure = new_failure;
+                true
+            }
+            _ => false,
+        };
+
+    loop {
+        let mut changed = false;
+
+        for idx in (0..program.blocks.len()).rev() {
+            if program.blocks.len() == 1 {
+                break;
+            }
+            let mut candidate = program.clone();
+            candidate.blocks.remove(idx);
+            if try_candidate(&candidate, &mut failure) {
+                program = candidate;
+                changed = true;
+            }
+        }
+
+        for block_idx in (0..program.blocks.len()).rev() {
+            for idx in (0..program.blocks[block_idx].instructions.len()).rev() {
+                let mut candidate = program.clone();
+                candidate.blocks[block_idx].instructions.remove(idx);
+                if try_candidate(&candidate, &mut failure) {
+                    program = candidate;
+                    changed = true;
+                }
+            }
+
+            if program.blocks[block_idx].ergs != u16::MAX {
+                let mut candidate = program.clone();
+                candidate.blocks[block_idx].ergs = u16::MAX;
+                if try_candidate(&candidate, &mut failure) {
+                    program = candidate;
+                    changed = true;
+                }
+            }
+        }
+
+        if !changed {
+            return (program, failure);
+        }
+    }
+}
+
+/// Outcome of a single random program, the failing one is reported before it's minimized
+#[derive(Debug, Clone)]
+pub enum FuzzProgress {
+    Passed { seed: u64 },
+    Failed { seed: u64, failure: FuzzFailure },
+}
+
+/// Runs `config.iterations` random programs, returns the minimized failing ones
+pub fn run_fuzzer(config: &FuzzerConfig) -> Vec<FuzzCase> {
+    run_fuzzer_with_progress(config, &mut |_| {})
+}
+
+/// Same as `run_fuzzer`, the outcome of every program is reported to `progress`
+pub fn run_fuzzer_with_progress(
+    config: &FuzzerConfig,
+    progress: &mut dyn FnMut(FuzzProgress),
+) -> Vec<FuzzCase> {
+    let mut cases = vec![];
+    for iteration in 0..config.iterations {
+        let seed = config.seed.wrapping_add(iteration as u64);
+        let program = generate_program(seed, config);
+        match check_program(&program, config) {
+            Ok(()) => progress(FuzzProgress::Passed { seed }),
+            Err(failure) => {
+                progress(FuzzProgress::Failed {
+                    seed,
+                    failure: failure.clone(),
+                });
+                let (program, failure) = minimize(program, failure, config);
+                cases.push(FuzzCase {
+                    seed,
+                    program,
+                    failure,
+                });
+            }
+        }
+    }
+
+    cases
+}
@@ -4,103 +4,109 @@ pub(crate) fn compute_compression_vks_and_write<DS: SetupDataSource + BlockDataS
     source: &mut DS,
     config: WrapperConfig,
     worker: &Worker,
-) {
+) -> WrapResult<()> {
     for circuit_type in config.get_compression_types() {
-        let vk = get_vk_for_previous_circuit(source, circuit_type).expect(&format!(
-            "VK of previous circuit should be present. Current circuit type: {}",
-            circuit_type
-        ));
-
-        let compression_circuit =
-            ZkSyncCompressionLayerCircuit::from_witness_and_vk(None, vk, circuit_type);
-        let proof_config = compression_circuit.proof_config_for_compression_step();
-
-        let (_, _, vk, _, _, _, finalization_hint) = create_compression_layer_setup_data(
-            compression_circuit,
-            &worker,
-            proof_config.fri_lde_factor,
-            proof_config.merkle_tree_cap_size,
-        );
+        let vk = get_vk_for_previous_circuit(source, circuit_type).map_err(source_failure(
+            format!("vk of previous circuit for compression {}", circuit_type),
+        ))?;
+
+        let (vk, finalization_hint) =
+            catch_prover_failure(WrapStage::Compression(circuit_type), || {
+                let compression_circuit =
+                    ZkSyncCompressionLayerCircuit::from_witness_and_vk(None, vk, circuit_type);
+                let proof_config = compression_circuit.proof_config_for_compression_step();
+
+                let (_, _, vk, _, _, _, finalization_hint) = create_compression_layer_setup_data(
+                    compression_circuit,
+                    &worker,
+                    proof_config.fri_lde_factor,
+                    proof_config.merkle_tree_cap_size,
+                );
+
+                (vk, finalization_hint)
+            })?;
 
         source
-            .set_compression_vk(ZkSyncCompressionLayerStorage::from_inner(
-                circuit_type,
-                vk.clone(),
-            ))
-            .unwrap();
+            .set_compression_vk(ZkSyncCompressionLayerStorage::from_inner(circuit_type, vk))
+            .map_err(source_failure("compression vk"))?;
         source
             .set_compression_hint(ZkSyncCompressionLayerStorage::from_inner(
                 circuit_type,
-                finalization_hint.clone(),
+                finalization_hint,
             ))
-            .unwrap();
+            .map_err(source_failure("compression hint"))?;
     }
+
+    Ok(())
 }
 
 pub(crate) fn compute_compression_circuits<DS: SetupDataSource + BlockDataSource>(
     source: &mut DS,
     config: WrapperConfig,
     worker: &Worker,
-) {
+    progress: &mut dyn FnMut(WrapProgress),
+) -> WrapResult<()> {
     for circuit_type in config.get_compression_types() {
         if source.get_compression_proof(circuit_type).is_err()
             || source.get_compression_vk(circuit_type).is_err()
             || source.get_compression_hint(circuit_type).is_err()
         {
-            let proof = get_proof_for_previous_circuit(source, circuit_type).expect(&format!(
-                "Proof of previous circuit should be present. Current circuit type: {}",
-                circuit_type
-            ));
-            let vk = get_vk_for_previous_circuit(source, circuit_type).expect(&format!(
-                "VK of previous circuit should be present. Current circuit type: {}",
-                circuit_type
-            ));
+            let stage = WrapStage::Compression(circuit_type);
+            progress(WrapProgress::Started(stage));
+            let start = std::time::Instant::now();
+
+            let proof =
+                get_proof_for_previous_circuit(source, circuit_type).map_err(source_failure(
+                    format!("proof of previous circuit for compression {}", circuit_type),
+                ))?;
+            let vk = get_vk_for_previous_circuit(source, circuit_type).map_err(source_failure(
+                format!("vk of previous circuit for compression {}", circuit_type),
+            ))?;
 
             let compression_circuit =
                 ZkSyncCompressionLayerCircuit::from_witness_and_vk(Some(proof), vk, circuit_type);
 
-            let (vk, finalization_hint, proof) =
-                compute_compression_circuit_inner(compression_circuit, &worker);
+            let (vk, finalization_hint, proof) = catch_prover_failure(stage, || {
+                compute_compression_circuit_inner(compression_circuit, &worker)
+            })??;
 
             source
                 .set_compression_vk(ZkSyncCompressionLayerStorage::from_inner(
                     circuit_type,
                     vk.clone(),
                 ))
-                .unwrap();
+                .map_err(source_failure("compression vk"))?;
             source
                 .set_compression_hint(ZkSyncCompressionLayerStorage::from_inner(
                     circuit_type,
                     finalization_hint.clone(),
                 ))
-                .unwrap();
+                .map_err(source_failure("compression hint"))?;
             source
                 .set_compression_proof(ZkSyncCompressionLayerStorage::from_inner(
                     circuit_type,
                     proof,
                 ))
-                .unwrap();
+                .map_err(source_failure("compression proof"))?;
+
+            progress(WrapProgress::Finished(stage, start.elapsed()));
         }
     }
+
+    Ok(())
 }
 
 fn compute_compression_circuit_inner(
     circuit: ZkSyncCompressionLayerCircuit,
     worker: &Worker,
-) -> (
+) -> WrapResult<(
     ZkSyncCompressionVerificationKey,
     FinalizationHintsForProver,
     ZkSyncCompressionProof,
-) {
-    let start = std::time::Instant::now();
-
+)> {
     let circuit_type = circuit.numeric_circuit_type();
 
     test_compression_circuit(circuit.clone());
-    println!(
-        "Input circuit for compression {} is satisfied",
-        circuit_type
-    );
 
     let proof_config = circuit.proof_config_for_compression_step();
 
@@ -113,9 +119,6 @@ fn compute_compression_circuit_inner(
             proof_config.merkle_tree_cap_size,
         );
 
-    // prove
-    println!("Proving compression level {} !", circuit_type);
-
     let proof = prove_compression_layer_circuit::<NoPow>(
         circuit,
         &worker,
@@ -130,14 +133,12 @@ fn compute_compression_circuit_inner(
     );
 
     let is_valid = verify_compression_layer_proof::<NoPow>(&setup_circuit, &proof, &vk);
+    if !is_valid {
+        return Err(WrapError::Prover(format!(
+            "compression {} proof is not valid",
+            circuit_type
+        )));
+    }
 
-    assert!(is_valid);
-
-    println!(
-        "Compression {} is done, taken {:?}",
-        circuit_type,
-        start.elapsed()
-    );
-
-    (vk, finalization_hint, proof)
+    Ok((vk, finalization_hint, proof))
 }
@@ -6,44 +6,57 @@ pub(crate) fn compute_compression_for_wrapper_vk_and_write<
     config: WrapperConfig,
     source: &mut DS,
     worker: &Worker,
-) {
+) -> WrapResult<()> {
     let compression_for_wrapper_type = config.get_compression_for_wrapper_type();
-    let vk = get_vk_for_previous_circuit(source, compression_for_wrapper_type).unwrap();
+    let vk = get_vk_for_previous_circuit(source, compression_for_wrapper_type).map_err(
+        source_failure(format!(
+            "vk of previous circuit for compression for wrapper {}",
+            compression_for_wrapper_type
+        )),
+    )?;
+
+    let stage = WrapStage::CompressionForWrapper(compression_for_wrapper_type);
+    let (vk, finalization_hint) = catch_prover_failure(stage, || {
+        let circuit = ZkSyncCompressionForWrapperCircuit::from_witness_and_vk(
+            None,
+            vk,
+            compression_for_wrapper_type,
+        );
 
-    let circuit = ZkSyncCompressionForWrapperCircuit::from_witness_and_vk(
-        None,
-        vk,
-        compression_for_wrapper_type,
-    );
+        let proof_config = circuit.proof_config_for_compression_step();
 
-    let proof_config = circuit.proof_config_for_compression_step();
+        let (_, _, vk, _, _, _, finalization_hint) = create_compression_for_wrapper_setup_data(
+            circuit,
+            &worker,
+            proof_config.fri_lde_factor,
+            proof_config.merkle_tree_cap_size,
+        );
 
-    let (_, _, vk, _, _, _, finalization_hint) = create_compression_for_wrapper_setup_data(
-        circuit,
-        &worker,
-        proof_config.fri_lde_factor,
-        proof_config.merkle_tree_cap_size,
-    );
+        (vk, finalization_hint)
+    })?;
 
     source
         .set_compression_for_wrapper_vk(ZkSyncCompressionLayerStorage::from_inner(
             compression_for_wrapper_type,
-            vk.clone(),
+            vk,
         ))
-        .unwrap();
+        .map_err(source_failure("compression for wrapper vk"))?;
     source
         .set_compression_for_wrapper_hint(ZkSyncCompressionLayerStorage::from_inner(
             compression_for_wrapper_type,
-            finalization_hint.clone(),
+            finalization_hint,
         ))
-        .unwrap();
+        .map_err(source_failure("compression for wrapper hint"))?;
+
+    Ok(())
 }
 
 pub(crate) fn compute_compression_for_wrapper_circuit<DS: SetupDataSource + BlockDataSource>(
     source: &mut DS,
     config: WrapperConfig,
     worker: &Worker,
-) {
+    progress: &mut dyn FnMut(WrapProgress),
+) -> WrapResult<()> {
     let circuit_type = config.get_compression_for_wrapper_type();
 
     if source.get_compression_for_wrapper_vk(circuit_type).is_err()
@@ -54,57 +67,65 @@ pub(crate) fn compute_compression_for_wrapper_circuit<DS: SetupDataSource + Bloc
             .get_compression_for_wrapper_proof(circuit_type)
             .is_err()
     {
-        let proof = get_proof_for_previous_circuit(source, circuit_type).expect(&format!(
-            "Proof of previous circuit should be present. Current circuit type: {}",
-            circuit_type
-        ));
-        let vk = get_vk_for_previous_circuit(source, circuit_type).expect(&format!(
-            "VK of previous circuit should be present. Current circuit type: {}",
-            circuit_type
-        ));
+        let stage = WrapStage::CompressionForWrapper(circuit_type);
+        progress(WrapProgress::Started(stage));
+        let start = std::time::Instant::now();
+
+        let proof = get_proof_for_previous_circuit(source, circuit_type).map_err(
+            source_failure(format!(
+                "proof of previous circuit for compression wrapper {}",
+                circuit_type
+            )),
+        )?;
+        let vk =
+            get_vk_for_previous_circuit(source, circuit_type).map_err(source_failure(format!(
+                "vk of previous circuit for compression wrapper {}",
+                circuit_type
+            )))?;
 
         let compression_circuit =
             ZkSyncCompressionForWrapperCircuit::from_witness_and_vk(Some(proof), vk, circuit_type);
 
-        let (vk, finalization_hint, proof) =
-            compute_compression_for_wrapper_circuit_inner(compression_circuit, worker);
+        let (vk, finalization_hint, proof) = catch_prover_failure(stage, || {
+            compute_compression_for_wrapper_circuit_inner(compression_circuit, worker)
+        })??;
 
-        // we did it above
         source
             .set_compression_for_wrapper_vk(ZkSyncCompressionLayerStorage::from_inner(
                 circuit_type,
                 vk.clone(),
             ))
-            .unwrap();
+            .map_err(source_failure("compression for wrapper vk"))?;
         source
             .set_compression_for_wrapper_hint(ZkSyncCompressionLayerStorage::from_inner(
                 circuit_type,
                 finalization_hint.clone(),
             ))
-            .unwrap();
+            .map_err(source_failure("compression for wrapper hint"))?;
         source
             .set_compression_for_wrapper_proof(ZkSyncCompressionLayerStorage::from_inner(
                 circuit_type,
                 proof,
             ))
-            .unwrap();
+            .map_err(source_failure("compression for wrapper proof"))?;
+
+        progress(WrapProgress::Finished(stage, start.elapsed()));
     }
+
+    Ok(())
 }
 
 fn compute_compression_for_wrapper_circuit_inner(
     circuit: ZkSyncCompressionForWrapperCircuit,
     worker: &Worker,
-) -> (
+) -> WrapResult<(
     ZkSyncCompressionVerificationKeyForWrapper,
     FinalizationHintsForProver,
     ZkSyncCompressionProofForWrapper,
-) {
-    let start = std::time::Instant::now();
-
+)> {
     let circuit_type = circuit.numeric_circuit_type();
 
     test_compression_for_wrapper_circuit(circuit.clone());
-    println!("Input Circuit for compression wrapper is satisfied");
 
     let proof_config = circuit.proof_config_for_compression_step();
 
@@ -117,10 +138,6 @@ fn compute_compression_for_wrapper_circuit_inner(
             proof_config.merkle_tree_cap_size,
         );
 
-    // prove
-    println!("Proving compression wrapper circuit.");
-    let now = std::time::Instant::now();
-
     let proof = prove_compression_for_wrapper_circuit::<NoPow>(
         circuit,
         &worker,
@@ -134,20 +151,13 @@ fn compute_compression_for_wrapper_circuit_inner(
         &finalization_hint,
     );
 
-    println!(
-        "Proving compression wrapper is DONE, taken {:?}",
-        now.elapsed()
-    );
-
     let is_valid = verify_compression_for_wrapper_proof::<NoPow>(&setup_circuit, &proof, &vk);
+    if !is_valid {
+        return Err(WrapError::Prover(format!(
+            "compression for wrapper {} proof is not valid",
+            circuit_type
+        )));
+    }
 
-    assert!(is_valid);
-
-    println!(
-        "Compression for wrapper {} is done, taken {:?}",
-        circuit_type,
-        start.elapsed()
-    );
-
-    (vk, finalization_hint, proof)
+    Ok((vk, finalization_hint, proof))
 }
@@ -0,0 +1,101 @@
+use std::fmt;
+use std::time::Duration;
+
+/// Error of the wrapping pipeline.
+/// Wrapping is run in long-lived services, so everything that can go wrong
+/// (including panics inside of the provers) is reported through this type.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WrapError {
+    /// Wrapper config is not supported, e.g. compression layers count is out of range
+    InvalidConfig(String),
+    /// Proof or vk is for the different circuit than expected
+    WrongCircuitType { expected: u8, actual: u8 },
+    /// Input scheduler proof doesn't verify against the provided vk
+    InvalidProof,
+    /// CRS file is not configured or can't be read
+    MissingCrs(String),
+    /// Failure while synthesizing, proving or verifying one of the intermediate circuits,
+    /// or while accessing the intermediate artifacts
+    Prover(String),
+}
+
+impl fmt::Display for WrapError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WrapError::InvalidConfig(reason) => write!(f, "invalid wrapper config: {}", reason),
+            WrapError::WrongCircuitType { expected, actual } => write!(
+                f,
+                "wrong circuit type: expected {}, got {}",
+                expected, actual
+            ),
+            WrapError::InvalidProof => write!(f, "provided scheduler proof is not valid"),
+            WrapError::MissingCrs(reason) => write!(f, "CRS is not available: {}", reason),
+            WrapError::Prover(reason) => write!(f, "prover failure: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for WrapError {}
+
+pub type WrapResult<T> = Result<T, WrapError>;
+
+/// Step of the wrapping pipeline, see `WrapProgress`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WrapStage {
+    /// Compression with Goldilocks Poseidon2 hash, with its circuit type
+    Compression(u8),
+    /// Final compression with Bn256 Poseidon2 hash
+    CompressionForWrapper(u8),
+    /// Bn256 SNARK wrapper
+    Wrapper(u8),
+    /// Aggregation of the scheduler proofs of several batches, with their count
+    InterblockAggregation(usize),
+}
+
+/// Progress report passed to the callback of `wrap_proof_with_progress`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WrapProgress {
+    Started(WrapStage),
+    Finished(WrapStage, Duration),
+}
+
+/// Callback that prints the progress, for the tools that want the old verbose behaviour
+pub fn print_wrap_progress(progress: WrapProgress) {
+    match progress {
+        WrapProgress::Started(stage) => println!("Computing {:?}", stage),
+        WrapProgress::Finished(stage, taken) => println!("{:?} is done, taken {:?}", stage, taken),
+    }
+}
+
+/// Runs the prover code and converts panics into `WrapError::Prover`.
+/// Synthesis and proving code of boojum/bellman panics on failures.
+pub(crate) fn catch_prover_failure<T>(stage: WrapStage, f: impl FnOnce() -> T) -> WrapResult<T> {
+    std::panic::catch_unwind(std::panic::AssertUnwindSafe(f)).map_err(|panic| {
+        let reason = panic
+            .downcast_ref::<&str>()
+            .map(|el| el.to_string())
+            .or_else(|| panic.downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "unknown panic".to_string());
+        WrapError::Prover(format!("{:?}: {}", stage, reason))
+    })
+}
+
+/// Converts errors returned by the bellman/boojum code into `WrapError::Prover`
+pub(crate) fn prover_failure<E: fmt::Debug>(stage: WrapStage) -> impl FnOnce(E) -> WrapError {
+    move |el| WrapError::Prover(format!("{:?}: {:?}", stage, el))
+}
+
+/// Proof, vk or setup is for the different compression layer than the wrapper config says
+pub(crate) fn check_circuit_type(expected: u8, actual: u8) -> WrapResult<()> {
+    if actual != expected {
+        return Err(WrapError::WrongCircuitType { expected, actual });
+    }
+
+    Ok(())
+}
+
+pub(crate) fn source_failure(
+    what: impl fmt::Display,
+) -> impl FnOnce(Box<dyn std::error::Error>) -> WrapError {
+    move |el| WrapError::Prover(format!("{}: {}", what, el))
+}
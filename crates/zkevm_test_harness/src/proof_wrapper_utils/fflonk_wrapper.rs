@@ -0,0 +1,228 @@
+use super::*;
+
+use circuit_definitions::fflonk::prover::create_proof as create_fflonk_proof;
+use circuit_definitions::fflonk::verify as verify_fflonk;
+
+/// FFLONK wrapper circuit is smaller (width 3, no lookups and custom gates),
+/// but the combined commitment makes the effective domain bigger, so the trace is one power lower.
+pub const FFLONK_L1_VERIFIER_DOMAIN_SIZE_LOG: usize = 23;
+
+pub fn get_fflonk_wrapper_setup_and_vk_from_compression_vk(
+    vk: ZkSyncCompressionForWrapperVerificationKey,
+    config: WrapperConfig,
+) -> WrapResult<(ZkSyncFflonkSnarkWrapperSetup, ZkSyncFflonkSnarkWrapperVK)> {
+    get_fflonk_wrapper_setup_and_vk_from_compression_vk_with_progress(vk, config, &mut |_| {})
+}
+
+/// Same as `get_fflonk_wrapper_setup_and_vk_from_compression_vk`, the wrapper step is reported to `progress`
+pub fn get_fflonk_wrapper_setup_and_vk_from_compression_vk_with_progress(
+    vk: ZkSyncCompressionForWrapperVerificationKey,
+    config: WrapperConfig,
+    progress: &mut dyn FnMut(WrapProgress),
+) -> WrapResult<(ZkSyncFflonkSnarkWrapperSetup, ZkSyncFflonkSnarkWrapperVK)> {
+    if config.get_wrapper_mode() != WrapperMode::Fflonk {
+        return Err(WrapError::InvalidConfig(
+            "use get_wrapper_setup_and_vk_from_compression_vk for PLONK wrapper".to_string(),
+        ));
+    }
+    let wrapper_type = config.get_wrapper_type();
+    let stage = WrapStage::Wrapper(wrapper_type);
+    progress(WrapProgress::Started(stage));
+    let start = std::time::Instant::now();
+
+    check_circuit_type(
+        config.get_compression_for_wrapper_type(),
+        vk.numeric_circuit_type(),
+    )?;
+    let crs_mons = try_get_trusted_setup()?;
+    let worker = BellmanWorker::new();
+
+    let snark_setup = compute_fflonk_wrapper_setup_inner(vk, config, &worker)?;
+    let snark_vk = FflonkVerificationKey::from_setup(&snark_setup, &crs_mons)
+        .map_err(|el| WrapError::Prover(format!("FFLONK wrapper vk: {:?}", el)))?;
+
+    progress(WrapProgress::Finished(stage, start.elapsed()));
+
+    Ok((
+        ZkSyncFflonkSnarkWrapperSetup::from_inner(wrapper_type, Arc::new(snark_setup)),
+        ZkSyncFflonkSnarkWrapperVK::from_inner(wrapper_type, snark_vk),
+    ))
+}
+
+pub(crate) fn compute_fflonk_wrapper_proof_and_vk<DS: SetupDataSource + BlockDataSource>(
+    source: &mut DS,
+    config: WrapperConfig,
+    worker: &BellmanWorker,
+    progress: &mut dyn FnMut(WrapProgress),
+) -> WrapResult<()> {
+    let wrapper_type = config.get_wrapper_type();
+    let stage = WrapStage::Wrapper(wrapper_type);
+    progress(WrapProgress::Started(stage));
+    let start = std::time::Instant::now();
+
+    if source.get_fflonk_wrapper_setup(wrapper_type).is_err() {
+        let vk = source
+            .get_compression_for_wrapper_vk(wrapper_type)
+            .map_err(source_failure("compression for wrapper vk"))?;
+
+        let snark_setup = compute_fflonk_wrapper_setup_inner(vk, config, worker)?;
+
+        let snark_setup =
+            ZkSyncFflonkWrapperStorage::from_inner(wrapper_type, Arc::new(snark_setup));
+        source
+            .set_fflonk_wrapper_setup(snark_setup)
+            .map_err(source_failure("FFLONK wrapper setup"))?;
+    }
+
+    if source.get_fflonk_wrapper_vk(wrapper_type).is_err() {
+        let snark_setup = source
+            .get_fflonk_wrapper_setup(wrapper_type)
+            .map_err(source_failure("FFLONK wrapper setup"))?;
+
+        let crs_mons = try_get_trusted_setup()?;
+        let snark_vk = FflonkVerificationKey::from_setup(&snark_setup.into_inner(), &crs_mons)
+            .map_err(|el| WrapError::Prover(format!("FFLONK wrapper vk: {:?}", el)))?;
+
+        let snark_vk = ZkSyncFflonkWrapperStorage::from_inner(wrapper_type, snark_vk);
+        source
+            .set_fflonk_wrapper_vk(snark_vk)
+            .map_err(source_failure("FFLONK wrapper vk"))?;
+    }
+
+    if source.get_fflonk_wrapper_proof(wrapper_type).is_err() {
+        let proof = source
+            .get_compression_for_wrapper_proof(wrapper_type)
+            .map_err(source_failure("compression for wrapper proof"))?;
+        let vk = source
+            .get_compression_for_wrapper_vk(wrapper_type)
+            .map_err(source_failure("compression for wrapper vk"))?;
+
+        let snark_setup = source
+            .get_fflonk_wrapper_setup(wrapper_type)
+            .map_err(source_failure("FFLONK wrapper setup"))?;
+
+        let snark_proof =
+            compute_fflonk_wrapper_proof_inner(proof, vk, snark_setup, config, worker)?;
+
+        let snark_vk = source
+            .get_fflonk_wrapper_vk(wrapper_type)
+            .map_err(source_failure("FFLONK wrapper vk"))?;
+        let is_valid = verify_fflonk::<_, _, RollingKeccakTranscript<Fr>>(
+            &snark_vk.into_inner(),
+            &snark_proof,
+            None,
+        )
+        .map_err(|el| WrapError::Prover(format!("FFLONK wrapper verification: {:?}", el)))?;
+        if !is_valid {
+            return Err(WrapError::Prover(
+                "FFLONK wrapper proof is not valid".to_string(),
+            ));
+        }
+
+        let snark_proof = ZkSyncFflonkWrapperStorage::from_inner(wrapper_type, snark_proof);
+        source
+            .set_fflonk_wrapper_proof(snark_proof)
+            .map_err(source_failure("FFLONK wrapper proof"))?;
+    }
+
+    progress(WrapProgress::Finished(stage, start.elapsed()));
+
+    Ok(())
+}
+
+fn make_fflonk_wrapper_circuit(
+    proof: Option<ZkSyncCompressionProofForWrapper>,
+    vk: ZkSyncCompressionVerificationKeyForWrapper,
+    wrapper_type: u8,
+) -> FflonkSnarkVerifierCircuit {
+    let fixed_parameters = vk.fixed_parameters.clone();
+
+    FflonkSnarkVerifierCircuit {
+        witness: proof,
+        vk,
+        fixed_parameters,
+        transcript_params: (),
+        wrapper_function: ZkSyncCompressionWrapper::from_numeric_circuit_type(wrapper_type),
+    }
+}
+
+pub(crate) fn compute_fflonk_wrapper_setup_inner(
+    vk: ZkSyncCompressionForWrapperVerificationKey,
+    config: WrapperConfig,
+    worker: &BellmanWorker,
+) -> WrapResult<FflonkSnarkVerifierCircuitSetup> {
+    let wrapper_type = config.get_wrapper_type();
+    let stage = WrapStage::Wrapper(wrapper_type);
+
+    let compression_for_wrapper_type = config.get_compression_for_wrapper_type();
+    check_circuit_type(compression_for_wrapper_type, vk.numeric_circuit_type())?;
+
+    let crs_mons = try_get_trusted_setup()?;
+
+    let mut assembly = FflonkAssembly::<Bn256, SynthesisModeGenerateSetup>::new();
+    let wrapper_circuit = make_fflonk_wrapper_circuit(None, vk.into_inner(), wrapper_type);
+
+    synthesize_fflonk_wrapper_circuit(wrapper_circuit, &mut assembly, stage)?;
+
+    catch_prover_failure(stage, || {
+        FflonkSetup::create_setup(&assembly, worker, &crs_mons)
+    })?
+    .map_err(prover_failure(stage))
+}
+
+fn compute_fflonk_wrapper_proof_inner(
+    proof: ZkSyncCompressionForWrapperProof,
+    vk: ZkSyncCompressionForWrapperVerificationKey,
+    snark_setup: ZkSyncFflonkSnarkWrapperSetup,
+    config: WrapperConfig,
+    worker: &BellmanWorker,
+) -> WrapResult<FflonkSnarkVerifierCircuitProof> {
+    let wrapper_type = config.get_wrapper_type();
+    let stage = WrapStage::Wrapper(wrapper_type);
+
+    let compression_for_wrapper_type = config.get_compression_for_wrapper_type();
+    check_circuit_type(compression_for_wrapper_type, proof.numeric_circuit_type())?;
+    check_circuit_type(compression_for_wrapper_type, vk.numeric_circuit_type())?;
+    check_circuit_type(wrapper_type, snark_setup.numeric_circuit_type())?;
+
+    let crs_mons = try_get_trusted_setup()?;
+    let snark_setup = snark_setup.into_inner();
+
+    let mut assembly = FflonkAssembly::<Bn256, SynthesisModeProve>::new();
+    let wrapper_circuit =
+        make_fflonk_wrapper_circuit(Some(proof.into_inner()), vk.into_inner(), wrapper_type);
+
+    synthesize_fflonk_wrapper_circuit(wrapper_circuit, &mut assembly, stage)?;
+
+    catch_prover_failure(stage, || {
+        create_fflonk_proof::<_, FflonkSnarkVerifierCircuit, _, _, _, RollingKeccakTranscript<Fr>>(
+            &assembly,
+            worker,
+            &snark_setup,
+            &crs_mons,
+            None,
+        )
+    })?
+    .map_err(prover_failure(stage))
+}
+
+fn synthesize_fflonk_wrapper_circuit<S: SynthesisMode + 'static>(
+    wrapper_circuit: FflonkSnarkVerifierCircuit,
+    assembly: &mut FflonkAssembly<Bn256, S>,
+    stage: WrapStage,
+) -> WrapResult<()> {
+    catch_prover_failure(stage, || {
+        wrapper_circuit
+            .synthesize(assembly)
+            .map(|_| assembly.finalize_to_size_log_2(FFLONK_L1_VERIFIER_DOMAIN_SIZE_LOG))
+    })?
+    .map_err(prover_failure(stage))?;
+    if !assembly.is_satisfied() {
+        return Err(WrapError::Prover(format!(
+            "{:?}: FFLONK wrapper circuit is not satisfied",
+            stage
+        )));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,152 @@
+use super::*;
+
+use crate::compute_setups::CircuitSetupData;
+use crate::prover_utils::{
+    prove_interblock_aggregation_circuit, verify_interblock_aggregation_proof,
+};
+use crate::witness::recursive_aggregation::create_interblock_aggregation_circuit;
+use circuit_definitions::circuit_definitions::recursion_layer::interblock::{
+    ZkSyncInterblockAggregationProof, ZkSyncInterblockAggregationVerificationKey,
+    INTERBLOCK_AGGREGATION_CAPACITY, INTERBLOCK_AGGREGATION_MASKING_VALUE,
+};
+use circuit_definitions::circuit_definitions::recursion_layer::ZkSyncRecursionLayerStorage;
+use circuit_definitions::recursion_layer_proof_config;
+
+/// Aggregates scheduler proofs of several batches into one proof.
+/// Setup data should be generated by `generate_interblock_aggregation_setup_data`.
+///
+/// Its public input is `interblock_aggregated_public_inputs` of the scheduler public inputs.
+/// The result should be wrapped with `wrap_interblock_aggregation_proof`.
+pub fn aggregate_scheduler_proofs(
+    proofs: Vec<ZkSyncRecursionLayerProof>,
+    scheduler_vk: ZkSyncRecursionLayerVerificationKey,
+    setup_data: &CircuitSetupData,
+    worker: &Worker,
+) -> WrapResult<(
+    ZkSyncInterblockAggregationProof,
+    ZkSyncInterblockAggregationVerificationKey,
+)> {
+    aggregate_scheduler_proofs_with_progress(proofs, scheduler_vk, setup_data, worker, &mut |_| {})
+}
+
+/// Same as `aggregate_scheduler_proofs`, the aggregation is reported to `progress`
+pub fn aggregate_scheduler_proofs_with_progress(
+    proofs: Vec<ZkSyncRecursionLayerProof>,
+    scheduler_vk: ZkSyncRecursionLayerVerificationKey,
+    setup_data: &CircuitSetupData,
+    worker: &Worker,
+    progress: &mut dyn FnMut(WrapProgress),
+) -> WrapResult<(
+    ZkSyncInterblockAggregationProof,
+    ZkSyncInterblockAggregationVerificationKey,
+)> {
+    if proofs.is_empty() || proofs.len() > INTERBLOCK_AGGREGATION_CAPACITY {
+        return Err(WrapError::InvalidConfig(format!(
+            "can aggregate from 1 to {} scheduler proofs, got {}",
+            INTERBLOCK_AGGREGATION_CAPACITY,
+            proofs.len()
+        )));
+    }
+    check_scheduler_circuit_type(scheduler_vk.numeric_circuit_type())?;
+
+    let mut scheduler_public_inputs = Vec::with_capacity(proofs.len());
+    for proof in proofs.iter() {
+        check_scheduler_circuit_type(proof.numeric_circuit_type())?;
+        let valid = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            verify_recursion_layer_proof_for_type::<NoPow>(
+                ZkSyncRecursionLayerStorageType::SchedulerCircuit,
+                &proof.clone().into_inner(),
+                &scheduler_vk.clone().into_inner(),
+            )
+        }))
+        .unwrap_or(false);
+        if !valid {
+            return Err(WrapError::InvalidProof);
+        }
+
+        let public_inputs: Vec<_> = proof
+            .clone()
+            .into_inner()
+            .public_inputs
+            .iter()
+            .map(|el| el.as_u64_reduced())
+            .collect();
+        scheduler_public_inputs.push(
+            public_inputs
+                .try_into()
+                .map_err(|_| WrapError::InvalidProof)?,
+        );
+    }
+    let expected_public_inputs =
+        circuit_sequencer_api::public_input::interblock_aggregated_public_inputs(
+            &scheduler_public_inputs,
+            INTERBLOCK_AGGREGATION_CAPACITY,
+            INTERBLOCK_AGGREGATION_MASKING_VALUE,
+        );
+
+    let stage = WrapStage::InterblockAggregation(proofs.len());
+    progress(WrapProgress::Started(stage));
+    let start = std::time::Instant::now();
+
+    let (setup_circuit, proof) = catch_prover_failure(stage, || {
+        let setup_circuit = create_interblock_aggregation_circuit(vec![], scheduler_vk.clone());
+        let circuit = create_interblock_aggregation_circuit(proofs, scheduler_vk);
+
+        let proof = prove_interblock_aggregation_circuit::<NoPow>(
+            circuit,
+            worker,
+            recursion_layer_proof_config(),
+            &setup_data.setup_base,
+            &setup_data.setup,
+            &setup_data.setup_tree,
+            &setup_data.vk,
+            &setup_data.vars_hint,
+            &setup_data.wits_hint,
+            &setup_data.finalization_hint,
+        );
+
+        (setup_circuit, proof)
+    })?;
+
+    let is_valid = catch_prover_failure(stage, || {
+        verify_interblock_aggregation_proof::<NoPow>(&setup_circuit, &proof, &setup_data.vk)
+    })?;
+    if !is_valid {
+        return Err(WrapError::Prover(format!(
+            "{:?}: aggregation proof is not valid",
+            stage
+        )));
+    }
+
+    let public_inputs: Vec<_> = proof
+        .public_inputs
+        .iter()
+        .map(|el| el.as_u64_reduced())
+        .collect();
+    if public_inputs != expected_public_inputs {
+        return Err(WrapError::Prover(format!(
+            "{:?}: aggregated public input doesn't match the out of circuit one",
+            stage
+        )));
+    }
+
+    progress(WrapProgress::Finished(stage, start.elapsed()));
+
+    Ok((proof, setup_data.vk.clone()))
+}
+
+/// Computes wrapper proof and vk from the interblock aggregation proof and vk.
+///
+/// Aggregation circuit has the same geometry as the scheduler, so the compression circuits
+/// are the scheduler ones, they are just set up with the aggregation vk.
+pub fn wrap_interblock_aggregation_proof(
+    proof: ZkSyncInterblockAggregationProof,
+    vk: ZkSyncInterblockAggregationVerificationKey,
+    config: WrapperConfig,
+) -> WrapResult<(ZkSyncSnarkWrapperProof, ZkSyncSnarkWrapperVK)> {
+    wrap_proof(
+        ZkSyncRecursionLayerStorage::SchedulerCircuit(proof),
+        ZkSyncRecursionLayerStorage::SchedulerCircuit(vk),
+        config,
+    )
+}
@@ -0,0 +1,413 @@
+use super::*;
+
+use crate::ethereum_types::U256;
+use crate::franklin_crypto::bellman::pairing::bn256::{Fq, Fq2, G1Affine, G2Affine};
+use crate::franklin_crypto::bellman::pairing::CurveAffine;
+use crate::snark_wrapper::franklin_crypto::bellman::plonk::domains::Domain;
+use crate::zk_evm::sha3::{Digest, Keccak256};
+
+/// Number of words in the proof array accepted by the L1 verifier
+pub const L1_VERIFIER_PROOF_LENGTH: usize = 44;
+/// Number of words in the VK memory layout of the L1 verifier, including the recursive flag
+pub const L1_VERIFIER_VK_LENGTH: usize = 41;
+
+const NUM_GATE_SETUP_COMMITMENTS: usize = 8;
+const NUM_GATE_SELECTORS_COMMITMENTS: usize = 2;
+const NUM_PERMUTATION_COMMITMENTS: usize = 4;
+const NUM_LOOKUP_TABLES_COMMITMENTS: usize = 4;
+
+/// Contract that the generated verifier inherits from
+const BASE_CONTRACT_NAME: &str = "Verifier";
+
+const VERIFY_FUNCTION_SIGNATURE: &str = "verify(uint256[],uint256[],uint256[])";
+
+fn fe_to_u256<F: PrimeField>(el: &F) -> U256 {
+    let mut buffer = [0u8; 32];
+    el.into_repr().write_be(&mut buffer[..]).unwrap();
+
+    U256::from_big_endian(&buffer)
+}
+
+fn g1_to_u256s(point: &G1Affine) -> [U256; 2] {
+    if point.is_zero() {
+        return [U256::zero(); 2];
+    }
+    let (x, y) = point.into_xy_unchecked();
+
+    [fe_to_u256::<Fq>(&x), fe_to_u256::<Fq>(&y)]
+}
+
+// EVM precompiles expect imaginary part first
+fn g2_to_u256s(point: &G2Affine) -> [U256; 4] {
+    let (x, y): (Fq2, Fq2) = point.into_xy_unchecked();
+
+    [
+        fe_to_u256(&x.c1),
+        fe_to_u256(&x.c0),
+        fe_to_u256(&y.c1),
+        fe_to_u256(&y.c0),
+    ]
+}
+
+fn format_u256(value: &U256) -> String {
+    format!("0x{:064x}", value)
+}
+
+/// One named point of the verification key, as laid out in the memory of the L1 verifier
+struct VkPoint {
+    slot_name: String,
+    value: [U256; 2],
+}
+
+fn push_points(dst: &mut Vec<VkPoint>, prefix: &str, points: &[G1Affine]) {
+    for (idx, point) in points.iter().enumerate() {
+        dst.push(VkPoint {
+            slot_name: format!("{prefix}_{idx}"),
+            value: g1_to_u256s(point),
+        });
+    }
+}
+
+fn vk_points<C: Circuit<Bn256>>(vk: &SnarkVK<Bn256, C>) -> Vec<VkPoint> {
+    assert_eq!(vk.gate_setup_commitments.len(), NUM_GATE_SETUP_COMMITMENTS);
+    assert_eq!(
+        vk.gate_selectors_commitments.len(),
+        NUM_GATE_SELECTORS_COMMITMENTS
+    );
+    assert_eq!(
+        vk.permutation_commitments.len(),
+        NUM_PERMUTATION_COMMITMENTS
+    );
+    assert_eq!(
+        vk.lookup_tables_commitments.len(),
+        NUM_LOOKUP_TABLES_COMMITMENTS
+    );
+
+    let mut result = vec![];
+    push_points(&mut result, "VK_GATE_SETUP", &vk.gate_setup_commitments);
+    push_points(
+        &mut result,
+        "VK_GATE_SELECTORS",
+        &vk.gate_selectors_commitments,
+    );
+    push_points(&mut result, "VK_PERMUTATION", &vk.permutation_commitments);
+    result.push(VkPoint {
+        slot_name: "VK_LOOKUP_SELECTOR".to_owned(),
+        value: g1_to_u256s(
+            vk.lookup_selector_commitment
+                .as_ref()
+                .expect("wrapper uses lookups"),
+        ),
+    });
+    push_points(
+        &mut result,
+        "VK_LOOKUP_TABLE",
+        &vk.lookup_tables_commitments,
+    );
+    result.push(VkPoint {
+        slot_name: "VK_LOOKUP_TABLE_TYPE".to_owned(),
+        value: g1_to_u256s(
+            vk.lookup_table_type_commitment
+                .as_ref()
+                .expect("wrapper uses lookups"),
+        ),
+    });
+
+    result
+}
+
+/// Words of the VK in the order they are placed in the memory of the L1 verifier
+pub fn l1_verifier_vk_words<C: Circuit<Bn256>>(vk: &SnarkVK<Bn256, C>) -> Vec<U256> {
+    let mut result: Vec<U256> = vk_points(vk)
+        .into_iter()
+        .flat_map(|point| point.value)
+        .collect();
+    // recursive flag, wrapper proofs have no recursive part
+    result.push(U256::zero());
+    assert_eq!(result.len(), L1_VERIFIER_VK_LENGTH);
+
+    result
+}
+
+/// Same value as `verificationKeyHash()` of the L1 verifier
+pub fn l1_verifier_vk_hash<C: Circuit<Bn256>>(vk: &SnarkVK<Bn256, C>) -> [u8; 32] {
+    let mut hasher = Keccak256::new();
+    for word in l1_verifier_vk_words(vk) {
+        let mut buffer = [0u8; 32];
+        word.to_big_endian(&mut buffer);
+        hasher.update(buffer);
+    }
+
+    let mut result = [0u8; 32];
+    result.copy_from_slice(&hasher.finalize());
+
+    result
+}
+
+/// Generates the `_loadVerificationKey` function of the L1 verifier
+pub fn generate_l1_verifier_vk_constants<C: Circuit<Bn256>>(vk: &SnarkVK<Bn256, C>) -> String {
+    let mut result = String::new();
+    result.push_str("    function _loadVerificationKey() internal pure override {\n");
+    result.push_str("        assembly {\n");
+
+    let mut previous_group = String::new();
+    for point in vk_points(vk) {
+        let group = point
+            .slot_name
+            .trim_end_matches(|c: char| c.is_ascii_digit());
+        if group != previous_group {
+            let comment = match group {
+                "VK_GATE_SETUP_" => "gate setup commitments",
+                "VK_GATE_SELECTORS_" => "gate selectors commitments",
+                "VK_PERMUTATION_" => "permutation commitments",
+                "VK_LOOKUP_SELECTOR" => "lookup selector commitment",
+                "VK_LOOKUP_TABLE_" => "lookup tables commitments",
+                "VK_LOOKUP_TABLE_TYPE" => "table type commitment",
+                _ => unreachable!(),
+            };
+            if !previous_group.is_empty() {
+                result.push('\n');
+            }
+            result.push_str(&format!("            // {comment}\n"));
+            previous_group = group.to_owned();
+        }
+        for (coordinate, value) in ["X", "Y"].iter().zip(point.value.iter()) {
+            result.push_str(&format!(
+                "            mstore({}_{}_SLOT, {})\n",
+                point.slot_name,
+                coordinate,
+                format_u256(value)
+            ));
+        }
+    }
+
+    result.push_str("\n            // flag for using recursive part\n");
+    result.push_str("            mstore(VK_RECURSIVE_FLAG_SLOT, 0)\n");
+    result.push_str("        }\n");
+    result.push_str("    }\n");
+
+    result
+}
+
+/// Generates a verifier contract that overrides the VK of the L1 `Verifier` contract.
+/// Domain size, omega, non-residues and G2 elements are constants of the base contract, so they
+/// are only emitted as a comment to be checked against it.
+pub fn generate_l1_verifier_contract<C: Circuit<Bn256>>(
+    vk: &SnarkVK<Bn256, C>,
+    contract_name: &str,
+) -> WrapResult<String> {
+    if contract_name == BASE_CONTRACT_NAME {
+        return Err(WrapError::InvalidConfig(format!(
+            "verifier contract can't be named as its base contract {}",
+            BASE_CONTRACT_NAME
+        )));
+    }
+
+    let domain_size = vk.n + 1;
+    assert!(domain_size.is_power_of_two());
+    let domain = Domain::<Fr>::new_for_size(domain_size as u64).unwrap();
+
+    let mut result = String::new();
+    result.push_str("// SPDX-License-Identifier: MIT\n\n");
+    result.push_str("pragma solidity 0.8.24;\n\n");
+    result.push_str(&format!(
+        "import {{{BASE_CONTRACT_NAME}}} from \"./{BASE_CONTRACT_NAME}.sol\";\n\n"
+    ));
+    result.push_str("/* This file is generated, do not edit by hand.\n");
+    result.push_str(&format!(
+        "   Verification key hash: 0x{}\n",
+        hex::encode(l1_verifier_vk_hash(vk))
+    ));
+    result.push_str("   Expected constants of the base contract:\n");
+    result.push_str(&format!(
+        "   DOMAIN_SIZE = 0x{:x}\n   OMEGA = {}\n",
+        domain_size,
+        format_u256(&fe_to_u256(&domain.generator))
+    ));
+    for (idx, non_residue) in vk.non_residues.iter().enumerate() {
+        result.push_str(&format!(
+            "   NON_RESIDUES_{} = {}\n",
+            idx,
+            format_u256(&fe_to_u256(non_residue))
+        ));
+    }
+    for (idx, element) in vk.g2_elements.iter().enumerate() {
+        for (name, value) in ["X1", "X2", "Y1", "Y2"]
+            .iter()
+            .zip(g2_to_u256s(element).iter())
+        {
+            result.push_str(&format!(
+                "   G2_ELEMENTS_{}_{} = {}\n",
+                idx,
+                name,
+                format_u256(value)
+            ));
+        }
+    }
+    result.push_str("*/\n");
+    result.push_str(&format!(
+        "contract {contract_name} is {BASE_CONTRACT_NAME} {{\n"
+    ));
+    result.push_str(&generate_l1_verifier_vk_constants(vk));
+    result.push_str("}\n");
+
+    Ok(result)
+}
+
+/// Computes the wrapper VK for the given config and generates the verifier contract for it
+pub fn generate_l1_verifier_contract_from_scheduler_vk(
+    scheduler_vk: ZkSyncRecursionLayerVerificationKey,
+    config: WrapperConfig,
+    contract_name: &str,
+) -> WrapResult<String> {
+    let (_, wrapper_vk) = get_wrapper_setup_and_vk_from_scheduler_vk(scheduler_vk, config)?;
+
+    generate_l1_verifier_contract(&wrapper_vk.into_inner(), contract_name)
+}
+
+/// Public inputs and proof in the form accepted by the `verify` function of the L1 verifier
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct L1VerifierCalldata {
+    pub public_inputs: Vec<U256>,
+    pub serialized_proof: Vec<U256>,
+    pub recursive_aggregation_input: Vec<U256>,
+}
+
+impl L1VerifierCalldata {
+    pub fn from_proof<C: Circuit<Bn256>>(proof: &SnarkProof<Bn256, C>) -> Self {
+        let public_inputs = proof.inputs.iter().map(fe_to_u256).collect();
+
+        let mut serialized_proof = vec![];
+        for commitment in proof.state_polys_commitments.iter() {
+            serialized_proof.extend(g1_to_u256s(commitment));
+        }
+        serialized_proof.extend(g1_to_u256s(
+            &proof.copy_permutation_grand_product_commitment,
+        ));
+        serialized_proof.extend(g1_to_u256s(
+            proof.lookup_s_poly_commitment.as_ref().unwrap(),
+        ));
+        serialized_proof.extend(g1_to_u256s(
+            proof.lookup_grand_product_commitment.as_ref().unwrap(),
+        ));
+        for commitment in proof.quotient_poly_parts_commitments.iter() {
+            serialized_proof.extend(g1_to_u256s(commitment));
+        }
+
+        for opening in proof.state_polys_openings_at_z.iter() {
+            serialized_proof.push(fe_to_u256(opening));
+        }
+        for (_, _, opening) in proof.state_polys_openings_at_dilations.iter() {
+            serialized_proof.push(fe_to_u256(opening));
+        }
+        assert!(proof.gate_setup_openings_at_z.is_empty());
+        for (_, opening) in proof.gate_selectors_openings_at_z.iter() {
+            serialized_proof.push(fe_to_u256(opening));
+        }
+        for opening in proof.copy_permutation_polys_openings_at_z.iter() {
+            serialized_proof.push(fe_to_u256(opening));
+        }
+        serialized_proof.push(fe_to_u256(
+            &proof.copy_permutation_grand_product_opening_at_z_omega,
+        ));
+        for opening in [
+            &proof.lookup_s_poly_opening_at_z_omega,
+            &proof.lookup_grand_product_opening_at_z_omega,
+            &proof.lookup_t_poly_opening_at_z,
+            &proof.lookup_t_poly_opening_at_z_omega,
+            &proof.lookup_selector_poly_opening_at_z,
+            &proof.lookup_table_type_poly_opening_at_z,
+        ] {
+            serialized_proof.push(fe_to_u256(opening.as_ref().unwrap()));
+        }
+        serialized_proof.push(fe_to_u256(&proof.quotient_poly_opening_at_z));
+        serialized_proof.push(fe_to_u256(&proof.linearization_poly_opening_at_z));
+        serialized_proof.extend(g1_to_u256s(&proof.opening_proof_at_z));
+        serialized_proof.extend(g1_to_u256s(&proof.opening_proof_at_z_omega));
+
+        assert_eq!(serialized_proof.len(), L1_VERIFIER_PROOF_LENGTH);
+
+        Self {
+            public_inputs,
+            serialized_proof,
+            recursive_aggregation_input: vec![],
+        }
+    }
+
+    /// ABI encoded call of `verify(uint256[],uint256[],uint256[])`
+    pub fn encode(&self) -> Vec<u8> {
+        let mut result = Keccak256::digest(VERIFY_FUNCTION_SIGNATURE.as_bytes())[..4].to_vec();
+
+        let arrays = [
+            &self.public_inputs,
+            &self.serialized_proof,
+            &self.recursive_aggregation_input,
+        ];
+        let mut head = vec![];
+        let mut tail = vec![];
+        for array in arrays {
+            head.push(U256::from(arrays.len() * 32 + tail.len() * 32));
+            tail.push(U256::from(array.len()));
+            tail.extend(array.iter().copied());
+        }
+        for word in head.into_iter().chain(tail) {
+            let mut buffer = [0u8; 32];
+            word.to_big_endian(&mut buffer);
+            result.extend(buffer);
+        }
+
+        result
+    }
+
+    /// Inverse of `encode`
+    pub fn decode(calldata: &[u8]) -> Option<Self> {
+        if calldata.len() < 4
+            || calldata[..4] != Keccak256::digest(VERIFY_FUNCTION_SIGNATURE.as_bytes())[..4]
+        {
+            return None;
+        }
+        let data = &calldata[4..];
+        let read_word = |offset: usize| -> Option<U256> {
+            data.get(offset..offset + 32).map(U256::from_big_endian)
+        };
+        let read_array = |head_idx: usize| -> Option<Vec<U256>> {
+            let offset = usize::try_from(read_word(head_idx * 32)?).ok()?;
+            let len = usize::try_from(read_word(offset)?).ok()?;
+            (0..len)
+                .map(|idx| read_word(offset + 32 * (idx + 1)))
+                .collect()
+        };
+
+        Some(Self {
+            public_inputs: read_array(0)?,
+            serialized_proof: read_array(1)?,
+            recursive_aggregation_input: read_array(2)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_verify_calldata_layout() {
+        let calldata = L1VerifierCalldata {
+            public_inputs: vec![U256::from(7)],
+            serialized_proof: (0..L1_VERIFIER_PROOF_LENGTH).map(U256::from).collect(),
+            recursive_aggregation_input: vec![],
+        };
+        let encoded = calldata.encode();
+
+        // selector of `verify(uint256[],uint256[],uint256[])`
+        assert_eq!(hex::encode(&encoded[..4]), "87d9d023");
+        // selector, 3 offsets, 3 lengths and the data itself
+        assert_eq!(
+            encoded.len(),
+            4 + 32 * (3 + 3 + 1 + L1_VERIFIER_PROOF_LENGTH)
+        );
+        assert_eq!(U256::from_big_endian(&encoded[4..36]), U256::from(0x60));
+        assert_eq!(U256::from_big_endian(&encoded[36..68]), U256::from(0xa0));
+        assert_eq!(L1VerifierCalldata::decode(&encoded), Some(calldata));
+    }
+}
@@ -21,12 +21,15 @@ use crate::franklin_crypto::bellman::pairing::bn256::{Bn256, Fr};
 use crate::franklin_crypto::bellman::{Field, PrimeField, PrimeFieldRepr};
 use circuit_definitions::circuit_definitions::aux_layer::*;
 use crate::boojum::worker::Worker;
+use circuit_definitions::fflonk::{FflonkAssembly, FflonkSetup, FflonkVerificationKey};
+use crate::snark_wrapper::franklin_crypto::bellman::plonk::better_better_cs::cs::{SynthesisMode, SynthesisModeGenerateSetup, SynthesisModeProve};
 
 pub type TreeHasherForWrapper = CircuitPoseidon2Sponge<Bn256, 2, 3, 3, true>;
 pub type TranscriptForWrapper = CircuitPoseidon2Transcript<Bn256, 2, 3, 3, true>;
 
 pub const DEFAULT_WRAPPER_CONFIG: WrapperConfig = WrapperConfig {
     compression_layers: 1,
+    mode: WrapperMode::Plonk,
 };
 
 use crate::data_source::in_memory_data_source::InMemoryDataSource;
@@ -46,11 +49,21 @@ mod proof_compression_test;
 
 mod compression;
 mod compression_for_wrapper;
+mod error;
+mod fflonk_wrapper;
+mod interblock_aggregation;
+mod l1_verifier;
+mod planner;
 mod utils;
 mod wrapper;
 
 pub use compression::*;
 pub use compression_for_wrapper::*;
+pub use error::*;
+pub use fflonk_wrapper::*;
+pub use interblock_aggregation::*;
+pub use l1_verifier::*;
+pub use planner::*;
 pub use utils::*;
 pub use wrapper::*;
 
@@ -61,22 +74,49 @@ pub use wrapper::*;
 ///
 /// Example: Scheduler -> CompressionMode1 -> CompressionMode2 ->
 /// -> CompressionMode3ForWrapper -> Wrapper
+///
+/// Wrapper itself can be either a PLONK (default) or an FFLONK proof, see `WrapperMode`.
 #[derive(Debug, Clone, Copy)]
 pub struct WrapperConfig {
     compression_layers: u8,
+    mode: WrapperMode,
+}
+
+/// Proof system used for the final Bn256 wrapper.
+/// PLONK wrapper uses width 4 gates with lookups, FFLONK one uses a plain width 3 circuit
+/// that is cheaper to verify on L1.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WrapperMode {
+    #[default]
+    Plonk,
+    Fflonk,
 }
 
 impl WrapperConfig {
     // For now we only support 1-5 compression layers
     pub const MAX_COMPRESSION_LAYERS: u8 = 5;
 
-    pub fn new(compression_layers: u8) -> Self {
-        assert!(
-            compression_layers > 0 && compression_layers <= Self::MAX_COMPRESSION_LAYERS,
-            "compression should be between 1 and 5"
-        );
+    pub fn new(compression_layers: u8) -> WrapResult<Self> {
+        if compression_layers == 0 || compression_layers > Self::MAX_COMPRESSION_LAYERS {
+            return Err(WrapError::InvalidConfig(format!(
+                "compression should be between 1 and {}, got {}",
+                Self::MAX_COMPRESSION_LAYERS,
+                compression_layers
+            )));
+        }
+
+        Ok(Self {
+            compression_layers,
+            mode: WrapperMode::Plonk,
+        })
+    }
+
+    pub fn with_mode(self, mode: WrapperMode) -> Self {
+        Self { mode, ..self }
+    }
 
-        Self { compression_layers }
+    pub fn get_wrapper_mode(&self) -> WrapperMode {
+        self.mode
     }
 
     pub fn get_compression_types(&self) -> Vec<u8> {
@@ -98,87 +138,177 @@ pub fn wrap_proof(
     proof: ZkSyncRecursionLayerProof,
     vk: ZkSyncRecursionLayerVerificationKey,
     config: WrapperConfig,
-) -> (ZkSyncSnarkWrapperProof, ZkSyncSnarkWrapperVK) {
-    // Check trusted setup exitance early, so that we fail quickly if it is not present.
-    check_trusted_setup_file_existace();
-    let worker = Worker::new();
+) -> WrapResult<(ZkSyncSnarkWrapperProof, ZkSyncSnarkWrapperVK)> {
+    wrap_proof_with_progress(proof, vk, config, &mut |_| {})
+}
+
+/// Same as `wrap_proof`, every step of the pipeline is reported to `progress`
+pub fn wrap_proof_with_progress(
+    proof: ZkSyncRecursionLayerProof,
+    vk: ZkSyncRecursionLayerVerificationKey,
+    config: WrapperConfig,
+    progress: &mut dyn FnMut(WrapProgress),
+) -> WrapResult<(ZkSyncSnarkWrapperProof, ZkSyncSnarkWrapperVK)> {
+    if config.get_wrapper_mode() != WrapperMode::Plonk {
+        return Err(WrapError::InvalidConfig(
+            "use wrap_proof_fflonk for FFLONK wrapper".to_string(),
+        ));
+    }
     let bellman_worker = BellmanWorker::new();
+    let mut source = compress_scheduler_proof(proof, vk, config, progress)?;
+
+    // 3. Wrapper
+    compute_wrapper_proof_and_vk(&mut source, config, &bellman_worker, progress)?;
+
+    // Get and return wrapper proof and vk
+    let wrapper_type = config.get_wrapper_type();
+    Ok((
+        source
+            .get_wrapper_proof(wrapper_type)
+            .map_err(source_failure("wrapper proof"))?,
+        source
+            .get_wrapper_vk(wrapper_type)
+            .map_err(source_failure("wrapper vk"))?,
+    ))
+}
+
+/// Same as `wrap_proof`, but the final wrapper is an FFLONK proof
+pub fn wrap_proof_fflonk(
+    proof: ZkSyncRecursionLayerProof,
+    vk: ZkSyncRecursionLayerVerificationKey,
+    config: WrapperConfig,
+) -> WrapResult<(ZkSyncFflonkSnarkWrapperProof, ZkSyncFflonkSnarkWrapperVK)> {
+    wrap_proof_fflonk_with_progress(proof, vk, config, &mut |_| {})
+}
+
+/// Same as `wrap_proof_fflonk`, every step of the pipeline is reported to `progress`
+pub fn wrap_proof_fflonk_with_progress(
+    proof: ZkSyncRecursionLayerProof,
+    vk: ZkSyncRecursionLayerVerificationKey,
+    config: WrapperConfig,
+    progress: &mut dyn FnMut(WrapProgress),
+) -> WrapResult<(ZkSyncFflonkSnarkWrapperProof, ZkSyncFflonkSnarkWrapperVK)> {
+    if config.get_wrapper_mode() != WrapperMode::Fflonk {
+        return Err(WrapError::InvalidConfig(
+            "use wrap_proof for PLONK wrapper".to_string(),
+        ));
+    }
+    let bellman_worker = BellmanWorker::new();
+    let mut source = compress_scheduler_proof(proof, vk, config, progress)?;
+
+    // 3. Wrapper
+    compute_fflonk_wrapper_proof_and_vk(&mut source, config, &bellman_worker, progress)?;
+
+    let wrapper_type = config.get_wrapper_type();
+    Ok((
+        source
+            .get_fflonk_wrapper_proof(wrapper_type)
+            .map_err(source_failure("FFLONK wrapper proof"))?,
+        source
+            .get_fflonk_wrapper_vk(wrapper_type)
+            .map_err(source_failure("FFLONK wrapper vk"))?,
+    ))
+}
 
+/// Runs all compression layers for the scheduler proof, the result is ready for wrapping
+fn compress_scheduler_proof(
+    proof: ZkSyncRecursionLayerProof,
+    vk: ZkSyncRecursionLayerVerificationKey,
+    config: WrapperConfig,
+    progress: &mut dyn FnMut(WrapProgress),
+) -> WrapResult<InMemoryDataSource> {
     // Check circuit type correctness.
-    // We only support wrapping of the scheduler circuit.
-    assert_eq!(
-        vk.numeric_circuit_type(),
-        ZkSyncRecursionLayerStorageType::SchedulerCircuit as u8
-    );
-    assert_eq!(
-        proof.numeric_circuit_type(),
-        ZkSyncRecursionLayerStorageType::SchedulerCircuit as u8
-    );
-
-    let valid = verify_recursion_layer_proof_for_type::<NoPow>(
-        ZkSyncRecursionLayerStorageType::SchedulerCircuit,
-        &proof.clone().into_inner(),
-        &vk.clone().into_inner(),
-    );
-    assert!(valid, "Provided scheduler proof is not valid.");
+    check_scheduler_circuit_type(vk.numeric_circuit_type())?;
+    check_scheduler_circuit_type(proof.numeric_circuit_type())?;
+
+    let valid = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        verify_recursion_layer_proof_for_type::<NoPow>(
+            ZkSyncRecursionLayerStorageType::SchedulerCircuit,
+            &proof.clone().into_inner(),
+            &vk.clone().into_inner(),
+        )
+    }))
+    .unwrap_or(false);
+    if !valid {
+        return Err(WrapError::InvalidProof);
+    }
+
+    // Check trusted setup exitance early, so that we fail quickly if it is not present.
+    check_trusted_setup_file()?;
+    let worker = Worker::new();
 
     // Initialize RAM storage and upload scheduler proof and vk
     let mut source = InMemoryDataSource::new();
     source
         .set_scheduler_proof(proof)
-        .expect("Failed to set scheduler proof");
+        .map_err(source_failure("scheduler proof"))?;
     source
         .set_recursion_layer_vk(vk)
-        .expect("Failed to set scheduler vk");
+        .map_err(source_failure("scheduler vk"))?;
 
     // 1. All but one layers of compression with Goldilocks Poseidon2 hash
-    println!("Computing a sequence of compressing circuits");
-    compute_compression_circuits(&mut source, config, &worker);
-    println!("Done computing a sequence of compressing circuits");
+    compute_compression_circuits(&mut source, config, &worker, progress)?;
     // 2. Final compression with Bn256 Poseidon2 hash
-    println!("Computing a Boojum circuit using Bn256 Poseidon2 hash");
-    compute_compression_for_wrapper_circuit(&mut source, config, &worker);
-    println!("Done computing a Boojum circuit using Bn256 Poseidon2 hash");
-    // 3. Wrapper
-    println!("Computing a Bellman circuit over Bn256");
-    compute_wrapper_proof_and_vk(&mut source, config, &bellman_worker);
-    println!("Done computing a Bellman circuit over Bn256");
+    compute_compression_for_wrapper_circuit(&mut source, config, &worker, progress)?;
 
-    // Get and return wrapper proof and vk
-    let wrapper_type = config.get_wrapper_type();
-    (
-        source.get_wrapper_proof(wrapper_type).unwrap(),
-        source.get_wrapper_vk(wrapper_type).unwrap(),
+    Ok(source)
+}
+
+/// We only support wrapping of the scheduler circuit.
+fn check_scheduler_circuit_type(actual: u8) -> WrapResult<()> {
+    check_circuit_type(
+        ZkSyncRecursionLayerStorageType::SchedulerCircuit as u8,
+        actual,
     )
 }
 
-/// Computes wrapper vk from scheduler vk
+/// Computes vks of all compression layers from scheduler vk and returns the last one
 /// We store all vks in the RAM
-pub fn get_wrapper_setup_and_vk_from_scheduler_vk(
+fn compute_compression_for_wrapper_vk_from_scheduler_vk(
     vk: ZkSyncRecursionLayerVerificationKey,
     config: WrapperConfig,
-) -> (ZkSyncSnarkWrapperSetup, ZkSyncSnarkWrapperVK) {
+) -> WrapResult<ZkSyncCompressionForWrapperVerificationKey> {
+    // Check circuit type correctness
+    check_scheduler_circuit_type(vk.numeric_circuit_type())?;
     // Check trusted setup file for later
-    check_trusted_setup_file_existace();
+    check_trusted_setup_file()?;
     let worker = Worker::new();
 
-    // Check circuit type correctness
-    assert_eq!(
-        vk.numeric_circuit_type(),
-        ZkSyncRecursionLayerStorageType::SchedulerCircuit as u8
-    );
-
-    // Initialize RAM storage and upload scheduler proof and vk
+    // Initialize RAM storage and upload scheduler vk
     let mut source = InMemoryDataSource::new();
-    source.set_recursion_layer_vk(vk).unwrap();
+    source
+        .set_recursion_layer_vk(vk)
+        .map_err(source_failure("scheduler vk"))?;
 
     // 1. All but one layers of compression with Goldilocks Poseidon2 hash
-    compute_compression_vks_and_write(&mut source, config, &worker);
+    compute_compression_vks_and_write(&mut source, config, &worker)?;
     // 2. Final compression with Bn256 Poseidon2 hash
-    compute_compression_for_wrapper_vk_and_write(config, &mut source, &worker);
+    compute_compression_for_wrapper_vk_and_write(config, &mut source, &worker)?;
+
+    source
+        .get_compression_for_wrapper_vk(config.get_compression_for_wrapper_type())
+        .map_err(source_failure("compression for wrapper vk"))
+}
+
+/// Computes wrapper vk from scheduler vk
+/// We store all vks in the RAM
+pub fn get_wrapper_setup_and_vk_from_scheduler_vk(
+    vk: ZkSyncRecursionLayerVerificationKey,
+    config: WrapperConfig,
+) -> WrapResult<(ZkSyncSnarkWrapperSetup, ZkSyncSnarkWrapperVK)> {
+    let wrapper_vk = compute_compression_for_wrapper_vk_from_scheduler_vk(vk, config)?;
 
     // 3. Wrapper
-    let wrapper_type = config.get_wrapper_type();
-    let wrapper_vk = source.get_compression_for_wrapper_vk(wrapper_type).unwrap();
     get_wrapper_setup_and_vk_from_compression_vk(wrapper_vk, config)
 }
+
+/// Computes FFLONK wrapper setup and vk from scheduler vk
+/// We store all vks in the RAM
+pub fn get_fflonk_wrapper_setup_and_vk_from_scheduler_vk(
+    vk: ZkSyncRecursionLayerVerificationKey,
+    config: WrapperConfig,
+) -> WrapResult<(ZkSyncFflonkSnarkWrapperSetup, ZkSyncFflonkSnarkWrapperVK)> {
+    let wrapper_vk = compute_compression_for_wrapper_vk_from_scheduler_vk(vk, config)?;
+
+    get_fflonk_wrapper_setup_and_vk_from_compression_vk(wrapper_vk, config)
+}
@@ -0,0 +1,341 @@
+use super::*;
+
+use circuit_definitions::boojum::cs::implementations::prover::ProofConfig;
+use circuit_definitions::boojum::cs::CSGeometry;
+use circuit_definitions::circuit_definitions::aux_layer::compression::ProofCompressionFunction;
+use circuit_definitions::circuit_definitions::aux_layer::compression_modes::*;
+
+// Everything below is an analytic model of the boojum prover, it's only precise enough
+// to compare the chains with each other, no circuits are synthesized.
+
+/// Size of the Merkle tree node for all the compression hashers
+/// (4 Goldilocks elements, Bn256 Fr or Keccak256 output)
+const DIGEST_SIZE_BYTES: usize = 32;
+const FIELD_ELEMENT_SIZE_BYTES: usize = 8;
+/// Size of the GoldilocksExt2, that bounds the soundness of all the FRI based steps
+const EXTENSION_FIELD_BITS: usize = 128;
+/// Folding factor that boojum uses if the folding schedule is not set explicitly
+const DEFAULT_FRI_FOLDING_LOG2: usize = 3;
+
+/// Parameters of one compression step that define its security, cost and proof size.
+/// Use `for_circuit_type` to get the ones of the supported modes, and change `proof_config`
+/// to evaluate a custom chain.
+#[derive(Debug, Clone)]
+pub struct CompressionStepParameters {
+    pub circuit_type: u8,
+    /// Last step of the chain uses Bn256 hasher, so it can be verified by the wrapper
+    pub for_wrapper: bool,
+    pub proof_config: ProofConfig,
+    pub trace_length: usize,
+    pub geometry: CSGeometry,
+}
+
+fn parameters_for_mode<CF: ProofCompressionFunction>(
+    circuit_type: u8,
+    for_wrapper: bool,
+) -> CompressionStepParameters {
+    CompressionStepParameters {
+        circuit_type,
+        for_wrapper,
+        proof_config: CF::proof_config_for_compression_step(),
+        trace_length: CF::size_hint_for_compression_step().0,
+        geometry: CF::geometry_for_compression_step(),
+    }
+}
+
+impl CompressionStepParameters {
+    pub fn for_circuit_type(circuit_type: u8, for_wrapper: bool) -> WrapResult<Self> {
+        let parameters = match (circuit_type, for_wrapper) {
+            (1, false) => parameters_for_mode::<CompressionMode1>(circuit_type, for_wrapper),
+            (2, false) => parameters_for_mode::<CompressionMode2>(circuit_type, for_wrapper),
+            (3, false) => parameters_for_mode::<CompressionMode3>(circuit_type, for_wrapper),
+            (4, false) => parameters_for_mode::<CompressionMode4>(circuit_type, for_wrapper),
+            (5, false) => parameters_for_mode::<CompressionMode5>(circuit_type, for_wrapper),
+            (1, true) => {
+                parameters_for_mode::<CompressionMode1ForWrapper>(circuit_type, for_wrapper)
+            }
+            (2, true) => {
+                parameters_for_mode::<CompressionMode2ForWrapper>(circuit_type, for_wrapper)
+            }
+            (3, true) => {
+                parameters_for_mode::<CompressionMode3ForWrapper>(circuit_type, for_wrapper)
+            }
+            (4, true) => {
+                parameters_for_mode::<CompressionMode4ForWrapper>(circuit_type, for_wrapper)
+            }
+            (5, true) => {
+                parameters_for_mode::<CompressionMode5ForWrapper>(circuit_type, for_wrapper)
+            }
+            _ => {
+                return Err(WrapError::InvalidConfig(format!(
+                    "unknown compression circuit type {}",
+                    circuit_type
+                )))
+            }
+        };
+
+        Ok(parameters)
+    }
+
+    fn validate(&self) -> WrapResult<()> {
+        let config = &self.proof_config;
+        if !config.fri_lde_factor.is_power_of_two() || config.fri_lde_factor < 2 {
+            return Err(WrapError::InvalidConfig(format!(
+                "FRI LDE factor of compression step {} should be a power of two larger than 1, got {}",
+                self.circuit_type, config.fri_lde_factor
+            )));
+        }
+        if !config.merkle_tree_cap_size.is_power_of_two() {
+            return Err(WrapError::InvalidConfig(format!(
+                "cap size of compression step {} should be a power of two, got {}",
+                self.circuit_type, config.merkle_tree_cap_size
+            )));
+        }
+        if self.geometry.max_allowed_constraint_degree < 2 {
+            return Err(WrapError::InvalidConfig(format!(
+                "constraint degree of compression step {} should be at least 2, got {}",
+                self.circuit_type, self.geometry.max_allowed_constraint_degree
+            )));
+        }
+        if !self.trace_length.is_power_of_two() {
+            return Err(WrapError::InvalidConfig(format!(
+                "trace length of compression step {} should be a power of two, got {}",
+                self.circuit_type, self.trace_length
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+/// Steps of the chain used by `wrap_proof` for the given config:
+/// regular compression modes followed by the compression for wrapper
+pub fn compression_chain_for_config(config: WrapperConfig) -> Vec<CompressionStepParameters> {
+    config
+        .get_compression_types()
+        .into_iter()
+        .map(|circuit_type| (circuit_type, false))
+        .chain(std::iter::once((
+            config.get_compression_for_wrapper_type(),
+            true,
+        )))
+        .map(|(circuit_type, for_wrapper)| {
+            CompressionStepParameters::for_circuit_type(circuit_type, for_wrapper)
+                .expect("config only contains supported circuit types")
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone)]
+pub struct CompressionStepEstimate {
+    pub parameters: CompressionStepParameters,
+    pub num_queries: usize,
+    /// Conjectured FRI security, including PoW bits
+    pub security_bits: usize,
+    /// Number of cells in the LDE of all the oracles, prover time is roughly proportional to it
+    pub prover_work: u64,
+    pub proof_size_bytes: usize,
+    /// Verification key size, dominated by the setup Merkle tree cap
+    pub vk_size_bytes: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct CompressionChainEstimate {
+    pub steps: Vec<CompressionStepEstimate>,
+    /// Security of the weakest step
+    pub security_bits: usize,
+    pub prover_work: u64,
+    /// Size of the compression for wrapper proof, the one verified by the wrapper.
+    /// The wrapper step itself is not estimated
+    pub for_wrapper_proof_size_bytes: usize,
+    pub for_wrapper_vk_size_bytes: usize,
+}
+
+/// Folding rounds of FRI, as log2 of the folding factor
+fn fri_folding_schedule(parameters: &CompressionStepParameters) -> Vec<usize> {
+    if let Some(schedule) = parameters.proof_config.fri_folding_schedule.as_ref() {
+        return schedule.clone();
+    }
+
+    let rate_log2 = parameters.proof_config.fri_lde_factor.trailing_zeros() as usize;
+    let cap_log2 = parameters
+        .proof_config
+        .merkle_tree_cap_size
+        .trailing_zeros() as usize;
+    let mut degree_log2 = parameters.trace_length.trailing_zeros() as usize;
+    let mut schedule = vec![];
+    // fold while the oracle is larger than its cap
+    while degree_log2 > 0 && degree_log2 + rate_log2 > cap_log2 {
+        let step = std::cmp::min(DEFAULT_FRI_FOLDING_LOG2, degree_log2);
+        schedule.push(step);
+        degree_log2 -= step;
+    }
+
+    schedule
+}
+
+pub fn estimate_compression_step(
+    parameters: &CompressionStepParameters,
+) -> WrapResult<CompressionStepEstimate> {
+    parameters.validate()?;
+
+    let config = &parameters.proof_config;
+    let geometry = &parameters.geometry;
+    let rate_log2 = config.fri_lde_factor.trailing_zeros() as usize;
+    let cap_log2 = config.merkle_tree_cap_size.trailing_zeros() as usize;
+    let trace_log2 = parameters.trace_length.trailing_zeros() as usize;
+    let domain_log2 = trace_log2 + rate_log2;
+
+    // same number of queries as the prover uses: PoW covers a part of the security level
+    let pow_bits = config.pow_bits as usize;
+    let num_queries = config
+        .security_level
+        .saturating_sub(pow_bits)
+        .div_ceil(rate_log2);
+    let fri_security_bits = num_queries * rate_log2 + pow_bits;
+    let security_bits = std::cmp::min(
+        fri_security_bits,
+        EXTENSION_FIELD_BITS.saturating_sub(domain_log2),
+    );
+
+    // witness, copy permutation sigmas and constants are in the base field,
+    // copy permutation grand product with intermediate products and quotient chunks are in the extension
+    let base_polys = 2 * geometry.num_columns_under_copy_permutation
+        + geometry.num_witness_columns
+        + geometry.num_constant_columns;
+    let degree = geometry.max_allowed_constraint_degree;
+    let extension_polys = 1
+        + geometry
+            .num_columns_under_copy_permutation
+            .div_ceil(degree - 1)
+        + degree;
+    let elements_per_query = base_polys + 2 * extension_polys;
+
+    let prover_work = (parameters.trace_length as u64)
+        * (config.fri_lde_factor as u64)
+        * (elements_per_query as u64);
+
+    // witness, stage 2, quotient and setup oracles
+    let num_base_oracles = 4;
+    let base_path_len = domain_log2.saturating_sub(cap_log2);
+    let schedule = fri_folding_schedule(parameters);
+    let mut query_size = elements_per_query * FIELD_ELEMENT_SIZE_BYTES
+        + num_base_oracles * base_path_len * DIGEST_SIZE_BYTES;
+    let mut oracle_domain_log2 = domain_log2;
+    for folding_log2 in schedule.iter().copied() {
+        let path_len = oracle_domain_log2
+            .saturating_sub(folding_log2)
+            .saturating_sub(cap_log2);
+        query_size +=
+            2 * (1 << folding_log2) * FIELD_ELEMENT_SIZE_BYTES + path_len * DIGEST_SIZE_BYTES;
+        oracle_domain_log2 = oracle_domain_log2.saturating_sub(folding_log2);
+    }
+    let final_degree_log2 = trace_log2.saturating_sub(schedule.iter().sum());
+    let caps_size =
+        (num_base_oracles + schedule.len()) * config.merkle_tree_cap_size * DIGEST_SIZE_BYTES;
+    let values_at_z_size = 2 * (base_polys + extension_polys) * 2 * FIELD_ELEMENT_SIZE_BYTES;
+    let final_monomials_size = 2 * (1 << final_degree_log2) * FIELD_ELEMENT_SIZE_BYTES;
+    let proof_size_bytes = num_queries * query_size
+        + caps_size
+        + values_at_z_size
+        + final_monomials_size
+        + FIELD_ELEMENT_SIZE_BYTES;
+
+    Ok(CompressionStepEstimate {
+        parameters: parameters.clone(),
+        num_queries,
+        security_bits,
+        prover_work,
+        proof_size_bytes,
+        vk_size_bytes: config.merkle_tree_cap_size * DIGEST_SIZE_BYTES,
+    })
+}
+
+/// Evaluates the chain of compression steps, the last one should be the compression for wrapper
+pub fn estimate_compression_chain(
+    steps: &[CompressionStepParameters],
+) -> WrapResult<CompressionChainEstimate> {
+    match steps.split_last() {
+        None => {
+            return Err(WrapError::InvalidConfig(
+                "compression chain is empty".to_string(),
+            ))
+        }
+        Some((last, rest)) => {
+            if !last.for_wrapper || rest.iter().any(|el| el.for_wrapper) {
+                return Err(WrapError::InvalidConfig(
+                    "only the last step of the compression chain should be for wrapper".to_string(),
+                ));
+            }
+        }
+    }
+
+    let steps = steps
+        .iter()
+        .map(estimate_compression_step)
+        .collect::<WrapResult<Vec<_>>>()?;
+    let last = steps.last().unwrap();
+
+    Ok(CompressionChainEstimate {
+        security_bits: steps.iter().map(|el| el.security_bits).min().unwrap(),
+        prover_work: steps.iter().map(|el| el.prover_work).sum(),
+        for_wrapper_proof_size_bytes: last.proof_size_bytes,
+        for_wrapper_vk_size_bytes: last.vk_size_bytes,
+        steps,
+    })
+}
+
+/// Estimates of all the chains supported by `WrapperConfig`.
+/// Compression chain doesn't depend on the wrapper mode, configs use the default one
+pub fn estimate_supported_compression_chains() -> Vec<(WrapperConfig, CompressionChainEstimate)> {
+    (1..=WrapperConfig::MAX_COMPRESSION_LAYERS)
+        .map(|compression_layers| {
+            let config = WrapperConfig::new(compression_layers).unwrap();
+            let estimate = estimate_compression_chain(&compression_chain_for_config(config))
+                .expect("supported compression modes are valid");
+            (config, estimate)
+        })
+        .collect()
+}
+
+/// Checks that every step of the (possibly custom) chain reaches the target security
+pub fn validate_compression_chain(
+    steps: &[CompressionStepParameters],
+    target_security_bits: usize,
+) -> WrapResult<CompressionChainEstimate> {
+    let estimate = estimate_compression_chain(steps)?;
+    if let Some(weakest) = estimate
+        .steps
+        .iter()
+        .find(|el| el.security_bits < target_security_bits)
+    {
+        return Err(WrapError::InvalidConfig(format!(
+            "compression step {} (for wrapper: {}) provides {} bits of security, {} required",
+            weakest.parameters.circuit_type,
+            weakest.parameters.for_wrapper,
+            weakest.security_bits,
+            target_security_bits
+        )));
+    }
+
+    Ok(estimate)
+}
+
+/// Recommends the supported chain for the security target.
+/// Wrapper verifies the compression for wrapper proof in-circuit and dominates the proving time,
+/// so the chain with the smallest such proof is chosen, ties are broken by the compression prover work.
+/// Returned config uses the default wrapper mode, it can be changed with `WrapperConfig::with_mode`.
+pub fn recommend_compression_chain(
+    target_security_bits: usize,
+) -> WrapResult<(WrapperConfig, CompressionChainEstimate)> {
+    estimate_supported_compression_chains()
+        .into_iter()
+        .filter(|(_, estimate)| estimate.security_bits >= target_security_bits)
+        .min_by_key(|(_, estimate)| (estimate.for_wrapper_proof_size_bytes, estimate.prover_work))
+        .ok_or_else(|| {
+            WrapError::InvalidConfig(format!(
+                "no supported compression chain provides {} bits of security",
+                target_security_bits
+            ))
+        })
+}
@@ -4,19 +4,37 @@ pub(crate) const CRS_FILE_ENV_VAR: &str = "CRS_FILE";
 
 /// Just to check if the file and environment variable are not forgotten
 pub fn check_trusted_setup_file_existace() {
-    let crs_file_str = std::env::var(CRS_FILE_ENV_VAR).expect("CRS_FILE env variable:");
-    let crs_file_path = std::path::Path::new(&crs_file_str);
-    let _crs_file = std::fs::File::open(&crs_file_path)
-        .expect(format!("Trying to open CRS FILE: {:?}", crs_file_path).as_str());
+    check_trusted_setup_file().unwrap();
 }
 
 /// Uploads trusted setup file to the RAM
 pub fn get_trusted_setup() -> Crs<Bn256, CrsForMonomialForm> {
-    let crs_file_str = std::env::var(CRS_FILE_ENV_VAR).expect("CRS_FILE env variable:");
-    let crs_file_path = std::path::Path::new(&crs_file_str);
-    let crs_file = std::fs::File::open(&crs_file_path)
-        .expect(format!("Trying to open CRS FILE: {:?}", crs_file_path).as_str());
-    Crs::read(&crs_file).expect(format!("Trying to read CRS FILE: {:?}", crs_file_path).as_str())
+    try_get_trusted_setup().unwrap()
+}
+
+/// Non-panicking version of `check_trusted_setup_file_existace`
+pub fn check_trusted_setup_file() -> WrapResult<()> {
+    open_trusted_setup_file().map(|_| ())
+}
+
+/// Non-panicking version of `get_trusted_setup`
+pub fn try_get_trusted_setup() -> WrapResult<Crs<Bn256, CrsForMonomialForm>> {
+    let (crs_file, crs_file_path) = open_trusted_setup_file()?;
+    Crs::read(&crs_file).map_err(|el| {
+        WrapError::MissingCrs(format!("reading CRS file {:?}: {}", crs_file_path, el))
+    })
+}
+
+fn open_trusted_setup_file() -> WrapResult<(std::fs::File, std::path::PathBuf)> {
+    let crs_file_str = std::env::var(CRS_FILE_ENV_VAR).map_err(|el| {
+        WrapError::MissingCrs(format!("{} env variable: {}", CRS_FILE_ENV_VAR, el))
+    })?;
+    let crs_file_path = std::path::PathBuf::from(crs_file_str);
+    let crs_file = std::fs::File::open(&crs_file_path).map_err(|el| {
+        WrapError::MissingCrs(format!("opening CRS file {:?}: {}", crs_file_path, el))
+    })?;
+
+    Ok((crs_file, crs_file_path))
 }
 
 /// Computes wrapper public input from stark one
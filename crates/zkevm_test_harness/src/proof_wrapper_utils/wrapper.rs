@@ -5,34 +5,56 @@ pub const L1_VERIFIER_DOMAIN_SIZE_LOG: usize = 24;
 pub fn get_wrapper_setup_and_vk_from_compression_vk(
     vk: ZkSyncCompressionForWrapperVerificationKey,
     config: WrapperConfig,
-) -> (ZkSyncSnarkWrapperSetup, ZkSyncSnarkWrapperVK) {
-    check_trusted_setup_file_existace();
+) -> WrapResult<(ZkSyncSnarkWrapperSetup, ZkSyncSnarkWrapperVK)> {
+    get_wrapper_setup_and_vk_from_compression_vk_with_progress(vk, config, &mut |_| {})
+}
 
+/// Same as `get_wrapper_setup_and_vk_from_compression_vk`, the wrapper step is reported to `progress`
+pub fn get_wrapper_setup_and_vk_from_compression_vk_with_progress(
+    vk: ZkSyncCompressionForWrapperVerificationKey,
+    config: WrapperConfig,
+    progress: &mut dyn FnMut(WrapProgress),
+) -> WrapResult<(ZkSyncSnarkWrapperSetup, ZkSyncSnarkWrapperVK)> {
+    if config.get_wrapper_mode() != WrapperMode::Plonk {
+        return Err(WrapError::InvalidConfig(
+            "use get_fflonk_wrapper_setup_and_vk_from_compression_vk for FFLONK wrapper"
+                .to_string(),
+        ));
+    }
+    let wrapper_type = config.get_wrapper_type();
+    let stage = WrapStage::Wrapper(wrapper_type);
+    progress(WrapProgress::Started(stage));
+    let start = std::time::Instant::now();
+
+    check_circuit_type(
+        config.get_compression_for_wrapper_type(),
+        vk.numeric_circuit_type(),
+    )?;
+    let crs_mons = try_get_trusted_setup()?;
     let worker = BellmanWorker::new();
 
-    println!("Computing Bn256 wrapper setup");
-    let snark_setup = compute_wrapper_setup_inner(vk, config, &worker);
-    println!("Loading CRS");
-    let crs_mons = get_trusted_setup();
-    println!("Computing Bn256 wrapper vk");
-    let snark_vk = SnarkVK::from_setup(&snark_setup, &worker, &crs_mons).unwrap();
+    let snark_setup = compute_wrapper_setup_inner(vk, config, &worker)?;
+    let snark_vk = SnarkVK::from_setup(&snark_setup, &worker, &crs_mons)
+        .map_err(|el| WrapError::Prover(format!("wrapper vk: {:?}", el)))?;
 
-    let wrapper_type = config.get_wrapper_type();
-    (
+    progress(WrapProgress::Finished(stage, start.elapsed()));
+
+    Ok((
         ZkSyncSnarkWrapperSetup::from_inner(wrapper_type, Arc::new(snark_setup)),
         ZkSyncSnarkWrapperVK::from_inner(wrapper_type, snark_vk),
-    )
+    ))
 }
 
 pub(crate) fn compute_wrapper_proof_and_vk<DS: SetupDataSource + BlockDataSource>(
     source: &mut DS,
     config: WrapperConfig,
     worker: &BellmanWorker,
-) {
+    progress: &mut dyn FnMut(WrapProgress),
+) -> WrapResult<()> {
     let wrapper_type = config.get_wrapper_type();
 
     // {
-    //     let proof = source
+    //     source
     //     .get_compression_for_wrapper_proof(wrapper_type)
     //     .unwrap();
     //     let vk = source.get_compression_for_wrapper_vk(wrapper_type).unwrap();
@@ -54,69 +76,85 @@ pub(crate) fn compute_wrapper_proof_and_vk<DS: SetupDataSource + BlockDataSource
     //     println!("Wrapper Bellman circuit over Bn256 is satisfied");
     // }
 
-    println!("Computing Bn256 wrapper setup");
+    let stage = WrapStage::Wrapper(wrapper_type);
+    progress(WrapProgress::Started(stage));
+    let start = std::time::Instant::now();
+
     if source.get_wrapper_setup(wrapper_type).is_err() {
-        let vk = source.get_compression_for_wrapper_vk(wrapper_type).unwrap();
+        let vk = source
+            .get_compression_for_wrapper_vk(wrapper_type)
+            .map_err(source_failure("compression for wrapper vk"))?;
 
-        let snark_setup = compute_wrapper_setup_inner(vk, config, worker);
+        let snark_setup = compute_wrapper_setup_inner(vk, config, worker)?;
 
         let snark_setup =
             ZkSyncCompressionLayerStorage::from_inner(wrapper_type, Arc::new(snark_setup));
-        source.set_wrapper_setup(snark_setup).unwrap();
+        source
+            .set_wrapper_setup(snark_setup)
+            .map_err(source_failure("wrapper setup"))?;
     }
 
-    println!("Computing Bn256 wrapper vk");
     if source.get_wrapper_vk(wrapper_type).is_err() {
-        let start = std::time::Instant::now();
-        let snark_setup = source.get_wrapper_setup(wrapper_type).unwrap();
-
-        let crs_mons = get_trusted_setup();
-        let snark_vk = SnarkVK::from_setup(&snark_setup.into_inner(), worker, &crs_mons).unwrap();
+        let snark_setup = source
+            .get_wrapper_setup(wrapper_type)
+            .map_err(source_failure("wrapper setup"))?;
 
-        println!(
-            "Wrapper vk {} is done, taken {:?}",
-            wrapper_type,
-            start.elapsed()
-        );
+        let crs_mons = try_get_trusted_setup()?;
+        let snark_vk = SnarkVK::from_setup(&snark_setup.into_inner(), worker, &crs_mons)
+            .map_err(|el| WrapError::Prover(format!("wrapper vk: {:?}", el)))?;
 
         let snark_vk = ZkSyncCompressionLayerStorage::from_inner(wrapper_type, snark_vk);
-        source.set_wrapper_vk(snark_vk).unwrap();
+        source
+            .set_wrapper_vk(snark_vk)
+            .map_err(source_failure("wrapper vk"))?;
     }
 
-    println!("Computing Bn256 wrapper proof");
     if source.get_wrapper_proof(wrapper_type).is_err() {
         let proof = source
             .get_compression_for_wrapper_proof(wrapper_type)
-            .unwrap();
-        let vk = source.get_compression_for_wrapper_vk(wrapper_type).unwrap();
+            .map_err(source_failure("compression for wrapper proof"))?;
+        let vk = source
+            .get_compression_for_wrapper_vk(wrapper_type)
+            .map_err(source_failure("compression for wrapper vk"))?;
 
-        let snark_setup = source.get_wrapper_setup(wrapper_type).unwrap();
+        let snark_setup = source
+            .get_wrapper_setup(wrapper_type)
+            .map_err(source_failure("wrapper setup"))?;
 
-        let snark_proof = compute_wrapper_proof_inner(proof, vk, snark_setup, config, worker);
+        let snark_proof = compute_wrapper_proof_inner(proof, vk, snark_setup, config, worker)?;
 
-        println!("Verifying");
-        let snark_vk = source.get_wrapper_vk(wrapper_type).unwrap();
+        let snark_vk = source
+            .get_wrapper_vk(wrapper_type)
+            .map_err(source_failure("wrapper vk"))?;
         use crate::snark_wrapper::franklin_crypto::bellman::plonk::better_better_cs::verifier::verify;
         let is_valid =
             verify::<_, _, RollingKeccakTranscript<Fr>>(&snark_vk.into_inner(), &snark_proof, None)
-                .unwrap();
-        assert!(is_valid);
+                .map_err(|el| WrapError::Prover(format!("wrapper verification: {:?}", el)))?;
+        if !is_valid {
+            return Err(WrapError::Prover("wrapper proof is not valid".to_string()));
+        }
 
         let snark_proof = ZkSyncCompressionLayerStorage::from_inner(wrapper_type, snark_proof);
-        source.set_wrapper_proof(snark_proof).unwrap();
+        source
+            .set_wrapper_proof(snark_proof)
+            .map_err(source_failure("wrapper proof"))?;
     }
+
+    progress(WrapProgress::Finished(stage, start.elapsed()));
+
+    Ok(())
 }
 
 pub(crate) fn compute_wrapper_setup_inner(
     vk: ZkSyncCompressionForWrapperVerificationKey,
     config: WrapperConfig,
     worker: &BellmanWorker,
-) -> SnarkSetup<Bn256, ZkSyncSnarkWrapperCircuit> {
-    let start = std::time::Instant::now();
+) -> WrapResult<SnarkSetup<Bn256, ZkSyncSnarkWrapperCircuit>> {
     let wrapper_type = config.get_wrapper_type();
+    let stage = WrapStage::Wrapper(wrapper_type);
 
     let compression_for_wrapper_type = config.get_compression_for_wrapper_type();
-    assert_eq!(compression_for_wrapper_type, vk.numeric_circuit_type());
+    check_circuit_type(compression_for_wrapper_type, vk.numeric_circuit_type())?;
     let vk = vk.into_inner();
 
     let mut assembly = SetupAssembly::<
@@ -136,31 +174,29 @@ pub(crate) fn compute_wrapper_setup_inner(
         wrapper_function,
     };
 
-    println!("Synthesizing into Bn256 setup assembly");
-    wrapper_circuit.synthesize(&mut assembly).unwrap();
-
-    assembly.finalize_to_size_log_2(L1_VERIFIER_DOMAIN_SIZE_LOG);
-    assert!(assembly.is_satisfied());
+    catch_prover_failure(stage, || {
+        wrapper_circuit
+            .synthesize(&mut assembly)
+            .map(|_| assembly.finalize_to_size_log_2(L1_VERIFIER_DOMAIN_SIZE_LOG))
+    })?
+    .map_err(prover_failure(stage))?;
+    if !assembly.is_satisfied() {
+        return Err(WrapError::Prover(format!(
+            "{:?}: wrapper circuit is not satisfied",
+            stage
+        )));
+    }
 
-    println!("Creating Bn256 setup");
-    let setup =
-        assembly
-            .create_setup::<WrapperCircuit<
-                _,
-                _,
-                TreeHasherForWrapper,
-                TranscriptForWrapper,
-                ZkSyncCompressionWrapper,
-            >>(worker)
-            .unwrap();
-
-    println!(
-        "Wrapper setup {} is done, taken {:?}",
-        wrapper_type,
-        start.elapsed()
-    );
-
-    setup
+    catch_prover_failure(stage, || {
+        assembly.create_setup::<WrapperCircuit<
+            _,
+            _,
+            TreeHasherForWrapper,
+            TranscriptForWrapper,
+            ZkSyncCompressionWrapper,
+        >>(worker)
+    })?
+    .map_err(prover_failure(stage))
 }
 
 #[allow(dead_code)]
@@ -209,16 +245,16 @@ fn compute_wrapper_proof_inner(
     snark_setup: ZkSyncSnarkWrapperSetup,
     config: WrapperConfig,
     worker: &BellmanWorker,
-) -> SnarkProof<Bn256, ZkSyncSnarkWrapperCircuit> {
-    check_trusted_setup_file_existace();
-
-    let start = std::time::Instant::now();
+) -> WrapResult<SnarkProof<Bn256, ZkSyncSnarkWrapperCircuit>> {
     let wrapper_type = config.get_wrapper_type();
+    let stage = WrapStage::Wrapper(wrapper_type);
 
     let compression_for_wrapper_type = config.get_compression_for_wrapper_type();
-    assert_eq!(compression_for_wrapper_type, proof.numeric_circuit_type());
-    assert_eq!(compression_for_wrapper_type, vk.numeric_circuit_type());
-    assert_eq!(wrapper_type, snark_setup.numeric_circuit_type());
+    check_circuit_type(compression_for_wrapper_type, proof.numeric_circuit_type())?;
+    check_circuit_type(compression_for_wrapper_type, vk.numeric_circuit_type())?;
+    check_circuit_type(wrapper_type, snark_setup.numeric_circuit_type())?;
+
+    let crs_mons = try_get_trusted_setup()?;
 
     let proof = proof.into_inner();
     let vk = vk.into_inner();
@@ -241,38 +277,27 @@ fn compute_wrapper_proof_inner(
         wrapper_function,
     };
 
-    println!("Synthesizing Bn256 Bellman wrapping proof");
-    wrapper_circuit.synthesize(&mut assembly).unwrap();
-
-    assembly.finalize_to_size_log_2(L1_VERIFIER_DOMAIN_SIZE_LOG);
-    assert!(assembly.is_satisfied());
-    println!("Bn256 Bellman wrapper circuit is SATISFIED");
+    catch_prover_failure(stage, || {
+        wrapper_circuit
+            .synthesize(&mut assembly)
+            .map(|_| assembly.finalize_to_size_log_2(L1_VERIFIER_DOMAIN_SIZE_LOG))
+    })?
+    .map_err(prover_failure(stage))?;
+    if !assembly.is_satisfied() {
+        return Err(WrapError::Prover(format!(
+            "{:?}: wrapper circuit is not satisfied",
+            stage
+        )));
+    }
 
-    println!(
-        "Wrapper benchmark: {} gates for mode {}",
-        assembly.n(),
-        wrapper_type
-    );
-
-    let crs_mons = get_trusted_setup();
-
-    println!("Proving");
-    let proof =
-        assembly
-            .create_proof::<WrapperCircuit<
-                _,
-                _,
-                TreeHasherForWrapper,
-                TranscriptForWrapper,
-                ZkSyncCompressionWrapper,
-            >, RollingKeccakTranscript<Fr>>(worker, &snark_setup, &crs_mons, None)
-            .unwrap();
-
-    println!(
-        "Wrapper proof {} is done, taken {:?}",
-        wrapper_type,
-        start.elapsed()
-    );
-
-    proof
+    catch_prover_failure(stage, || {
+        assembly.create_proof::<WrapperCircuit<
+            _,
+            _,
+            TreeHasherForWrapper,
+            TranscriptForWrapper,
+            ZkSyncCompressionWrapper,
+        >, RollingKeccakTranscript<Fr>>(worker, &snark_setup, &crs_mons, None)
+    })?
+    .map_err(prover_failure(stage))
 }
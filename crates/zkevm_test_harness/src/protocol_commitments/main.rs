@@ -0,0 +1,71 @@
+use std::fs::File;
+use std::path::PathBuf;
+
+use structopt::StructOpt;
+use zkevm_test_harness::data_source::local_file_data_source::LocalFileDataSource;
+use zkevm_test_harness::protocol_commitments::{compute_protocol_commitments, ProtocolCommitments};
+
+#[derive(Debug, StructOpt)]
+#[structopt(
+    name = "Protocol commitments",
+    about = "Tool for exporting and comparing the commitments to the protocol verification keys"
+)]
+enum Opt {
+    /// Computes commitments from the setup directory
+    Export {
+        /// Directory with the setup data (verification keys)
+        #[structopt(long, default_value = "./setup")]
+        setup_path: String,
+        /// Snark wrapper circuit type (number of compression layers), wrapper vk hash is skipped if not set
+        #[structopt(long)]
+        wrapper_type: Option<u8>,
+        /// File to write the commitments to, printed to stdout if not set
+        #[structopt(long)]
+        output: Option<PathBuf>,
+    },
+    /// Prints the commitments that changed between two exported files, fails if there are any
+    Diff { old: PathBuf, new: PathBuf },
+}
+
+fn read_commitments(path: &PathBuf) -> ProtocolCommitments {
+    let file = File::open(path).unwrap_or_else(|el| panic!("Unable to open {:?}: {}", path, el));
+    serde_json::from_reader(file).unwrap_or_else(|el| panic!("Unable to parse {:?}: {}", path, el))
+}
+
+fn main() {
+    match Opt::from_args() {
+        Opt::Export {
+            setup_path,
+            wrapper_type,
+            output,
+        } => {
+            let mut source = LocalFileDataSource {
+                setup_data_location: setup_path,
+                ..Default::default()
+            };
+            let commitments = compute_protocol_commitments(&mut source, wrapper_type)
+                .expect("Unable to compute commitments");
+            let serialized = serde_json::to_string_pretty(&commitments).unwrap();
+            match output {
+                Some(path) => std::fs::write(path, serialized).expect("Unable to write data"),
+                None => println!("{}", serialized),
+            }
+        }
+        Opt::Diff { old, new } => {
+            let changes = read_commitments(&old).diff(&read_commitments(&new));
+            if changes.is_empty() {
+                println!("Commitments are the same");
+                return;
+            }
+            for change in changes.iter() {
+                println!(
+                    "{}: {} -> {}",
+                    change.name,
+                    change.old.as_deref().unwrap_or("<none>"),
+                    change.new.as_deref().unwrap_or("<none>")
+                );
+            }
+            std::process::exit(1);
+        }
+    }
+}
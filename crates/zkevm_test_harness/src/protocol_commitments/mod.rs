@@ -0,0 +1,124 @@
+//! Commitments to the verification keys and parameters of the protocol, that are stored on L1
+//! and have to be updated with every protocol upgrade.
+
+use crate::boojum::field::goldilocks::GoldilocksField;
+use crate::boojum::field::{SmallField, U64Representable};
+use crate::compute_setups::compute_leaf_params;
+use crate::data_source::{SetupDataSource, SourceResult};
+use crate::proof_wrapper_utils::l1_verifier_vk_hash;
+use crate::witness::recursive_aggregation::{
+    compute_leaf_vks_and_params_commitment, compute_node_vk_commitment,
+};
+use circuit_definitions::circuit_definitions::recursion_layer::{
+    RecursionTreeArity, ZkSyncRecursionLayerStorageType,
+};
+use serde::{Deserialize, Serialize};
+
+/// All the commitments that L1 needs to verify the proofs produced with the setup.
+/// Goldilocks commitments are encoded in the same way as in the L1 contracts:
+/// 0x-prefixed concatenation of the elements, 16 hex digits each.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProtocolCommitments {
+    /// Commitment to the leaf parameters (base layer and leaf layer vks) of all circuit types
+    pub leaf_layer_parameters: String,
+    pub node_layer_vk: String,
+    pub recursion_tip_vk: String,
+    pub scheduler_vk: String,
+    /// Keccak256 of the snark wrapper vk, same as `verificationKeyHash()` of the L1 verifier.
+    /// Not set if the wrapper was not requested
+    pub snark_wrapper_vk_hash: Option<String>,
+    pub recursion_tree_arity: RecursionTreeArity,
+}
+
+/// One value that differs between two `ProtocolCommitments`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProtocolCommitmentChange {
+    pub name: String,
+    pub old: Option<String>,
+    pub new: Option<String>,
+}
+
+fn goldilocks_commitment_to_hex(commitment: &[GoldilocksField]) -> String {
+    let mut result = "0x".to_owned();
+    for el in commitment.iter() {
+        result.push_str(&format!("{:016x}", el.as_u64_reduced()));
+    }
+
+    result
+}
+
+/// Computes commitments from the setup.
+/// Source must contain base layer, leaf, node, recursion tip and scheduler vks,
+/// and the snark wrapper vk of `wrapper_type` if it's set.
+pub fn compute_protocol_commitments(
+    source: &mut dyn SetupDataSource,
+    wrapper_type: Option<u8>,
+) -> SourceResult<ProtocolCommitments> {
+    let leaf_layer_params = compute_leaf_params(source)?
+        .into_iter()
+        .map(|el| el.1)
+        .collect::<Vec<_>>()
+        .try_into()
+        .map_err(|_| "unexpected number of leaf parameters".to_string())?;
+    let leaf_layer_parameters = compute_leaf_vks_and_params_commitment(leaf_layer_params);
+
+    let node_layer_vk = compute_node_vk_commitment(source.get_recursion_layer_node_vk()?);
+    let recursion_tip_vk = compute_node_vk_commitment(source.get_recursion_tip_vk()?);
+    let scheduler_vk = compute_node_vk_commitment(
+        source.get_recursion_layer_vk(ZkSyncRecursionLayerStorageType::SchedulerCircuit as u8)?,
+    );
+
+    let snark_wrapper_vk_hash = match wrapper_type {
+        Some(wrapper_type) => {
+            let vk = source.get_wrapper_vk(wrapper_type)?.into_inner();
+            Some(format!("0x{}", hex::encode(l1_verifier_vk_hash(&vk))))
+        }
+        None => None,
+    };
+
+    Ok(ProtocolCommitments {
+        leaf_layer_parameters: goldilocks_commitment_to_hex(&leaf_layer_parameters),
+        node_layer_vk: goldilocks_commitment_to_hex(&node_layer_vk),
+        recursion_tip_vk: goldilocks_commitment_to_hex(&recursion_tip_vk),
+        scheduler_vk: goldilocks_commitment_to_hex(&scheduler_vk),
+        snark_wrapper_vk_hash,
+        recursion_tree_arity: source.get_recursion_tree_arity()?,
+    })
+}
+
+impl ProtocolCommitments {
+    fn values(&self) -> Vec<(&'static str, Option<String>)> {
+        vec![
+            (
+                "leaf_layer_parameters",
+                Some(self.leaf_layer_parameters.clone()),
+            ),
+            ("node_layer_vk", Some(self.node_layer_vk.clone())),
+            ("recursion_tip_vk", Some(self.recursion_tip_vk.clone())),
+            ("scheduler_vk", Some(self.scheduler_vk.clone())),
+            ("snark_wrapper_vk_hash", self.snark_wrapper_vk_hash.clone()),
+            (
+                "recursion_tree_arity.leaf_layer_capacity",
+                Some(self.recursion_tree_arity.leaf_layer_capacity.to_string()),
+            ),
+            (
+                "recursion_tree_arity.node_layer_capacity",
+                Some(self.recursion_tree_arity.node_layer_capacity.to_string()),
+            ),
+        ]
+    }
+
+    /// Values that changed from `self` (old version) to `other` (new version)
+    pub fn diff(&self, other: &Self) -> Vec<ProtocolCommitmentChange> {
+        self.values()
+            .into_iter()
+            .zip(other.values())
+            .filter(|((_, old), (_, new))| old != new)
+            .map(|((name, old), (_, new))| ProtocolCommitmentChange {
+                name: name.to_string(),
+                old,
+                new,
+            })
+            .collect()
+    }
+}
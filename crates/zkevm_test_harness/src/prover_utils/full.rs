@@ -14,6 +14,7 @@ use crate::GoldilocksField;
 use circuit_definitions::boojum::cs::implementations::reference_cs::CSReferenceAssembly;
 use circuit_definitions::circuit_definitions::aux_layer::{compression::*, *};
 use circuit_definitions::circuit_definitions::base_layer::ZkSyncBaseLayerCircuit;
+use circuit_definitions::circuit_definitions::recursion_layer::interblock::ZkSyncInterblockAggregationCircuit;
 use circuit_definitions::circuit_definitions::recursion_layer::verifier_builder::dyn_verifier_builder_for_recursive_circuit_type;
 use circuit_definitions::circuit_definitions::recursion_layer::*;
 use circuit_definitions::circuit_definitions::verifier_builder::dyn_verifier_builder_for_circuit_type;
@@ -243,6 +244,10 @@ pub fn verify_base_layer_proof<POW: PoWRunner>(
     proof: &Proof<F, H, EXT>,
     vk: &VerificationKey<F, H>,
 ) -> bool {
+    #[cfg(feature = "mock_prover")]
+    if super::mock::is_mock_proof(proof) {
+        return true;
+    }
     let verifier_builder = dyn_verifier_builder_for_circuit_type(circuit.numeric_circuit_type());
     let verifier = verifier_builder.create_verifier();
     verifier.verify::<H, TR, POW>((), vk, proof)
@@ -253,6 +258,10 @@ pub fn verify_base_layer_proof_for_type<POW: PoWRunner>(
     proof: &Proof<F, H, EXT>,
     vk: &VerificationKey<F, H>,
 ) -> bool {
+    #[cfg(feature = "mock_prover")]
+    if super::mock::is_mock_proof(proof) {
+        return true;
+    }
     let verifier_builder = dyn_verifier_builder_for_circuit_type(circuit_type);
     let verifier = verifier_builder.create_verifier();
     verifier.verify::<H, TR, POW>((), vk, proof)
@@ -388,6 +397,10 @@ pub fn verify_recursion_layer_proof<POW: PoWRunner>(
     proof: &Proof<F, H, EXT>,
     vk: &VerificationKey<F, H>,
 ) -> bool {
+    #[cfg(feature = "mock_prover")]
+    if super::mock::is_mock_proof(proof) {
+        return true;
+    }
     let verifier_builder = circuit.into_dyn_verifier_builder();
     let verifier = verifier_builder.create_verifier();
     verifier.verify::<H, TR, POW>((), vk, proof)
@@ -398,11 +411,103 @@ pub fn verify_recursion_layer_proof_for_type<POW: PoWRunner>(
     proof: &Proof<F, H, EXT>,
     vk: &VerificationKey<F, H>,
 ) -> bool {
+    #[cfg(feature = "mock_prover")]
+    if super::mock::is_mock_proof(proof) {
+        return true;
+    }
     let verifier_builder = dyn_verifier_builder_for_recursive_circuit_type(circuit_type);
     let verifier = verifier_builder.create_verifier();
     verifier.verify::<H, TR, POW>((), vk, proof)
 }
 
+pub fn create_interblock_aggregation_setup_data(
+    circuit: ZkSyncInterblockAggregationCircuit,
+    worker: &Worker,
+    fri_lde_factor: usize,
+    merkle_tree_cap_size: usize,
+) -> (
+    SetupBaseStorage<F, P>,
+    SetupStorage<F, P>,
+    VerificationKey<F, H>,
+    MerkleTreeWithCap<F, H>,
+    DenseVariablesCopyHint,
+    DenseWitnessCopyHint,
+    FinalizationHintsForProver,
+) {
+    let (cs, finalization_hint) = get_cs_finalization_hint_for_interblock_aggregation(circuit);
+
+    let (setup_base, setup, vk, setup_tree, vars_hint, witness_hints) =
+        cs.get_full_setup(worker, fri_lde_factor, merkle_tree_cap_size);
+
+    (
+        setup_base,
+        setup,
+        vk,
+        setup_tree,
+        vars_hint,
+        witness_hints,
+        finalization_hint,
+    )
+}
+
+pub fn prove_interblock_aggregation_circuit<POW: PoWRunner>(
+    circuit: ZkSyncInterblockAggregationCircuit,
+    worker: &Worker,
+    proof_config: ProofConfig,
+    setup_base: &SetupBaseStorage<F, P>,
+    setup: &SetupStorage<F, P>,
+    setup_tree: &MerkleTreeWithCap<F, H>,
+    vk: &VerificationKey<F, H>,
+    vars_hint: &DenseVariablesCopyHint,
+    wits_hint: &DenseWitnessCopyHint,
+    finalization_hint: &FinalizationHintsForProver,
+) -> Proof<F, H, EXT> {
+    use crate::boojum::cs::cs_builder::new_builder;
+    use crate::boojum::cs::cs_builder_reference::CsReferenceImplementationBuilder;
+
+    let geometry = circuit.geometry();
+    let (max_trace_len, num_vars) = circuit.size_hint();
+
+    let builder_impl = CsReferenceImplementationBuilder::<GoldilocksField, P, ProvingCSConfig>::new(
+        geometry,
+        max_trace_len.unwrap(),
+    );
+    let builder = new_builder::<_, GoldilocksField>(builder_impl);
+
+    let builder = circuit.configure_builder_proxy(builder);
+    let mut cs = builder.build(num_vars.unwrap());
+    circuit.add_tables(&mut cs);
+    circuit.synthesize_into_cs(&mut cs);
+    cs.pad_and_shrink_using_hint(finalization_hint);
+    let cs = cs.into_assembly::<std::alloc::Global>();
+
+    cs.prove_from_precomputations::<EXT, TR, H, POW>(
+        proof_config,
+        setup_base,
+        setup,
+        setup_tree,
+        vk,
+        vars_hint,
+        wits_hint,
+        (),
+        worker,
+    )
+}
+
+pub fn verify_interblock_aggregation_proof<POW: PoWRunner>(
+    circuit: &ZkSyncInterblockAggregationCircuit,
+    proof: &Proof<F, H, EXT>,
+    vk: &VerificationKey<F, H>,
+) -> bool {
+    #[cfg(feature = "mock_prover")]
+    if super::mock::is_mock_proof(proof) {
+        return true;
+    }
+    let verifier_builder = circuit.into_dyn_verifier_builder();
+    let verifier = verifier_builder.create_verifier();
+    verifier.verify::<H, TR, POW>((), vk, proof)
+}
+
 pub fn create_compression_layer_setup_data(
     circuit: ZkSyncCompressionLayerCircuit,
     worker: &Worker,
@@ -505,6 +610,10 @@ pub fn verify_compression_layer_proof<POW: PoWRunner>(
     proof: &Proof<F, H, EXT>,
     vk: &VerificationKey<F, H>,
 ) -> bool {
+    #[cfg(feature = "mock_prover")]
+    if super::mock::is_mock_proof(proof) {
+        return true;
+    }
     let verifier_builder = circuit.into_dyn_verifier_builder();
     let verifier = verifier_builder.create_verifier();
     verifier.verify::<H, TR, POW>((), vk, proof)
@@ -616,6 +725,10 @@ pub fn verify_compression_for_wrapper_proof<POW: PoWRunner>(
     proof: &Proof<F, TreeHasherForWrapper, EXT>,
     vk: &VerificationKey<F, TreeHasherForWrapper>,
 ) -> bool {
+    #[cfg(feature = "mock_prover")]
+    if super::mock::is_mock_proof(proof) {
+        return true;
+    }
     let verifier_builder = circuit.into_dyn_verifier_builder();
     let verifier = verifier_builder.create_verifier();
     verifier.verify::<TreeHasherForWrapper, TranscriptForWrapper, POW>((), vk, proof)
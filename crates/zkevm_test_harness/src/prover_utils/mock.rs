@@ -0,0 +1,398 @@
+//! Mock proving backend for the integration tests of the orchestration code.
+//!
+//! Every circuit is still synthesized from its witness, but instead of running the prover
+//! we emit a placeholder proof that is tagged with `MOCK_PROOF_TAG` and carries the
+//! public inputs that the real proof would have. Verification helpers from this module's
+//! parent accept such proofs when the `mock_prover` feature is enabled, so the whole
+//! base -> leaf -> node -> scheduler -> compression chain can be run without the real prover.
+//!
+//! What is checked:
+//! - base layer circuits are synthesized and checked for satisfiability, and the public input
+//!   is compared against `expected_public_input` if it's set;
+//! - recursive circuits (leaf, node, recursion tip, scheduler) only accept mock proofs as inputs
+//!   and are synthesized with placeholder proofs in place of them, so all the witness level checks
+//!   (queues, vk commitments, block data) still run. The recursive verifier inside of them
+//!   can not be satisfied by a placeholder, so satisfiability is NOT checked for these circuits;
+//! - compression layers consist of the verifier only, so we just check the tag of the inner proof
+//!   and pass its public inputs through, the same as the circuit does.
+//!
+//! The SNARK wrapper is out of scope: it's a Bn256 proof that can not be faked this way.
+//!
+//! Never enable `mock_prover` feature in production builds.
+
+use super::*;
+
+use crate::boojum::algebraic_props::round_function::AbsorptionModeOverwrite;
+use crate::boojum::algebraic_props::sponge::GoldilocksPoseidon2Sponge;
+use crate::boojum::config::{CSConfig, DevCSConfig};
+use crate::boojum::cs::implementations::proof::Proof;
+use crate::boojum::cs::implementations::prover::ProofConfig;
+use crate::boojum::cs::oracle::TreeHasher;
+use crate::boojum::dag::StCircuitResolver;
+use crate::boojum::field::goldilocks::GoldilocksExt2;
+use crate::boojum::field::SmallField;
+use crate::boojum::gadgets::traits::witnessable::WitnessHookable;
+use crate::boojum::worker::Worker;
+use circuit_definitions::circuit_definitions::recursion_layer::interblock::INTERBLOCK_AGGREGATION_MASKING_VALUE;
+use circuit_definitions::circuit_definitions::{
+    ZkSyncUniformCircuitInstance, ZkSyncUniformSynthesisFunction,
+};
+use circuit_definitions::zkevm_circuits::fsm_input_output::circuit_inputs::INPUT_OUTPUT_COMMITMENT_LENGTH;
+use circuit_sequencer_api::public_input::interblock_aggregated_public_inputs;
+use circuit_sequencer_api::NUM_SCHEDULER_PUBLIC_INPUTS;
+use std::alloc::Global;
+use std::collections::VecDeque;
+
+type F = GoldilocksField;
+type EXT = GoldilocksExt2;
+type H = GoldilocksPoseidon2Sponge<AbsorptionModeOverwrite>;
+
+/// Value of the `pow_challenge` of the mock proofs, ASCII "mockprf" in LE
+pub const MOCK_PROOF_TAG: u64 = u64::from_le_bytes(*b"mockprf\0");
+
+/// Placeholder proof with the given public inputs. All oracles and queries are empty,
+/// so it can never pass the real verifier.
+pub fn mock_proof<TH: TreeHasher<F>>(
+    public_inputs: Vec<F>,
+    proof_config: ProofConfig,
+) -> Proof<F, TH, EXT> {
+    Proof {
+        proof_config,
+        public_inputs,
+        witness_oracle_cap: vec![],
+        stage_2_oracle_cap: vec![],
+        quotient_oracle_cap: vec![],
+        final_fri_monomials: [vec![], vec![]],
+        values_at_z: vec![],
+        values_at_z_omega: vec![],
+        values_at_0: vec![],
+        fri_base_oracle_cap: vec![],
+        fri_intermediate_oracles_caps: vec![],
+        queries_per_fri_repetition: vec![],
+        pow_challenge: MOCK_PROOF_TAG,
+        _marker: std::marker::PhantomData,
+    }
+}
+
+pub fn is_mock_proof<TH: TreeHasher<F>>(proof: &Proof<F, TH, EXT>) -> bool {
+    proof.pow_challenge == MOCK_PROOF_TAG
+        && proof.witness_oracle_cap.is_empty()
+        && proof.fri_base_oracle_cap.is_empty()
+        && proof.queries_per_fri_repetition.is_empty()
+}
+
+/// Takes the inner proofs out of the recursive circuit witness, so the circuit
+/// allocates placeholders instead of them
+fn take_mock_proofs<TH: TreeHasher<F>>(
+    proofs: &mut VecDeque<Proof<F, TH, EXT>>,
+) -> Vec<Proof<F, TH, EXT>> {
+    let proofs: Vec<_> = std::mem::take(proofs).into();
+    for (idx, proof) in proofs.iter().enumerate() {
+        assert!(
+            is_mock_proof(proof),
+            "mock prover can only aggregate mock proofs, but proof {} is a real one",
+            idx
+        );
+    }
+
+    proofs
+}
+
+fn synthesize_base_layer_instance<S: ZkSyncUniformSynthesisFunction<F>>(
+    circuit: ZkSyncUniformCircuitInstance<F, S>,
+    worker: &Worker,
+) -> [F; INPUT_OUTPUT_COMMITMENT_LENGTH] {
+    use crate::boojum::cs::cs_builder::new_builder;
+    use crate::boojum::cs::cs_builder_reference::CsReferenceImplementationBuilder;
+
+    let geometry = circuit.geometry_proxy();
+    let (max_trace_len, num_vars) = circuit.size_hint();
+
+    let builder_impl = CsReferenceImplementationBuilder::<
+        F,
+        P,
+        DevCSConfig,
+        StCircuitResolver<F, <DevCSConfig as CSConfig>::ResolverConfig>,
+    >::new(geometry, max_trace_len.unwrap());
+    let builder = new_builder::<_, F>(builder_impl);
+    let builder = circuit.configure_builder_proxy(builder);
+    let mut cs = builder.build(num_vars.unwrap());
+    circuit.add_tables_proxy(&mut cs);
+
+    let ZkSyncUniformCircuitInstance {
+        witness,
+        config,
+        round_function,
+        expected_public_input,
+    } = circuit;
+    let witness = witness.take().unwrap_or_default();
+    let public_input_var =
+        S::synthesize_into_cs_inner(&mut cs, witness, &round_function, (*config).clone());
+    let public_input = public_input_var.witness_hook(&cs)()
+        .expect("public input of the base layer circuit must be resolved");
+    if let Some(expected_public_input) = expected_public_input {
+        assert_eq!(
+            expected_public_input, public_input,
+            "we expected public input to be {:?}, but circuit returned {:?}",
+            expected_public_input, public_input
+        );
+    }
+
+    let _ = cs.pad_and_shrink();
+    let mut cs = cs.into_assembly::<Global>();
+    assert!(
+        cs.check_if_satisfied(worker),
+        "base layer circuit {} is not satisfied",
+        S::description()
+    );
+
+    public_input
+}
+
+/// Synthesizes the circuit, checks that it's satisfied and returns a mock proof
+/// with the circuit's public input
+pub fn mock_prove_base_layer_circuit(
+    circuit: ZkSyncBaseLayerCircuit,
+    worker: &Worker,
+    proof_config: ProofConfig,
+) -> Proof<F, H, EXT> {
+    let public_input = match circuit {
+        ZkSyncBaseLayerCircuit::MainVM(inner) => synthesize_base_layer_instance(inner, worker),
+        ZkSyncBaseLayerCircuit::CodeDecommittmentsSorter(inner) => {
+            synthesize_base_layer_instance(inner, worker)
+        }
+        ZkSyncBaseLayerCircuit::CodeDecommitter(inner) => {
+            synthesize_base_layer_instance(inner, worker)
+        }
+        ZkSyncBaseLayerCircuit::LogDemuxer(inner) => synthesize_base_layer_instance(inner, worker),
+        ZkSyncBaseLayerCircuit::KeccakRoundFunction(inner) => {
+            synthesize_base_layer_instance(inner, worker)
+        }
+        ZkSyncBaseLayerCircuit::Sha256RoundFunction(inner) => {
+            synthesize_base_layer_instance(inner, worker)
+        }
+        ZkSyncBaseLayerCircuit::ECRecover(inner) => synthesize_base_layer_instance(inner, worker),
+        ZkSyncBaseLayerCircuit::RAMPermutation(inner) => {
+            synthesize_base_layer_instance(inner, worker)
+        }
+        ZkSyncBaseLayerCircuit::StorageSorter(inner) => {
+            synthesize_base_layer_instance(inner, worker)
+        }
+        ZkSyncBaseLayerCircuit::StorageApplication(inner) => {
+            synthesize_base_layer_instance(inner, worker)
+        }
+        ZkSyncBaseLayerCircuit::EventsSorter(inner) => {
+            synthesize_base_layer_instance(inner, worker)
+        }
+        ZkSyncBaseLayerCircuit::L1MessagesSorter(inner) => {
+            synthesize_base_layer_instance(inner, worker)
+        }
+        ZkSyncBaseLayerCircuit::L1MessagesHasher(inner) => {
+            synthesize_base_layer_instance(inner, worker)
+        }
+        ZkSyncBaseLayerCircuit::TransientStorageSorter(inner) => {
+            synthesize_base_layer_instance(inner, worker)
+        }
+        ZkSyncBaseLayerCircuit::Secp256r1Verify(inner) => {
+            synthesize_base_layer_instance(inner, worker)
+        }
+        ZkSyncBaseLayerCircuit::EIP4844Repack(inner) => {
+            synthesize_base_layer_instance(inner, worker)
+        }
+    };
+
+    mock_proof(public_input.to_vec(), proof_config)
+}
+
+/// Synthesizes the circuit with placeholders in place of the inner (mock) proofs
+/// and returns a mock proof with the circuit's public inputs. Satisfiability is not checked,
+/// see the module level docs.
+pub fn mock_prove_recursion_layer_circuit(
+    circuit: ZkSyncRecursiveLayerCircuit,
+    proof_config: ProofConfig,
+) -> Proof<F, H, EXT> {
+    use crate::boojum::cs::cs_builder::new_builder;
+    use crate::boojum::cs::cs_builder_reference::CsReferenceImplementationBuilder;
+
+    let geometry = circuit.geometry();
+    let (max_trace_len, num_vars) = circuit.size_hint();
+
+    let builder_impl = CsReferenceImplementationBuilder::<
+        F,
+        P,
+        DevCSConfig,
+        StCircuitResolver<F, <DevCSConfig as CSConfig>::ResolverConfig>,
+    >::new(geometry, max_trace_len.unwrap());
+    let builder = new_builder::<_, F>(builder_impl);
+    let round_function = ZkSyncDefaultRoundFunction::default();
+
+    let public_inputs = match circuit {
+        ZkSyncRecursiveLayerCircuit::SchedulerCircuit(mut inner) => {
+            let _ = take_mock_proofs(&mut inner.witness.proof_witnesses);
+            let builder = inner.configure_builder_proxy(builder);
+            let mut cs = builder.build(num_vars.unwrap());
+            inner.add_tables(&mut cs);
+            inner.synthesize_into_cs(&mut cs, &round_function);
+            // scheduler doesn't return its public inputs, it only places the chunks
+            // of the input hash into the public input gates, so we read the values from there
+            let _ = cs.pad_and_shrink();
+            let mut cs = cs.into_assembly::<Global>();
+            let (vars_hint, _) = cs.create_copy_hints();
+            let witness = cs.materialize_witness_vec();
+            let public_inputs: Vec<_> = witness
+                .public_inputs_locations
+                .iter()
+                .map(|&(column, row)| {
+                    let variable = vars_hint.maps[column][row];
+                    witness.all_values[variable.as_variable_index() as usize]
+                })
+                .collect();
+            assert_eq!(public_inputs.len(), NUM_SCHEDULER_PUBLIC_INPUTS);
+
+            Some(public_inputs)
+        }
+        ZkSyncRecursiveLayerCircuit::NodeLayerCircuit(mut inner) => {
+            let _ = take_mock_proofs(&mut inner.witness.proof_witnesses);
+            let builder = inner.configure_builder_proxy(builder);
+            let mut cs = builder.build(num_vars.unwrap());
+            inner.add_tables(&mut cs);
+            let public_inputs = inner.synthesize_into_cs(&mut cs, &round_function);
+            public_inputs.witness_hook(&cs)().map(|el| el.to_vec())
+        }
+        ZkSyncRecursiveLayerCircuit::LeafLayerCircuitForMainVM(mut inner)
+        | ZkSyncRecursiveLayerCircuit::LeafLayerCircuitForCodeDecommittmentsSorter(mut inner)
+        | ZkSyncRecursiveLayerCircuit::LeafLayerCircuitForCodeDecommitter(mut inner)
+        | ZkSyncRecursiveLayerCircuit::LeafLayerCircuitForLogDemuxer(mut inner)
+        | ZkSyncRecursiveLayerCircuit::LeafLayerCircuitForKeccakRoundFunction(mut inner)
+        | ZkSyncRecursiveLayerCircuit::LeafLayerCircuitForSha256RoundFunction(mut inner)
+        | ZkSyncRecursiveLayerCircuit::LeafLayerCircuitForECRecover(mut inner)
+        | ZkSyncRecursiveLayerCircuit::LeafLayerCircuitForRAMPermutation(mut inner)
+        | ZkSyncRecursiveLayerCircuit::LeafLayerCircuitForStorageSorter(mut inner)
+        | ZkSyncRecursiveLayerCircuit::LeafLayerCircuitForStorageApplication(mut inner)
+        | ZkSyncRecursiveLayerCircuit::LeafLayerCircuitForEventsSorter(mut inner)
+        | ZkSyncRecursiveLayerCircuit::LeafLayerCircuitForL1MessagesSorter(mut inner)
+        | ZkSyncRecursiveLayerCircuit::LeafLayerCircuitForL1MessagesHasher(mut inner)
+        | ZkSyncRecursiveLayerCircuit::LeafLayerCircuitForTransientStorageSorter(mut inner)
+        | ZkSyncRecursiveLayerCircuit::LeafLayerCircuitForSecp256r1Verify(mut inner)
+        | ZkSyncRecursiveLayerCircuit::LeafLayerCircuitForEIP4844Repack(mut inner) => {
+            let inner_proofs = take_mock_proofs(&mut inner.witness.proof_witnesses);
+            // the only thing that the verifier would check on top of the placeholders
+            // is that the proofs are for the queued base layer circuits
+            assert_eq!(
+                inner_proofs.len(),
+                inner.witness.queue_witness.elements.len(),
+                "number of proofs doesn't match the recursion queue length"
+            );
+            let queue_inputs = inner
+                .witness
+                .queue_witness
+                .elements
+                .iter()
+                .map(|(el, _)| el.input_commitment.to_vec());
+            for (idx, (proof, expected)) in inner_proofs.iter().zip(queue_inputs).enumerate() {
+                assert_eq!(
+                    proof.public_inputs, expected,
+                    "public input of the proof {} doesn't match the recursion queue",
+                    idx
+                );
+            }
+            let builder = inner.configure_builder_proxy(builder);
+            let mut cs = builder.build(num_vars.unwrap());
+            inner.add_tables(&mut cs);
+            let public_inputs = inner.synthesize_into_cs(&mut cs, &round_function);
+            public_inputs.witness_hook(&cs)().map(|el| el.to_vec())
+        }
+        ZkSyncRecursiveLayerCircuit::RecursionTipCircuit(mut inner) => {
+            let _ = take_mock_proofs(&mut inner.witness.proof_witnesses);
+            let builder = inner.configure_builder_proxy(builder);
+            let mut cs = builder.build(num_vars.unwrap());
+            inner.add_tables(&mut cs);
+            let public_inputs = inner.synthesize_into_cs(&mut cs, &round_function);
+            public_inputs.witness_hook(&cs)().map(|el| el.to_vec())
+        }
+    };
+
+    let public_inputs =
+        public_inputs.expect("public inputs of the recursive circuit must be resolved");
+
+    mock_proof(public_inputs, proof_config)
+}
+
+/// Interblock aggregation only consists of the scheduler proofs verification, so public inputs
+/// are computed out of circuit from the inner mock proofs
+pub fn mock_prove_interblock_aggregation_circuit(
+    mut circuit: ZkSyncInterblockAggregationCircuit,
+    proof_config: ProofConfig,
+) -> Proof<F, H, EXT> {
+    let inner_proofs = take_mock_proofs(&mut circuit.witness.proof_witnesses);
+    let batches_public_inputs: Vec<[u64; NUM_SCHEDULER_PUBLIC_INPUTS]> = inner_proofs
+        .iter()
+        .map(|proof| {
+            let public_inputs: Vec<_> = proof
+                .public_inputs
+                .iter()
+                .map(|el| el.as_u64_reduced())
+                .collect();
+            public_inputs
+                .try_into()
+                .expect("scheduler proof must have 4 public inputs")
+        })
+        .collect();
+
+    let public_inputs = interblock_aggregated_public_inputs(
+        &batches_public_inputs,
+        circuit.config.capacity,
+        INTERBLOCK_AGGREGATION_MASKING_VALUE,
+    );
+
+    mock_proof(
+        public_inputs
+            .iter()
+            .map(|el| F::from_u64_unchecked(*el))
+            .collect(),
+        proof_config,
+    )
+}
+
+fn inner_compression_proof(witness: Option<Proof<F, H, EXT>>) -> Proof<F, H, EXT> {
+    let proof = witness.expect("compression circuit must have a witness");
+    assert!(
+        is_mock_proof(&proof),
+        "mock prover can only compress mock proofs"
+    );
+
+    proof
+}
+
+/// Compression circuits pass the public inputs of the previous step through
+pub fn mock_prove_compression_layer_circuit(
+    circuit: ZkSyncCompressionLayerCircuit,
+) -> Proof<F, H, EXT> {
+    let proof_config = circuit.proof_config_for_compression_step();
+    let inner = match circuit {
+        ZkSyncCompressionLayerCircuit::CompressionMode1Circuit(inner) => inner.witness,
+        ZkSyncCompressionLayerCircuit::CompressionMode2Circuit(inner) => inner.witness,
+        ZkSyncCompressionLayerCircuit::CompressionMode3Circuit(inner) => inner.witness,
+        ZkSyncCompressionLayerCircuit::CompressionMode4Circuit(inner) => inner.witness,
+        ZkSyncCompressionLayerCircuit::CompressionMode5Circuit(inner) => inner.witness,
+    };
+    let inner = inner_compression_proof(inner);
+
+    mock_proof(inner.public_inputs, proof_config)
+}
+
+pub fn mock_prove_compression_for_wrapper_circuit(
+    circuit: ZkSyncCompressionForWrapperCircuit,
+) -> Proof<F, TreeHasherForWrapper, EXT> {
+    let proof_config = circuit.proof_config_for_compression_step();
+    let inner = match circuit {
+        ZkSyncCompressionForWrapperCircuit::CompressionMode1Circuit(inner) => inner.witness,
+        ZkSyncCompressionForWrapperCircuit::CompressionMode2Circuit(inner) => inner.witness,
+        ZkSyncCompressionForWrapperCircuit::CompressionMode3Circuit(inner) => inner.witness,
+        ZkSyncCompressionForWrapperCircuit::CompressionMode4Circuit(inner) => inner.witness,
+        ZkSyncCompressionForWrapperCircuit::CompressionMode5Circuit(inner) => inner.witness,
+    };
+    let inner = inner_compression_proof(inner);
+
+    mock_proof(inner.public_inputs, proof_config)
+}
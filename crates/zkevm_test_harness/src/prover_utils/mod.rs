@@ -1,5 +1,7 @@
 mod full;
 pub mod light;
+#[cfg(feature = "mock_prover")]
+pub mod mock;
 
 use crate::boojum::cs::implementations::reference_cs::CSReferenceAssembly;
 use crate::boojum::cs::implementations::setup::FinalizationHintsForProver;
@@ -14,6 +16,7 @@ use circuit_definitions::circuit_definitions::aux_layer::{
     ZkSyncCompressionForWrapperCircuit, ZkSyncCompressionLayerCircuit,
 };
 use circuit_definitions::circuit_definitions::base_layer::ZkSyncBaseLayerCircuit;
+use circuit_definitions::circuit_definitions::recursion_layer::interblock::ZkSyncInterblockAggregationCircuit;
 use circuit_definitions::circuit_definitions::recursion_layer::ZkSyncRecursiveLayerCircuit;
 use circuit_definitions::ZkSyncDefaultRoundFunction;
 pub use full::*;
@@ -242,6 +245,32 @@ fn get_cs_finalization_hint_for_recursive_layer(
     }
 }
 
+fn get_cs_finalization_hint_for_interblock_aggregation(
+    circuit: ZkSyncInterblockAggregationCircuit,
+) -> (
+    CSReferenceAssembly<GoldilocksField, P, SetupCSConfig>,
+    FinalizationHintsForProver,
+) {
+    use crate::boojum::cs::cs_builder::new_builder;
+    use crate::boojum::cs::cs_builder_reference::CsReferenceImplementationBuilder;
+
+    let geometry = circuit.geometry();
+    let (max_trace_len, num_vars) = circuit.size_hint();
+
+    let builder_impl = CsReferenceImplementationBuilder::<GoldilocksField, P, SetupCSConfig>::new(
+        geometry,
+        max_trace_len.unwrap(),
+    );
+    let builder = new_builder::<_, GoldilocksField>(builder_impl);
+
+    let builder = circuit.configure_builder_proxy(builder);
+    let mut cs = builder.build(num_vars.unwrap());
+    circuit.add_tables(&mut cs);
+    circuit.synthesize_into_cs(&mut cs);
+    let (_, finalization_hint) = cs.pad_and_shrink();
+    (cs.into_assembly::<std::alloc::Global>(), finalization_hint)
+}
+
 fn get_cs_finalization_hint_for_compression(
     circuit: ZkSyncCompressionLayerCircuit,
 ) -> (
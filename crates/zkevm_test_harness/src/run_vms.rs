@@ -4,7 +4,7 @@ use crate::boojum::gadgets::traits::allocatable::*;
 use crate::entry_point::*;
 use crate::snark_wrapper::boojum::field::goldilocks::GoldilocksExt2;
 use crate::snark_wrapper::boojum::gadgets::recursion::recursive_tree_hasher::CircuitGoldilocksPoseidon2Sponge;
-use crate::toolset::create_tools;
+use crate::toolset::create_tools_for_entry_point;
 use crate::toolset::GeometryConfig;
 use crate::witness::oracle::create_artifacts_from_tracer;
 use crate::witness::oracle::WitnessGenerationArtifact;
@@ -18,7 +18,6 @@ use crate::zk_evm::abstractions::Storage;
 use crate::zk_evm::abstractions::*;
 use crate::zk_evm::aux_structures::*;
 use crate::zk_evm::bytecode_to_code_hash;
-use crate::zk_evm::contract_bytecode_to_words;
 use crate::zk_evm::witness_trace::VmWitnessTracer;
 use crate::zk_evm::GenericNoopTracer;
 use crate::zkevm_circuits::linear_hasher::input::LinearHasherOutputDataWitness;
@@ -35,7 +34,9 @@ use crate::{
 use circuit_definitions::boojum::field::Field;
 use circuit_definitions::circuit_definitions::base_layer::ZkSyncBaseLayerCircuit;
 use circuit_definitions::encodings::recursion_request::RecursionQueueSimulator;
-use circuit_definitions::zk_evm::reference_impls::memory::SimpleMemory;
+use circuit_definitions::zk_evm::reference_impls::memory::{
+    MemoryLimits, MemoryMetrics, SimpleMemory,
+};
 use circuit_definitions::zk_evm::tracing::Tracer;
 use circuit_definitions::zk_evm::zkevm_opcode_defs::VersionedHashLen32;
 use circuit_definitions::zkevm_circuits::fsm_input_output::ClosedFormInputCompactFormWitness;
@@ -51,9 +52,11 @@ pub enum RunVmError {
     OutOfCircuitExecutionError(String),
 }
 
+/// Scheduler witness, AUX witness and the memory usage of the out-of-circuit VM
 pub type RunVMsResult = (
     SchedulerCircuitInstanceWitness<MainField, CircuitGoldilocksPoseidon2Sponge, GoldilocksExt2>,
     BlockAuxilaryOutputWitness<MainField>,
+    MemoryMetrics,
 );
 
 /// Executes a given set of instructions, and returns things necessary to do the proving:
@@ -83,6 +86,52 @@ pub fn run_vms<S: Storage>(
     eip_4844_repack_inputs: [Option<Vec<u8>>; MAX_4844_BLOBS_PER_BLOCK],
     artifacts_callback_sender: SyncSender<WitnessGenerationArtifact>,
     out_of_circuit_tracer: &mut impl Tracer<SupportedMemory = SimpleMemory>,
+) -> Result<RunVMsResult, RunVmError> {
+    run_vms_with_memory_limits(
+        caller,
+        entry_point_address,
+        entry_point_code,
+        initial_heap_content,
+        zk_porter_is_available,
+        default_aa_code_hash,
+        evm_simulator_code_hash,
+        used_bytecodes,
+        ram_verification_queries,
+        cycle_limit,
+        MemoryLimits::default(),
+        geometry,
+        storage,
+        tree,
+        trusted_setup_path,
+        eip_4844_repack_inputs,
+        artifacts_callback_sender,
+        out_of_circuit_tracer,
+    )
+}
+
+/// Same as `run_vms`, but the memory of the out-of-circuit VM is bounded by `memory_limits`.
+/// Exceeding them is reported as `RunVmError::OutOfCircuitExecutionError`
+pub fn run_vms_with_memory_limits<S: Storage>(
+    caller: Address,                 // for real block must be zero
+    entry_point_address: Address,    // for real block must be the bootloader
+    entry_point_code: Vec<[u8; 32]>, // for read block must be a bootloader code
+    initial_heap_content: Vec<u8>,   // bootloader starts with non-deterministic heap
+    zk_porter_is_available: bool,
+    default_aa_code_hash: U256,
+    evm_simulator_code_hash: U256,
+    used_bytecodes: std::collections::HashMap<U256, Vec<[u8; 32]>>, // auxilary information to avoid passing a full set of all used codes
+    ram_verification_queries: Vec<(u32, U256)>, // we may need to check that after the bootloader's memory is filled
+    cycle_limit: usize,
+    memory_limits: MemoryLimits,
+    geometry: GeometryConfig,
+    storage: S,
+    tree: impl BinarySparseStorageTree<256, 32, 32, 8, 32, Blake2s256, ZkSyncStorageLeaf>
+        + 'static
+        + std::marker::Send,
+    trusted_setup_path: String,
+    eip_4844_repack_inputs: [Option<Vec<u8>>; MAX_4844_BLOBS_PER_BLOCK],
+    artifacts_callback_sender: SyncSender<WitnessGenerationArtifact>,
+    out_of_circuit_tracer: &mut impl Tracer<SupportedMemory = SimpleMemory>,
 ) -> Result<RunVMsResult, RunVmError> {
     let round_function = ZkSyncDefaultRoundFunction::default();
 
@@ -97,62 +146,21 @@ pub fn run_vms<S: Storage>(
     let initial_rollup_root = tree.root();
     let initial_rollup_enumeration_counter = tree.next_enumeration_index();
 
-    let bytecode_hash = bytecode_to_code_hash(&entry_point_code).unwrap();
-
-    let mut tools = create_tools(storage, &geometry);
+    let entry_point_code_hash_as_u256 =
+        U256::from_big_endian(&bytecode_to_code_hash(&entry_point_code).unwrap());
 
-    // fill the tools
-    let mut to_fill = vec![];
-    let entry_point_code_hash_as_u256 = U256::from_big_endian(&bytecode_hash);
-    if !used_bytecodes.contains_key(&entry_point_code_hash_as_u256) {
-        to_fill.push((
-            entry_point_code_hash_as_u256,
-            contract_bytecode_to_words(&entry_point_code),
-        ));
-    }
-    for (k, v) in used_bytecodes.into_iter() {
-        to_fill.push((k, contract_bytecode_to_words(&v)));
-    }
-    tools.decommittment_processor.populate(to_fill);
+    let (tools, entry_point_decommittment_query, entry_point_decommittment_query_witness) =
+        create_tools_for_entry_point(
+            storage,
+            &geometry,
+            memory_limits,
+            &entry_point_code,
+            used_bytecodes,
+        );
 
     let heap_writes = calldata_to_aligned_data(&initial_heap_content);
     let num_non_deterministic_heap_queries = heap_writes.len();
 
-    let (header, normalized_preimage) = crate::zk_evm::zkevm_opcode_defs::definitions::versioned_hash::ContractCodeSha256Format::normalize_for_decommitment(&bytecode_hash);
-
-    // bootloader decommit query
-    let entry_point_decommittment_query = DecommittmentQuery {
-        header,
-        normalized_preimage,
-        timestamp: Timestamp(SCHEDULER_TIMESTAMP),
-        memory_page: MemoryPage(crate::zk_evm::zkevm_opcode_defs::BOOTLOADER_CODE_PAGE),
-        decommitted_length: entry_point_code.len() as u16,
-        is_fresh: true,
-    };
-
-    // manually decommit entry point
-    let prepared_entry_point_decommittment_query = tools
-        .decommittment_processor
-        .prepare_to_decommit(0, entry_point_decommittment_query)
-        .expect("must prepare decommit of entry point");
-    tools
-        .witness_tracer
-        .prepare_for_decommittment(0, entry_point_decommittment_query);
-    let entry_point_decommittment_query_witness = tools
-        .decommittment_processor
-        .decommit_into_memory(
-            0,
-            prepared_entry_point_decommittment_query,
-            &mut tools.memory,
-        )
-        .expect("must execute decommit of entry point");
-    let entry_point_decommittment_query_witness = entry_point_decommittment_query_witness.unwrap();
-    tools.witness_tracer.execute_decommittment(
-        0,
-        entry_point_decommittment_query,
-        entry_point_decommittment_query_witness.clone(),
-    );
-
     let block_properties = create_out_of_circuit_global_context(
         zk_porter_is_available,
         default_aa_code_hash,
@@ -202,7 +210,7 @@ pub fn run_vms<S: Storage>(
         }
         out_of_circuit_vm
             .cycle(out_of_circuit_tracer)
-            .expect("cycle should finish succesfully");
+            .map_err(|err| RunVmError::OutOfCircuitExecutionError(err.to_string()))?;
     }
 
     if !out_of_circuit_vm.execution_has_ended() {
@@ -216,6 +224,7 @@ pub fn run_vms<S: Storage>(
         ));
     }
 
+    let memory_metrics = out_of_circuit_vm.memory.metrics();
     println!("Out of circuit tracing is complete, now running witness generation");
 
     let vm_local_state = out_of_circuit_vm.local_state.clone();
@@ -637,5 +646,5 @@ pub fn run_vms<S: Storage>(
         (scheduler_circuit_witness, aux_data)
     };
 
-    Ok((scheduler_circuit_witness, aux_data))
+    Ok((scheduler_circuit_witness, aux_data, memory_metrics))
 }
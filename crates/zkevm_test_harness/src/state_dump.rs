@@ -0,0 +1,402 @@
+//! State dumps: pre-state or post-state of a test case, that is the storage slots of the rollup shard
+//! with the enumeration indexes of their leaves in the storage tree, and the known bytecodes.
+//!
+//! JSON format (`StateDump::to_json`), addresses and words are hex strings:
+//!
+//! ```json
+//! {
+//!   "next_enumeration_index": 3,
+//!   "storage": [
+//!     { "address": "0x0000000000000000000000000000000000008002", "key": "0x8001", "value": "0x100...", "enumeration_index": 1 },
+//!     { "address": "0x0000000000000000000000000000000000008004", "key": "0x100...", "value": "0x1", "enumeration_index": 2 }
+//!   ],
+//!   "bytecodes": [[[0, 0, ...], [4, 0, ...]]]
+//! }
+//! ```
+//!
+//! Every slot has a leaf in the tree, so the enumeration indexes are unique, start from 1 and are less
+//! than `next_enumeration_index`. Bytecodes are lists of 32 byte words, in the same way as in `TestArtifact`.
+//! `dump_state` takes the bytecodes whose hashes are marked as known in the `KnownCodesStorage`,
+//! so both the predeployed bytecodes and the ones published during the batch are dumped.
+//!
+//! Binary format (`StateDump::write`): the `ERASTATE` magic, the format version (`u32`, little endian)
+//! and the bincode encoded `StateDump`.
+//!
+//! The post-state after `run_vms` is taken from the storage and the tree passed to it
+//! through `SharedStorage` and `SharedTree`, see `dump_state`.
+
+use std::collections::{HashMap, HashSet};
+use std::io::{Read, Write};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use serde::{Deserialize, Serialize};
+
+use crate::asm_runner::storage::InMemoryCustomRefundStorage;
+use crate::blake2::Blake2s256;
+use crate::ethereum_types::{Address, U256};
+use crate::helper::artifact_utils::KNOWN_CODE_HASHES_ADDRESS;
+use crate::witness::tree::{
+    BinarySparseStorageTree, EnumeratedBinaryLeaf, LeafQuery, ZkSyncStorageLeaf,
+};
+use crate::zk_evm::abstractions::{Storage, StorageAccessRefund};
+use crate::zk_evm::aux_structures::{LogQuery, PubdataCost, Timestamp};
+use crate::zk_evm::reference_impls::decommitter::SimpleDecommitter;
+use crate::zk_evm::testing::storage::InMemoryStorage;
+use crate::zk_evm::{bytecode_to_code_hash, contract_bytecode_to_words};
+
+pub const STATE_DUMP_MAGIC: [u8; 8] = *b"ERASTATE";
+/// Should be bumped on any change of `StateDump`
+pub const STATE_DUMP_FORMAT_VERSION: u32 = 1;
+
+/// Shard of the rollup, the only one that has the storage tree
+pub(crate) const ROLLUP_SHARD_ID: u8 = 0;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct StorageSlot {
+    pub address: Address,
+    pub key: U256,
+    pub value: U256,
+    pub enumeration_index: u64,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StateDump {
+    pub next_enumeration_index: u64,
+    pub storage: Vec<StorageSlot>,
+    pub bytecodes: Vec<Vec<[u8; 32]>>,
+}
+
+impl Default for StateDump {
+    fn default() -> Self {
+        Self {
+            // index 0 is reserved for the empty leaves
+            next_enumeration_index: 1,
+            storage: vec![],
+            bytecodes: vec![],
+        }
+    }
+}
+
+impl StateDump {
+    pub fn from_json(json: &str) -> Result<Self, String> {
+        serde_json::from_str(json).map_err(|el| el.to_string())
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap()
+    }
+
+    pub fn read<R: Read>(mut reader: R) -> Result<Self, String> {
+        let mut header = [0u8; 12];
+        reader
+            .read_exact(&mut header)
+            .map_err(|el| format!("Unable to read the header: {}", el))?;
+        if header[..8] != STATE_DUMP_MAGIC {
+            return Err("Not a state dump".to_owned());
+        }
+        let version = u32::from_le_bytes(header[8..].try_into().unwrap());
+        if version != STATE_DUMP_FORMAT_VERSION {
+            return Err(format!(
+                "Unsupported state dump format version {}, expected {}",
+                version, STATE_DUMP_FORMAT_VERSION
+            ));
+        }
+
+        bincode::deserialize_from(reader).map_err(|el| el.to_string())
+    }
+
+    pub fn write<W: Write>(&self, mut writer: W) -> Result<(), String> {
+        writer
+            .write_all(&STATE_DUMP_MAGIC)
+            .and_then(|_| writer.write_all(&STATE_DUMP_FORMAT_VERSION.to_le_bytes()))
+            .map_err(|el| el.to_string())?;
+        bincode::serialize_into(writer, self).map_err(|el| el.to_string())
+    }
+
+    /// Checks the enumeration indexes and that the slots are unique
+    pub fn validate(&self) -> Result<(), String> {
+        let mut slots = HashSet::new();
+        let mut indexes = HashSet::new();
+        for slot in self.storage.iter() {
+            if !slots.insert((slot.address, slot.key)) {
+                return Err(format!("Duplicate slot {:?} {:#x}", slot.address, slot.key));
+            }
+            if slot.enumeration_index == 0 || slot.enumeration_index >= self.next_enumeration_index
+            {
+                return Err(format!(
+                    "Slot {:?} {:#x} has enumeration index {} out of range [1, {})",
+                    slot.address, slot.key, slot.enumeration_index, self.next_enumeration_index
+                ));
+            }
+            if !indexes.insert(slot.enumeration_index) {
+                return Err(format!(
+                    "Duplicate enumeration index {}",
+                    slot.enumeration_index
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Populates the storage, the decommitter and the tree, that should be empty.
+    /// Leaves of the tree get the enumeration indexes of the dump.
+    pub fn load<
+        const B: bool,
+        T: BinarySparseStorageTree<256, 32, 32, 8, 32, Blake2s256, ZkSyncStorageLeaf>,
+    >(
+        &self,
+        storage: &mut impl PopulateStorage,
+        decommitter: &mut SimpleDecommitter<B>,
+        tree: &mut T,
+    ) -> Result<(), String> {
+        self.validate()?;
+        if tree.root() != T::empty().root() {
+            return Err("The tree is not empty".to_owned());
+        }
+
+        storage.populate(
+            self.storage
+                .iter()
+                .map(|el| (ROLLUP_SHARD_ID, el.address, el.key, el.value))
+                .collect(),
+        );
+
+        let mut slots = self.storage.clone();
+        slots.sort_by_key(|el| el.enumeration_index);
+        for slot in slots.iter() {
+            insert_enumerated_leaf(tree, slot);
+        }
+        tree.set_next_enumeration_index(self.next_enumeration_index);
+
+        let mut known_bytecodes = vec![];
+        for bytecode in self.bytecodes.iter() {
+            let hash = bytecode_to_code_hash(bytecode)
+                .map_err(|_| format!("Invalid bytecode of {} words", bytecode.len()))?;
+            known_bytecodes.push((
+                U256::from_big_endian(&hash),
+                contract_bytecode_to_words(bytecode),
+            ));
+        }
+        decommitter.populate(known_bytecodes);
+
+        Ok(())
+    }
+}
+
+/// Storage that can be filled with the slots of a dump
+pub trait PopulateStorage {
+    /// Sets the values of `(shard_id, address, key, value)`
+    fn populate(&mut self, elements: Vec<(u8, Address, U256, U256)>);
+}
+
+impl PopulateStorage for InMemoryStorage {
+    fn populate(&mut self, elements: Vec<(u8, Address, U256, U256)>) {
+        InMemoryStorage::populate(self, elements)
+    }
+}
+
+impl PopulateStorage for InMemoryCustomRefundStorage {
+    fn populate(&mut self, elements: Vec<(u8, Address, U256, U256)>) {
+        self.storage.populate(elements)
+    }
+}
+
+impl<S: Storage + PopulateStorage> PopulateStorage for SharedStorage<S> {
+    fn populate(&mut self, elements: Vec<(u8, Address, U256, U256)>) {
+        self.lock().populate(elements)
+    }
+}
+
+/// Inserts the leaf of the slot with its enumeration index, changes the next enumeration index of the tree
+pub(crate) fn insert_enumerated_leaf(
+    tree: &mut impl BinarySparseStorageTree<256, 32, 32, 8, 32, Blake2s256, ZkSyncStorageLeaf>,
+    slot: &StorageSlot,
+) {
+    let index = LogQuery::derive_final_address_for_params(&slot.address, &slot.key);
+    let mut leaf = ZkSyncStorageLeaf::empty();
+    let mut buffer = [0u8; 32];
+    slot.value.to_big_endian(&mut buffer);
+    leaf.set_value(&buffer);
+
+    // the tree enumerates new leaves with its next index
+    tree.set_next_enumeration_index(slot.enumeration_index);
+    tree.insert_leaf(&index, leaf);
+}
+
+/// State of the rollup shard of the storage and the enumeration indexes of the tree.
+/// Slots that are not in the tree are only expected to be zero, e.g. written to zero without being set before.
+/// Bytecodes are the ones marked as known in the storage, `used_bytecodes` (by hash, as passed to `run_vms`)
+/// should contain all of them.
+pub fn dump_state(
+    storage: &InMemoryStorage,
+    tree: &mut impl BinarySparseStorageTree<256, 32, 32, 8, 32, Blake2s256, ZkSyncStorageLeaf>,
+    used_bytecodes: &HashMap<U256, Vec<[u8; 32]>>,
+) -> Result<StateDump, String> {
+    let mut slots = vec![];
+    for (address, values) in storage.inner[ROLLUP_SHARD_ID as usize].iter() {
+        for (key, value) in values.iter() {
+            let index = LogQuery::derive_final_address_for_params(address, key);
+            let leaf = tree.get_leaf(&index).leaf;
+            let enumeration_index = leaf.current_index();
+            if enumeration_index == ZkSyncStorageLeaf::empty_index() {
+                if value.is_zero() {
+                    continue;
+                }
+                return Err(format!("Slot {:?} {:#x} is not in the tree", address, key));
+            }
+            if U256::from_big_endian(leaf.value()) != *value {
+                return Err(format!(
+                    "Slot {:?} {:#x} has different values in the storage and the tree",
+                    address, key
+                ));
+            }
+
+            slots.push(StorageSlot {
+                address: *address,
+                key: *key,
+                value: *value,
+                enumeration_index,
+            });
+        }
+    }
+    slots.sort_by_key(|el| el.enumeration_index);
+
+    let mut known_hashes: Vec<_> = slots
+        .iter()
+        .filter(|el| el.address == KNOWN_CODE_HASHES_ADDRESS && !el.value.is_zero())
+        .map(|el| el.key)
+        .collect();
+    known_hashes.sort();
+    let mut bytecodes = vec![];
+    for hash in known_hashes.into_iter() {
+        let bytecode = used_bytecodes.get(&hash).ok_or_else(|| {
+            format!(
+                "Bytecode {:#x} is known, but not in the used bytecodes",
+                hash
+            )
+        })?;
+        bytecodes.push(bytecode.clone());
+    }
+
+    Ok(StateDump {
+        next_enumeration_index: tree.next_enumeration_index(),
+        storage: slots,
+        bytecodes,
+    })
+}
+
+/// Storage that can be passed to `run_vms` by value and inspected after it
+#[derive(Debug)]
+pub struct SharedStorage<S: Storage>(Arc<Mutex<S>>);
+
+impl<S: Storage> Clone for SharedStorage<S> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<S: Storage> SharedStorage<S> {
+    pub fn new(storage: S) -> Self {
+        Self(Arc::new(Mutex::new(storage)))
+    }
+
+    pub fn lock(&self) -> MutexGuard<'_, S> {
+        self.0.lock().unwrap()
+    }
+}
+
+impl<S: Storage> Storage for SharedStorage<S> {
+    fn get_access_refund(
+        &mut self,
+        monotonic_cycle_counter: u32,
+        partial_query: &LogQuery,
+    ) -> StorageAccessRefund {
+        self.lock()
+            .get_access_refund(monotonic_cycle_counter, partial_query)
+    }
+
+    fn execute_partial_query(
+        &mut self,
+        monotonic_cycle_counter: u32,
+        query: LogQuery,
+    ) -> (LogQuery, PubdataCost) {
+        self.lock()
+            .execute_partial_query(monotonic_cycle_counter, query)
+    }
+
+    fn start_frame(&mut self, timestamp: Timestamp) {
+        self.lock().start_frame(timestamp)
+    }
+
+    fn finish_frame(&mut self, timestamp: Timestamp, panicked: bool) {
+        self.lock().finish_frame(timestamp, panicked)
+    }
+
+    fn start_new_tx(&mut self, timestamp: Timestamp) {
+        self.lock().start_new_tx(timestamp)
+    }
+}
+
+/// Tree that can be passed to `run_vms` by value and inspected after it
+pub struct SharedTree<T>(Arc<Mutex<T>>);
+
+impl<T> Clone for SharedTree<T> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<T> SharedTree<T> {
+    pub fn new(tree: T) -> Self {
+        Self(Arc::new(Mutex::new(tree)))
+    }
+
+    pub fn lock(&self) -> MutexGuard<'_, T> {
+        self.0.lock().unwrap()
+    }
+}
+
+impl<T: BinarySparseStorageTree<256, 32, 32, 8, 32, Blake2s256, ZkSyncStorageLeaf>>
+    BinarySparseStorageTree<256, 32, 32, 8, 32, Blake2s256, ZkSyncStorageLeaf> for SharedTree<T>
+{
+    fn empty() -> Self {
+        Self::new(T::empty())
+    }
+    fn next_enumeration_index(&self) -> u64 {
+        self.lock().next_enumeration_index()
+    }
+    fn set_next_enumeration_index(&mut self, value: u64) {
+        self.lock().set_next_enumeration_index(value)
+    }
+    fn root(&self) -> [u8; 32] {
+        self.lock().root()
+    }
+    fn get_leaf(&mut self, index: &[u8; 32]) -> LeafQuery<256, 32, 32, 32, ZkSyncStorageLeaf> {
+        self.lock().get_leaf(index)
+    }
+    fn insert_leaf(
+        &mut self,
+        index: &[u8; 32],
+        leaf: ZkSyncStorageLeaf,
+    ) -> LeafQuery<256, 32, 32, 32, ZkSyncStorageLeaf> {
+        self.lock().insert_leaf(index, leaf)
+    }
+    fn filter_renumerate<'a>(
+        &self,
+        indexes: impl Iterator<Item = &'a [u8; 32]>,
+        leafs: impl Iterator<Item = ZkSyncStorageLeaf>,
+    ) -> (
+        u64,
+        Vec<([u8; 32], ZkSyncStorageLeaf)>,
+        Vec<ZkSyncStorageLeaf>,
+    ) {
+        self.lock().filter_renumerate(indexes, leafs)
+    }
+    fn verify_inclusion(
+        root: &[u8; 32],
+        query: &LeafQuery<256, 32, 32, 32, ZkSyncStorageLeaf>,
+    ) -> bool {
+        T::verify_inclusion(root, query)
+    }
+}
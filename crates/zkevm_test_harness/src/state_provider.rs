@@ -0,0 +1,433 @@
+//! Lazy state for replaying batches without preloading all the touched state: `ForkedStorage`,
+//! `ForkedDecommitter` and `ForkedTree` ask a `StateProvider` for slot values, bytecodes and tree leaves
+//! on the first access that misses the local state.
+//!
+//! Providers are local: `StateDumpProvider` serves a state dump file or an in-process `StateDump`.
+//! `RecordingProvider` remembers everything that was fetched, so the minimal pre-state of the replay
+//! can be written afterwards (`RecordingProvider::pre_state`) and loaded with `StateDump::load`.
+//!
+//! One provider is usually shared by the storage, the decommitter and the tree as `Arc<Mutex<P>>`.
+//! Leaves fetched by the tree during `run_vms` change its root after the initial root was taken,
+//! so the witness of a forked run is not consistent: the run is used to collect the pre-state,
+//! and the batch is proven with the pre-state loaded.
+
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use crate::blake2::Blake2s256;
+use crate::ethereum_types::{Address, U256};
+use crate::state_dump::{
+    insert_enumerated_leaf, StateDump, StorageSlot, ROLLUP_SHARD_ID, STATE_DUMP_MAGIC,
+};
+use crate::witness::tree::{
+    BinarySparseStorageTree, EnumeratedBinaryLeaf, LeafQuery, ZkSyncStorageLeaf,
+};
+use crate::zk_evm::abstractions::*;
+use crate::zk_evm::aux_structures::*;
+use crate::zk_evm::reference_impls::decommitter::SimpleDecommitter;
+use crate::zk_evm::testing::storage::InMemoryStorage;
+use crate::zk_evm::zkevm_opcode_defs::system_params::STORAGE_AUX_BYTE;
+use crate::zk_evm::zkevm_opcode_defs::{
+    ContractCodeSha256Format, VersionedHashLen32, VersionedHashNormalizedPreimage,
+};
+use crate::zk_evm::{bytecode_to_code_hash, contract_bytecode_to_words};
+
+/// Source of the state that is not present locally
+pub trait StateProvider: std::fmt::Debug {
+    /// Slot of the rollup shard, `None` if it was never written
+    fn storage_slot(&mut self, address: Address, key: U256) -> Option<StorageSlot>;
+    /// Slot by the index of its leaf in the tree, see `LogQuery::derive_final_address_for_params`
+    fn tree_leaf(&mut self, index: &[u8; 32]) -> Option<StorageSlot>;
+    /// Bytecode by the normalized preimage of its versioned hash
+    fn bytecode(&mut self, hash: VersionedHashNormalizedPreimage) -> Option<Vec<[u8; 32]>>;
+    /// Enumeration index of the next new leaf of the tree
+    fn next_enumeration_index(&mut self) -> u64;
+}
+
+impl<P: StateProvider> StateProvider for Arc<Mutex<P>> {
+    fn storage_slot(&mut self, address: Address, key: U256) -> Option<StorageSlot> {
+        self.lock().unwrap().storage_slot(address, key)
+    }
+    fn tree_leaf(&mut self, index: &[u8; 32]) -> Option<StorageSlot> {
+        self.lock().unwrap().tree_leaf(index)
+    }
+    fn bytecode(&mut self, hash: VersionedHashNormalizedPreimage) -> Option<Vec<[u8; 32]>> {
+        self.lock().unwrap().bytecode(hash)
+    }
+    fn next_enumeration_index(&mut self) -> u64 {
+        self.lock().unwrap().next_enumeration_index()
+    }
+}
+
+fn normalized_hash(bytecode: &[[u8; 32]]) -> Result<VersionedHashNormalizedPreimage, String> {
+    let hash = bytecode_to_code_hash(bytecode)
+        .map_err(|_| format!("Invalid bytecode of {} words", bytecode.len()))?;
+    let (_, normalized) = ContractCodeSha256Format::normalize_for_decommitment(&hash);
+
+    Ok(normalized)
+}
+
+/// Provider backed by a state dump
+#[derive(Debug)]
+pub struct StateDumpProvider {
+    next_enumeration_index: u64,
+    slots: HashMap<(Address, U256), StorageSlot>,
+    leaves: HashMap<[u8; 32], StorageSlot>,
+    bytecodes: HashMap<VersionedHashNormalizedPreimage, Vec<[u8; 32]>>,
+}
+
+impl Default for StateDumpProvider {
+    fn default() -> Self {
+        Self::new(StateDump::default()).unwrap()
+    }
+}
+
+impl StateDumpProvider {
+    pub fn new(dump: StateDump) -> Result<Self, String> {
+        dump.validate()?;
+
+        let mut bytecodes = HashMap::new();
+        for bytecode in dump.bytecodes.into_iter() {
+            bytecodes.insert(normalized_hash(&bytecode)?, bytecode);
+        }
+
+        Ok(Self {
+            next_enumeration_index: dump.next_enumeration_index,
+            slots: dump
+                .storage
+                .iter()
+                .map(|el| ((el.address, el.key), *el))
+                .collect(),
+            leaves: dump
+                .storage
+                .iter()
+                .map(|el| {
+                    (
+                        LogQuery::derive_final_address_for_params(&el.address, &el.key),
+                        *el,
+                    )
+                })
+                .collect(),
+            bytecodes,
+        })
+    }
+
+    /// Reads the dump in the binary format, or in the JSON format if the file doesn't start with the magic
+    pub fn open(path: &Path) -> Result<Self, String> {
+        let content =
+            std::fs::read(path).map_err(|el| format!("Unable to read {:?}: {}", path, el))?;
+        let dump = if content.starts_with(&STATE_DUMP_MAGIC) {
+            StateDump::read(&content[..])?
+        } else {
+            StateDump::from_json(
+                std::str::from_utf8(&content).map_err(|el| format!("{:?}: {}", path, el))?,
+            )?
+        };
+
+        Self::new(dump)
+    }
+}
+
+impl StateProvider for StateDumpProvider {
+    fn storage_slot(&mut self, address: Address, key: U256) -> Option<StorageSlot> {
+        self.slots.get(&(address, key)).copied()
+    }
+    fn tree_leaf(&mut self, index: &[u8; 32]) -> Option<StorageSlot> {
+        self.leaves.get(index).copied()
+    }
+    fn bytecode(&mut self, hash: VersionedHashNormalizedPreimage) -> Option<Vec<[u8; 32]>> {
+        self.bytecodes.get(&hash).cloned()
+    }
+    fn next_enumeration_index(&mut self) -> u64 {
+        self.next_enumeration_index
+    }
+}
+
+/// Provider that records the fetched state
+#[derive(Debug, Default)]
+pub struct RecordingProvider<P: StateProvider> {
+    inner: P,
+    next_enumeration_index: Option<u64>,
+    slots: HashMap<(Address, U256), StorageSlot>,
+    bytecodes: HashMap<VersionedHashNormalizedPreimage, Vec<[u8; 32]>>,
+}
+
+impl<P: StateProvider> RecordingProvider<P> {
+    pub fn new(inner: P) -> Self {
+        Self {
+            inner,
+            next_enumeration_index: None,
+            slots: HashMap::new(),
+            bytecodes: HashMap::new(),
+        }
+    }
+
+    fn record_slot(&mut self, slot: Option<StorageSlot>) -> Option<StorageSlot> {
+        if let Some(slot) = slot {
+            self.slots.insert((slot.address, slot.key), slot);
+        }
+
+        slot
+    }
+
+    /// State fetched so far, enough to replay the same execution without the provider
+    pub fn pre_state(&mut self) -> StateDump {
+        let next_enumeration_index = self.next_enumeration_index();
+        let mut storage: Vec<_> = self.slots.values().copied().collect();
+        storage.sort_by_key(|el| el.enumeration_index);
+        let mut bytecodes: Vec<_> = self.bytecodes.iter().collect();
+        bytecodes.sort_by_key(|(hash, _)| hash.0);
+
+        StateDump {
+            next_enumeration_index,
+            storage,
+            bytecodes: bytecodes.into_iter().map(|(_, el)| el.clone()).collect(),
+        }
+    }
+}
+
+impl<P: StateProvider> StateProvider for RecordingProvider<P> {
+    fn storage_slot(&mut self, address: Address, key: U256) -> Option<StorageSlot> {
+        let slot = self.inner.storage_slot(address, key);
+        self.record_slot(slot)
+    }
+    fn tree_leaf(&mut self, index: &[u8; 32]) -> Option<StorageSlot> {
+        let slot = self.inner.tree_leaf(index);
+        self.record_slot(slot)
+    }
+    fn bytecode(&mut self, hash: VersionedHashNormalizedPreimage) -> Option<Vec<[u8; 32]>> {
+        let bytecode = self.inner.bytecode(hash);
+        if let Some(bytecode) = bytecode.as_ref() {
+            self.bytecodes.insert(hash, bytecode.clone());
+        }
+
+        bytecode
+    }
+    fn next_enumeration_index(&mut self) -> u64 {
+        *self
+            .next_enumeration_index
+            .get_or_insert_with(|| self.inner.next_enumeration_index())
+    }
+}
+
+/// Storage that fetches the slots of the rollup shard on the first access
+#[derive(Debug)]
+pub struct ForkedStorage<P: StateProvider> {
+    pub storage: InMemoryStorage,
+    provider: P,
+    fetched: HashSet<(Address, U256)>,
+}
+
+impl<P: StateProvider> ForkedStorage<P> {
+    /// Slots that are already in the storage are not fetched
+    pub fn new(storage: InMemoryStorage, provider: P) -> Self {
+        Self {
+            storage,
+            provider,
+            fetched: HashSet::new(),
+        }
+    }
+
+    fn fetch(&mut self, address: Address, key: U256) {
+        if !self.fetched.insert((address, key)) {
+            return;
+        }
+        let is_known = self.storage.inner[ROLLUP_SHARD_ID as usize]
+            .get(&address)
+            .is_some_and(|el| el.contains_key(&key));
+        if is_known {
+            return;
+        }
+        if let Some(slot) = self.provider.storage_slot(address, key) {
+            self.storage
+                .populate(vec![(ROLLUP_SHARD_ID, address, key, slot.value)]);
+        }
+    }
+}
+
+impl<P: StateProvider> Storage for ForkedStorage<P> {
+    fn get_access_refund(
+        &mut self,
+        monotonic_cycle_counter: u32,
+        partial_query: &LogQuery,
+    ) -> StorageAccessRefund {
+        self.storage
+            .get_access_refund(monotonic_cycle_counter, partial_query)
+    }
+
+    fn execute_partial_query(
+        &mut self,
+        monotonic_cycle_counter: u32,
+        query: LogQuery,
+    ) -> (LogQuery, PubdataCost) {
+        // writes also return the previous value
+        if query.aux_byte == STORAGE_AUX_BYTE && query.shard_id == ROLLUP_SHARD_ID {
+            self.fetch(query.address, query.key);
+        }
+        self.storage
+            .execute_partial_query(monotonic_cycle_counter, query)
+    }
+
+    fn start_frame(&mut self, timestamp: Timestamp) {
+        self.storage.start_frame(timestamp)
+    }
+
+    fn finish_frame(&mut self, timestamp: Timestamp, panicked: bool) {
+        self.storage.finish_frame(timestamp, panicked)
+    }
+
+    fn start_new_tx(&mut self, timestamp: Timestamp) {
+        self.storage.start_new_tx(timestamp)
+    }
+}
+
+/// Decommitter that fetches the unknown bytecodes
+#[derive(Debug)]
+pub struct ForkedDecommitter<P: StateProvider, const B: bool> {
+    pub decommitter: SimpleDecommitter<B>,
+    provider: P,
+}
+
+impl<P: StateProvider, const B: bool> ForkedDecommitter<P, B> {
+    pub fn new(decommitter: SimpleDecommitter<B>, provider: P) -> Self {
+        Self {
+            decommitter,
+            provider,
+        }
+    }
+
+    fn fetch(&mut self, hash: VersionedHashNormalizedPreimage) -> anyhow::Result<()> {
+        if self.decommitter.get_preimage_by_hash(hash).is_some() {
+            return Ok(());
+        }
+        let Some(bytecode) = self.provider.bytecode(hash) else {
+            // reported by the decommitter
+            return Ok(());
+        };
+        if normalized_hash(&bytecode).map_err(|el| anyhow::anyhow!(el))? != hash {
+            anyhow::bail!(
+                "Provider returned a bytecode with a different hash for {:?}",
+                hash
+            );
+        }
+        let versioned_hash = bytecode_to_code_hash(&bytecode).unwrap();
+        self.decommitter.populate(vec![(
+            U256::from_big_endian(&versioned_hash),
+            contract_bytecode_to_words(&bytecode),
+        )]);
+
+        Ok(())
+    }
+}
+
+impl<P: StateProvider, const B: bool> DecommittmentProcessor for ForkedDecommitter<P, B> {
+    fn prepare_to_decommit(
+        &mut self,
+        monotonic_cycle_counter: u32,
+        partial_query: DecommittmentQuery,
+    ) -> anyhow::Result<DecommittmentQuery> {
+        self.fetch(partial_query.normalized_preimage)?;
+        self.decommitter
+            .prepare_to_decommit(monotonic_cycle_counter, partial_query)
+    }
+
+    fn decommit_into_memory<M: Memory>(
+        &mut self,
+        monotonic_cycle_counter: u32,
+        partial_query: DecommittmentQuery,
+        memory: &mut M,
+    ) -> anyhow::Result<Option<Vec<U256>>> {
+        self.decommitter
+            .decommit_into_memory(monotonic_cycle_counter, partial_query, memory)
+    }
+}
+
+/// Tree that fetches the leaves on the first access, with the enumeration indexes of the provider
+#[derive(Debug)]
+pub struct ForkedTree<T, P: StateProvider> {
+    pub tree: T,
+    provider: P,
+    fetched: HashSet<[u8; 32]>,
+}
+
+impl<
+        T: BinarySparseStorageTree<256, 32, 32, 8, 32, Blake2s256, ZkSyncStorageLeaf>,
+        P: StateProvider,
+    > ForkedTree<T, P>
+{
+    /// The tree should be empty or contain a part of the provider's state
+    pub fn new(mut tree: T, mut provider: P) -> Self {
+        tree.set_next_enumeration_index(provider.next_enumeration_index());
+
+        Self {
+            tree,
+            provider,
+            fetched: HashSet::new(),
+        }
+    }
+
+    fn fetch(&mut self, index: &[u8; 32]) {
+        if !self.fetched.insert(*index) {
+            return;
+        }
+        if self.tree.get_leaf(index).leaf.current_index() != ZkSyncStorageLeaf::empty_index() {
+            return;
+        }
+        if let Some(slot) = self.provider.tree_leaf(index) {
+            let next_enumeration_index = self.tree.next_enumeration_index();
+            insert_enumerated_leaf(&mut self.tree, &slot);
+            self.tree.set_next_enumeration_index(next_enumeration_index);
+        }
+    }
+}
+
+impl<
+        T: BinarySparseStorageTree<256, 32, 32, 8, 32, Blake2s256, ZkSyncStorageLeaf>,
+        P: StateProvider + Default,
+    > BinarySparseStorageTree<256, 32, 32, 8, 32, Blake2s256, ZkSyncStorageLeaf>
+    for ForkedTree<T, P>
+{
+    fn empty() -> Self {
+        Self::new(T::empty(), P::default())
+    }
+    fn next_enumeration_index(&self) -> u64 {
+        self.tree.next_enumeration_index()
+    }
+    fn set_next_enumeration_index(&mut self, value: u64) {
+        self.tree.set_next_enumeration_index(value)
+    }
+    fn root(&self) -> [u8; 32] {
+        self.tree.root()
+    }
+    fn get_leaf(&mut self, index: &[u8; 32]) -> LeafQuery<256, 32, 32, 32, ZkSyncStorageLeaf> {
+        self.fetch(index);
+        self.tree.get_leaf(index)
+    }
+    fn insert_leaf(
+        &mut self,
+        index: &[u8; 32],
+        leaf: ZkSyncStorageLeaf,
+    ) -> LeafQuery<256, 32, 32, 32, ZkSyncStorageLeaf> {
+        self.fetch(index);
+        self.tree.insert_leaf(index, leaf)
+    }
+    /// Works with the leaves fetched so far
+    fn filter_renumerate<'a>(
+        &self,
+        indexes: impl Iterator<Item = &'a [u8; 32]>,
+        leafs: impl Iterator<Item = ZkSyncStorageLeaf>,
+    ) -> (
+        u64,
+        Vec<([u8; 32], ZkSyncStorageLeaf)>,
+        Vec<ZkSyncStorageLeaf>,
+    ) {
+        self.tree.filter_renumerate(indexes, leafs)
+    }
+    fn verify_inclusion(
+        root: &[u8; 32],
+        query: &LeafQuery<256, 32, 32, 32, ZkSyncStorageLeaf>,
+    ) -> bool {
+        T::verify_inclusion(root, query)
+    }
+}
@@ -48,7 +48,7 @@ mod test {
             panic!();
         };
 
-        let config = WrapperConfig::new(1);
-        let _ = wrap_proof(proof, vk, config);
+        let config = WrapperConfig::new(1).unwrap();
+        wrap_proof(proof, vk, config).unwrap();
     }
 }
@@ -0,0 +1,335 @@
+use super::*;
+use crate::boojum::field::Field as _;
+use crate::boojum::gadgets::queue::QueueState;
+use crate::boojum::worker::Worker;
+use crate::compute_setups::compute_leaf_params;
+use crate::prover_utils::mock::*;
+use crate::witness::recursive_aggregation::{
+    compute_node_vk_commitment, create_leaf_witnesses, create_node_witnesses,
+};
+use crate::zkevm_circuits::recursion::leaf_layer::input::RecursionLeafParametersWitness;
+use crate::zkevm_circuits::recursion::recursion_tip::input::*;
+use crate::zkevm_circuits::recursion::recursion_tip::RecursionTipConfig;
+use crate::zkevm_circuits::scheduler::aux::BaseLayerCircuitType;
+use crate::zkevm_circuits::scheduler::SchedulerConfig;
+use circuit_definitions::circuit_definitions::recursion_layer::recursion_tip::RecursionTipCircuit;
+use circuit_definitions::zkevm_circuits::fsm_input_output::circuit_inputs::INPUT_OUTPUT_COMMITMENT_LENGTH;
+use circuit_sequencer_api::NUM_SCHEDULER_PUBLIC_INPUTS;
+
+fn testing_blobs() -> [Option<Vec<u8>>; MAX_4844_BLOBS_PER_BLOCK] {
+    std::array::from_fn(|i| {
+        if i == 0 {
+            Some(vec![0xff; ENCODABLE_BYTES_PER_BLOB])
+        } else {
+            None
+        }
+    })
+}
+
+/// Base layer circuit with a corrupted witness must not get a mock proof.
+#[test]
+fn mock_prover_rejects_unsatisfied_base_layer_circuit() {
+    let test_artifact = read_basic_test_artifact();
+    let geometry = get_testing_geometry_config();
+    let (basic_block_circuits, _, _) =
+        generate_base_layer(test_artifact, 40000, geometry, testing_blobs());
+
+    let circuit = basic_block_circuits
+        .into_iter()
+        .find(|el| match el {
+            ZkSyncBaseLayerCircuit::RAMPermutation(inner) => inner
+                .clone_witness()
+                .map(|witness| !witness.sorted_queue_witness.elements.is_empty())
+                .unwrap_or(false),
+            _ => false,
+        })
+        .expect("test artifact must produce a non-empty RAM permutation circuit");
+
+    let ZkSyncBaseLayerCircuit::RAMPermutation(inner) = &circuit else {
+        unreachable!()
+    };
+    let mut witness = inner.witness.take().unwrap();
+    let (query, _) = witness.sorted_queue_witness.elements.front_mut().unwrap();
+    query.value = !query.value;
+    inner.witness.store(Some(witness));
+
+    let worker = Worker::new_with_num_threads(8);
+    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        mock_prove_base_layer_circuit(circuit, &worker, base_layer_proof_config())
+    }));
+    assert!(
+        result.is_err(),
+        "mock prover must not prove a circuit with a corrupted witness"
+    );
+}
+
+/// Runs base -> leaf -> node -> recursion tip -> scheduler -> compression with mock proofs.
+/// Verification keys are taken from the committed setup, so the production geometry is used.
+#[test]
+fn mock_prover_full_chain() {
+    let test_artifact = read_basic_test_artifact();
+    let blobs = testing_blobs();
+    let geometry = crate::geometry_config::ProtocolGeometry::latest().config();
+
+    let (basic_block_circuits, mut recursion_queues, scheduler_partial_input) =
+        generate_base_layer(test_artifact, 40000, geometry, blobs.clone());
+    recursion_queues.sort_by_key(|(circuit, _, _)| circuit.clone());
+
+    let worker = Worker::new_with_num_threads(8);
+    let mut setup_source = LocalFileDataSource::default();
+    let mut source = InMemoryDataSource::new();
+
+    let mut instances = HashMap::new();
+    for el in basic_block_circuits.into_iter() {
+        let circuit_type = el.numeric_circuit_type();
+        let proof = mock_prove_base_layer_circuit(el.clone(), &worker, base_layer_proof_config());
+        let vk = setup_source.get_base_layer_vk(circuit_type).unwrap();
+        assert!(verify_base_layer_proof::<NoPow>(
+            &el,
+            &proof,
+            &vk.into_inner()
+        ));
+
+        let instance_idx = instances.entry(circuit_type).or_insert(0);
+        source
+            .set_base_layer_proof(
+                *instance_idx,
+                ZkSyncBaseLayerProof::from_inner(circuit_type, proof),
+            )
+            .unwrap();
+        *instance_idx += 1;
+    }
+
+    let leaf_vk_commits = compute_leaf_params(&mut setup_source).unwrap();
+
+    let mut all_leaf_aggregations = vec![];
+    for subset in recursion_queues.clone().into_iter() {
+        let circuit_type = subset.0 as u8;
+        let proofs = (0..subset.1.num_items as usize)
+            .map(|idx| source.get_base_layer_proof(circuit_type, idx).unwrap())
+            .collect();
+        let vk = setup_source.get_base_layer_vk(circuit_type).unwrap();
+        let param = leaf_vk_commits
+            .iter()
+            .find(|el| el.0 == circuit_type)
+            .cloned()
+            .unwrap();
+
+        let (aggregations, recursive_circuits, _) =
+            create_leaf_witnesses(subset, proofs, vk, param);
+        for (idx, el) in recursive_circuits.into_iter().enumerate() {
+            let proof =
+                mock_prove_recursion_layer_circuit(el.clone(), recursion_layer_proof_config());
+            source
+                .set_leaf_layer_proof(
+                    idx,
+                    ZkSyncRecursionLayerProof::from_inner(el.numeric_circuit_type(), proof),
+                )
+                .unwrap();
+        }
+        all_leaf_aggregations.push(aggregations);
+    }
+
+    let node_vk = setup_source.get_recursion_layer_node_vk().unwrap();
+    let node_vk_commitment = compute_node_vk_commitment(node_vk.clone());
+
+    let mut top_node_proofs = HashMap::new();
+    for aggregations in all_leaf_aggregations.into_iter() {
+        if aggregations.is_empty() {
+            continue;
+        }
+
+        let recursive_circuit_type = base_circuit_type_into_recursive_leaf_circuit_type(
+            BaseLayerCircuitType::from_numeric_value(aggregations[0].0 as u8),
+        ) as u8;
+        let mut vk = setup_source
+            .get_recursion_layer_vk(recursive_circuit_type)
+            .unwrap();
+        let mut next_aggregations = aggregations;
+        let mut depth = 0;
+        loop {
+            let proofs = (0..next_aggregations.len())
+                .map(|idx| {
+                    let proof = if depth == 0 {
+                        source.get_leaf_layer_proof(recursive_circuit_type, idx)
+                    } else {
+                        source.get_node_layer_proof(recursive_circuit_type, depth - 1, idx)
+                    };
+                    proof.unwrap()
+                })
+                .collect();
+            let (new_aggregations, recursive_circuits) = create_node_witnesses(
+                next_aggregations,
+                proofs,
+                vk,
+                node_vk_commitment,
+                &leaf_vk_commits,
+            );
+            for (idx, el) in recursive_circuits.into_iter().enumerate() {
+                let proof =
+                    mock_prove_recursion_layer_circuit(el.clone(), recursion_layer_proof_config());
+                assert!(verify_recursion_layer_proof::<NoPow>(
+                    &el,
+                    &proof,
+                    &node_vk.clone().into_inner()
+                ));
+                source
+                    .set_node_layer_proof(
+                        recursive_circuit_type,
+                        depth,
+                        idx,
+                        ZkSyncRecursionLayerProof::NodeLayerCircuit(proof),
+                    )
+                    .unwrap();
+            }
+
+            next_aggregations = new_aggregations;
+            vk = node_vk.clone();
+            if next_aggregations.len() == 1 {
+                break;
+            }
+            depth += 1;
+        }
+        let proof = source
+            .get_node_layer_proof(recursive_circuit_type, depth, 0)
+            .unwrap();
+        top_node_proofs.insert(recursive_circuit_type, proof);
+    }
+
+    let recursion_tip_proofs: Vec<_> = ((ZkSyncRecursionLayerStorageType::LeafLayerCircuitForMainVM
+        as u8)
+        ..=(ZkSyncRecursionLayerStorageType::LeafLayerCircuitForEIP4844Repack as u8))
+        .map(|recursive_circuit_type| {
+            match top_node_proofs.remove(&recursive_circuit_type) {
+                Some(proof) => proof.into_inner(),
+                // not used by the recursion tip, as the queue for this type is empty
+                None => mock_proof(
+                    vec![GoldilocksField::ZERO; INPUT_OUTPUT_COMMITMENT_LENGTH],
+                    recursion_layer_proof_config(),
+                ),
+            }
+        })
+        .collect();
+    assert_eq!(recursion_tip_proofs.len(), NUM_CIRCUIT_TYPES_TO_SCHEDULE);
+
+    let leaf_layer_params: [RecursionLeafParametersWitness<GoldilocksField>; 16] = leaf_vk_commits
+        .iter()
+        .map(|el| el.1.clone())
+        .collect::<Vec<_>>()
+        .try_into()
+        .unwrap();
+
+    let mut branch_circuit_type_set = [GoldilocksField::ZERO; RECURSION_TIP_ARITY];
+    let mut queue_sets: [_; RECURSION_TIP_ARITY] =
+        std::array::from_fn(|_| QueueState::placeholder_witness());
+    for ((circuit_type, queue_state), (src_type, src_queue, _)) in branch_circuit_type_set
+        .iter_mut()
+        .zip(queue_sets.iter_mut())
+        .zip(recursion_queues.iter())
+    {
+        *circuit_type = GoldilocksField::from_u64_unchecked(*src_type);
+        *queue_state = take_sponge_like_queue_state_from_simulator(src_queue);
+    }
+
+    let witness = RecursionTipInstanceWitness {
+        input: RecursionTipInputWitness {
+            leaf_layer_parameters: leaf_layer_params.clone(),
+            node_layer_vk_commitment: node_vk_commitment,
+            branch_circuit_type_set,
+            queue_set: queue_sets,
+        },
+        vk_witness: node_vk.clone().into_inner(),
+        proof_witnesses: recursion_tip_proofs.into(),
+    };
+    let circuit = ZkSyncRecursiveLayerCircuit::RecursionTipCircuit(RecursionTipCircuit {
+        witness,
+        config: RecursionTipConfig {
+            proof_config: recursion_layer_proof_config(),
+            vk_fixed_parameters: node_vk.clone().into_inner().fixed_parameters,
+            _marker: std::marker::PhantomData,
+        },
+        transcript_params: (),
+        _marker: std::marker::PhantomData,
+    });
+    let tip_proof = mock_prove_recursion_layer_circuit(circuit, recursion_layer_proof_config());
+    source
+        .set_recursive_tip_proof(ZkSyncRecursionLayerProof::RecursionTipCircuit(
+            tip_proof.clone(),
+        ))
+        .unwrap();
+
+    let recursion_tip_vk = setup_source.get_recursion_tip_vk().unwrap().into_inner();
+    let config = SchedulerConfig {
+        proof_config: recursion_layer_proof_config(),
+        leaf_layer_parameters: leaf_layer_params,
+        node_layer_vk: node_vk.into_inner(),
+        recursion_tip_vk: recursion_tip_vk.clone(),
+        vk_fixed_parameters: recursion_tip_vk.fixed_parameters,
+        capacity: SCHEDULER_CAPACITY,
+        _marker: std::marker::PhantomData,
+    };
+
+    let mut scheduler_witness = scheduler_partial_input;
+    scheduler_witness.proof_witnesses = vec![tip_proof].into();
+    scheduler_witness.eip4844_witnesses = blobs.map(|blob| {
+        blob.map(|blob| {
+            let (_blob_arr, linear_hash, _versioned_hash, output_hash) =
+                generate_eip4844_witness::<GoldilocksField>(&blob, "../kzg/src/trusted_setup.json");
+            EIP4844OutputDataWitness {
+                linear_hash,
+                output_hash,
+            }
+        })
+    });
+
+    let scheduler_circuit = ZkSyncRecursiveLayerCircuit::SchedulerCircuit(SchedulerCircuit {
+        witness: scheduler_witness,
+        config,
+        transcript_params: (),
+        _marker: std::marker::PhantomData,
+    });
+    let scheduler_proof =
+        mock_prove_recursion_layer_circuit(scheduler_circuit, recursion_layer_proof_config());
+    assert_eq!(
+        scheduler_proof.public_inputs.len(),
+        NUM_SCHEDULER_PUBLIC_INPUTS
+    );
+    // chunks of the keccak of the block data, can't be all zeroes
+    assert!(scheduler_proof
+        .public_inputs
+        .iter()
+        .any(|el| el.as_u64_reduced() != 0));
+    source
+        .set_scheduler_proof(ZkSyncRecursionLayerProof::SchedulerCircuit(
+            scheduler_proof.clone(),
+        ))
+        .unwrap();
+
+    let scheduler_vk = setup_source
+        .get_recursion_layer_vk(ZkSyncRecursionLayerStorageType::SchedulerCircuit as u8)
+        .unwrap()
+        .into_inner();
+    let proof = source.get_scheduler_proof().unwrap().into_inner();
+
+    let compression_circuit = ZkSyncCompressionLayerCircuit::from_witness_and_vk(
+        Some(proof.clone()),
+        scheduler_vk.clone(),
+        1,
+    );
+    let compression_proof = mock_prove_compression_layer_circuit(compression_circuit);
+    assert!(is_mock_proof(&compression_proof));
+    assert_eq!(
+        compression_proof.public_inputs,
+        scheduler_proof.public_inputs
+    );
+
+    let compression_for_wrapper_circuit =
+        ZkSyncCompressionForWrapperCircuit::from_witness_and_vk(Some(proof), scheduler_vk, 1);
+    let compression_for_wrapper_proof =
+        mock_prove_compression_for_wrapper_circuit(compression_for_wrapper_circuit);
+    assert!(is_mock_proof(&compression_for_wrapper_proof));
+    assert_eq!(
+        compression_for_wrapper_proof.public_inputs,
+        scheduler_proof.public_inputs
+    );
+}
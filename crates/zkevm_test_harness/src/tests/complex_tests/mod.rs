@@ -8,6 +8,8 @@ mod test_synthesis;
 pub mod testing_wrapper;
 #[cfg(test)]
 mod wrapper_negative_tests;
+#[cfg(all(test, feature = "mock_prover"))]
+mod mock_chain;
 
 use std::collections::{HashMap, HashSet, VecDeque};
 use std::sync::mpsc::sync_channel;
@@ -97,7 +99,7 @@ fn test_single_compression() {
 fn test_compression_all_modes() {
     for compression in 1..=WrapperConfig::MAX_COMPRESSION_LAYERS {
         println!("Testing wrapper for mode {}", compression);
-        let config = WrapperConfig::new(compression as u8);
+        let config = WrapperConfig::new(compression).unwrap();
         testing_wrapper::test_compression_for_compression_num(config);
     }
 }
@@ -635,9 +637,10 @@ fn run_and_try_create_witness_inner(
     use crate::compute_setups::compute_leaf_params;
     use crate::zkevm_circuits::scheduler::aux::BaseLayerCircuitType;
     let leaf_vk_commits = compute_leaf_params(&mut source).unwrap();
+    let arity = source.get_recursion_tree_arity().unwrap();
 
     let mut all_leaf_aggregations = vec![];
-    use crate::witness::recursive_aggregation::create_leaf_witnesses;
+    use crate::witness::recursive_aggregation::create_leaf_witnesses_for_arity;
 
     println!("Creating leaf aggregation circuits");
 
@@ -659,7 +662,7 @@ fn run_and_try_create_witness_inner(
             .unwrap();
 
         let (aggregations, recursive_circuits, _closed_form_inputs) =
-            create_leaf_witnesses(subset, proofs, vk, param);
+            create_leaf_witnesses_for_arity(subset, proofs, vk, param, arity);
         all_leaf_aggregations.push((aggregations, recursive_circuits));
         all_closed_form_inputs_for_scheduler.extend(_closed_form_inputs);
     }
@@ -940,7 +943,7 @@ fn run_and_try_create_witness_inner(
         let recursive_circuit_type =
             base_circuit_type_into_recursive_leaf_circuit_type(circuit_type_enum);
 
-        use crate::witness::recursive_aggregation::create_node_witnesses;
+        use crate::witness::recursive_aggregation::create_node_witnesses_for_arity;
         let vk = if depth == 0 {
             source
                 .get_recursion_layer_vk(recursive_circuit_type as u8)
@@ -967,12 +970,13 @@ fn run_and_try_create_witness_inner(
 
                 proofs.push(proof);
             }
-            let (new_aggregations, recursive_circuits) = create_node_witnesses(
+            let (new_aggregations, recursive_circuits) = create_node_witnesses_for_arity(
                 next_aggregations,
                 proofs,
                 vk.clone(),
                 node_vk_commitment,
                 &leaf_vk_commits,
+                arity,
             );
             next_aggregations = new_aggregations;
 
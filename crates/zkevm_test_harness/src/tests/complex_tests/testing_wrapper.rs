@@ -3,7 +3,10 @@ use super::*;
 use crate::proof_wrapper_utils::WrapperConfig;
 use crate::proof_wrapper_utils::{
     compress_stark_pi_to_snark_pi, compute_compression_circuits,
-    compute_compression_for_wrapper_circuit, compute_wrapper_proof_and_vk,
+    compute_compression_for_wrapper_circuit, compute_wrapper_proof_and_vk, print_wrap_progress,
+};
+use circuit_definitions::circuit_definitions::aux_layer::{
+    ZkSyncFflonkSnarkWrapperProof, ZkSyncFflonkSnarkWrapperSetup, ZkSyncFflonkSnarkWrapperVK,
 };
 
 use snark_wrapper::franklin_crypto::bellman::worker::Worker as BellmanWorker;
@@ -28,9 +31,16 @@ pub(crate) fn test_compression_for_compression_num(config: WrapperConfig) {
         )
         .unwrap();
 
-    compute_compression_circuits(&mut source, config, &worker);
-    compute_compression_for_wrapper_circuit(&mut source, config, &worker);
-    compute_wrapper_proof_and_vk(&mut source, config, &bellman_worker);
+    compute_compression_circuits(&mut source, config, &worker, &mut print_wrap_progress).unwrap();
+    compute_compression_for_wrapper_circuit(&mut source, config, &worker, &mut print_wrap_progress)
+        .unwrap();
+    compute_wrapper_proof_and_vk(
+        &mut source,
+        config,
+        &bellman_worker,
+        &mut print_wrap_progress,
+    )
+    .unwrap();
 
     // Write wrapper proof and vk
     let wrapper_type = config.get_wrapper_type();
@@ -63,6 +73,44 @@ pub(crate) fn test_wrapper_pi_inner<DS: SetupDataSource + BlockDataSource>(
 
     let expected_wrapper_pi = compress_stark_pi_to_snark_pi(scheduler_pi);
     assert_eq!(expected_wrapper_pi, wrapper_pi);
+
+    // sequencer side verifier works with the serialized proof and vk only
+    use crate::snark_wrapper::franklin_crypto::bellman::Field;
+    use circuit_sequencer_api::proof::{FinalProof, FinalProofVerificationKey, ProofVerifier};
+    let wrapper_vk = source
+        .get_wrapper_vk(wrapper_type)
+        .expect("wrapper vk should be present")
+        .into_inner();
+    let final_proof: FinalProof =
+        serde_json::from_str(&serde_json::to_string(&wrapper_proof).unwrap()).unwrap();
+    let final_vk: FinalProofVerificationKey =
+        serde_json::from_str(&serde_json::to_string(&wrapper_vk).unwrap()).unwrap();
+
+    let verifier = ProofVerifier::new(final_vk);
+    verifier.verify(&final_proof, expected_wrapper_pi).unwrap();
+
+    let mut wrong_pi = expected_wrapper_pi;
+    wrong_pi.double();
+    assert!(verifier.verify(&final_proof, wrong_pi).is_err());
+}
+
+#[test]
+fn test_final_proof_circuit_declares_wrapper_gates() {
+    use crate::snark_wrapper::franklin_crypto::bellman::pairing::bn256::Bn256;
+    use crate::snark_wrapper::franklin_crypto::bellman::plonk::better_better_cs::cs::{
+        Circuit, GateInternal,
+    };
+    use circuit_definitions::circuit_definitions::aux_layer::ZkSyncSnarkWrapperCircuit;
+    use circuit_sequencer_api::proof::GenericCircuit;
+
+    fn gate_names(gates: Vec<Box<dyn GateInternal<Bn256>>>) -> Vec<&'static str> {
+        gates.iter().map(|gate| gate.name()).collect()
+    }
+
+    assert_eq!(
+        gate_names(GenericCircuit::declare_used_gates().unwrap()),
+        gate_names(ZkSyncSnarkWrapperCircuit::declare_used_gates().unwrap())
+    );
 }
 
 #[ignore = "broken test"]
@@ -103,7 +151,19 @@ fn test_pi_aggregation_function() {
         pi.push(lc);
     }
 
+    // sequencer side computation must agree with the circuit one
+    let sequencer_pi =
+        circuit_sequencer_api::public_input::scheduler_public_inputs(input_keccak_hash);
+    assert_eq!(
+        sequencer_pi.to_vec(),
+        pi.iter().map(|el| el.as_u64_reduced()).collect::<Vec<_>>()
+    );
+
     let wrapper_pi = compress_stark_pi_to_snark_pi(pi.try_into().unwrap());
+    assert_eq!(
+        wrapper_pi,
+        circuit_sequencer_api::public_input::compress_stark_pi_to_snark_pi(sequencer_pi)
+    );
 
     println!("{:x?}", input_keccak_hash);
     println!("{:?}", wrapper_pi);
@@ -122,17 +182,339 @@ fn test_wrapper_vk_generation() {
         .unwrap();
 
     use crate::proof_wrapper_utils::get_wrapper_setup_and_vk_from_scheduler_vk;
-    let (_, wrapper_vk) = get_wrapper_setup_and_vk_from_scheduler_vk(scheduler_vk, config);
+    let (_, wrapper_vk) = get_wrapper_setup_and_vk_from_scheduler_vk(scheduler_vk, config).unwrap();
 
     source.set_wrapper_vk(wrapper_vk).unwrap();
 }
 
+/// Saves FFLONK wrapper setup, vk and proof to the source and checks that they are read back unchanged
+/// and don't take the place of the PLONK wrapper ones.
+fn check_fflonk_wrapper_data_round_trip<DS: SetupDataSource + BlockDataSource>(
+    source: &mut DS,
+    setup: ZkSyncFflonkSnarkWrapperSetup,
+    vk: ZkSyncFflonkSnarkWrapperVK,
+    proof: ZkSyncFflonkSnarkWrapperProof,
+) {
+    let wrapper_type = vk.numeric_circuit_type();
+    assert!(source.get_fflonk_wrapper_setup(wrapper_type).is_err());
+    assert!(source.get_fflonk_wrapper_vk(wrapper_type).is_err());
+    assert!(source.get_fflonk_wrapper_proof(wrapper_type).is_err());
+
+    source.set_fflonk_wrapper_setup(setup.clone()).unwrap();
+    source.set_fflonk_wrapper_vk(vk.clone()).unwrap();
+    source.set_fflonk_wrapper_proof(proof.clone()).unwrap();
+
+    let stored_setup = source.get_fflonk_wrapper_setup(wrapper_type).unwrap();
+    assert_eq!(stored_setup.numeric_circuit_type(), wrapper_type);
+    let mut stored_setup_bytes = vec![];
+    stored_setup
+        .into_inner()
+        .write(&mut stored_setup_bytes)
+        .unwrap();
+    let mut setup_bytes = vec![];
+    setup.into_inner().write(&mut setup_bytes).unwrap();
+    assert_eq!(stored_setup_bytes, setup_bytes);
+    assert_eq!(
+        serde_json::to_string(&source.get_fflonk_wrapper_vk(wrapper_type).unwrap()).unwrap(),
+        serde_json::to_string(&vk).unwrap()
+    );
+    assert_eq!(
+        serde_json::to_string(&source.get_fflonk_wrapper_proof(wrapper_type).unwrap()).unwrap(),
+        serde_json::to_string(&proof).unwrap()
+    );
+
+    assert!(source.get_wrapper_vk(wrapper_type).is_err());
+    assert!(source.get_wrapper_proof(wrapper_type).is_err());
+}
+
+#[test]
+fn test_fflonk_wrapper_data_is_separate() {
+    const WRAPPER_TYPE: u8 = 1;
+
+    // only the PLONK wrapper is committed
+    let source = LocalFileDataSource::default();
+    assert!(source.get_wrapper_vk(WRAPPER_TYPE).is_ok());
+    assert!(source.get_wrapper_proof(WRAPPER_TYPE).is_ok());
+    assert!(source.get_fflonk_wrapper_vk(WRAPPER_TYPE).is_err());
+    assert!(source.get_fflonk_wrapper_proof(WRAPPER_TYPE).is_err());
+
+    let mut in_memory_source = InMemoryDataSource::new();
+    in_memory_source
+        .set_wrapper_vk(source.get_wrapper_vk(WRAPPER_TYPE).unwrap())
+        .unwrap();
+    in_memory_source
+        .set_wrapper_proof(source.get_wrapper_proof(WRAPPER_TYPE).unwrap())
+        .unwrap();
+    assert!(in_memory_source
+        .get_fflonk_wrapper_vk(WRAPPER_TYPE)
+        .is_err());
+    assert!(in_memory_source
+        .get_fflonk_wrapper_proof(WRAPPER_TYPE)
+        .is_err());
+}
+
+/// Storage of the FFLONK wrapper artifacts, checked on the synthetic ones to not run the prover
+#[test]
+fn test_fflonk_wrapper_data_round_trip() {
+    use circuit_definitions::circuit_definitions::aux_layer::{
+        FflonkSnarkVerifierCircuitProof, FflonkSnarkVerifierCircuitSetup,
+        FflonkSnarkVerifierCircuitVK, ZkSyncFflonkWrapperStorage,
+    };
+    use snark_wrapper::franklin_crypto::bellman::pairing::bn256::{Fr, G1Affine, G2Affine};
+    use snark_wrapper::franklin_crypto::bellman::pairing::CurveAffine;
+    use snark_wrapper::franklin_crypto::bellman::plonk::better_better_cs::setup::Setup as SnarkSetup;
+    use snark_wrapper::franklin_crypto::bellman::PrimeField;
+
+    const WRAPPER_TYPE: u8 = 5;
+
+    let setup = FflonkSnarkVerifierCircuitSetup {
+        original_setup: SnarkSetup::empty(),
+        c0_commitment: G1Affine::one(),
+    };
+    let vk = FflonkSnarkVerifierCircuitVK::new(
+        (1 << 10) - 1,
+        G1Affine::one(),
+        1,
+        3,
+        0,
+        0,
+        [G2Affine::one(); 2],
+    );
+    let mut proof = FflonkSnarkVerifierCircuitProof::empty();
+    proof.n = vk.n;
+    proof.inputs = vec![Fr::from_str("42").unwrap()];
+    proof.commitments = vec![G1Affine::one(); 4];
+    proof.evaluations = vec![Fr::from_str("7").unwrap(); 3];
+
+    let setup = ZkSyncFflonkWrapperStorage::from_inner(WRAPPER_TYPE, std::sync::Arc::new(setup));
+    let vk = ZkSyncFflonkWrapperStorage::from_inner(WRAPPER_TYPE, vk);
+    let proof = ZkSyncFflonkWrapperStorage::from_inner(WRAPPER_TYPE, proof);
+
+    check_fflonk_wrapper_data_round_trip(
+        &mut InMemoryDataSource::new(),
+        setup.clone(),
+        vk.clone(),
+        proof.clone(),
+    );
+
+    let location = std::env::temp_dir().join(format!("fflonk_wrapper_data_{}", std::process::id()));
+    let mut file_source = LocalFileDataSource {
+        setup_data_location: location.to_str().unwrap().to_string(),
+        block_data_location: location.to_str().unwrap().to_string(),
+    };
+    file_source.create_folders_for_storing_data();
+    check_fflonk_wrapper_data_round_trip(&mut file_source, setup, vk, proof);
+    std::fs::remove_dir_all(location).unwrap();
+}
+
+#[ignore = "too slow"]
+#[test]
+fn test_fflonk_wrapper() {
+    use crate::proof_wrapper_utils::{
+        get_fflonk_wrapper_setup_and_vk_from_scheduler_vk, wrap_proof_fflonk_with_progress,
+        WrapperMode,
+    };
+
+    let config = get_testing_wrapper_config().with_mode(WrapperMode::Fflonk);
+
+    let source = LocalFileDataSource::default();
+    let scheduler_proof = source.get_scheduler_proof().unwrap();
+    let scheduler_vk = source
+        .get_recursion_layer_vk(ZkSyncRecursionLayerStorageType::SchedulerCircuit as u8)
+        .unwrap();
+
+    let (wrapper_proof, wrapper_vk) = wrap_proof_fflonk_with_progress(
+        scheduler_proof.clone(),
+        scheduler_vk.clone(),
+        config,
+        &mut print_wrap_progress,
+    )
+    .unwrap();
+
+    // public input is the same as for the PLONK wrapper
+    let scheduler_pi = scheduler_proof
+        .into_inner()
+        .public_inputs
+        .try_into()
+        .unwrap();
+    assert_eq!(
+        wrapper_proof.clone().into_inner().inputs,
+        vec![compress_stark_pi_to_snark_pi(scheduler_pi)]
+    );
+
+    // vk doesn't depend on the proof
+    let (wrapper_setup, vk_from_scheduler_vk) =
+        get_fflonk_wrapper_setup_and_vk_from_scheduler_vk(scheduler_vk, config).unwrap();
+    assert_eq!(
+        serde_json::to_string(&vk_from_scheduler_vk).unwrap(),
+        serde_json::to_string(&wrapper_vk).unwrap()
+    );
+
+    check_fflonk_wrapper_data_round_trip(
+        &mut InMemoryDataSource::new(),
+        wrapper_setup.clone(),
+        wrapper_vk.clone(),
+        wrapper_proof.clone(),
+    );
+
+    let location = std::env::temp_dir().join(format!("fflonk_wrapper_{}", std::process::id()));
+    let mut file_source = LocalFileDataSource {
+        setup_data_location: location.to_str().unwrap().to_string(),
+        block_data_location: location.to_str().unwrap().to_string(),
+    };
+    file_source.create_folders_for_storing_data();
+    check_fflonk_wrapper_data_round_trip(
+        &mut file_source,
+        wrapper_setup,
+        wrapper_vk,
+        wrapper_proof,
+    );
+    std::fs::remove_dir_all(location).unwrap();
+}
+
+/// Checks that the L1 verifier contract and calldata generated for the stored wrapper vk and proof
+/// didn't change (or saves them if UPDATE_TESTDATA is present in environment).
+#[test]
+fn test_l1_verifier_golden_files() {
+    use crate::proof_wrapper_utils::{
+        generate_l1_verifier_contract, l1_verifier_vk_words, L1VerifierCalldata,
+        L1_VERIFIER_VK_LENGTH,
+    };
+    use crate::snark_wrapper::franklin_crypto::bellman::pairing::bn256::Fr;
+    use crate::snark_wrapper::franklin_crypto::bellman::plonk::better_better_cs::verifier::verify;
+    use crate::snark_wrapper::franklin_crypto::bellman::plonk::commitments::transcript::keccak_transcript::RollingKeccakTranscript;
+
+    const TESTDATA_LOCATION: &str = "src/proof_wrapper_utils/testdata/l1_verifier";
+    const WRAPPER_TYPE: u8 = 1;
+
+    let source = LocalFileDataSource::default();
+    let wrapper_proof = source.get_wrapper_proof(WRAPPER_TYPE).unwrap().into_inner();
+    let wrapper_vk = source.get_wrapper_vk(WRAPPER_TYPE).unwrap().into_inner();
+
+    // the constants are generated for the same vk the proof verifies against
+    assert!(
+        verify::<_, _, RollingKeccakTranscript<Fr>>(&wrapper_vk, &wrapper_proof, None).unwrap()
+    );
+    assert_eq!(
+        l1_verifier_vk_words(&wrapper_vk).len(),
+        L1_VERIFIER_VK_LENGTH
+    );
+
+    let calldata = L1VerifierCalldata::from_proof(&wrapper_proof);
+    assert_eq!(calldata.public_inputs.len(), 1);
+    let encoded_calldata = calldata.encode();
+    assert_eq!(
+        L1VerifierCalldata::decode(&encoded_calldata),
+        Some(calldata)
+    );
+
+    // the generated contract inherits from `Verifier`, so it can't have the same name
+    assert!(generate_l1_verifier_contract(&wrapper_vk, "Verifier").is_err());
+    let contract = generate_l1_verifier_contract(&wrapper_vk, "TestVerifier").unwrap();
+    let contract_path = format!("{}/verifier_{}.sol", TESTDATA_LOCATION, WRAPPER_TYPE);
+    let calldata_path = format!("{}/calldata_{}.hex", TESTDATA_LOCATION, WRAPPER_TYPE);
+    let encoded_calldata = hex::encode(encoded_calldata);
+
+    if std::env::var("UPDATE_TESTDATA").is_ok() {
+        std::fs::create_dir_all(TESTDATA_LOCATION).unwrap();
+        std::fs::write(&contract_path, contract).unwrap();
+        std::fs::write(&calldata_path, encoded_calldata).unwrap();
+    } else {
+        assert!(
+            std::fs::read_to_string(&contract_path).unwrap() == contract,
+            "Verifier contracts differ. Run with UPDATE_TESTDATA env variable to see the details."
+        );
+        assert!(
+            std::fs::read_to_string(&calldata_path).unwrap() == encoded_calldata,
+            "Calldata differ. Run with UPDATE_TESTDATA env variable to see the details."
+        );
+    }
+}
+
+#[ignore = "too slow"]
+#[test]
+fn test_interblock_aggregation_and_wrapping() {
+    use crate::compute_setups::generate_interblock_aggregation_setup_data;
+    use crate::proof_wrapper_utils::{
+        aggregate_scheduler_proofs, wrap_interblock_aggregation_proof,
+    };
+    use circuit_definitions::boojum::field::PrimeField;
+    use circuit_definitions::circuit_definitions::recursion_layer::interblock::{
+        INTERBLOCK_AGGREGATION_CAPACITY, INTERBLOCK_AGGREGATION_MASKING_VALUE,
+    };
+
+    let config = get_testing_wrapper_config();
+    let worker = Worker::new();
+
+    let mut source = LocalFileDataSource::default();
+    source.create_folders_for_storing_data();
+
+    let scheduler_proof = source.get_scheduler_proof().unwrap();
+    let scheduler_vk = source
+        .get_recursion_layer_vk(ZkSyncRecursionLayerStorageType::SchedulerCircuit as u8)
+        .unwrap();
+    let setup_data = generate_interblock_aggregation_setup_data(&mut source).unwrap();
+
+    // the same batch twice is enough to exercise the aggregation
+    let (aggregated_proof, aggregated_vk) = aggregate_scheduler_proofs(
+        vec![scheduler_proof.clone(), scheduler_proof.clone()],
+        scheduler_vk,
+        &setup_data,
+        &worker,
+    )
+    .unwrap();
+
+    let scheduler_pi: [u64; 4] = scheduler_proof
+        .into_inner()
+        .public_inputs
+        .iter()
+        .map(|el| el.as_u64_reduced())
+        .collect::<Vec<_>>()
+        .try_into()
+        .unwrap();
+    let aggregated_pi = circuit_sequencer_api::public_input::interblock_aggregated_public_inputs(
+        &[scheduler_pi, scheduler_pi],
+        INTERBLOCK_AGGREGATION_CAPACITY,
+        INTERBLOCK_AGGREGATION_MASKING_VALUE,
+    );
+
+    // stored under its own key, so it doesn't replace the scheduler proof
+    source
+        .set_interblock_aggregation_proof(aggregated_proof.clone())
+        .unwrap();
+    assert_eq!(
+        source
+            .get_interblock_aggregation_proof()
+            .unwrap()
+            .public_inputs,
+        aggregated_proof.public_inputs
+    );
+    assert_ne!(
+        source
+            .get_scheduler_proof()
+            .unwrap()
+            .into_inner()
+            .public_inputs,
+        aggregated_proof.public_inputs
+    );
+
+    let (wrapper_proof, _) =
+        wrap_interblock_aggregation_proof(aggregated_proof, aggregated_vk, config).unwrap();
+    let wrapper_proof = wrapper_proof.into_inner();
+    assert_eq!(wrapper_proof.inputs.len(), 1);
+    assert_eq!(
+        wrapper_proof.inputs[0],
+        circuit_sequencer_api::public_input::compress_stark_pi_to_snark_pi(aggregated_pi)
+    );
+}
+
 pub(crate) fn get_testing_wrapper_config() -> WrapperConfig {
     let compression =
         std::env::var("COMPRESSION_NUM").map(|s| s.parse::<usize>().expect("should be a number"));
 
     if let Ok(compression) = compression {
         WrapperConfig::new(compression as u8)
+            .expect("COMPRESSION_NUM should be a valid layers count")
     } else {
         DEFAULT_WRAPPER_CONFIG
     }
@@ -6,7 +6,8 @@ use crate::data_source::{
 };
 use crate::proof_wrapper_utils::{
     compute_compression_circuits, compute_compression_for_wrapper_circuit,
-    compute_compression_for_wrapper_vk_and_write, compute_compression_vks_and_write, WrapperConfig,
+    compute_compression_for_wrapper_vk_and_write, compute_compression_vks_and_write,
+    print_wrap_progress, WrapperConfig,
 };
 use crate::snark_wrapper::franklin_crypto::bellman::Field as BellmanField;
 use crate::tests::complex_tests::testing_wrapper::get_testing_wrapper_config;
@@ -27,9 +28,9 @@ fn get_compression_for_wrapper_vk_for_testing(
         // Scheduler vk should be present!
         let worker = Worker::new();
         // 1. All but one layers of compression with Goldilocks Poseidon2 hash
-        compute_compression_vks_and_write(&mut source, config, &worker);
+        compute_compression_vks_and_write(&mut source, config, &worker).unwrap();
         // 2. Final compression with Bn256 Poseidon2 hash
-        compute_compression_for_wrapper_vk_and_write(config, &mut source, &worker);
+        compute_compression_for_wrapper_vk_and_write(config, &mut source, &worker).unwrap();
     }
 
     source
@@ -50,9 +51,16 @@ fn get_compression_for_wrapper_proof_for_testing(
         // Scheduler vk and proof should be present!
         let worker = Worker::new();
         // 1. All but one layers of compression with Goldilocks Poseidon2 hash
-        compute_compression_circuits(&mut source, config, &worker);
+        compute_compression_circuits(&mut source, config, &worker, &mut print_wrap_progress)
+            .unwrap();
         // 2. Final compression with Bn256 Poseidon2 hash
-        compute_compression_for_wrapper_circuit(&mut source, config, &worker);
+        compute_compression_for_wrapper_circuit(
+            &mut source,
+            config,
+            &worker,
+            &mut print_wrap_progress,
+        )
+        .unwrap();
     }
 
     source
@@ -203,3 +211,127 @@ mod wrapper_tests {
         try_to_synthesize_wrapper(proof, vk, config);
     }
 }
+
+#[cfg(test)]
+mod wrap_error_tests {
+    use super::*;
+    use crate::proof_wrapper_utils::{
+        get_fflonk_wrapper_setup_and_vk_from_compression_vk,
+        get_wrapper_setup_and_vk_from_compression_vk, get_wrapper_setup_and_vk_from_scheduler_vk,
+        wrap_proof, WrapError, WrapperMode, CRS_FILE_ENV_VAR, DEFAULT_WRAPPER_CONFIG,
+    };
+    use circuit_definitions::circuit_definitions::recursion_layer::{
+        ZkSyncRecursionLayerProof, ZkSyncRecursionLayerStorageType,
+        ZkSyncRecursionLayerVerificationKey,
+    };
+
+    const SCHEDULER_TYPE: u8 = ZkSyncRecursionLayerStorageType::SchedulerCircuit as u8;
+
+    fn get_testdata_source() -> LocalFileDataSource {
+        LocalFileDataSource {
+            setup_data_location: "src/proof_wrapper_utils/testdata/proof_compression".to_string(),
+            block_data_location: "src/proof_wrapper_utils/testdata/proof_compression".to_string(),
+        }
+    }
+
+    fn get_scheduler_proof_and_vk() -> (
+        ZkSyncRecursionLayerProof,
+        ZkSyncRecursionLayerVerificationKey,
+    ) {
+        let source = get_testdata_source();
+
+        (
+            source.get_scheduler_proof().unwrap(),
+            source.get_recursion_layer_vk(SCHEDULER_TYPE).unwrap(),
+        )
+    }
+
+    #[test]
+    fn wrong_circuit_type() {
+        let (proof, vk) = get_scheduler_proof_and_vk();
+        let node_type = ZkSyncRecursionLayerStorageType::NodeLayerCircuit as u8;
+        let expected_error = WrapError::WrongCircuitType {
+            expected: SCHEDULER_TYPE,
+            actual: node_type,
+        };
+
+        let node_proof =
+            ZkSyncRecursionLayerProof::from_inner(node_type, proof.clone().into_inner());
+        assert_eq!(
+            wrap_proof(node_proof, vk.clone(), DEFAULT_WRAPPER_CONFIG).err(),
+            Some(expected_error.clone())
+        );
+
+        let node_vk = ZkSyncRecursionLayerVerificationKey::from_inner(node_type, vk.into_inner());
+        assert_eq!(
+            wrap_proof(proof, node_vk.clone(), DEFAULT_WRAPPER_CONFIG).err(),
+            Some(expected_error.clone())
+        );
+        assert_eq!(
+            get_wrapper_setup_and_vk_from_scheduler_vk(node_vk, DEFAULT_WRAPPER_CONFIG).err(),
+            Some(expected_error)
+        );
+    }
+
+    #[test]
+    fn wrong_compression_for_wrapper_type() {
+        // testdata has the compression for wrapper vk of the second layer only
+        let vk = get_testdata_source()
+            .get_compression_for_wrapper_vk(2)
+            .unwrap();
+        let expected_error = WrapError::WrongCircuitType {
+            expected: 1,
+            actual: 2,
+        };
+
+        assert_eq!(
+            get_wrapper_setup_and_vk_from_compression_vk(vk.clone(), DEFAULT_WRAPPER_CONFIG).err(),
+            Some(expected_error.clone())
+        );
+        let fflonk_config = DEFAULT_WRAPPER_CONFIG.with_mode(WrapperMode::Fflonk);
+        assert_eq!(
+            get_fflonk_wrapper_setup_and_vk_from_compression_vk(vk.clone(), fflonk_config).err(),
+            Some(expected_error)
+        );
+
+        assert!(matches!(
+            get_wrapper_setup_and_vk_from_compression_vk(vk.clone(), fflonk_config),
+            Err(WrapError::InvalidConfig(_))
+        ));
+        assert!(matches!(
+            get_fflonk_wrapper_setup_and_vk_from_compression_vk(vk, DEFAULT_WRAPPER_CONFIG),
+            Err(WrapError::InvalidConfig(_))
+        ));
+    }
+
+    #[test]
+    fn invalid_proof() {
+        let (proof, vk) = get_scheduler_proof_and_vk();
+
+        let mut proof = proof.into_inner();
+        proof.public_inputs[0].add_assign(&GoldilocksField::ONE);
+        let proof = ZkSyncRecursionLayerProof::from_inner(SCHEDULER_TYPE, proof);
+
+        assert_eq!(
+            wrap_proof(proof, vk, DEFAULT_WRAPPER_CONFIG).err(),
+            Some(WrapError::InvalidProof)
+        );
+    }
+
+    #[test]
+    fn missing_crs() {
+        let (proof, vk) = get_scheduler_proof_and_vk();
+
+        let crs_file = std::env::var(CRS_FILE_ENV_VAR);
+        std::env::set_var(CRS_FILE_ENV_VAR, "/nonexistent/setup_2^24.key");
+        let wrap_result = wrap_proof(proof, vk.clone(), DEFAULT_WRAPPER_CONFIG);
+        let vk_result = get_wrapper_setup_and_vk_from_scheduler_vk(vk, DEFAULT_WRAPPER_CONFIG);
+        match crs_file {
+            Ok(crs_file) => std::env::set_var(CRS_FILE_ENV_VAR, crs_file),
+            Err(_) => std::env::remove_var(CRS_FILE_ENV_VAR),
+        }
+
+        assert!(matches!(wrap_result, Err(WrapError::MissingCrs(_))));
+        assert!(matches!(vk_result, Err(WrapError::MissingCrs(_))));
+    }
+}
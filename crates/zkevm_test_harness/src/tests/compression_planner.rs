@@ -0,0 +1,65 @@
+use crate::proof_wrapper_utils::{
+    compression_chain_for_config, estimate_compression_chain,
+    estimate_supported_compression_chains, recommend_compression_chain, validate_compression_chain,
+    WrapError, WrapperConfig,
+};
+use circuit_definitions::L1_SECURITY_BITS;
+
+#[test]
+fn supported_compression_chains_reach_l1_security() {
+    let chains = estimate_supported_compression_chains();
+    assert_eq!(chains.len(), WrapperConfig::MAX_COMPRESSION_LAYERS as usize);
+    for (config, estimate) in chains {
+        assert_eq!(
+            estimate.steps.len(),
+            config.get_compression_for_wrapper_type() as usize
+        );
+        assert!(estimate.security_bits >= L1_SECURITY_BITS);
+    }
+
+    let (config, _) = recommend_compression_chain(L1_SECURITY_BITS).unwrap();
+    validate_compression_chain(&compression_chain_for_config(config), L1_SECURITY_BITS).unwrap();
+}
+
+#[test]
+fn single_compression_layer_estimate() {
+    let estimate = estimate_compression_chain(&compression_chain_for_config(
+        WrapperConfig::new(1).unwrap(),
+    ))
+    .unwrap();
+    assert_eq!(estimate.steps.len(), 1);
+    let step = &estimate.steps[0];
+
+    // LDE factor 2 gives 1 bit per query without PoW
+    assert_eq!(step.num_queries, L1_SECURITY_BITS);
+    assert_eq!(step.security_bits, L1_SECURITY_BITS);
+    // 2^16 rows, 52 copiable, 78 witness and 4 constant columns, constraint degree 8:
+    // 186 base field and 17 extension field polynomials, 220 elements per query
+    assert_eq!(step.prover_work, (1 << 16) * 2 * 220);
+    // every query opens 220 elements with 4 paths of length 13 and 5 FRI foldings by 8
+    assert_eq!(
+        step.proof_size_bytes,
+        80 * 4768 + 9 * 16 * 32 + 6496 + 32 + 8
+    );
+    assert_eq!(step.vk_size_bytes, 16 * 32);
+    assert_eq!(estimate.for_wrapper_proof_size_bytes, step.proof_size_bytes);
+}
+
+#[test]
+fn unreachable_security_target() {
+    let result = recommend_compression_chain(200);
+    assert!(matches!(result, Err(WrapError::InvalidConfig(_))));
+}
+
+#[test]
+fn custom_chain_below_target() {
+    let mut steps = compression_chain_for_config(WrapperConfig::new(2).unwrap());
+    steps[0].proof_config.security_level = 40;
+    let result = validate_compression_chain(&steps, L1_SECURITY_BITS);
+    assert!(matches!(result, Err(WrapError::InvalidConfig(_))));
+
+    // compression for wrapper step can only be the last one
+    steps.reverse();
+    let result = validate_compression_chain(&steps, 0);
+    assert!(matches!(result, Err(WrapError::InvalidConfig(_))));
+}
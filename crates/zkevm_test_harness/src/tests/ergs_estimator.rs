@@ -0,0 +1,167 @@
+use std::collections::HashMap;
+
+use crate::asm_runner::storage::StorageRefund;
+use crate::asm_runner::AsmTest;
+use crate::ergs_estimator::{
+    create_contract_call_vm, estimate_bootloader, estimate_entry_point, estimate_ergs,
+    estimate_far_call, execute_with_ergs_limit, with_estimation_tracer,
+};
+use crate::ethereum_types::Address;
+use crate::helper::artifact_utils::TestArtifact;
+use crate::tests::utils::preprocess_asm::asm_with_default_config;
+use crate::vm_debugger::{compile_test, create_vm};
+use crate::vm_diff::create_bootloader_out_of_circuit_vm;
+use crate::zk_evm::zkevm_opcode_defs::circuit_prices::STORAGE_APPLICATION_COST_IN_ERGS;
+use crate::zk_evm::zkevm_opcode_defs::system_params::BOOTLOADER_FORMAL_ADDRESS;
+use crate::zk_evm::zkevm_opcode_defs::{Opcode, RetOpcode};
+
+fn storage_write_program() -> AsmTest {
+    program(
+        r#"
+    __entry:
+    .main:
+        add 25, r0, r1
+        add 24, r0, r2
+        log.swrite r1, r2, r0
+        ret.ok r0
+    "#,
+    )
+}
+
+/// Writes the first word of the calldata (or of the heap with `LOAD` = `ld.1 r0`) to the storage,
+/// panics if it's not 42
+const CHECKED_WRITE: &str = r#"
+    __entry:
+    .main:
+        LOAD, r2
+        sub! 42, r2, r0
+        jump.ne @.panic
+        add 25, r0, r1
+        log.swrite r1, r2, r0
+        ret.ok r0
+    .panic:
+        ret.panic r0
+    "#;
+
+fn program(entry_point: &str) -> AsmTest {
+    AsmTest {
+        name: "program".to_owned(),
+        entry_point: asm_with_default_config(entry_point),
+        contracts: vec![],
+        dictionary: Default::default(),
+        expectations: Default::default(),
+        options: Default::default(),
+        check_circuits: false,
+        entry_point_path: None,
+    }
+}
+
+#[test]
+fn test_estimate_ergs() {
+    let contracts = compile_test(&storage_write_program()).unwrap();
+    let estimate = estimate_entry_point(&contracts, 1 << 24, 1000).unwrap();
+
+    assert_eq!(estimate.ergs_used, estimate.ergs_limit);
+    assert!(estimate.pubdata_cost > 0);
+    assert_eq!(estimate.storage_refunds, 0);
+    assert_eq!(estimate.counters.storage_writes, 1);
+    assert_eq!(
+        estimate.circuit_costs.storage_application,
+        STORAGE_APPLICATION_COST_IN_ERGS as u64
+    );
+    assert!(estimate.circuit_costs.total() > estimate.circuit_costs.main_vm);
+
+    let outcome = execute_with_ergs_limit(
+        with_estimation_tracer(create_vm(&contracts)),
+        estimate.ergs_limit - 1,
+        1000,
+    )
+    .unwrap();
+    assert!(!outcome.succeeded);
+
+    // the refund is returned after the write, so the full price should be available,
+    // and only the `ret.ok` after the write can be paid by the refund
+    let warm = estimate_ergs(
+        || {
+            let vm = with_estimation_tracer(create_vm(&contracts));
+            vm.storage
+                .create_refund_controller()
+                .set_storage_refund(StorageRefund::Warm, 100);
+            vm
+        },
+        1 << 24,
+        1000,
+    )
+    .unwrap();
+    assert_eq!(
+        warm.ergs_limit,
+        estimate.ergs_limit - Opcode::Ret(RetOpcode::Ok).ergs_price()
+    );
+    assert_eq!(warm.ergs_used, estimate.ergs_used - 100);
+    assert_eq!(warm.storage_refunds, 100);
+
+    assert!(estimate_entry_point(&contracts, estimate.ergs_limit - 1, 1000).is_err());
+}
+
+fn word(value: u8) -> Vec<u8> {
+    let mut word = vec![0u8; 32];
+    word[31] = value;
+    word
+}
+
+#[test]
+fn test_estimate_far_call() {
+    let callee = compile_test(&program(&CHECKED_WRITE.replace("LOAD", "ld r1"))).unwrap();
+    let address = Address::from_low_u64_be(65536);
+    let caller = Address::from_low_u64_be(65537);
+    let contracts = HashMap::from([(address, callee[0].bytecode.clone())]);
+
+    let estimate =
+        estimate_far_call(&contracts, caller, address, &word(42), 1 << 24, 1000).unwrap();
+    assert!(estimate.pubdata_cost > 0);
+    assert_eq!(estimate.counters.storage_writes, 1);
+
+    let outcome = execute_with_ergs_limit(
+        with_estimation_tracer(create_contract_call_vm(
+            &contracts,
+            caller,
+            address,
+            &word(42),
+        )),
+        estimate.ergs_limit - 1,
+        1000,
+    )
+    .unwrap();
+    assert!(!outcome.succeeded);
+
+    // the callee checks the calldata
+    assert!(estimate_far_call(&contracts, caller, address, &word(41), 1 << 24, 1000).is_err());
+    assert!(estimate_far_call(&contracts, caller, caller, &word(42), 1 << 24, 1000).is_err());
+}
+
+#[test]
+fn test_estimate_bootloader() {
+    let bootloader = compile_test(&program(&CHECKED_WRITE.replace("LOAD", "ld.1 r0"))).unwrap();
+    let artifact = TestArtifact {
+        entry_point_address: *BOOTLOADER_FORMAL_ADDRESS,
+        entry_point_code: bootloader[0].bytecode.clone(),
+        default_account_code: bootloader[0].bytecode.clone(),
+        evm_simulator_code: bootloader[0].bytecode.clone(),
+        predeployed_contracts: HashMap::new(),
+    };
+
+    let estimate = estimate_bootloader(&artifact, &word(42), 1 << 24, 1000).unwrap();
+    assert!(estimate.pubdata_cost > 0);
+    assert_eq!(estimate.counters.storage_writes, 1);
+
+    let outcome = execute_with_ergs_limit(
+        with_estimation_tracer(create_bootloader_out_of_circuit_vm(&artifact, &word(42))),
+        estimate.ergs_limit - 1,
+        1000,
+    )
+    .unwrap();
+    assert!(!outcome.succeeded);
+
+    // the bootloader checks the heap content
+    assert!(estimate_bootloader(&artifact, &word(41), 1 << 24, 1000).is_err());
+}
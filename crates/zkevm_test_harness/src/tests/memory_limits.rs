@@ -0,0 +1,92 @@
+use std::collections::HashMap;
+use std::sync::mpsc::sync_channel;
+use std::thread;
+
+use crate::asm_runner::preprocess_asm::{asm_with_default_config, TemplateDictionary};
+use crate::asm_runner::{compile, test_geometry, Options};
+use crate::ethereum_types::{Address, U256};
+use crate::run_vms::{run_vms_with_memory_limits, RunVMsResult, RunVmError};
+use crate::witness::tree::{BinarySparseStorageTree, ZKSyncTestingTree};
+use crate::zk_evm::bytecode_to_code_hash;
+use crate::zk_evm::reference_impls::memory::{MemoryLimits, SimpleMemory};
+use crate::zk_evm::testing::storage::InMemoryStorage;
+use crate::zk_evm::zkevm_opcode_defs::system_params::BOOTLOADER_FORMAL_ADDRESS;
+use crate::zk_evm::GenericNoopTracer;
+
+/// Runs a program which stores a single heap word at the byte offset 2^20: the word itself fits
+/// into a single chunk of less than 2^12 bytes, while the index of the heap page grows to more
+/// than 2^9 entries, which take 2^12 bytes on their own
+fn run_far_heap_write(memory_limits: MemoryLimits) -> Result<RunVMsResult, RunVmError> {
+    let entry_point = compile(
+        &asm_with_default_config(
+            r#"
+__entry:
+.main:
+    add 1, r0, r1
+    shl.s 20, r1, r1
+    add 42, r0, r2
+    st.1 r1, r2
+    ret.ok r0
+    "#,
+        ),
+        None,
+        &TemplateDictionary::new(),
+    )
+    .unwrap();
+
+    let empty_code_hash = U256::from_big_endian(&bytecode_to_code_hash(&[[0; 32]]).unwrap());
+    let (sender, receiver) = sync_channel(1);
+    let receiver_handle = thread::spawn(move || while receiver.recv().is_ok() {});
+    let result = run_vms_with_memory_limits(
+        Address::zero(),
+        *BOOTLOADER_FORMAL_ADDRESS,
+        entry_point,
+        vec![],
+        false,
+        empty_code_hash,
+        empty_code_hash,
+        HashMap::new(),
+        vec![],
+        1 << 10,
+        memory_limits,
+        test_geometry(4),
+        InMemoryStorage::new(),
+        ZKSyncTestingTree::empty(),
+        Options::default().trusted_setup_path,
+        std::array::from_fn(|_| None),
+        sender,
+        &mut GenericNoopTracer::<SimpleMemory>::new(),
+    );
+    receiver_handle.join().unwrap();
+
+    result
+}
+
+#[test]
+fn test_run_vms_reports_memory_metrics() {
+    let (_, _, metrics) = run_far_heap_write(MemoryLimits::default()).unwrap();
+
+    assert!(metrics.peak_size > 1 << 12, "{metrics:?}");
+    assert!(metrics.peak_size >= metrics.current_size, "{metrics:?}");
+    assert!(metrics.allocated_size >= metrics.peak_size, "{metrics:?}");
+}
+
+#[test]
+fn test_run_vms_stops_at_memory_limit() {
+    let result = run_far_heap_write(MemoryLimits {
+        max_total_size: None,
+        max_page_size: Some(1 << 12),
+    });
+
+    match result {
+        Err(RunVmError::OutOfCircuitExecutionError(msg)) => {
+            assert!(msg.contains("memory page"), "{msg}");
+            assert!(
+                msg.contains(&format!("limit of {} bytes", 1 << 12)),
+                "{msg}"
+            );
+        }
+        Err(err) => panic!("unexpected error: {err:?}"),
+        Ok(_) => panic!("the memory limit is not reported"),
+    }
+}
@@ -0,0 +1,46 @@
+use super::*;
+use crate::boojum::field::SmallField;
+use crate::data_source::in_memory_data_source::InMemoryDataSource;
+use crate::data_source::BlockDataSource;
+use crate::prover_utils::mock::*;
+use circuit_definitions::base_layer_proof_config;
+use circuit_definitions::circuit_definitions::base_layer::{
+    BaseLayerCircuitType, ZkSyncBaseLayerProof, ZkSyncBaseProof,
+};
+
+fn testing_public_inputs() -> Vec<GoldilocksField> {
+    (1..=4).map(GoldilocksField::from_u64_unchecked).collect()
+}
+
+#[test]
+fn mock_proof_is_tagged() {
+    let proof = mock_proof(testing_public_inputs(), base_layer_proof_config());
+    assert!(is_mock_proof(&proof));
+    assert_eq!(proof.public_inputs, testing_public_inputs());
+
+    let mut proof = proof;
+    proof.pow_challenge = 0;
+    assert!(!is_mock_proof(&proof));
+}
+
+#[test]
+fn mock_proof_survives_block_data_source() {
+    let circuit_type = BaseLayerCircuitType::VM as u8;
+    let proof = mock_proof(testing_public_inputs(), base_layer_proof_config());
+
+    let mut source = InMemoryDataSource::new();
+    source
+        .set_base_layer_proof(0, ZkSyncBaseLayerProof::from_inner(circuit_type, proof))
+        .unwrap();
+    let proof = source
+        .get_base_layer_proof(circuit_type, 0)
+        .unwrap()
+        .into_inner();
+    assert!(is_mock_proof(&proof));
+
+    // same for the serialized form, that is used by the file based sources
+    let serialized = serde_json::to_vec(&proof).unwrap();
+    let proof: ZkSyncBaseProof = serde_json::from_slice(&serialized).unwrap();
+    assert!(is_mock_proof(&proof));
+    assert_eq!(proof.public_inputs, testing_public_inputs());
+}
@@ -2,13 +2,39 @@ use super::*;
 
 pub mod complex_tests;
 #[cfg(test)]
+mod compression_planner;
+#[cfg(test)]
+mod ergs_estimator;
+#[cfg(test)]
+mod memory_limits;
+#[cfg(all(test, feature = "mock_prover"))]
+mod mock_prover;
+#[cfg(test)]
+mod opcode_fuzzer;
+#[cfg(test)]
+mod profiler;
+#[cfg(test)]
+mod protocol_commitments;
+#[cfg(test)]
+mod recursion_tree_arity;
+#[cfg(test)]
 pub mod run_manually;
 #[cfg(test)]
 pub mod simple_tests;
 #[cfg(test)]
-pub(crate) mod storage;
+mod state_dump;
+#[cfg(test)]
+mod state_provider;
+#[cfg(test)]
+pub(crate) use crate::asm_runner::storage;
 #[cfg(test)]
 pub(crate) mod utils;
+#[cfg(test)]
+mod vm_debugger;
+#[cfg(test)]
+mod vm_diff;
+#[cfg(test)]
+mod vm_trace;
 
 use crate::blake2::Blake2s256;
 use crate::boojum::worker::Worker;
@@ -96,166 +122,7 @@ pub(crate) fn save_predeployed_contracts(
     }
 }
 
-pub(crate) fn base_test_circuit(circuit: ZkSyncBaseLayerCircuit) {
-    use crate::boojum::config::DevCSConfig;
-    use crate::boojum::cs::cs_builder::new_builder;
-    use crate::boojum::cs::cs_builder_reference::CsReferenceImplementationBuilder;
-
-    type P = GoldilocksField;
-    // type P = MixedGL;
-
-    let worker = Worker::new();
-
-    let geometry = circuit.geometry();
-    let (max_trace_len, num_vars) = circuit.size_hint();
-
-    use crate::boojum::config::CSConfig;
-    let builder_impl = CsReferenceImplementationBuilder::<
-        GoldilocksField,
-        P,
-        DevCSConfig,
-        crate::boojum::dag::StCircuitResolver<
-            GoldilocksField,
-            <DevCSConfig as CSConfig>::ResolverConfig,
-        >,
-    >::new(geometry, max_trace_len.unwrap());
-    let arg = num_vars.unwrap();
-    let builder = new_builder::<_, GoldilocksField>(builder_impl);
-
-    let mut cs = match circuit {
-        ZkSyncBaseLayerCircuit::MainVM(inner) => {
-            let builder = inner.configure_builder_proxy(builder);
-            let mut cs = builder.build(arg);
-            inner.add_tables_proxy(&mut cs);
-            inner.synthesize_proxy(&mut cs);
-            let _ = cs.pad_and_shrink();
-            cs.into_assembly::<Global>()
-        }
-        ZkSyncBaseLayerCircuit::CodeDecommittmentsSorter(inner) => {
-            let builder = inner.configure_builder_proxy(builder);
-            let mut cs = builder.build(arg);
-            inner.add_tables_proxy(&mut cs);
-            inner.synthesize_proxy(&mut cs);
-            let _ = cs.pad_and_shrink();
-            cs.into_assembly::<std::alloc::Global>()
-        }
-        ZkSyncBaseLayerCircuit::CodeDecommitter(inner) => {
-            let builder = inner.configure_builder_proxy(builder);
-            let mut cs = builder.build(arg);
-            inner.add_tables_proxy(&mut cs);
-            inner.synthesize_proxy(&mut cs);
-            let _ = cs.pad_and_shrink();
-            cs.into_assembly::<std::alloc::Global>()
-        }
-        ZkSyncBaseLayerCircuit::LogDemuxer(inner) => {
-            let builder = inner.configure_builder_proxy(builder);
-            let mut cs = builder.build(arg);
-            inner.add_tables_proxy(&mut cs);
-            inner.synthesize_proxy(&mut cs);
-            let _ = cs.pad_and_shrink();
-            cs.into_assembly::<std::alloc::Global>()
-        }
-        ZkSyncBaseLayerCircuit::KeccakRoundFunction(inner) => {
-            let builder = inner.configure_builder_proxy(builder);
-            let mut cs = builder.build(arg);
-            inner.add_tables_proxy(&mut cs);
-            inner.synthesize_proxy(&mut cs);
-            let _ = cs.pad_and_shrink();
-            cs.into_assembly::<std::alloc::Global>()
-        }
-        ZkSyncBaseLayerCircuit::Sha256RoundFunction(inner) => {
-            let builder = inner.configure_builder_proxy(builder);
-            let mut cs = builder.build(arg);
-            inner.add_tables_proxy(&mut cs);
-            inner.synthesize_proxy(&mut cs);
-            let _ = cs.pad_and_shrink();
-            cs.into_assembly::<std::alloc::Global>()
-        }
-        ZkSyncBaseLayerCircuit::ECRecover(inner) => {
-            let builder = inner.configure_builder_proxy(builder);
-            let mut cs = builder.build(arg);
-            inner.add_tables_proxy(&mut cs);
-            inner.synthesize_proxy(&mut cs);
-            let _ = cs.pad_and_shrink();
-            cs.into_assembly::<std::alloc::Global>()
-        }
-        ZkSyncBaseLayerCircuit::RAMPermutation(inner) => {
-            let builder = inner.configure_builder_proxy(builder);
-            let mut cs = builder.build(arg);
-            inner.add_tables_proxy(&mut cs);
-            inner.synthesize_proxy(&mut cs);
-            let _ = cs.pad_and_shrink();
-            cs.into_assembly::<std::alloc::Global>()
-        }
-        ZkSyncBaseLayerCircuit::StorageSorter(inner) => {
-            let builder = inner.configure_builder_proxy(builder);
-            let mut cs = builder.build(arg);
-            inner.add_tables_proxy(&mut cs);
-            inner.synthesize_proxy(&mut cs);
-            let _ = cs.pad_and_shrink();
-            cs.into_assembly::<std::alloc::Global>()
-        }
-        ZkSyncBaseLayerCircuit::StorageApplication(inner) => {
-            let builder = inner.configure_builder_proxy(builder);
-            let mut cs = builder.build(arg);
-            inner.add_tables_proxy(&mut cs);
-            inner.synthesize_proxy(&mut cs);
-            let _ = cs.pad_and_shrink();
-            cs.into_assembly::<std::alloc::Global>()
-        }
-        ZkSyncBaseLayerCircuit::EventsSorter(inner) => {
-            let builder = inner.configure_builder_proxy(builder);
-            let mut cs = builder.build(arg);
-            inner.add_tables_proxy(&mut cs);
-            inner.synthesize_proxy(&mut cs);
-            let _ = cs.pad_and_shrink();
-            cs.into_assembly::<std::alloc::Global>()
-        }
-        ZkSyncBaseLayerCircuit::L1MessagesSorter(inner) => {
-            let builder = inner.configure_builder_proxy(builder);
-            let mut cs = builder.build(arg);
-            inner.add_tables_proxy(&mut cs);
-            inner.synthesize_proxy(&mut cs);
-            let _ = cs.pad_and_shrink();
-            cs.into_assembly::<std::alloc::Global>()
-        }
-        ZkSyncBaseLayerCircuit::L1MessagesHasher(inner) => {
-            let builder = inner.configure_builder_proxy(builder);
-            let mut cs = builder.build(num_vars.unwrap());
-            inner.add_tables_proxy(&mut cs);
-            inner.synthesize_proxy(&mut cs);
-            let _ = cs.pad_and_shrink();
-            cs.into_assembly::<std::alloc::Global>()
-        }
-        ZkSyncBaseLayerCircuit::TransientStorageSorter(inner) => {
-            let builder = inner.configure_builder_proxy(builder);
-            let mut cs = builder.build(num_vars.unwrap());
-            inner.add_tables_proxy(&mut cs);
-            inner.synthesize_proxy(&mut cs);
-            let _ = cs.pad_and_shrink();
-            cs.into_assembly::<std::alloc::Global>()
-        }
-        ZkSyncBaseLayerCircuit::Secp256r1Verify(inner) => {
-            let builder = inner.configure_builder_proxy(builder);
-            let mut cs = builder.build(num_vars.unwrap());
-            inner.add_tables_proxy(&mut cs);
-            inner.synthesize_proxy(&mut cs);
-            let _ = cs.pad_and_shrink();
-            cs.into_assembly::<std::alloc::Global>()
-        }
-        ZkSyncBaseLayerCircuit::EIP4844Repack(inner) => {
-            let builder = inner.configure_builder_proxy(builder);
-            let mut cs = builder.build(num_vars.unwrap());
-            inner.add_tables_proxy(&mut cs);
-            inner.synthesize_proxy(&mut cs);
-            let _ = cs.pad_and_shrink();
-            cs.into_assembly::<std::alloc::Global>()
-        }
-    };
-
-    let is_satisfied = cs.check_if_satisfied(&worker);
-    assert!(is_satisfied);
-}
+pub(crate) use crate::helper::circuit_utils::base_test_circuit;
 
 pub(crate) fn test_recursive_circuit(circuit: ZkSyncRecursiveLayerCircuit) {
     use crate::boojum::config::DevCSConfig;
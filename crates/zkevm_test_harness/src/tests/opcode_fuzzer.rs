@@ -0,0 +1,106 @@
+use crate::asm_runner::compile;
+use crate::asm_runner::preprocess_asm::TemplateDictionary;
+use crate::opcode_fuzzer::{
+    callee_contracts, check_program, generate_program, run_fuzzer, FuzzBlock, FuzzProgram,
+    FuzzerConfig, BLOCK_END_LABEL, CALLEES,
+};
+
+#[test]
+fn generated_programs_are_deterministic_and_compile() {
+    let config = FuzzerConfig::default();
+    for seed in 0..200 {
+        let program = generate_program(seed, &config);
+        assert_eq!(program, generate_program(seed, &config));
+        assert!(!program.blocks.is_empty() && program.blocks.len() <= config.max_blocks);
+
+        let asm = program.to_asm();
+        assert!(!asm.contains(BLOCK_END_LABEL));
+        if let Err(err) = compile(&asm, None, &TemplateDictionary::new()) {
+            panic!("seed {seed}: {err}\n{asm}");
+        }
+    }
+}
+
+#[test]
+fn panicking_blocks_are_handled() {
+    let program = FuzzProgram {
+        blocks: vec![
+            FuzzBlock {
+                ergs: 100,
+                instructions: vec![
+                    "add 1, r0, r2".to_owned(),
+                    // pointer arithmetic on integers
+                    "ptr.add r2, r2, r3".to_owned(),
+                ],
+            },
+            FuzzBlock {
+                ergs: u16::MAX,
+                instructions: vec![
+                    "sub.s! 1, r0, r2".to_owned(),
+                    format!("jump.lt {BLOCK_END_LABEL}"),
+                    "invalid".to_owned(),
+                ],
+            },
+        ],
+    };
+    check_program(&program, &FuzzerConfig::default()).unwrap();
+}
+
+#[test]
+fn generated_programs_contain_far_calls() {
+    let config = FuzzerConfig::default();
+    let far_calls = (0..200)
+        .flat_map(|seed| generate_program(seed, &config).blocks)
+        .flat_map(|block| block.instructions)
+        .filter(|instruction| instruction.contains("far_call"))
+        .count();
+    assert!(far_calls > 0);
+    assert_eq!(callee_contracts().unwrap().len(), CALLEES.len());
+}
+
+#[test]
+fn far_returns_of_all_callees_are_handled() {
+    // 63/64 of the available ergs are passed, so the callees reach their far returns
+    let far_call = |address: u16, modifiers: &str| {
+        format!(
+            "add 65535, r0, r1\n    shl.s 208, r1, r1\n    add {address}, r0, r2\n    far_call{modifiers} r1, r2, {BLOCK_END_LABEL}"
+        )
+    };
+    let blocks = CALLEES
+        .iter()
+        .flat_map(|(address, _)| {
+            ["", ".static", ".delegate"]
+                .iter()
+                .map(move |modifiers| FuzzBlock {
+                    ergs: u16::MAX,
+                    instructions: vec![far_call(*address, modifiers), "add 1, r0, r3".to_owned()],
+                })
+        })
+        .collect();
+
+    check_program(&FuzzProgram { blocks }, &FuzzerConfig::default()).unwrap();
+}
+
+#[test]
+fn fuzz_opcodes() {
+    let config = FuzzerConfig {
+        seed: 1,
+        iterations: 4,
+        ..Default::default()
+    };
+    let cases = run_fuzzer(&config);
+    assert!(
+        cases.is_empty(),
+        "{}",
+        cases
+            .iter()
+            .map(|case| format!(
+                "seed {}: {}\n{}",
+                case.seed,
+                case.failure,
+                case.program.to_asm()
+            ))
+            .collect::<Vec<_>>()
+            .join("\n")
+    );
+}
@@ -0,0 +1,119 @@
+use crate::asm_runner::AsmTest;
+use crate::ethereum_types::Address;
+use crate::tests::utils::preprocess_asm::asm_with_default_config;
+use crate::vm_debugger::{compile_test, create_vm};
+use crate::zk_evm::profiler::{ProfileMetric, ProfilingTracer};
+use crate::zk_evm::zkevm_opcode_defs::system_params::BOOTLOADER_FORMAL_ADDRESS;
+
+const CALLEE_ADDRESS: u64 = 65520;
+
+fn test_program() -> AsmTest {
+    let entry_point = asm_with_default_config(&format!(
+        r#"
+    __entry:
+    .main:
+        near_call r0, @helper, @panic
+        add {CALLEE_ADDRESS}, r0, r2
+        ; the ergs are passed explicitly, zero in the far call ABI passes no ergs
+        add 50000, r0, r1
+        shl.s 192, r1, r1
+        far_call r1, r2, @panic
+        add 1000, r0, r3
+        near_call r3, @panicking, @after_panic
+    after_panic:
+        ret.ok r0
+    helper:
+        add 3, r0, r4
+        log.swrite r4, r4, r0
+        ret.ok r0
+    panicking:
+        ; the write costs more than the passed ergs, so the frame runs out of ergs
+        log.swrite r4, r4, r0
+        ret.ok r0
+    panic:
+        ret.panic r0
+    "#
+    ));
+    let callee = asm_with_default_config(
+        r#"
+    __entry:
+    .main:
+        add 5, r0, r4
+        log.swrite r4, r4, r0
+        log.sread r4, r0, r2
+        ret.ok r0
+    "#,
+    );
+
+    AsmTest {
+        name: "program".to_owned(),
+        entry_point,
+        contracts: vec![(CALLEE_ADDRESS, callee)],
+        dictionary: Default::default(),
+        expectations: Default::default(),
+        options: Default::default(),
+        check_circuits: false,
+        entry_point_path: None,
+    }
+}
+
+#[test]
+fn test_costs_of_near_and_far_calls() {
+    let contracts = compile_test(&test_program()).unwrap();
+    let mut tracer = ProfilingTracer::new()
+        .with_contract_name(*BOOTLOADER_FORMAL_ADDRESS, "bootloader".to_owned())
+        .with_contract_name(
+            Address::from_low_u64_be(CALLEE_ADDRESS),
+            "callee".to_owned(),
+        )
+        .with_function_labels(
+            *BOOTLOADER_FORMAL_ADDRESS,
+            contracts[0].assembly.function_labels.clone(),
+        );
+
+    let mut vm = create_vm(&contracts);
+    let mut cycles = 0u64;
+    while !vm.execution_has_ended() {
+        vm.cycle(&mut tracer).unwrap();
+        cycles += 1;
+        assert!(cycles < 100, "execution hasn't ended");
+    }
+
+    let costs = tracer.costs_by_stack();
+    for (stack, _) in costs.iter() {
+        assert_eq!(stack[0], "bootloader");
+    }
+    let costs_of = |frame: &str| {
+        let (_, costs) = costs
+            .iter()
+            .find(|(stack, _)| stack.last().map(|el| el.as_str()) == Some(frame))
+            .unwrap_or_else(|| panic!("no stack for {frame}: {costs:?}"));
+        *costs
+    };
+
+    // near call frame
+    let helper = costs_of("helper");
+    assert_eq!(helper.cycles, 3);
+    assert_eq!(helper.storage_accesses, 1);
+
+    // far call frame is attributed to the callee, not to the caller
+    let callee = costs_of("callee");
+    assert_eq!(callee.cycles, 4);
+    assert_eq!(callee.storage_accesses, 2);
+    assert!(callee.ergs > 0);
+
+    // all the ergs that were passed to the near call are burnt by the out of ergs panic,
+    // unlike `ret.panic` that returns the remaining ergs
+    let panicking = costs_of("panicking");
+    assert_eq!(panicking.cycles, 1);
+    assert_eq!(panicking.ergs, 1000);
+
+    let total = tracer.total_costs();
+    assert_eq!(total.cycles, cycles);
+    assert_eq!(total.storage_accesses, 3);
+
+    let collapsed = tracer.collapsed_stacks(ProfileMetric::StorageAccesses);
+    assert_eq!(collapsed.lines().count(), 2, "{collapsed}");
+    assert!(collapsed.lines().any(|el| el.ends_with(";callee 2")));
+    assert!(collapsed.lines().any(|el| el.ends_with(";helper 1")));
+}
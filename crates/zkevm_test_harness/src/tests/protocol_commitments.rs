@@ -0,0 +1,150 @@
+use crate::data_source::in_memory_data_source::InMemoryDataSource;
+use crate::data_source::local_file_data_source::LocalFileDataSource;
+use crate::data_source::SetupDataSource;
+use crate::protocol_commitments::{compute_protocol_commitments, ProtocolCommitments};
+use crate::zkevm_circuits::scheduler::aux::BaseLayerCircuitType;
+use circuit_definitions::circuit_definitions::recursion_layer::{
+    base_circuit_type_into_recursive_leaf_circuit_type, RecursionTreeArity,
+    ZkSyncRecursionLayerStorageType, ZkSyncRecursionLayerVerificationKey,
+};
+
+fn commitments() -> ProtocolCommitments {
+    ProtocolCommitments {
+        leaf_layer_parameters: "0x01".to_string(),
+        node_layer_vk: "0x02".to_string(),
+        recursion_tip_vk: "0x03".to_string(),
+        scheduler_vk: "0x04".to_string(),
+        snark_wrapper_vk_hash: None,
+        recursion_tree_arity: RecursionTreeArity::default(),
+    }
+}
+
+#[test]
+fn protocol_commitments_diff() {
+    let old = commitments();
+    assert!(old.diff(&old).is_empty());
+
+    let mut new = commitments();
+    new.scheduler_vk = "0x05".to_string();
+    new.snark_wrapper_vk_hash = Some("0x06".to_string());
+    let changes = old.diff(&new);
+    assert_eq!(changes.len(), 2);
+    assert_eq!(changes[0].name, "scheduler_vk");
+    assert_eq!(changes[0].old.as_deref(), Some("0x04"));
+    assert_eq!(changes[0].new.as_deref(), Some("0x05"));
+    assert_eq!(changes[1].name, "snark_wrapper_vk_hash");
+    assert_eq!(changes[1].old, None);
+
+    let serialized = serde_json::to_string(&new).unwrap();
+    let deserialized: ProtocolCommitments = serde_json::from_str(&serialized).unwrap();
+    assert_eq!(deserialized, new);
+}
+
+const WRAPPER_TYPE: u8 = 1;
+
+/// Copies everything that the commitments depend on from the committed setup
+fn committed_setup_in_memory() -> InMemoryDataSource {
+    let setup = LocalFileDataSource::default();
+    let mut source = InMemoryDataSource::new();
+    for circuit_type in ((BaseLayerCircuitType::VM as u8)
+        ..=(BaseLayerCircuitType::Secp256r1Verify as u8))
+        .chain(std::iter::once(BaseLayerCircuitType::EIP4844Repack as u8))
+    {
+        source
+            .set_base_layer_vk(setup.get_base_layer_vk(circuit_type).unwrap())
+            .unwrap();
+        let recursive_circuit_type = base_circuit_type_into_recursive_leaf_circuit_type(
+            BaseLayerCircuitType::from_numeric_value(circuit_type),
+        );
+        source
+            .set_recursion_layer_vk(
+                setup
+                    .get_recursion_layer_vk(recursive_circuit_type as u8)
+                    .unwrap(),
+            )
+            .unwrap();
+    }
+    source
+        .set_recursion_layer_node_vk(setup.get_recursion_layer_node_vk().unwrap())
+        .unwrap();
+    source
+        .set_recursion_tip_vk(setup.get_recursion_tip_vk().unwrap())
+        .unwrap();
+    source
+        .set_recursion_layer_vk(
+            setup
+                .get_recursion_layer_vk(ZkSyncRecursionLayerStorageType::SchedulerCircuit as u8)
+                .unwrap(),
+        )
+        .unwrap();
+    source
+        .set_wrapper_vk(setup.get_wrapper_vk(WRAPPER_TYPE).unwrap())
+        .unwrap();
+
+    source
+}
+
+#[test]
+fn protocol_commitments_of_committed_setup() {
+    let mut setup = LocalFileDataSource::default();
+    let commitments = compute_protocol_commitments(&mut setup, Some(WRAPPER_TYPE)).unwrap();
+
+    // same hash as in the L1 verifier generated for the committed wrapper vk
+    let verifier =
+        std::fs::read_to_string("src/proof_wrapper_utils/testdata/l1_verifier/verifier_1.sol")
+            .unwrap();
+    let expected_vk_hash = verifier
+        .lines()
+        .find_map(|line| line.trim().strip_prefix("Verification key hash: "))
+        .unwrap();
+    assert_eq!(
+        commitments.snark_wrapper_vk_hash.as_deref(),
+        Some(expected_vk_hash)
+    );
+
+    let goldilocks_commitments = [
+        &commitments.leaf_layer_parameters,
+        &commitments.node_layer_vk,
+        &commitments.recursion_tip_vk,
+        &commitments.scheduler_vk,
+    ];
+    for (idx, commitment) in goldilocks_commitments.iter().enumerate() {
+        // 4 elements, 16 hex digits each
+        assert_eq!(commitment.len(), 2 + 4 * 16);
+        assert!(commitment.starts_with("0x"));
+        assert_ne!(commitment.trim_start_matches("0x").trim_matches('0'), "");
+        for other in goldilocks_commitments[idx + 1..].iter() {
+            assert_ne!(commitment, other);
+        }
+    }
+    assert_eq!(
+        commitments.recursion_tree_arity,
+        RecursionTreeArity::default()
+    );
+
+    // the result only depends on the vks, not on the source
+    let mut source = committed_setup_in_memory();
+    assert_eq!(
+        compute_protocol_commitments(&mut source, Some(WRAPPER_TYPE)).unwrap(),
+        commitments
+    );
+    let without_wrapper = compute_protocol_commitments(&mut source, None).unwrap();
+    assert_eq!(without_wrapper.snark_wrapper_vk_hash, None);
+    assert_eq!(without_wrapper.scheduler_vk, commitments.scheduler_vk);
+
+    // and every vk is committed to: replacing the scheduler vk only changes its commitment
+    let recursion_tip_vk = source.get_recursion_tip_vk().unwrap().into_inner();
+    source
+        .set_recursion_layer_vk(ZkSyncRecursionLayerVerificationKey::SchedulerCircuit(
+            recursion_tip_vk,
+        ))
+        .unwrap();
+    let changed = compute_protocol_commitments(&mut source, Some(WRAPPER_TYPE)).unwrap();
+    let changes = commitments.diff(&changed);
+    assert_eq!(changes.len(), 1);
+    assert_eq!(changes[0].name, "scheduler_vk");
+    assert_eq!(
+        changes[0].new.as_deref(),
+        Some(commitments.recursion_tip_vk.as_str())
+    );
+}
@@ -0,0 +1,111 @@
+use crate::data_source::in_memory_data_source::InMemoryDataSource;
+use crate::data_source::local_file_data_source::LocalFileDataSource;
+use crate::data_source::SetupDataSource;
+use crate::witness::recursive_aggregation::{
+    check_recursion_tree_arity, compute_leaf_params, create_leaf_witness,
+    create_leaf_witness_for_arity,
+};
+use crate::zkevm_circuits::scheduler::aux::BaseLayerCircuitType;
+use circuit_definitions::circuit_definitions::recursion_layer::{
+    base_circuit_type_into_recursive_leaf_circuit_type, RecursionTreeArity,
+    ZkSyncRecursiveLayerCircuit, RECURSION_ARITY,
+};
+use circuit_definitions::encodings::recursion_request::RecursionQueueSimulator;
+
+#[test]
+fn default_recursion_tree_arity() {
+    let arity = RecursionTreeArity::default();
+    assert_eq!(arity.leaf_layer_capacity, RECURSION_ARITY);
+    assert_eq!(arity.node_layer_capacity, RECURSION_ARITY);
+
+    let source = InMemoryDataSource::new();
+    assert_eq!(source.get_recursion_tree_arity().unwrap(), arity);
+}
+
+#[test]
+fn invalid_recursion_tree_arity() {
+    assert_eq!(
+        RecursionTreeArity::new(16, 1),
+        Err("node capacity must be at least 2".to_owned())
+    );
+    assert!(RecursionTreeArity::new(0, 32).is_err());
+    assert!(RecursionTreeArity::new(1 << 16, 1 << 16).is_err());
+}
+
+#[test]
+fn witness_arity_must_match_setup() {
+    let mut source = InMemoryDataSource::new();
+    let arity = RecursionTreeArity::new(64, 8).unwrap();
+
+    check_recursion_tree_arity(&source, RecursionTreeArity::default()).unwrap();
+    assert!(check_recursion_tree_arity(&source, arity).is_err());
+
+    source.set_recursion_tree_arity(arity).unwrap();
+    check_recursion_tree_arity(&source, arity).unwrap();
+    assert!(check_recursion_tree_arity(&source, RecursionTreeArity::default()).is_err());
+}
+
+#[test]
+fn recursion_tree_arity_in_local_file_source() {
+    let location =
+        std::env::temp_dir().join(format!("recursion_tree_arity_{}", std::process::id()));
+    let mut source = LocalFileDataSource {
+        setup_data_location: location.to_str().unwrap().to_string(),
+        block_data_location: location.to_str().unwrap().to_string(),
+    };
+    source.create_folders_for_storing_data();
+
+    // setups without the arity file use the default one
+    assert_eq!(
+        source.get_recursion_tree_arity().unwrap(),
+        RecursionTreeArity::default()
+    );
+
+    let arity = RecursionTreeArity::new(64, 8).unwrap();
+    source.set_recursion_tree_arity(arity).unwrap();
+    assert_eq!(source.get_recursion_tree_arity().unwrap(), arity);
+
+    std::fs::remove_dir_all(location).unwrap();
+}
+
+// Arity is not a part of the leaf parameters, it's pinned by the leaf and node vks that
+// are generated for it. So the default arity must build the same circuits as before.
+#[test]
+fn leaf_circuit_capacity_follows_arity() {
+    let source = LocalFileDataSource::default();
+    let circuit_type = BaseLayerCircuitType::VM as u8;
+    let base_vk = source.get_base_layer_vk(circuit_type).unwrap();
+    let leaf_vk = source
+        .get_recursion_layer_vk(base_circuit_type_into_recursive_leaf_circuit_type(
+            BaseLayerCircuitType::VM,
+        ) as u8)
+        .unwrap();
+    let params = (
+        circuit_type,
+        compute_leaf_params(circuit_type, base_vk.clone(), leaf_vk),
+    );
+
+    let leaf_capacity = |circuit: ZkSyncRecursiveLayerCircuit| match circuit {
+        ZkSyncRecursiveLayerCircuit::LeafLayerCircuitForMainVM(inner) => inner.config.capacity,
+        _ => unreachable!(),
+    };
+
+    let (_, circuit) = create_leaf_witness(
+        circuit_type as u64,
+        RecursionQueueSimulator::empty(),
+        vec![],
+        &base_vk,
+        &params,
+    );
+    assert_eq!(leaf_capacity(circuit), RECURSION_ARITY);
+
+    let (_, circuit) = create_leaf_witness_for_arity(
+        circuit_type as u64,
+        RecursionQueueSimulator::empty(),
+        vec![],
+        &base_vk,
+        &params,
+        RecursionTreeArity::new(64, 8).unwrap(),
+    );
+    assert_eq!(leaf_capacity(circuit), 64);
+}
@@ -25,7 +25,6 @@ use crate::zkevm_circuits::main_vm::main_vm_entry_point;
 use boojum::gadgets::queue::full_state_queue::FullStateCircuitQueueRawWitness;
 use circuit_definitions::aux_definitions::witness_oracle::VmWitnessOracle;
 use circuit_definitions::zk_evm::vm_state::cycle;
-use storage::{InMemoryCustomRefundStorage, StorageRefund};
 use witness::oracle::WitnessGenerationArtifact;
 use zkevm_assembly::Assembly;
 use zkevm_circuits::base_structures::vm_state::FULL_SPONGE_QUEUE_STATE_WIDTH;
@@ -172,6 +171,102 @@ fn run_pseudo_benchmark() {
     run_and_try_create_witness_inner(asm, 30000);
 }
 
+#[test]
+fn state_diffs_compression_roundtrip() {
+    use crate::zk_evm::aux_structures::LogQuery;
+    use circuit_definitions::encodings::state_diff_record::*;
+
+    let asm = r#"
+        .text
+        .file	"Test_26"
+        .rodata.cst32
+        .p2align	5
+        .text
+        .globl	__entry
+    __entry:
+    .main:
+        add 1, r0, r1
+        add 2, r0, r2
+        sstore r1, r2
+        add 5, r0, r1
+        add 6, r0, r2
+        sstore r1, r2
+        sload r1, r0
+        sstore r1, r0
+        add 7, r0, r1
+        sload r1, r0
+        ret.ok r0
+    "#;
+
+    let mut assembly = Assembly::try_from(asm.to_owned()).unwrap();
+    let bytecode = assembly.compile_to_bytecode().unwrap();
+
+    let (basic_block_circuits, (_, aux_data, _)) = run_and_collect_circuits(
+        bytecode,
+        Options {
+            cycle_limit: 30,
+            ..Default::default()
+        },
+    );
+
+    // rebuild the records from the storage application witnesses
+    let mut state_diffs = vec![];
+    for circuit in basic_block_circuits {
+        let ZkSyncBaseLayerCircuit::StorageApplication(inner) = circuit else {
+            continue;
+        };
+        let witness = inner.clone_witness().unwrap();
+        assert_eq!(
+            witness.storage_queue_witness.elements.len(),
+            witness.leaf_indexes_for_reads.len()
+        );
+        for ((query, _), enumeration_index) in witness
+            .storage_queue_witness
+            .elements
+            .iter()
+            .zip(witness.leaf_indexes_for_reads.iter())
+        {
+            if !query.rw_flag {
+                continue;
+            }
+            state_diffs.push(StateDiffRecord {
+                address: query.address,
+                key: query.key,
+                derived_key: LogQuery::derive_final_address_for_params(&query.address, &query.key),
+                enumeration_index: *enumeration_index,
+                initial_value: query.read_value,
+                final_value: query.written_value,
+            });
+        }
+    }
+    assert!(!state_diffs.is_empty());
+
+    assert_eq!(
+        state_diffs_keccak256_hash(&state_diffs),
+        aux_data.rollup_state_diff_for_compression
+    );
+
+    for record in state_diffs.iter() {
+        assert_eq!(
+            StateDiffRecord::decode(&record.encode()).encode(),
+            record.encode()
+        );
+    }
+
+    let compressed = compress_state_diffs(&state_diffs);
+    let decompressed = decompress_state_diffs(&compressed).unwrap();
+    assert_eq!(decompressed.len(), state_diffs.len());
+
+    let (initial_writes, repeated_writes): (Vec<_>, Vec<_>) =
+        state_diffs.iter().partition(|el| el.is_write_initial());
+    for (diff, record) in decompressed
+        .iter()
+        .zip(initial_writes.into_iter().chain(repeated_writes))
+    {
+        assert!(diff.matches(record));
+    }
+}
+
 #[allow(dead_code)]
 pub(crate) fn run_and_try_create_witness_inner(asm: &str, cycle_limit: usize) {
     let mut assembly = Assembly::try_from(asm.to_owned()).unwrap();
@@ -180,28 +275,7 @@ pub(crate) fn run_and_try_create_witness_inner(asm: &str, cycle_limit: usize) {
     run_and_try_create_witness_for_extended_state(bytecode, vec![], cycle_limit)
 }
 
-const DEFAULT_CYCLE_LIMIT: usize = 50;
-const DEFAULT_CYCLES_PER_VM_SNAPSHOT: u32 = 5;
-#[derive(Clone)]
-pub struct Options {
-    // How many cycles should the main VM run for.
-    // If not set - default is DEFAULT_CYCLE_LIMIT (50).
-    pub cycle_limit: usize,
-    // Additional contracts that should be deployed (pairs 'address, bytecode')
-    pub other_contracts: Vec<(H160, Vec<[u8; 32]>)>,
-    // How many cycles should a single VM handle (default is DEFAULT_CYCLES_PER_VM_SNAPSHOT = 5)
-    pub cycles_per_vm_snapshot: u32,
-}
-
-impl Default for Options {
-    fn default() -> Self {
-        Self {
-            cycle_limit: DEFAULT_CYCLE_LIMIT,
-            other_contracts: Default::default(),
-            cycles_per_vm_snapshot: DEFAULT_CYCLES_PER_VM_SNAPSHOT,
-        }
-    }
-}
+pub use crate::asm_runner::Options;
 
 pub(crate) fn run_and_try_create_witness_for_extended_state(
     entry_point_bytecode: Vec<[u8; 32]>,
@@ -219,138 +293,17 @@ pub(crate) fn run_and_try_create_witness_for_extended_state(
 }
 
 pub(crate) fn run_with_options(entry_point_bytecode: Vec<[u8; 32]>, options: Options) {
-    use crate::run_vms::{run_vms, RunVmError};
-    use crate::tests::utils::testing_tracer::TestingTracer;
-    use crate::toolset::GeometryConfig;
-    use crate::zk_evm::zkevm_opcode_defs::system_params::BOOTLOADER_FORMAL_ADDRESS;
-
-    let geometry = GeometryConfig {
-        cycles_per_vm_snapshot: options.cycles_per_vm_snapshot,
-        cycles_code_decommitter_sorter: 16,
-        cycles_per_log_demuxer: 8,
-        cycles_per_storage_sorter: 4,
-        cycles_per_events_or_l1_messages_sorter: 2,
-        cycles_per_ram_permutation: 4,
-        cycles_per_code_decommitter: 4,
-        cycles_per_storage_application: 2,
-        cycles_per_keccak256_circuit: 1,
-        cycles_per_sha256_circuit: 1,
-        cycles_per_ecrecover_circuit: 1,
-        cycles_per_secp256r1_verify_circuit: 1,
-        cycles_per_transient_storage_sorter: 4,
-
-        limit_for_l1_messages_pudata_hasher: 8,
-    };
-
-    use crate::witness::tree::BinarySparseStorageTree;
-    use crate::witness::tree::ZKSyncTestingTree;
-
-    let mut used_bytecodes_and_hashes = HashMap::new();
-    used_bytecodes_and_hashes.extend(options.other_contracts.iter().cloned().map(|(_, code)| {
-        let code_hash = bytecode_to_code_hash(&code).unwrap();
-
-        (U256::from_big_endian(&code_hash), code)
-    }));
-
-    // We must pass a correct empty code hash (with proper version) into the run method.
-    let empty_code_hash = U256::from_big_endian(&bytecode_to_code_hash(&[[0; 32]]).unwrap());
-
-    let mut storage_impl = InMemoryCustomRefundStorage::new();
-
-    let mut tree = ZKSyncTestingTree::empty();
-
-    let mut known_contracts = HashMap::new();
-    known_contracts.extend(options.other_contracts.iter().cloned());
-
-    save_predeployed_contracts(&mut storage_impl.storage, &mut tree, &known_contracts);
-
-    let mut basic_block_circuits = vec![];
-
-    // we are using TestingTracer to track prints and exceptions inside out_of_circuit_vm cycles
-    let mut out_of_circuit_tracer =
-        TestingTracer::new(Some(storage_impl.create_refund_controller()));
-
-    let (sender, receiver) = sync_channel(1);
-
-    let artifacts_receiver_handle = thread::spawn(move || {
-        while let Ok(artifact) = receiver.recv() {
-            match artifact {
-                WitnessGenerationArtifact::BaseLayerCircuit(circuit) => {
-                    basic_block_circuits.push(circuit)
-                }
-                _ => {}
-            }
-        }
-
-        basic_block_circuits
-    });
-
-    if let Err(err) = run_vms(
-        Address::zero(),
-        *BOOTLOADER_FORMAL_ADDRESS,
-        entry_point_bytecode,
-        vec![],
-        false,
-        empty_code_hash,
-        empty_code_hash,
-        used_bytecodes_and_hashes,
-        vec![],
-        options.cycle_limit,
-        geometry,
-        storage_impl,
-        tree,
-        "../kzg/src/trusted_setup.json".to_owned(),
-        std::array::from_fn(|_| None),
-        sender,
-        &mut out_of_circuit_tracer,
-    ) {
-        let error_text = match err {
-            RunVmError::InvalidInput(msg) => {
-                format!("Invalid input error: {msg}")
-            }
-            RunVmError::OutOfCircuitExecutionError(msg) => {
-                let msg = if let Some(exception) = out_of_circuit_tracer.exception {
-                    format!("{msg} {exception}")
-                } else {
-                    msg
-                };
-                format!("Out-of-circuit execution error: {msg}")
-            }
-        };
-        panic!("{error_text}");
-    }
-
-    println!("Simulation and witness creation are completed");
-
-    let basic_block_circuits = artifacts_receiver_handle.join().unwrap();
+    let (basic_block_circuits, _) = run_and_collect_circuits(entry_point_bytecode, options);
 
     for el in basic_block_circuits {
         println!("Doing {} circuit", el.short_description());
         base_test_circuit(el);
     }
+}
 
-    // // for el in flattened.into_iter() {
-    // //     use crate::bellman::plonk::better_better_cs::cs::PlonkCsWidth4WithNextStepAndCustomGatesParams;
-    // //     circuit_testing::prove_and_verify_circuit::<Bn256, _, PlonkCsWidth4WithNextStepAndCustomGatesParams>(el).unwrap();
-    // // }
-
-    // let flattened = basic_block_circuits.into_flattened_set();
-    // let flattened_inputs = basic_block_circuits_inputs.into_flattened_set();
-
-    // for (idx, (el, input_value)) in flattened.into_iter().zip(flattened_inputs.into_iter()).enumerate() {
-    //     let descr = el.short_description();
-    //     println!("Doing {}: {}", idx, descr);
-    //     use crate::abstract_zksync_circuit::concrete_circuits::ZkSyncCircuit;
-    //     if !matches!(&el, ZkSyncCircuit::MainVM(..)) {
-    //         continue;
-    //     }
-    //     // el.debug_witness();
-    //     use crate::bellman::plonk::better_better_cs::cs::PlonkCsWidth4WithNextStepAndCustomGatesParams;
-    //     let (is_satisfied, public_input) = circuit_testing::check_if_satisfied::<Bn256, _, PlonkCsWidth4WithNextStepAndCustomGatesParams>(el).unwrap();
-    //     assert!(is_satisfied);
-    //     assert_eq!(public_input, input_value, "Public input diverged for circuit {} of type {}", idx, descr);
-    //     // if public_input != input_value {
-    //     //     println!("Public input diverged for circuit {} of type {}", idx, descr);
-    //     // }
-    // }
+pub(crate) fn run_and_collect_circuits(
+    entry_point_bytecode: Vec<[u8; 32]>,
+    options: Options,
+) -> (Vec<ZkSyncBaseLayerCircuit>, crate::run_vms::RunVMsResult) {
+    crate::asm_runner::execute(entry_point_bytecode, options).unwrap_or_else(|err| panic!("{err}"))
 }
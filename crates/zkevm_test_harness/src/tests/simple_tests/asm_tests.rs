@@ -84,3 +84,98 @@ fn test_manual_asm() {
         Default::default(),
     )
 }
+
+#[test_log::test]
+fn test_asm_runner_panic_expectation() {
+    use crate::asm_runner::{run_asm_test, AsmTest, AsmTestExpectations, ExpectedOutcome};
+    use crate::tests::utils::preprocess_asm::asm_with_default_config;
+
+    let asm = asm_with_default_config(
+        r#"
+    __entry:
+    .main:
+        revert("expected failure")
+    "#,
+    );
+    let mut test = AsmTest {
+        name: "revert".to_owned(),
+        entry_point: asm,
+        contracts: vec![],
+        dictionary: Default::default(),
+        expectations: AsmTestExpectations {
+            outcome: ExpectedOutcome::Panic(Some("expected failure".to_owned())),
+            ..Default::default()
+        },
+        options: Default::default(),
+        check_circuits: true,
+        entry_point_path: None,
+    };
+    let result = run_asm_test(&test);
+    assert!(result.is_success(), "{:?}", result.failure);
+
+    test.expectations.outcome = ExpectedOutcome::Success;
+    let result = run_asm_test(&test);
+    assert!(!result.is_success());
+}
+
+#[test_log::test]
+fn test_asm_runner_coverage() {
+    use crate::asm_runner::coverage::CoverageReport;
+    use crate::asm_runner::{run_asm_test_with_coverage, AsmTest};
+    use crate::tests::utils::preprocess_asm::asm_with_default_config;
+    use crate::zk_evm::coverage::BranchCoverage;
+
+    let asm = asm_with_default_config(
+        r#"
+    __entry:
+    .main:
+        add 1, r0, r1
+        sub! 1, r1, r0
+        jump.ne @.panic
+        print("covered")
+        ret.ok r0
+    .panic:
+        ret.panic r0
+    "#,
+    );
+    let source_line = |text: &str| asm.lines().position(|line| line.contains(text)).unwrap() + 1;
+    let test = AsmTest {
+        name: "coverage".to_owned(),
+        entry_point: asm.clone(),
+        contracts: vec![],
+        dictionary: Default::default(),
+        expectations: Default::default(),
+        options: Default::default(),
+        check_circuits: false,
+        entry_point_path: None,
+    };
+
+    let mut report = CoverageReport::new();
+    let result = run_asm_test_with_coverage(&test, &mut report);
+    assert!(result.is_success(), "{:?}", result.failure);
+
+    let file = &report.files["coverage"];
+    assert_eq!(file.lines[&source_line("jump.ne")], 1);
+    assert_eq!(file.lines[&source_line("print(")], 1);
+    assert_eq!(file.lines[&source_line("ret.panic")], 0);
+    let branches: Vec<_> = file
+        .branches
+        .iter()
+        .map(|((line, _), el)| (*line, *el))
+        .collect();
+    assert_eq!(
+        branches,
+        vec![(
+            source_line("jump.ne"),
+            Some(BranchCoverage {
+                taken: 0,
+                not_taken: 1
+            })
+        )]
+    );
+
+    let lcov = report.to_lcov();
+    assert!(lcov.starts_with("TN:\nSF:coverage\n"));
+    assert!(lcov.contains("BRF:2\nBRH:1\n"));
+    assert!(lcov.contains(&format!("DA:{},0\n", source_line("ret.panic"))));
+}
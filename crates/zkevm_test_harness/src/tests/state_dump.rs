@@ -0,0 +1,250 @@
+use std::collections::HashMap;
+use std::sync::mpsc::sync_channel;
+use std::thread;
+
+use crate::asm_runner::preprocess_asm::{asm_with_default_config, TemplateDictionary};
+use crate::asm_runner::{compile, test_geometry, Options};
+use crate::ethereum_types::{Address, U256};
+use crate::helper::artifact_utils::{save_predeployed_contracts, KNOWN_CODE_HASHES_ADDRESS};
+use crate::run_vms::run_vms;
+use crate::state_dump::{dump_state, SharedStorage, SharedTree, StateDump, StorageSlot};
+use crate::witness::tree::{BinarySparseStorageTree, EnumeratedBinaryLeaf, ZKSyncTestingTree};
+use crate::zk_evm::aux_structures::LogQuery;
+use crate::zk_evm::bytecode_to_code_hash;
+use crate::zk_evm::reference_impls::decommitter::SimpleDecommitter;
+use crate::zk_evm::reference_impls::memory::SimpleMemory;
+use crate::zk_evm::testing::storage::InMemoryStorage;
+use crate::zk_evm::zkevm_opcode_defs::system_params::BOOTLOADER_FORMAL_ADDRESS;
+use crate::zk_evm::GenericNoopTracer;
+
+fn code_hash(bytecode: &[[u8; 32]]) -> U256 {
+    U256::from_big_endian(&bytecode_to_code_hash(bytecode).unwrap())
+}
+
+fn used_bytecodes(bytecodes: &[Vec<[u8; 32]>]) -> HashMap<U256, Vec<[u8; 32]>> {
+    bytecodes
+        .iter()
+        .map(|el| (code_hash(el), el.clone()))
+        .collect()
+}
+
+fn test_dump() -> StateDump {
+    StateDump {
+        next_enumeration_index: 5,
+        storage: vec![
+            StorageSlot {
+                address: Address::from_low_u64_be(0x8002),
+                key: U256::from(0x10000),
+                value: U256::from(42),
+                enumeration_index: 4,
+            },
+            StorageSlot {
+                address: Address::from_low_u64_be(0x10000),
+                key: U256::from(1),
+                value: U256::MAX,
+                enumeration_index: 2,
+            },
+            StorageSlot {
+                address: KNOWN_CODE_HASHES_ADDRESS,
+                key: code_hash(&[[0u8; 32]]),
+                value: U256::from(1),
+                enumeration_index: 3,
+            },
+        ],
+        bytecodes: vec![vec![[0u8; 32]]],
+    }
+}
+
+#[test]
+fn test_load_and_dump() {
+    let dump = test_dump();
+    let mut storage = InMemoryStorage::new();
+    let mut decommitter = SimpleDecommitter::<true>::new();
+    let tree = SharedTree::new(ZKSyncTestingTree::empty());
+    dump.load(&mut storage, &mut decommitter, &mut tree.clone())
+        .unwrap();
+
+    let mut tree = tree.lock();
+    assert_eq!(tree.next_enumeration_index(), 5);
+    for slot in dump.storage.iter() {
+        let index = LogQuery::derive_final_address_for_params(&slot.address, &slot.key);
+        assert_eq!(
+            tree.get_leaf(&index).leaf.current_index(),
+            slot.enumeration_index
+        );
+        assert_eq!(storage.inner[0][&slot.address][&slot.key], slot.value);
+    }
+
+    let used = used_bytecodes(&dump.bytecodes);
+    let mut expected = dump.clone();
+    expected.storage.sort_by_key(|el| el.enumeration_index);
+    assert_eq!(dump_state(&storage, &mut *tree, &used).unwrap(), expected);
+    // the known bytecode is not available
+    assert!(dump_state(&storage, &mut *tree, &HashMap::new()).is_err());
+
+    // the tree is not empty anymore
+    assert!(dump
+        .load(
+            &mut InMemoryStorage::new(),
+            &mut SimpleDecommitter::<true>::new(),
+            &mut *tree
+        )
+        .is_err());
+
+    // the value written to the storage only
+    storage.inner[0]
+        .get_mut(&Address::from_low_u64_be(0x10000))
+        .unwrap()
+        .insert(U256::from(1), U256::from(7));
+    assert!(dump_state(&storage, &mut *tree, &used).is_err());
+}
+
+#[test]
+fn test_serialization() {
+    let dump = test_dump();
+    assert_eq!(StateDump::from_json(&dump.to_json()).unwrap(), dump);
+
+    let mut encoded = vec![];
+    dump.write(&mut encoded).unwrap();
+    assert_eq!(StateDump::read(&encoded[..]).unwrap(), dump);
+    assert!(StateDump::read(&encoded[4..]).is_err());
+
+    let mut invalid = dump.clone();
+    invalid.storage[1].enumeration_index = 4;
+    assert!(invalid.validate().is_err());
+    invalid.storage[1].enumeration_index = 5;
+    assert!(invalid.validate().is_err());
+}
+
+#[test]
+fn test_dump_after_run_vms() {
+    // the bytecode that is published by the entry point, it's never executed
+    let published = vec![[1u8; 32]; 3];
+    // marks the hash from the calldata as known, in the same way as `KnownCodesStorage`
+    let known_codes_storage = compile(
+        &asm_with_default_config(
+            r#"
+__entry:
+.main:
+    ld r1, r2
+    add 1, r0, r3
+    log.swrite r2, r3, r0
+    ret.ok r0
+    "#,
+        ),
+        None,
+        &TemplateDictionary::new(),
+    )
+    .unwrap();
+    let entry_point = compile(
+        &format!(
+            r#"
+    .text
+    .file	"Test_zkevm"
+    .rodata.cst32
+    .p2align	5
+CPI0_0:
+    .cell {}
+    .text
+    .globl	__entry
+__entry:
+.main:
+    add 3, r0, r1
+    add 42, r0, r2
+    log.swrite r1, r2, r0
+    add @CPI0_0[0], r0, r3
+    st.1 r0, r3
+    ; 32 bytes of the heap as the calldata, more ergs than available, so 63/64 of them are passed
+    add 32, r0, r1
+    shl.s 96, r1, r1
+    add 65535, r0, r3
+    shl.s 208, r3, r3
+    or r3, r1, r1
+    add {}, r0, r2
+    far_call r1, r2, @panic
+    ret.ok r0
+panic:
+    ret.panic r0
+    "#,
+            code_hash(&published),
+            KNOWN_CODE_HASHES_ADDRESS.to_low_u64_be()
+        ),
+        None,
+        &TemplateDictionary::new(),
+    )
+    .unwrap();
+    let used = used_bytecodes(&[known_codes_storage.clone(), published.clone()]);
+
+    let storage = SharedStorage::new(InMemoryStorage::new());
+    let tree = SharedTree::new(ZKSyncTestingTree::empty());
+    save_predeployed_contracts(
+        &mut storage.lock(),
+        &mut *tree.lock(),
+        &HashMap::from([(KNOWN_CODE_HASHES_ADDRESS, known_codes_storage.clone())]),
+    );
+
+    let empty_code_hash = code_hash(&[[0; 32]]);
+    let (sender, receiver) = sync_channel(1);
+    let receiver_handle = thread::spawn(move || while receiver.recv().is_ok() {});
+    run_vms(
+        Address::zero(),
+        *BOOTLOADER_FORMAL_ADDRESS,
+        entry_point,
+        vec![],
+        false,
+        empty_code_hash,
+        empty_code_hash,
+        used.clone(),
+        vec![],
+        1 << 10,
+        test_geometry(4),
+        storage.clone(),
+        tree.clone(),
+        Options::default().trusted_setup_path,
+        std::array::from_fn(|_| None),
+        sender,
+        &mut GenericNoopTracer::<SimpleMemory>::new(),
+    )
+    .unwrap();
+    receiver_handle.join().unwrap();
+
+    let dump = dump_state(&storage.lock(), &mut *tree.lock(), &used).unwrap();
+    dump.validate().unwrap();
+    let has_slot = |address: Address, key: U256, value: U256| {
+        dump.storage
+            .iter()
+            .any(|el| el.address == address && el.key == key && el.value == value)
+    };
+    assert!(has_slot(
+        *BOOTLOADER_FORMAL_ADDRESS,
+        U256::from(3),
+        U256::from(42)
+    ));
+    assert!(has_slot(
+        KNOWN_CODE_HASHES_ADDRESS,
+        code_hash(&published),
+        U256::from(1)
+    ));
+    assert_eq!(dump.bytecodes.len(), 2);
+    assert!(dump.bytecodes.contains(&published));
+
+    // the post-state can't be loaded over itself
+    assert!(dump
+        .load(
+            &mut InMemoryStorage::new(),
+            &mut SimpleDecommitter::<true>::new(),
+            &mut tree.clone()
+        )
+        .is_err());
+
+    let mut reloaded_storage = SharedStorage::new(InMemoryStorage::new());
+    let mut decommitter = SimpleDecommitter::<true>::new();
+    let mut reloaded_tree = ZKSyncTestingTree::empty();
+    dump.load(&mut reloaded_storage, &mut decommitter, &mut reloaded_tree)
+        .unwrap();
+    assert_eq!(reloaded_tree.root(), tree.lock().root());
+    assert_eq!(
+        dump_state(&reloaded_storage.lock(), &mut reloaded_tree, &used).unwrap(),
+        dump
+    );
+}
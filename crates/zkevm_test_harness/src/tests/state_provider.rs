@@ -0,0 +1,139 @@
+use std::sync::{Arc, Mutex};
+
+use crate::ethereum_types::{Address, U256};
+use crate::state_dump::{StateDump, StorageSlot};
+use crate::state_provider::{
+    ForkedDecommitter, ForkedStorage, ForkedTree, RecordingProvider, StateDumpProvider,
+    StateProvider,
+};
+use crate::witness::tree::{BinarySparseStorageTree, EnumeratedBinaryLeaf, ZKSyncTestingTree};
+use crate::zk_evm::abstractions::*;
+use crate::zk_evm::aux_structures::*;
+use crate::zk_evm::bytecode_to_code_hash;
+use crate::zk_evm::reference_impls::decommitter::SimpleDecommitter;
+use crate::zk_evm::testing::storage::InMemoryStorage;
+use crate::zk_evm::zkevm_opcode_defs::system_params::STORAGE_AUX_BYTE;
+use crate::zk_evm::zkevm_opcode_defs::{ContractCodeSha256Format, VersionedHashLen32};
+
+fn slot(address: u64, key: u64, value: u64, enumeration_index: u64) -> StorageSlot {
+    StorageSlot {
+        address: Address::from_low_u64_be(address),
+        key: U256::from(key),
+        value: U256::from(value),
+        enumeration_index,
+    }
+}
+
+fn storage_query(address: u64, key: u64, written_value: Option<u64>) -> LogQuery {
+    LogQuery {
+        timestamp: Timestamp(1),
+        tx_number_in_block: 0,
+        aux_byte: STORAGE_AUX_BYTE,
+        shard_id: 0,
+        address: Address::from_low_u64_be(address),
+        key: U256::from(key),
+        read_value: U256::zero(),
+        written_value: U256::from(written_value.unwrap_or_default()),
+        rw_flag: written_value.is_some(),
+        rollback: false,
+        is_service: false,
+    }
+}
+
+#[test]
+fn test_forked_state() {
+    let bytecode = vec![[0u8; 32]];
+    let remote = StateDump {
+        next_enumeration_index: 4,
+        storage: vec![
+            slot(0x10000, 1, 5, 1),
+            slot(0x10000, 2, 6, 2),
+            slot(0x10001, 1, 7, 3),
+        ],
+        bytecodes: vec![bytecode.clone(), vec![[1u8; 32]]],
+    };
+    let provider = Arc::new(Mutex::new(RecordingProvider::new(
+        StateDumpProvider::new(remote).unwrap(),
+    )));
+
+    let mut local = InMemoryStorage::new();
+    local.populate(vec![(
+        0,
+        Address::from_low_u64_be(0x10000),
+        U256::from(2),
+        U256::from(9),
+    )]);
+    let mut storage = ForkedStorage::new(local, provider.clone());
+    let (query, _) = storage.execute_partial_query(0, storage_query(0x10000, 1, None));
+    assert_eq!(query.read_value, U256::from(5));
+    // local state is preferred
+    let (query, _) = storage.execute_partial_query(0, storage_query(0x10000, 2, Some(0)));
+    assert_eq!(query.read_value, U256::from(9));
+    let (query, _) = storage.execute_partial_query(0, storage_query(0x10002, 1, None));
+    assert_eq!(query.read_value, U256::zero());
+
+    let mut tree = ForkedTree::new(ZKSyncTestingTree::empty(), provider.clone());
+    let index = LogQuery::derive_final_address_for_params(
+        &Address::from_low_u64_be(0x10000),
+        &U256::from(1),
+    );
+    assert_eq!(tree.get_leaf(&index).leaf.current_index(), 1);
+    assert_eq!(tree.next_enumeration_index(), 4);
+
+    let mut decommitter =
+        ForkedDecommitter::new(SimpleDecommitter::<true>::new(), provider.clone());
+    let (header, normalized_preimage) = ContractCodeSha256Format::normalize_for_decommitment(
+        &bytecode_to_code_hash(&bytecode).unwrap(),
+    );
+    let query = decommitter
+        .prepare_to_decommit(
+            0,
+            DecommittmentQuery {
+                header,
+                normalized_preimage,
+                timestamp: Timestamp(1),
+                memory_page: MemoryPage(8),
+                decommitted_length: 0,
+                is_fresh: false,
+            },
+        )
+        .unwrap();
+    assert!(query.is_fresh);
+    assert_eq!(query.decommitted_length, 1);
+
+    // only the touched state
+    let pre_state = provider.lock().unwrap().pre_state();
+    assert_eq!(
+        pre_state,
+        StateDump {
+            next_enumeration_index: 4,
+            storage: vec![slot(0x10000, 1, 5, 1)],
+            bytecodes: vec![bytecode],
+        }
+    );
+}
+
+#[test]
+fn test_open_state_dump() {
+    let dump = StateDump {
+        next_enumeration_index: 2,
+        storage: vec![slot(0x10000, 1, 5, 1)],
+        bytecodes: vec![],
+    };
+    let dir = std::env::temp_dir();
+    let json_path = dir.join(format!("state_provider_{}.json", std::process::id()));
+    let binary_path = dir.join(format!("state_provider_{}.bin", std::process::id()));
+    std::fs::write(&json_path, dump.to_json()).unwrap();
+    dump.write(std::fs::File::create(&binary_path).unwrap())
+        .unwrap();
+
+    for path in [json_path, binary_path] {
+        let mut provider = StateDumpProvider::open(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(
+            provider.storage_slot(Address::from_low_u64_be(0x10000), U256::from(1)),
+            Some(dump.storage[0])
+        );
+        assert_eq!(provider.next_enumeration_index(), 2);
+    }
+}
@@ -1,2 +1,2 @@
-pub mod preprocess_asm;
-pub mod testing_tracer;
+pub use crate::asm_runner::preprocess_asm;
+pub use crate::asm_runner::testing_tracer;
@@ -0,0 +1,160 @@
+use crate::asm_runner::AsmTest;
+use crate::ethereum_types::U256;
+use crate::tests::utils::preprocess_asm::asm_with_default_config;
+use crate::vm_debugger::{compile_test, create_vm, AsmDebugger, Repl};
+use crate::zk_evm::debugger::{Breakpoint, StepMode, StopReason};
+use crate::zk_evm::vm_state::heap_page_from_base;
+
+fn test_program() -> AsmTest {
+    program(
+        r#"
+    __entry:
+    .main:
+        add 3, r0, r1
+        add 10000, r0, r4
+        near_call r4, @inner, @.panic
+        add 5, r0, r2
+        ret.ok r0
+    inner:
+        add 15, r0, r3
+        log.swrite r1, r3, r0
+        ret.ok r0
+    .panic:
+        ret.panic r0
+    "#,
+    )
+}
+
+fn program(asm: &str) -> AsmTest {
+    AsmTest {
+        name: "program".to_owned(),
+        entry_point: asm_with_default_config(asm),
+        contracts: vec![],
+        dictionary: Default::default(),
+        expectations: Default::default(),
+        options: Default::default(),
+        check_circuits: false,
+        entry_point_path: None,
+    }
+}
+
+#[test]
+fn test_step_modes() {
+    let contracts = compile_test(&test_program()).unwrap();
+    let mut debugger = AsmDebugger::new(create_vm(&contracts));
+    debugger.set_function_labels(
+        contracts[0].address,
+        contracts[0].assembly.function_labels.clone(),
+    );
+
+    assert_eq!(debugger.step(StepMode::Cycle, 1), StopReason::Step);
+    assert_eq!(debugger.register(1).value, U256::from(3));
+    assert_eq!(debugger.cycles(), 1);
+
+    // until the near call
+    assert_eq!(debugger.step(StepMode::NearCall, 100), StopReason::Step);
+    assert_eq!(debugger.callstack().len(), 2);
+    assert!(debugger.current_frame().is_local_frame);
+    assert_eq!(
+        debugger.function_label(debugger.current_frame()),
+        Some("inner")
+    );
+
+    // until the return from it
+    assert_eq!(debugger.step(StepMode::NearCall, 100), StopReason::Step);
+    assert_eq!(debugger.callstack().len(), 1);
+    assert_eq!(debugger.register(3).value, U256::from(15));
+
+    let id = debugger.add_breakpoint(Breakpoint::Opcode("Ret".to_owned()));
+    assert_eq!(
+        debugger.step(StepMode::Continue, 100),
+        StopReason::Breakpoint(id)
+    );
+    assert_eq!(debugger.register(2).value, U256::from(5));
+    assert!(debugger.remove(id));
+    assert_eq!(
+        debugger.step(StepMode::Continue, 100),
+        StopReason::ExecutionEnded
+    );
+}
+
+#[test]
+fn test_repl_breakpoints_and_watchpoints() {
+    let mut repl = Repl::new(compile_test(&test_program()).unwrap());
+
+    assert_eq!(repl.execute("break fn inner").unwrap(), "breakpoint 1");
+    let output = repl.execute("continue").unwrap();
+    assert!(output.starts_with("breakpoint 1\n"), "{}", output);
+    assert!(output.contains("in inner"), "{}", output);
+    assert!(output.contains("| add 15, r0, r3"), "{}", output);
+    assert!(repl
+        .execute("bt")
+        .unwrap()
+        .starts_with("#1 near program pc"));
+
+    assert_eq!(
+        repl.execute("watch storage 0x8001 3").unwrap(),
+        "watchpoint 2"
+    );
+    let output = repl.execute("continue").unwrap();
+    assert!(
+        output.starts_with("watchpoint 2: StorageWrite"),
+        "{}",
+        output
+    );
+
+    assert_eq!(repl.execute("watch reg 2").unwrap(), "watchpoint 3");
+    let output = repl.execute("c").unwrap();
+    assert!(output.starts_with("watchpoint 3: Value"), "{}", output);
+    assert!(repl.execute("regs").unwrap().starts_with("r1  0x3\n"));
+
+    assert!(repl.execute("watch reg 16").is_err());
+    assert!(repl.execute("delete 42").is_err());
+    assert!(repl.execute("ptr 2").is_err());
+    assert!(repl.execute("unknown").is_err());
+
+    // the return of the root frame clears the registers
+    let output = repl.execute("continue").unwrap();
+    assert!(output.starts_with("watchpoint 3: Value"), "{}", output);
+    assert_eq!(repl.execute("delete 3").unwrap(), "deleted 3");
+    let output = repl.execute("continue").unwrap();
+    assert!(output.starts_with("execution has ended"), "{}", output);
+}
+
+#[test]
+fn test_repl_memory_types() {
+    let contracts = compile_test(&program(
+        r#"
+    __entry:
+    .main:
+        add 7, r0, r2
+        st.2 r0, r2
+        add 9, r0, r2
+        st.1 r0, r2
+        ret.ok r0
+    "#,
+    ))
+    .unwrap();
+    let mut repl = Repl::new(contracts);
+
+    assert_eq!(
+        repl.execute("watch mem aux_heap 0").unwrap(),
+        "watchpoint 1"
+    );
+    let output = repl.execute("continue").unwrap();
+    assert!(output.starts_with("watchpoint 1: Value"), "{}", output);
+    assert_eq!(repl.execute("mem aux_heap 0").unwrap(), "0x7");
+    assert_eq!(repl.execute("mem heap 0").unwrap(), "0x0");
+
+    repl.execute("step 2").unwrap();
+    assert_eq!(repl.execute("mem heap 0").unwrap(), "0x9");
+    let heap_page = heap_page_from_base(repl.debugger().current_frame().base_memory_page);
+    assert_eq!(
+        repl.execute(&format!("mem {} 0", heap_page.0)).unwrap(),
+        "0x9"
+    );
+
+    assert!(repl.execute("mem heap").is_err());
+    assert!(repl.execute("mem static 0").is_err());
+    assert!(repl.execute("watch mem heap 0 1").is_err());
+}
@@ -0,0 +1,139 @@
+use std::collections::HashMap;
+
+use crate::asm_runner::AsmTest;
+use crate::ethereum_types::Address;
+use crate::helper::artifact_utils::TestArtifact;
+use crate::tests::utils::preprocess_asm::asm_with_default_config;
+use crate::vm_debugger::compile_test;
+use crate::vm_diff::{diff_configurations, DiffConfiguration};
+use crate::vm_trace::record_trace;
+use crate::zk_evm::trace_diff::{first_frame_divergence, split_into_frames};
+use crate::zk_evm::zkevm_opcode_defs::system_params::BOOTLOADER_FORMAL_ADDRESS;
+use crate::zk_evm::zkevm_opcode_defs::Opcode;
+
+fn test_program(entry_point: String, contracts: Vec<(u64, String)>) -> AsmTest {
+    AsmTest {
+        name: "program".to_owned(),
+        entry_point,
+        contracts,
+        dictionary: Default::default(),
+        expectations: Default::default(),
+        options: Default::default(),
+        check_circuits: false,
+        entry_point_path: None,
+    }
+}
+
+/// `inner` is placed at the start of the third code word (4 instructions per word), so the labels
+/// and the code words read before it don't depend on it
+fn near_call_program(inner: &str) -> AsmTest {
+    test_program(
+        asm_with_default_config(&format!(
+            r#"
+    __entry:
+    .main:
+        add 10000, r0, r4
+        near_call r4, @inner, @.panic
+        add 5, r0, r2
+        ret.ok r0
+    .panic:
+        ret.panic r0
+    inner:
+        add 15, r0, r3
+        add 16, r0, r3
+        add 17, r0, r3
+        {inner}
+        ret.ok r0
+    "#
+        )),
+        vec![],
+    )
+}
+
+#[test]
+fn test_frame_alignment() {
+    let expected = record_trace(&compile_test(&near_call_program("")).unwrap(), 1000).unwrap();
+    let actual = record_trace(
+        &compile_test(&near_call_program("add 1, r0, r4")).unwrap(),
+        1000,
+    )
+    .unwrap();
+
+    let frames = split_into_frames(&expected);
+    assert_eq!(frames.len(), 2);
+    assert_eq!(frames[1].path, vec![0]);
+    assert!(first_frame_divergence(&expected, &expected).is_none());
+
+    let divergence = first_frame_divergence(&expected, &actual).unwrap();
+    assert_eq!(divergence.path, vec![0]);
+    assert_eq!(divergence.position, 3);
+    assert!(
+        divergence.difference.starts_with("opcode"),
+        "{}",
+        divergence
+    );
+    assert_eq!(divergence.callstack.len(), 2);
+    assert_eq!(divergence.callstack[1].call_pc, Some(1));
+    assert!(matches!(
+        divergence.expected.unwrap().decoded_opcode().variant.opcode,
+        Opcode::Ret(_)
+    ));
+}
+
+#[test]
+fn test_diff_configurations() {
+    let callee =
+        |body: &str| asm_with_default_config(&format!("__entry:\n.main:\n{body}\nret.ok r0\n"));
+    let test = test_program(
+        asm_with_default_config(
+            r#"
+    __entry:
+    .main:
+        add 1, r0, r2
+        shl.s 16, r2, r2
+        add 0, r0, r1
+        far_call r1, r2, @.panic
+        ret.ok r0
+    .panic:
+        ret.panic r0
+    "#,
+        ),
+        vec![(65536, callee("add 1, r0, r3"))],
+    );
+    let contracts = compile_test(&test).unwrap();
+    let artifact = TestArtifact {
+        entry_point_address: *BOOTLOADER_FORMAL_ADDRESS,
+        entry_point_code: contracts[0].bytecode.clone(),
+        default_account_code: contracts[1].bytecode.clone(),
+        evm_simulator_code: contracts[1].bytecode.clone(),
+        predeployed_contracts: HashMap::from([(
+            contracts[1].address,
+            contracts[1].bytecode.clone(),
+        )]),
+    };
+
+    let old = DiffConfiguration::default();
+    assert!(diff_configurations(&artifact, &[], &old, &old, 10000)
+        .unwrap()
+        .is_none());
+
+    let other_callee = compile_test(&test_program(callee("add 2, r0, r3"), vec![])).unwrap();
+    let new = DiffConfiguration {
+        contracts: HashMap::from([(
+            Address::from_low_u64_be(65536),
+            other_callee[0].bytecode.clone(),
+        )]),
+        ..Default::default()
+    };
+    // the code hash of the callee is read from the storage by the far call in the root frame
+    let divergence = diff_configurations(&artifact, &[], &old, &new, 10000)
+        .unwrap()
+        .unwrap();
+    assert!(divergence.path.is_empty());
+    assert!(
+        divergence.difference.starts_with("log query"),
+        "{}",
+        divergence
+    );
+    assert_eq!(divergence.callstack.len(), 1);
+}
@@ -0,0 +1,69 @@
+use crate::asm_runner::AsmTest;
+use crate::ethereum_types::U256;
+use crate::tests::utils::preprocess_asm::asm_with_default_config;
+use crate::vm_debugger::compile_test;
+use crate::vm_trace::{read_trace, record_trace, replay_trace, write_trace};
+use crate::zk_evm::zkevm_opcode_defs::{LogOpcode, Opcode};
+
+fn test_program(value: u64) -> AsmTest {
+    let asm = asm_with_default_config(&format!(
+        r#"
+    __entry:
+    .main:
+        add {value}, r0, r2
+        add 3, r0, r1
+        log.swrite r1, r2, r0
+        log.sread r1, r0, r3
+        ret.ok r0
+    "#
+    ));
+
+    AsmTest {
+        name: "program".to_owned(),
+        entry_point: asm,
+        contracts: vec![],
+        dictionary: Default::default(),
+        expectations: Default::default(),
+        options: Default::default(),
+        check_circuits: false,
+        entry_point_path: None,
+    }
+}
+
+#[test]
+fn test_record_and_replay() {
+    let contracts = compile_test(&test_program(15)).unwrap();
+    let trace = record_trace(&contracts, 1000).unwrap();
+
+    let write = trace
+        .cycles
+        .iter()
+        .find(|el| el.decoded_opcode().variant.opcode == Opcode::Log(LogOpcode::StorageWrite))
+        .unwrap();
+    assert_eq!(write.log_queries.len(), 1);
+    assert_eq!(write.log_queries[0].written_value, U256::from(15));
+    assert!(trace.cycles.iter().any(|el| !el.memory_queries.is_empty()));
+
+    let mut encoded = vec![];
+    write_trace(&trace, &mut encoded).unwrap();
+    assert_eq!(read_trace(&encoded[..]).unwrap(), trace);
+    assert!(read_trace(&encoded[1..]).is_err());
+
+    replay_trace(&contracts, &trace).unwrap();
+
+    // the same trace against a program with a different immediate. It's in the first instruction,
+    // as the code word is read by the first cycle: a later instruction would diverge in that read
+    let divergence = replay_trace(&compile_test(&test_program(16)).unwrap(), &trace).unwrap_err();
+    assert!(
+        divergence.difference.starts_with("opcode"),
+        "{}",
+        divergence
+    );
+    assert_eq!(divergence.cycle, 0);
+    assert_eq!(
+        Some(divergence),
+        trace.first_divergence(
+            &record_trace(&compile_test(&test_program(16)).unwrap(), 1000).unwrap()
+        )
+    );
+}
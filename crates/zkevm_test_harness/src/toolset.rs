@@ -1,9 +1,17 @@
+use std::collections::HashMap;
+
+use crate::run_vms::SCHEDULER_TIMESTAMP;
 use crate::witness::tracer::tracer::WitnessTracer;
-use crate::zk_evm::abstractions::Storage;
+use crate::zk_evm::abstractions::{DecommittmentProcessor, Storage};
+use crate::zk_evm::aux_structures::{DecommittmentQuery, MemoryPage, Timestamp};
 use crate::zk_evm::reference_impls::decommitter::SimpleDecommitter;
 use crate::zk_evm::reference_impls::event_sink::InMemoryEventSink;
+use crate::zk_evm::reference_impls::memory::MemoryLimits;
+use crate::zk_evm::witness_trace::VmWitnessTracer;
 use crate::zk_evm::zk_evm_abstractions::precompiles::DefaultPrecompilesProcessor;
+use crate::zk_evm::zkevm_opcode_defs::definitions::versioned_hash::ContractCodeSha256Format;
 use crate::zk_evm::zkevm_opcode_defs::system_params::VM_INITIAL_FRAME_ERGS;
+use crate::zk_evm::{bytecode_to_code_hash, contract_bytecode_to_words};
 
 /// Set should only differ due to another storage that would be sustituted from outside,
 /// and all other tools can be as simple as possible
@@ -38,8 +46,76 @@ pub fn create_tools<S: Storage>(storage: S, config: &GeometryConfig) -> ProvingT
     }
 }
 
+/// Tools for the execution of the entry point: the decommitter is filled with the used bytecodes and the entry point,
+/// and the entry point is decommitted into the bootloader code page.
+/// Returns the entry point decommittment query and its witness too, they are needed for the witness generation
+pub fn create_tools_for_entry_point<S: Storage>(
+    storage: S,
+    config: &GeometryConfig,
+    memory_limits: MemoryLimits,
+    entry_point_code: &[[u8; 32]],
+    used_bytecodes: impl IntoIterator<Item = (U256, Vec<[u8; 32]>)>,
+) -> (ProvingToolset<S>, DecommittmentQuery, Vec<U256>) {
+    let mut tools = create_tools(storage, config);
+    tools.memory.set_limits(memory_limits);
+
+    let bytecode_hash = bytecode_to_code_hash(entry_point_code).unwrap();
+    let mut to_fill: HashMap<_, _> = used_bytecodes
+        .into_iter()
+        .map(|(hash, bytecode)| (hash, contract_bytecode_to_words(&bytecode)))
+        .collect();
+    to_fill
+        .entry(U256::from_big_endian(&bytecode_hash))
+        .or_insert_with(|| contract_bytecode_to_words(entry_point_code));
+    tools
+        .decommittment_processor
+        .populate(to_fill.into_iter().collect());
+
+    let (header, normalized_preimage) =
+        ContractCodeSha256Format::normalize_for_decommitment(&bytecode_hash);
+
+    // bootloader decommit query
+    let entry_point_decommittment_query = DecommittmentQuery {
+        header,
+        normalized_preimage,
+        timestamp: Timestamp(SCHEDULER_TIMESTAMP),
+        memory_page: MemoryPage(BOOTLOADER_CODE_PAGE),
+        decommitted_length: entry_point_code.len() as u16,
+        is_fresh: true,
+    };
+
+    // manually decommit entry point
+    let prepared_entry_point_decommittment_query = tools
+        .decommittment_processor
+        .prepare_to_decommit(0, entry_point_decommittment_query)
+        .expect("must prepare decommit of entry point");
+    tools
+        .witness_tracer
+        .prepare_for_decommittment(0, entry_point_decommittment_query);
+    let entry_point_decommittment_query_witness = tools
+        .decommittment_processor
+        .decommit_into_memory(
+            0,
+            prepared_entry_point_decommittment_query,
+            &mut tools.memory,
+        )
+        .expect("must execute decommit of entry point")
+        .unwrap();
+    tools.witness_tracer.execute_decommittment(
+        0,
+        entry_point_decommittment_query,
+        entry_point_decommittment_query_witness.clone(),
+    );
+
+    (
+        tools,
+        entry_point_decommittment_query,
+        entry_point_decommittment_query_witness,
+    )
+}
+
 use crate::entry_point::initial_out_of_circuit_context;
-use crate::ethereum_types::Address;
+use crate::ethereum_types::{Address, U256};
 use crate::zk_evm::block_properties::BlockProperties;
 use crate::zk_evm::reference_impls::memory::SimpleMemory;
 use crate::zk_evm::vm_state::{PrimitiveValue, VmState};
@@ -0,0 +1,55 @@
+use std::io::{BufRead, Write};
+use std::path::PathBuf;
+
+use structopt::StructOpt;
+use zkevm_test_harness::asm_runner::AsmTest;
+use zkevm_test_harness::vm_debugger::{compile_test, Repl, DEFAULT_MAX_CYCLES_PER_COMMAND};
+
+#[derive(Debug, StructOpt)]
+#[structopt(
+    name = "EraVM debugger",
+    about = "Steps through an .asm test in the out-of-circuit VM, type `help` for the commands"
+)]
+struct Opt {
+    /// Test directory (with `entry.asm` and `ADDRESS.asm` dependencies) or a single `.asm` file
+    test: PathBuf,
+    /// Limit of cycles for a single `continue`, `next` or `far` command
+    #[structopt(long, default_value = "100000")]
+    max_cycles: u64,
+}
+
+fn main() {
+    let opt = Opt::from_args();
+
+    let test = if opt.test.is_dir() {
+        AsmTest::from_dir(&opt.test)
+    } else {
+        AsmTest::from_file(&opt.test)
+    };
+    let test = test.unwrap_or_else(|el| panic!("Unable to load test {:?}: {}", opt.test, el));
+    let contracts = compile_test(&test).unwrap_or_else(|el| panic!("{}", el));
+
+    let mut repl = Repl::new(contracts);
+    if opt.max_cycles != DEFAULT_MAX_CYCLES_PER_COMMAND {
+        repl.max_cycles_per_command = opt.max_cycles;
+    }
+    println!("{}", repl.location());
+
+    let stdin = std::io::stdin();
+    loop {
+        print!("(dbg) ");
+        std::io::stdout().flush().unwrap();
+        let mut line = String::new();
+        if stdin.lock().read_line(&mut line).unwrap() == 0 {
+            break;
+        }
+        match line.trim() {
+            "quit" | "q" => break,
+            line => match repl.execute(line) {
+                Ok(output) if output.is_empty() => {}
+                Ok(output) => println!("{}", output),
+                Err(err) => println!("error: {}", err),
+            },
+        }
+    }
+}
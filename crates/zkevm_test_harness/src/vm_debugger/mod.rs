@@ -0,0 +1,501 @@
+//! Line-oriented REPL over the step debugger of zk_evm (`zk_evm::debugger`).
+//! Programs are the assembly tests in the `asm_runner` format, they are compiled with the debug information
+//! (function labels and source lines) and executed in the out-of-circuit VM, without the circuits.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+
+use crate::asm_runner::coverage::{mark_source_lines, pc_source_lines};
+use crate::asm_runner::preprocess_asm::TemplateDictionary;
+use crate::asm_runner::storage::InMemoryCustomRefundStorage;
+use crate::asm_runner::{assemble, test_geometry, AsmTest};
+use crate::entry_point::create_out_of_circuit_global_context;
+use crate::ethereum_types::{Address, U256};
+use crate::helper::artifact_utils::save_predeployed_contracts;
+use crate::toolset::{create_out_of_circuit_vm, create_tools_for_entry_point};
+use crate::witness::tracer::tracer::WitnessTracer;
+use crate::witness::tree::{BinarySparseStorageTree, ZKSyncTestingTree};
+use crate::zk_evm::abstractions::MemoryType;
+use crate::zk_evm::aux_structures::*;
+use crate::zk_evm::bytecode_to_code_hash;
+use crate::zk_evm::debugger::{Breakpoint, Debugger, StepMode, StopReason, Watchpoint};
+use crate::zk_evm::reference_impls::decommitter::SimpleDecommitter;
+use crate::zk_evm::reference_impls::event_sink::InMemoryEventSink;
+use crate::zk_evm::reference_impls::memory::{MemoryLimits, SimpleMemory};
+use crate::zk_evm::vm_state::{
+    aux_heap_page_from_base, heap_page_from_base, stack_page_from_base, PrimitiveValue, VmState,
+};
+use crate::zk_evm::zk_evm_abstractions::precompiles::DefaultPrecompilesProcessor;
+use crate::zk_evm::zkevm_opcode_defs::system_params::BOOTLOADER_FORMAL_ADDRESS;
+use zkevm_assembly::Assembly;
+
+/// Witness is not used, so the snapshots can be rare
+const CYCLES_PER_VM_SNAPSHOT: u32 = 1024;
+/// Limit for a single `continue`, `next` or `far` command, so the infinite loops don't hang the REPL
+pub const DEFAULT_MAX_CYCLES_PER_COMMAND: u64 = 100_000;
+
+pub type AsmVm = VmState<
+    InMemoryCustomRefundStorage,
+    SimpleMemory,
+    InMemoryEventSink,
+    DefaultPrecompilesProcessor<true>,
+    SimpleDecommitter<true>,
+    WitnessTracer,
+>;
+
+pub type AsmDebugger = Debugger<
+    InMemoryCustomRefundStorage,
+    InMemoryEventSink,
+    DefaultPrecompilesProcessor<true>,
+    SimpleDecommitter<true>,
+    WitnessTracer,
+>;
+
+/// Compiled contract with the debug information
+pub struct DebugContract {
+    pub address: Address,
+    /// Source file, or the test name if the test wasn't loaded from the disk
+    pub name: String,
+    pub source: String,
+    pub assembly: Assembly,
+    pub bytecode: Vec<[u8; 32]>,
+    /// Lines of the source (1-based) per pc
+    pub pc_lines: BTreeMap<usize, usize>,
+}
+
+impl DebugContract {
+    fn source_line(&self, line: usize) -> &str {
+        self.source
+            .lines()
+            .nth(line.wrapping_sub(1))
+            .unwrap_or_default()
+            .trim()
+    }
+}
+
+/// Compiles the test, the entry point goes first
+pub fn compile_test(test: &AsmTest) -> Result<Vec<DebugContract>, String> {
+    let dictionary: TemplateDictionary = test
+        .dictionary
+        .iter()
+        .map(|(key, value)| (key.as_str(), value.clone()))
+        .collect();
+
+    let mut contracts = vec![];
+    for (address, source) in test.contracts.iter() {
+        let (assembly, bytecode) = assemble(&mark_source_lines(source), None, &dictionary)?;
+        contracts.push(DebugContract {
+            address: Address::from_low_u64_be(*address),
+            name: test.source_name(Some(*address)),
+            source: source.clone(),
+            pc_lines: pc_source_lines(&assembly),
+            assembly,
+            bytecode,
+        });
+    }
+    let deployed = contracts
+        .iter()
+        .map(|el| (el.address, el.bytecode.clone()))
+        .collect();
+    let (assembly, bytecode) = assemble(
+        &mark_source_lines(&test.entry_point),
+        Some(&deployed),
+        &dictionary,
+    )?;
+    contracts.insert(
+        0,
+        DebugContract {
+            address: *BOOTLOADER_FORMAL_ADDRESS,
+            name: test.source_name(None),
+            source: test.entry_point.clone(),
+            pc_lines: pc_source_lines(&assembly),
+            assembly,
+            bytecode,
+        },
+    );
+
+    Ok(contracts)
+}
+
+/// Out-of-circuit VM set up in the same way as in `run_vms`: the first contract is the entry point
+/// at the bootloader address, others are deployed at their addresses
+pub fn create_vm(contracts: &[DebugContract]) -> AsmVm {
+    let entry_point = &contracts[0];
+    let mut storage_impl = InMemoryCustomRefundStorage::new();
+    let mut tree = ZKSyncTestingTree::empty();
+    let known_contracts: HashMap<_, _> = contracts[1..]
+        .iter()
+        .map(|el| (el.address, el.bytecode.clone()))
+        .collect();
+    save_predeployed_contracts(&mut storage_impl.storage, &mut tree, &known_contracts);
+
+    let (tools, _, _) = create_tools_for_entry_point(
+        storage_impl,
+        &test_geometry(CYCLES_PER_VM_SNAPSHOT),
+        MemoryLimits::default(),
+        &entry_point.bytecode,
+        contracts.iter().map(|el| {
+            let code_hash = bytecode_to_code_hash(&el.bytecode).unwrap();
+            (U256::from_big_endian(&code_hash), el.bytecode.clone())
+        }),
+    );
+
+    // We must pass a correct empty code hash (with proper version) into the run method.
+    let empty_code_hash = U256::from_big_endian(&bytecode_to_code_hash(&[[0; 32]]).unwrap());
+    let block_properties =
+        create_out_of_circuit_global_context(false, empty_code_hash, empty_code_hash);
+
+    create_out_of_circuit_vm(
+        tools,
+        block_properties,
+        Address::zero(),
+        *BOOTLOADER_FORMAL_ADDRESS,
+    )
+}
+
+pub const HELP: &str = "\
+step [N]                      execute N cycles (1 by default)
+next                          run until the next near or far call or return
+far                           run until the next far call or return
+continue                      run until a breakpoint, a watchpoint or the end of execution
+break pc PC [ADDRESS]         stop before the instruction at PC (in any contract if ADDRESS is not set)
+break opcode NAME             stop before the opcode, NAME is a prefix of its name, e.g. FarCall or Log(StorageWrite)
+break address ADDRESS         stop on entering the code of ADDRESS
+break fn LABEL [ADDRESS]      stop at the function label (in the entry point if ADDRESS is not set)
+watch reg N                   stop when the register rN changes
+watch mem PAGE|TYPE INDEX     stop when the memory word changes
+watch storage ADDRESS KEY     stop on any access to the storage slot
+delete ID                     remove the breakpoint or watchpoint
+info                          list the breakpoints and watchpoints
+regs                          registers and flags
+bt                            call stack
+frame                         fields of the current frame
+ptr N                         fat pointer in the register rN
+mem PAGE|TYPE INDEX           memory word, TYPE is the heap, aux_heap, stack or code page of the current frame
+list                          source around the current instruction
+quit                          exit
+Numbers and addresses can be decimal or 0x-prefixed hex.";
+
+fn parse_u256(value: &str) -> Result<U256, String> {
+    let result = match value.strip_prefix("0x") {
+        Some(hex) => U256::from_str_radix(hex, 16).ok(),
+        None => U256::from_dec_str(value).ok(),
+    };
+    result.ok_or_else(|| format!("Invalid number {:?}", value))
+}
+
+fn parse_u64(value: &str) -> Result<u64, String> {
+    let result = parse_u256(value)?;
+    if result > U256::from(u64::MAX) {
+        return Err(format!("Number {} is too large", value));
+    }
+    Ok(result.as_u64())
+}
+
+fn parse_address(value: &str) -> Result<Address, String> {
+    let value = parse_u256(value)?;
+    let mut bytes = [0u8; 32];
+    value.to_big_endian(&mut bytes);
+    if bytes[..12].iter().any(|el| *el != 0) {
+        return Err(format!("Address {:#x} is too large", value));
+    }
+    Ok(Address::from_slice(&bytes[12..]))
+}
+
+fn format_value(value: &PrimitiveValue) -> String {
+    if value.is_pointer {
+        format!("{:#x} (ptr)", value.value)
+    } else {
+        format!("{:#x}", value.value)
+    }
+}
+
+pub struct Repl {
+    debugger: AsmDebugger,
+    contracts: Vec<DebugContract>,
+    pub max_cycles_per_command: u64,
+}
+
+impl Repl {
+    pub fn new(contracts: Vec<DebugContract>) -> Self {
+        let mut debugger = AsmDebugger::new(create_vm(&contracts));
+        for contract in contracts.iter() {
+            debugger
+                .set_function_labels(contract.address, contract.assembly.function_labels.clone());
+        }
+
+        Self {
+            debugger,
+            contracts,
+            max_cycles_per_command: DEFAULT_MAX_CYCLES_PER_COMMAND,
+        }
+    }
+
+    pub fn debugger(&self) -> &AsmDebugger {
+        &self.debugger
+    }
+
+    /// `PAGE INDEX`, or `TYPE INDEX` for the page of the current frame
+    fn memory_location(&self, args: &[&str]) -> Result<MemoryLocation, String> {
+        let [page, index] = args else {
+            return Err("Expected PAGE|TYPE INDEX".to_owned());
+        };
+        let frame = self.debugger.current_frame();
+        let (memory_type, page) = match *page {
+            "heap" => (
+                MemoryType::Heap,
+                heap_page_from_base(frame.base_memory_page),
+            ),
+            "aux_heap" => (
+                MemoryType::AuxHeap,
+                aux_heap_page_from_base(frame.base_memory_page),
+            ),
+            "stack" => (
+                MemoryType::Stack,
+                stack_page_from_base(frame.base_memory_page),
+            ),
+            "code" => (MemoryType::Code, frame.code_page),
+            page => (MemoryType::Heap, MemoryPage(parse_u64(page)? as u32)),
+        };
+
+        Ok(MemoryLocation {
+            memory_type,
+            page,
+            index: MemoryIndex(parse_u64(index)? as u32),
+        })
+    }
+
+    fn contract(&self, address: &Address) -> Option<&DebugContract> {
+        self.contracts.iter().find(|el| el.address == *address)
+    }
+
+    /// Current contract, pc, function label and source line
+    pub fn location(&self) -> String {
+        if self.debugger.vm().execution_has_ended() {
+            return "execution has ended".to_owned();
+        }
+        let frame = self.debugger.current_frame();
+        let contract = self.contract(&frame.code_address);
+        let name = contract
+            .map(|el| el.name.clone())
+            .unwrap_or_else(|| format!("{:?}", frame.code_address));
+        let mut result = format!("{} pc {}", name, frame.pc);
+        if let Some(label) = self.debugger.function_label(frame) {
+            write!(result, " in {}", label).unwrap();
+        }
+        if let Some(opcode) = self.debugger.next_opcode() {
+            write!(result, ": {:?}", opcode.variant.opcode).unwrap();
+        }
+        if let Some(contract) = contract {
+            if let Some(line) = contract.pc_lines.get(&(frame.pc as usize)) {
+                write!(result, "\n{:>5} | {}", line, contract.source_line(*line)).unwrap();
+            }
+        }
+
+        result
+    }
+
+    fn describe_stop(&self, reason: StopReason) -> String {
+        let reason = match reason {
+            StopReason::Step => String::new(),
+            StopReason::Breakpoint(id) => format!("breakpoint {}\n", id),
+            StopReason::Watchpoint { id, change } => format!("watchpoint {}: {:?}\n", id, change),
+            StopReason::ExecutionEnded => "execution has ended\n".to_owned(),
+            StopReason::CycleLimit => {
+                format!("stopped after {} cycles\n", self.max_cycles_per_command)
+            }
+            StopReason::Error(err) => format!("VM error: {}\n", err),
+        };
+
+        format!("{}{}", reason, self.location())
+    }
+
+    fn run(&mut self, mode: StepMode) -> String {
+        let reason = self.debugger.step(mode, self.max_cycles_per_command);
+        self.describe_stop(reason)
+    }
+
+    fn add_breakpoint(&mut self, args: &[&str]) -> Result<String, String> {
+        let breakpoint = match args {
+            ["pc", pc] => Breakpoint::Pc {
+                address: None,
+                pc: parse_u64(pc)? as u16,
+            },
+            ["pc", pc, address] => Breakpoint::Pc {
+                address: Some(parse_address(address)?),
+                pc: parse_u64(pc)? as u16,
+            },
+            ["opcode", name] => Breakpoint::Opcode(name.to_string()),
+            ["address", address] => Breakpoint::Address(parse_address(address)?),
+            ["fn", label] => Breakpoint::Function {
+                address: *BOOTLOADER_FORMAL_ADDRESS,
+                label: label.to_string(),
+            },
+            ["fn", label, address] => Breakpoint::Function {
+                address: parse_address(address)?,
+                label: label.to_string(),
+            },
+            _ => return Err("Usage: break pc|opcode|address|fn ...".to_owned()),
+        };
+        let id = self.debugger.add_breakpoint(breakpoint);
+
+        Ok(format!("breakpoint {}", id))
+    }
+
+    fn add_watchpoint(&mut self, args: &[&str]) -> Result<String, String> {
+        let watchpoint = match args {
+            ["reg", idx] => {
+                let idx = parse_u64(idx)? as usize;
+                if idx == 0 || idx > 15 {
+                    return Err("Registers are r1..r15".to_owned());
+                }
+                Watchpoint::Register(idx)
+            }
+            ["mem", location @ ..] => Watchpoint::Memory(self.memory_location(location)?),
+            ["storage", address, key] => Watchpoint::Storage {
+                address: parse_address(address)?,
+                key: parse_u256(key)?,
+            },
+            _ => return Err("Usage: watch reg|mem|storage ...".to_owned()),
+        };
+        let id = self.debugger.add_watchpoint(watchpoint);
+
+        Ok(format!("watchpoint {}", id))
+    }
+
+    fn info(&self) -> String {
+        let mut result = String::new();
+        let mut breakpoints: Vec<_> = self.debugger.breakpoints().collect();
+        breakpoints.sort_by_key(|(id, _)| **id);
+        for (id, breakpoint) in breakpoints {
+            writeln!(result, "{}: {:?}", id, breakpoint).unwrap();
+        }
+        let mut watchpoints: Vec<_> = self.debugger.watchpoints().collect();
+        watchpoints.sort_by_key(|(id, _)| **id);
+        for (id, watchpoint) in watchpoints {
+            writeln!(result, "{}: {:?}", id, watchpoint).unwrap();
+        }
+        write!(result, "{} cycles executed", self.debugger.cycles()).unwrap();
+
+        result
+    }
+
+    fn registers(&self) -> String {
+        let mut result = String::new();
+        for idx in 1..16 {
+            writeln!(
+                result,
+                "r{:<2} {}",
+                idx,
+                format_value(&self.debugger.register(idx))
+            )
+            .unwrap();
+        }
+        let flags = &self.debugger.vm().local_state.flags;
+        write!(
+            result,
+            "flags: lt/of {}, eq {}, gt {}",
+            flags.overflow_or_less_than_flag, flags.equality_flag, flags.greater_than_flag
+        )
+        .unwrap();
+
+        result
+    }
+
+    fn backtrace(&self) -> String {
+        let mut result = vec![];
+        for (depth, frame) in self.debugger.callstack().iter().enumerate().rev() {
+            let name = self
+                .contract(&frame.code_address)
+                .map(|el| el.name.clone())
+                .unwrap_or_else(|| format!("{:?}", frame.code_address));
+            let kind = if frame.is_local_frame { "near" } else { "far" };
+            let mut line = format!("#{} {} {} pc {}", depth, kind, name, frame.pc);
+            if let Some(label) = self.debugger.function_label(frame) {
+                write!(line, " in {}", label).unwrap();
+            }
+            write!(line, ", ergs {}", frame.ergs_remaining).unwrap();
+            result.push(line);
+        }
+
+        result.join("\n")
+    }
+
+    fn list(&self) -> Result<String, String> {
+        let frame = self.debugger.current_frame();
+        let contract = self
+            .contract(&frame.code_address)
+            .ok_or_else(|| "No source for the current contract".to_owned())?;
+        let current = contract
+            .pc_lines
+            .get(&(frame.pc as usize))
+            .copied()
+            .ok_or_else(|| format!("No source line for pc {}", frame.pc))?;
+        let mut result = vec![];
+        for line in current.saturating_sub(5).max(1)..=current + 5 {
+            let Some(text) = contract.source.lines().nth(line - 1) else {
+                break;
+            };
+            let marker = if line == current { "->" } else { "  " };
+            result.push(format!("{} {:>5} | {}", marker, line, text));
+        }
+
+        Ok(result.join("\n"))
+    }
+
+    /// Executes the command, see `HELP`
+    pub fn execute(&mut self, line: &str) -> Result<String, String> {
+        let words: Vec<_> = line.split_whitespace().collect();
+        let Some((command, args)) = words.split_first() else {
+            return Ok(String::new());
+        };
+        match (*command, args) {
+            ("step" | "s", []) => {
+                let reason = self.debugger.step(StepMode::Cycle, 1);
+                Ok(self.describe_stop(reason))
+            }
+            ("step" | "s", [count]) => {
+                let mut reason = StopReason::Step;
+                for _ in 0..parse_u64(count)? {
+                    reason = self.debugger.step(StepMode::Cycle, 1);
+                    if reason != StopReason::Step {
+                        break;
+                    }
+                }
+                Ok(self.describe_stop(reason))
+            }
+            ("next" | "n", []) => Ok(self.run(StepMode::NearCall)),
+            ("far" | "f", []) => Ok(self.run(StepMode::FarCall)),
+            ("continue" | "c", []) => Ok(self.run(StepMode::Continue)),
+            ("break" | "b", args) => self.add_breakpoint(args),
+            ("watch" | "w", args) => self.add_watchpoint(args),
+            ("delete" | "d", [id]) => {
+                if self.debugger.remove(parse_u64(id)? as usize) {
+                    Ok(format!("deleted {}", id))
+                } else {
+                    Err(format!("No breakpoint or watchpoint {}", id))
+                }
+            }
+            ("info" | "i", []) => Ok(self.info()),
+            ("regs" | "r", []) => Ok(self.registers()),
+            ("bt", []) => Ok(self.backtrace()),
+            ("frame", []) => Ok(format!("{:#?}", self.debugger.current_frame())),
+            ("ptr", [idx]) => {
+                let idx = parse_u64(idx)? as usize;
+                if idx > 15 {
+                    return Err("Registers are r0..r15".to_owned());
+                }
+                AsmDebugger::fat_pointer(self.debugger.register(idx))
+                    .map(|el| format!("{:?}", el))
+                    .ok_or_else(|| format!("r{} is not a pointer", idx))
+            }
+            ("mem", location) => {
+                let value = self.debugger.memory_word(self.memory_location(location)?);
+                Ok(format_value(&value))
+            }
+            ("list" | "l", []) => self.list(),
+            ("help" | "h", []) => Ok(HELP.to_owned()),
+            _ => Err(format!("Unknown command {:?}, see help", line.trim())),
+        }
+    }
+}
@@ -0,0 +1,125 @@
+use std::fs::File;
+use std::path::PathBuf;
+
+use structopt::StructOpt;
+use zkevm_test_harness::helper::artifact_utils::TestArtifact;
+use zkevm_test_harness::vm_diff::{
+    diff_configurations, record_bootloader_trace, DiffConfiguration,
+};
+use zkevm_test_harness::vm_trace::{read_trace, write_trace};
+use zkevm_test_harness::zk_evm::trace_diff::{first_frame_divergence, FrameDivergence};
+
+#[derive(Debug, StructOpt)]
+#[structopt(
+    name = "EraVM diff",
+    about = "Finds the first divergence of the bootloader executions, aligned by call frames"
+)]
+enum Opt {
+    /// Runs the test artifact with two configurations
+    Run {
+        /// Test artifact (JSON), in the format of `src/tests/complex_tests/test_artifacts/basic_test.json`
+        artifact: PathBuf,
+        /// Configuration (JSON) applied over the artifact, the artifact is used as is if not set
+        #[structopt(long)]
+        old: Option<PathBuf>,
+        #[structopt(long)]
+        new: Option<PathBuf>,
+        /// Initial content of the bootloader heap (raw bytes)
+        #[structopt(long)]
+        heap: Option<PathBuf>,
+        #[structopt(long, default_value = "16777216")]
+        cycle_limit: usize,
+    },
+    /// Runs the test artifact with the configuration and writes the trace, to compare the builds of zk_evm
+    Record {
+        artifact: PathBuf,
+        output: PathBuf,
+        #[structopt(long)]
+        config: Option<PathBuf>,
+        #[structopt(long)]
+        heap: Option<PathBuf>,
+        #[structopt(long, default_value = "16777216")]
+        cycle_limit: usize,
+    },
+    /// Compares two recorded traces (by `record` or `vm_trace record`)
+    Traces { expected: PathBuf, actual: PathBuf },
+}
+
+fn read_json<T: serde::de::DeserializeOwned>(path: &PathBuf) -> T {
+    let file = File::open(path).unwrap_or_else(|el| panic!("Unable to open {:?}: {}", path, el));
+    serde_json::from_reader(std::io::BufReader::new(file))
+        .unwrap_or_else(|el| panic!("Unable to parse {:?}: {}", path, el))
+}
+
+fn read_configuration(path: Option<PathBuf>) -> DiffConfiguration {
+    path.map(|el| read_json(&el)).unwrap_or_default()
+}
+
+fn read_heap(path: Option<PathBuf>) -> Vec<u8> {
+    path.map(|el| {
+        std::fs::read(&el).unwrap_or_else(|err| panic!("Unable to read {:?}: {}", el, err))
+    })
+    .unwrap_or_default()
+}
+
+fn report(divergence: Option<FrameDivergence>) {
+    match divergence {
+        Some(divergence) => {
+            println!("{}", divergence);
+            std::process::exit(1);
+        }
+        None => println!("Executions are the same"),
+    }
+}
+
+fn main() {
+    match Opt::from_args() {
+        Opt::Run {
+            artifact,
+            old,
+            new,
+            heap,
+            cycle_limit,
+        } => {
+            let artifact: TestArtifact = read_json(&artifact);
+            let divergence = diff_configurations(
+                &artifact,
+                &read_heap(heap),
+                &read_configuration(old),
+                &read_configuration(new),
+                cycle_limit,
+            )
+            .unwrap_or_else(|el| panic!("{}", el));
+            report(divergence);
+        }
+        Opt::Record {
+            artifact,
+            output,
+            config,
+            heap,
+            cycle_limit,
+        } => {
+            let artifact: TestArtifact = read_json(&artifact);
+            let trace = record_bootloader_trace(
+                &artifact,
+                &read_configuration(config),
+                &read_heap(heap),
+                cycle_limit,
+            )
+            .unwrap_or_else(|el| panic!("{}", el));
+            let file = File::create(&output)
+                .unwrap_or_else(|el| panic!("Unable to create {:?}: {}", output, el));
+            write_trace(&trace, std::io::BufWriter::new(file)).expect("Unable to write data");
+            println!("Recorded {} cycles", trace.cycles.len());
+        }
+        Opt::Traces { expected, actual } => {
+            let load = |path: &PathBuf| {
+                let file = File::open(path)
+                    .unwrap_or_else(|el| panic!("Unable to open {:?}: {}", path, el));
+                read_trace(std::io::BufReader::new(file))
+                    .unwrap_or_else(|el| panic!("Unable to read {:?}: {}", path, el))
+            };
+            report(first_frame_divergence(&load(&expected), &load(&actual)));
+        }
+    }
+}
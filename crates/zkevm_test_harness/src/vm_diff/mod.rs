@@ -0,0 +1,176 @@
+//! Runs the same bootloader input (test artifact and the initial bootloader heap) with two configurations,
+//! for example with the system contracts of two protocol versions, and reports the first divergence
+//! of the executions aligned by call frames (`zk_evm::trace_diff`).
+//! Changes of the VM itself are compared by recording the traces with two builds and diffing the trace files.
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::asm_runner::test_geometry;
+use crate::entry_point::create_out_of_circuit_global_context;
+use crate::ethereum_types::{Address, U256};
+use crate::helper::artifact_utils::{save_predeployed_contracts, TestArtifact};
+use crate::toolset::{create_out_of_circuit_vm, create_tools_for_entry_point};
+use crate::utils::calldata_to_aligned_data;
+use crate::vm_trace::{run_and_record, with_trace_recorder, TracedVm};
+use crate::witness::tracer::tracer::WitnessTracer;
+use crate::witness::tree::{BinarySparseStorageTree, ZKSyncTestingTree};
+use crate::zk_evm::abstractions::*;
+use crate::zk_evm::aux_structures::*;
+use crate::zk_evm::bytecode_to_code_hash;
+use crate::zk_evm::execution_trace::ExecutionTrace;
+use crate::zk_evm::reference_impls::decommitter::SimpleDecommitter;
+use crate::zk_evm::reference_impls::event_sink::InMemoryEventSink;
+use crate::zk_evm::reference_impls::memory::{MemoryLimits, SimpleMemory};
+use crate::zk_evm::testing::storage::InMemoryStorage;
+use crate::zk_evm::trace_diff::{first_frame_divergence, FrameDivergence};
+use crate::zk_evm::vm_state::VmState;
+use crate::zk_evm::zk_evm_abstractions::precompiles::DefaultPrecompilesProcessor;
+use crate::zk_evm::zkevm_opcode_defs::system_params::BOOTLOADER_FORMAL_ADDRESS;
+use crate::zk_evm::zkevm_opcode_defs::BOOTLOADER_HEAP_PAGE;
+
+/// Witness is not collected, so the snapshots can be rare
+const CYCLES_PER_VM_SNAPSHOT: u32 = 1 << 16;
+
+/// Part of the bootloader input that differs between the configurations, applied over the test artifact
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct DiffConfiguration {
+    pub default_account_code: Option<Vec<[u8; 32]>>,
+    pub evm_simulator_code: Option<Vec<[u8; 32]>>,
+    /// Contracts deployed in addition to the predeployed contracts of the artifact, or instead of them
+    #[serde(default)]
+    pub contracts: HashMap<Address, Vec<[u8; 32]>>,
+}
+
+impl DiffConfiguration {
+    pub fn apply(&self, artifact: &TestArtifact) -> TestArtifact {
+        let mut predeployed_contracts = artifact.predeployed_contracts.clone();
+        predeployed_contracts.extend(self.contracts.clone());
+
+        TestArtifact {
+            entry_point_address: artifact.entry_point_address,
+            entry_point_code: artifact.entry_point_code.clone(),
+            default_account_code: self
+                .default_account_code
+                .clone()
+                .unwrap_or_else(|| artifact.default_account_code.clone()),
+            evm_simulator_code: self
+                .evm_simulator_code
+                .clone()
+                .unwrap_or_else(|| artifact.evm_simulator_code.clone()),
+            predeployed_contracts,
+        }
+    }
+}
+
+/// Out-of-circuit VM set up in the same way as in `run_vms`, with the entry point at the bootloader address
+pub fn create_bootloader_out_of_circuit_vm(
+    artifact: &TestArtifact,
+    initial_heap_content: &[u8],
+) -> VmState<
+    InMemoryStorage,
+    SimpleMemory,
+    InMemoryEventSink,
+    DefaultPrecompilesProcessor<true>,
+    SimpleDecommitter<true>,
+    WitnessTracer,
+> {
+    let mut storage_impl = InMemoryStorage::new();
+    let mut tree = ZKSyncTestingTree::empty();
+    let predeployed_contracts: HashMap<_, _> = artifact
+        .predeployed_contracts
+        .clone()
+        .into_iter()
+        .chain(Some((
+            *BOOTLOADER_FORMAL_ADDRESS,
+            artifact.entry_point_code.clone(),
+        )))
+        .collect();
+    save_predeployed_contracts(&mut storage_impl, &mut tree, &predeployed_contracts);
+
+    let hash_of =
+        |bytecode: &[[u8; 32]]| U256::from_big_endian(&bytecode_to_code_hash(bytecode).unwrap());
+    let (tools, _, _) = create_tools_for_entry_point(
+        storage_impl,
+        &test_geometry(CYCLES_PER_VM_SNAPSHOT),
+        MemoryLimits::default(),
+        &artifact.entry_point_code,
+        predeployed_contracts
+            .values()
+            .chain([&artifact.default_account_code, &artifact.evm_simulator_code])
+            .map(|bytecode| (hash_of(bytecode), bytecode.clone())),
+    );
+
+    let block_properties = create_out_of_circuit_global_context(
+        false,
+        hash_of(&artifact.default_account_code),
+        hash_of(&artifact.evm_simulator_code),
+    );
+    let mut vm = create_out_of_circuit_vm(
+        tools,
+        block_properties,
+        Address::zero(),
+        *BOOTLOADER_FORMAL_ADDRESS,
+    );
+
+    // non-deterministic writes into the bootloader's heap
+    for (idx, el) in calldata_to_aligned_data(&initial_heap_content.to_vec())
+        .into_iter()
+        .enumerate()
+    {
+        let query = MemoryQuery {
+            timestamp: Timestamp(0),
+            location: MemoryLocation {
+                memory_type: MemoryType::Heap,
+                page: MemoryPage(BOOTLOADER_HEAP_PAGE),
+                index: MemoryIndex(idx as u32),
+            },
+            rw_flag: true,
+            value: el,
+            value_is_pointer: false,
+        };
+        vm.memory.execute_partial_query(0, query);
+    }
+
+    vm
+}
+
+/// Same VM as `create_bootloader_out_of_circuit_vm`, with the queries collected for the trace
+pub fn create_bootloader_vm(
+    artifact: &TestArtifact,
+    initial_heap_content: &[u8],
+) -> TracedVm<InMemoryStorage> {
+    with_trace_recorder(create_bootloader_out_of_circuit_vm(
+        artifact,
+        initial_heap_content,
+    ))
+}
+
+/// Runs the bootloader input with the configuration until the end of execution
+pub fn record_bootloader_trace(
+    artifact: &TestArtifact,
+    configuration: &DiffConfiguration,
+    initial_heap_content: &[u8],
+    cycle_limit: usize,
+) -> Result<ExecutionTrace, String> {
+    let mut vm = create_bootloader_vm(&configuration.apply(artifact), initial_heap_content);
+    run_and_record(&mut vm, cycle_limit)
+}
+
+/// First divergence of the execution with the `new` configuration from the one with the `old` configuration,
+/// `None` if they behave in the same way
+pub fn diff_configurations(
+    artifact: &TestArtifact,
+    initial_heap_content: &[u8],
+    old: &DiffConfiguration,
+    new: &DiffConfiguration,
+    cycle_limit: usize,
+) -> Result<Option<FrameDivergence>, String> {
+    let old_trace = record_bootloader_trace(artifact, old, initial_heap_content, cycle_limit)
+        .map_err(|el| format!("Old configuration: {}", el))?;
+    let new_trace = record_bootloader_trace(artifact, new, initial_heap_content, cycle_limit)
+        .map_err(|el| format!("New configuration: {}", el))?;
+
+    Ok(first_frame_divergence(&old_trace, &new_trace))
+}
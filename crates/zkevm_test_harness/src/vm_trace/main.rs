@@ -0,0 +1,82 @@
+use std::fs::File;
+use std::path::PathBuf;
+
+use structopt::StructOpt;
+use zkevm_test_harness::asm_runner::AsmTest;
+use zkevm_test_harness::vm_debugger::{compile_test, DebugContract};
+use zkevm_test_harness::vm_trace::{
+    format_cycle, read_trace, record_trace, replay_trace, write_trace,
+};
+use zkevm_test_harness::zk_evm::execution_trace::ExecutionTrace;
+
+#[derive(Debug, StructOpt)]
+#[structopt(
+    name = "EraVM execution trace",
+    about = "Records, replays and compares the binary execution traces of the .asm tests"
+)]
+enum Opt {
+    /// Runs the test and writes its trace
+    Record {
+        /// Test directory (with `entry.asm` and `ADDRESS.asm` dependencies) or a single `.asm` file
+        test: PathBuf,
+        output: PathBuf,
+        #[structopt(long, default_value = "1048576")]
+        max_cycles: usize,
+    },
+    /// Re-executes the test and checks every cycle against the trace, fails on the first divergence
+    Replay { test: PathBuf, trace: PathBuf },
+    /// Compares two traces, fails on the first divergence
+    Diff { expected: PathBuf, actual: PathBuf },
+    /// Prints the trace in the human readable form
+    Dump { trace: PathBuf },
+}
+
+fn compile(path: &PathBuf) -> Vec<DebugContract> {
+    let test = if path.is_dir() {
+        AsmTest::from_dir(path)
+    } else {
+        AsmTest::from_file(path)
+    };
+    let test = test.unwrap_or_else(|el| panic!("Unable to load test {:?}: {}", path, el));
+    compile_test(&test).unwrap_or_else(|el| panic!("{}", el))
+}
+
+fn load(path: &PathBuf) -> ExecutionTrace {
+    let file = File::open(path).unwrap_or_else(|el| panic!("Unable to open {:?}: {}", path, el));
+    read_trace(std::io::BufReader::new(file))
+        .unwrap_or_else(|el| panic!("Unable to read {:?}: {}", path, el))
+}
+
+fn main() {
+    let result = match Opt::from_args() {
+        Opt::Record {
+            test,
+            output,
+            max_cycles,
+        } => {
+            let trace =
+                record_trace(&compile(&test), max_cycles).unwrap_or_else(|el| panic!("{}", el));
+            let file = File::create(&output)
+                .unwrap_or_else(|el| panic!("Unable to create {:?}: {}", output, el));
+            write_trace(&trace, std::io::BufWriter::new(file)).expect("Unable to write data");
+            println!("Recorded {} cycles", trace.cycles.len());
+            Ok(())
+        }
+        Opt::Replay { test, trace } => replay_trace(&compile(&test), &load(&trace)),
+        Opt::Diff { expected, actual } => match load(&expected).first_divergence(&load(&actual)) {
+            Some(divergence) => Err(divergence),
+            None => Ok(()),
+        },
+        Opt::Dump { trace } => {
+            for cycle in load(&trace).cycles.iter() {
+                println!("{}", format_cycle(cycle));
+            }
+            Ok(())
+        }
+    };
+
+    if let Err(divergence) = result {
+        println!("{}", divergence);
+        std::process::exit(1);
+    }
+}
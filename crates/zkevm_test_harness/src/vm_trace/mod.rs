@@ -0,0 +1,191 @@
+//! Binary execution traces of the assembly tests (`zk_evm::execution_trace`), to attach to the bug reports
+//! and to compare the behavior of different zk_evm versions: a trace recorded with one version
+//! is replayed with another one, and the first differing cycle is reported.
+
+use std::fmt::Write as _;
+use std::io::{Read, Write};
+
+use crate::asm_runner::storage::InMemoryCustomRefundStorage;
+use crate::vm_debugger::{create_vm, DebugContract};
+use crate::witness::tracer::tracer::WitnessTracer;
+use crate::zk_evm::abstractions::Storage;
+use crate::zk_evm::execution_trace::{
+    replay, ExecutionTrace, TraceCycle, TraceDivergence, TraceRecorder, TraceWitnessRecorder,
+};
+use crate::zk_evm::reference_impls::decommitter::SimpleDecommitter;
+use crate::zk_evm::reference_impls::event_sink::InMemoryEventSink;
+use crate::zk_evm::reference_impls::memory::SimpleMemory;
+use crate::zk_evm::vm_state::VmState;
+use crate::zk_evm::zk_evm_abstractions::precompiles::DefaultPrecompilesProcessor;
+
+pub const TRACE_MAGIC: [u8; 8] = *b"ERAVMTRC";
+/// Should be bumped on any change of the types of `zk_evm::execution_trace`
+pub const TRACE_FORMAT_VERSION: u32 = 1;
+
+pub type TracedVm<S = InMemoryCustomRefundStorage> = VmState<
+    S,
+    SimpleMemory,
+    InMemoryEventSink,
+    DefaultPrecompilesProcessor<true>,
+    SimpleDecommitter<true>,
+    TraceWitnessRecorder,
+>;
+
+/// Replaces the witness tracer of the VM created by `create_out_of_circuit_vm`
+pub fn with_trace_recorder<S: Storage>(
+    vm: VmState<
+        S,
+        SimpleMemory,
+        InMemoryEventSink,
+        DefaultPrecompilesProcessor<true>,
+        SimpleDecommitter<true>,
+        WitnessTracer,
+    >,
+) -> TracedVm<S> {
+    VmState {
+        local_state: vm.local_state,
+        block_properties: vm.block_properties,
+        storage: vm.storage,
+        memory: vm.memory,
+        event_sink: vm.event_sink,
+        precompiles_processor: vm.precompiles_processor,
+        decommittment_processor: vm.decommittment_processor,
+        witness_tracer: TraceWitnessRecorder::new(),
+    }
+}
+
+/// Same VM as `create_vm` of the debugger, with the queries collected for the trace
+pub fn create_traced_vm(contracts: &[DebugContract]) -> TracedVm {
+    with_trace_recorder(create_vm(contracts))
+}
+
+/// Runs the VM until the end of execution
+pub fn run_and_record<S: Storage>(
+    vm: &mut TracedVm<S>,
+    max_cycles: usize,
+) -> Result<ExecutionTrace, String> {
+    let mut recorder = TraceRecorder::new();
+    for _ in 0..max_cycles {
+        if vm.execution_has_ended() {
+            return Ok(recorder.into_trace(std::mem::take(&mut vm.witness_tracer)));
+        }
+        vm.cycle(&mut recorder)
+            .map_err(|el| format!("Cycle failed: {}", el))?;
+    }
+
+    Err(format!("Execution hasn't ended in {} cycles", max_cycles))
+}
+
+/// Runs the compiled test until the end of execution
+pub fn record_trace(
+    contracts: &[DebugContract],
+    max_cycles: usize,
+) -> Result<ExecutionTrace, String> {
+    run_and_record(&mut create_traced_vm(contracts), max_cycles)
+}
+
+/// Re-executes the compiled test and checks it against the trace, including that the execution ends
+/// after the last cycle of the trace
+pub fn replay_trace(
+    contracts: &[DebugContract],
+    trace: &ExecutionTrace,
+) -> Result<(), TraceDivergence> {
+    let mut vm = create_traced_vm(contracts);
+    replay(&mut vm, trace)?;
+    if !vm.execution_has_ended() {
+        return Err(TraceDivergence {
+            cycle: trace.cycles.len(),
+            difference: "execution hasn't ended".to_owned(),
+            expected: None,
+            actual: None,
+        });
+    }
+
+    Ok(())
+}
+
+/// Writes the trace as the magic, the format version (little endian) and the bincode encoded trace
+pub fn write_trace<W: Write>(trace: &ExecutionTrace, mut writer: W) -> Result<(), String> {
+    writer
+        .write_all(&TRACE_MAGIC)
+        .and_then(|_| writer.write_all(&TRACE_FORMAT_VERSION.to_le_bytes()))
+        .map_err(|el| el.to_string())?;
+    bincode::serialize_into(writer, trace).map_err(|el| el.to_string())
+}
+
+pub fn read_trace<R: Read>(mut reader: R) -> Result<ExecutionTrace, String> {
+    let mut header = [0u8; 12];
+    reader
+        .read_exact(&mut header)
+        .map_err(|el| format!("Unable to read the header: {}", el))?;
+    if header[..8] != TRACE_MAGIC {
+        return Err("Not an execution trace".to_owned());
+    }
+    let version = u32::from_le_bytes(header[8..].try_into().unwrap());
+    if version != TRACE_FORMAT_VERSION {
+        return Err(format!(
+            "Unsupported trace format version {}, expected {}",
+            version, TRACE_FORMAT_VERSION
+        ));
+    }
+
+    bincode::deserialize_from(reader).map_err(|el| el.to_string())
+}
+
+/// Human readable line per cycle, with the queries on the following lines
+pub fn format_cycle(cycle: &TraceCycle) -> String {
+    let mut result = format!(
+        "{} depth {} {:?} pc {} {:?} ergs {}",
+        cycle.monotonic_cycle_counter,
+        cycle.callstack_depth,
+        cycle.code_address,
+        cycle.pc,
+        cycle.decoded_opcode().variant,
+        cycle.ergs_remaining
+    );
+    for write in cycle.register_writes.iter() {
+        write!(
+            result,
+            " r{}={:#x}{}",
+            write.index,
+            write.value,
+            if write.is_pointer { "(ptr)" } else { "" }
+        )
+        .unwrap();
+    }
+    for query in cycle.memory_queries.iter() {
+        write!(
+            result,
+            "\n    mem {} page {} index {} {:#x}",
+            if query.rw_flag { "write" } else { "read" },
+            query.location.page.0,
+            query.location.index.0,
+            query.value
+        )
+        .unwrap();
+    }
+    for query in cycle.log_queries.iter() {
+        write!(
+            result,
+            "\n    log {} {:?} key {:#x} read {:#x} written {:#x}{}",
+            query.aux_byte,
+            query.address,
+            query.key,
+            query.read_value,
+            query.written_value,
+            if query.rollback { " rollback" } else { "" }
+        )
+        .unwrap();
+    }
+    for query in cycle.decommits.iter() {
+        write!(
+            result,
+            "\n    decommit {} into page {}",
+            hex::encode(query.normalized_preimage.0),
+            query.memory_page.0
+        )
+        .unwrap();
+    }
+
+    result
+}
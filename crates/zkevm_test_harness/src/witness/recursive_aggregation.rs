@@ -11,6 +11,7 @@ use super::*;
 use crate::boojum::gadgets::num::Num;
 use crate::boojum::gadgets::queue::full_state_queue::FullStateCircuitQueueRawWitness;
 use crate::boojum::gadgets::recursion::recursive_tree_hasher::CircuitGoldilocksPoseidon2Sponge;
+use crate::data_source::{SetupDataSource, SourceResult};
 use crate::witness::utils::take_sponge_like_queue_state_from_simulator;
 use crate::zkevm_circuits::recursion::{
     leaf_layer::input::*,
@@ -37,6 +38,7 @@ use crate::zkevm_circuits::fsm_input_output::commit_variable_length_encodable_it
 use crate::zkevm_circuits::recursion::leaf_layer::LeafLayerRecursionConfig;
 use crate::zkevm_circuits::scheduler::aux::BaseLayerCircuitType;
 use circuit_definitions::circuit_definitions::base_layer::*;
+use circuit_definitions::circuit_definitions::recursion_layer::interblock::ZkSyncInterblockAggregationCircuit;
 use circuit_definitions::circuit_definitions::recursion_layer::leaf_layer::*;
 use circuit_definitions::circuit_definitions::recursion_layer::{
     node_layer::ZkSyncNodeLayerRecursiveCircuit, *,
@@ -45,11 +47,39 @@ use circuit_definitions::encodings::recursion_request::RecursionQueueSimulator;
 use circuit_definitions::encodings::CircuitEquivalentReflection;
 
 pub fn split_recursion_queue(queue: RecursionQueueSimulator<F>) -> Vec<RecursionQueueSimulator<F>> {
+    split_recursion_queue_with_capacity(queue, RECURSION_ARITY)
+}
+
+/// Checks that witnesses are built for the recursion tree arity that the leaf and node vks
+/// of the source were generated for, otherwise they can't be proven with these vks.
+pub fn check_recursion_tree_arity(
+    source: &dyn SetupDataSource,
+    arity: RecursionTreeArity,
+) -> SourceResult<()> {
+    arity.validate()?;
+    let setup_arity = source.get_recursion_tree_arity()?;
+    if setup_arity != arity {
+        return Err(format!(
+            "recursion layer vks are generated for {:?}, but witnesses are built for {:?}",
+            setup_arity, arity
+        )
+        .into());
+    }
+
+    Ok(())
+}
+
+/// Splits the queue into chunks for the leaves of the given capacity.
+pub fn split_recursion_queue_with_capacity(
+    queue: RecursionQueueSimulator<F>,
+    leaf_layer_capacity: usize,
+) -> Vec<RecursionQueueSimulator<F>> {
     let round_function = ZkSyncDefaultRoundFunction::default();
-    queue.split_by(RECURSION_ARITY, &round_function)
+    queue.split_by(leaf_layer_capacity, &round_function)
 }
 
-/// Creates leaf witnesses: each leaf aggregates RECURSION_ARITY (32) basic circuits of a given type.
+/// Creates leaf witnesses: each leaf aggregates up to RECURSION_ARITY basic circuits of a given type,
+/// which is the default recursion tree arity.
 pub fn create_leaf_witnesses(
     subset: (
         u64, // circuit type
@@ -67,13 +97,46 @@ pub fn create_leaf_witnesses(
     Vec<ZkSyncRecursiveLayerCircuit>, // proofs for chunks
     Vec<ZkSyncBaseLayerClosedFormInput<F>>,
 ) {
+    create_leaf_witnesses_for_arity(
+        subset,
+        proofs,
+        vk,
+        leaf_params,
+        RecursionTreeArity::default(),
+    )
+}
+
+/// Creates leaf witnesses: each leaf aggregates up to `arity.leaf_layer_capacity` basic circuits
+/// of a given type. Leaf vk from the leaf parameters must be generated for the same arity
+/// (see `check_recursion_tree_arity`).
+pub fn create_leaf_witnesses_for_arity(
+    subset: (
+        u64, // circuit type
+        RecursionQueueSimulator<F>,
+        Vec<ZkSyncBaseLayerClosedFormInput<F>>,
+    ),
+    proofs: Vec<ZkSyncBaseLayerProof>, // proofs coming from the base layer
+    vk: ZkSyncBaseLayerVerificationKey,
+    leaf_params: (u8, RecursionLeafParametersWitness<F>),
+    arity: RecursionTreeArity,
+) -> (
+    Vec<(
+        u64,                        // type of the basic circuit
+        RecursionQueueSimulator<F>, // chunk
+    )>,
+    Vec<ZkSyncRecursiveLayerCircuit>, // proofs for chunks
+    Vec<ZkSyncBaseLayerClosedFormInput<F>>,
+) {
+    arity
+        .validate()
+        .unwrap_or_else(|err| panic!("invalid recursion tree arity: {err}"));
     let (circuit_type, queue, closed_form_inputs) = subset;
     assert_eq!(queue.num_items as usize, proofs.len());
     assert_eq!(circuit_type, vk.numeric_circuit_type() as u64);
 
     assert_eq!(leaf_params.0, circuit_type as u8);
 
-    let queue_splits = split_recursion_queue(queue);
+    let queue_splits = split_recursion_queue_with_capacity(queue, arity.leaf_layer_capacity);
     let mut proofs_iter = proofs.into_iter();
 
     let mut results = Vec::with_capacity(queue_splits.len());
@@ -86,8 +149,14 @@ pub fn create_leaf_witnesses(
             proofs.push(t);
         }
 
-        let (circuit_type, circuit) =
-            create_leaf_witness(circuit_type, el.clone(), proofs, &vk, &leaf_params);
+        let (circuit_type, circuit) = create_leaf_witness_for_arity(
+            circuit_type,
+            el.clone(),
+            proofs,
+            &vk,
+            &leaf_params,
+            arity,
+        );
 
         results.push((circuit_type, el));
         recursive_circuits.push(circuit);
@@ -105,6 +174,27 @@ pub fn create_leaf_witness(
 ) -> (
     u64,                         // type of the basic circuit
     ZkSyncRecursiveLayerCircuit, // proofs for chunks
+) {
+    create_leaf_witness_for_arity(
+        circuit_type,
+        queue,
+        proofs,
+        vk,
+        leaf_params,
+        RecursionTreeArity::default(),
+    )
+}
+
+pub fn create_leaf_witness_for_arity(
+    circuit_type: u64, // circuit type
+    queue: RecursionQueueSimulator<F>,
+    proofs: Vec<ZkSyncBaseLayerProof>, // proofs coming from the base layer
+    vk: &ZkSyncBaseLayerVerificationKey,
+    leaf_params: &(u8, RecursionLeafParametersWitness<F>),
+    arity: RecursionTreeArity,
+) -> (
+    u64,                         // type of the basic circuit
+    ZkSyncRecursiveLayerCircuit, // proofs for chunks
 ) {
     assert_eq!(queue.num_items as usize, proofs.len());
     assert_eq!(circuit_type, vk.numeric_circuit_type() as u64);
@@ -112,6 +202,8 @@ pub fn create_leaf_witness(
     let (t, params) = leaf_params;
     assert_eq!(*t, circuit_type as u8);
 
+    assert!(queue.num_items as usize <= arity.leaf_layer_capacity);
+
     let mut proofs_iter = proofs.into_iter();
     let mut proof_witnesses = VecDeque::new();
     for _ in 0..queue.num_items {
@@ -143,7 +235,7 @@ pub fn create_leaf_witness(
     > {
         proof_config: recursion_layer_proof_config(),
         vk_fixed_parameters: vk.clone().into_inner().fixed_parameters,
-        capacity: RECURSION_ARITY,
+        capacity: arity.leaf_layer_capacity,
         _marker: std::marker::PhantomData,
     };
 
@@ -236,7 +328,8 @@ pub fn compute_node_vk_commitment(
     vk_commitment
 }
 
-/// Creates nodes witnesses, one witness is aggregating up to RECURSION_ARITY (32) leaves (or nodes) of a single circuit type.
+/// Creates nodes witnesses, one witness is aggregating up to RECURSION_ARITY leaves (or nodes) of a single circuit type,
+/// which is the default recursion tree arity.
 pub fn create_node_witnesses(
     chunks: Vec<(
         u64,                        // circuit type
@@ -253,6 +346,39 @@ pub fn create_node_witnesses(
     )>,
     Vec<ZkSyncRecursiveLayerCircuit>, // proofs for chunks
 ) {
+    create_node_witnesses_for_arity(
+        chunks,
+        proofs,
+        vk,
+        node_vk_commitment,
+        leaf_layer_params,
+        RecursionTreeArity::default(),
+    )
+}
+
+/// Creates nodes witnesses, one witness is aggregating up to `arity.node_layer_capacity` leaves (or nodes)
+/// of a single circuit type. Node vk must be generated for the same arity
+/// (see `check_recursion_tree_arity`).
+pub fn create_node_witnesses_for_arity(
+    chunks: Vec<(
+        u64,                        // circuit type
+        RecursionQueueSimulator<F>, // chunk
+    )>,
+    proofs: Vec<ZkSyncRecursionLayerProof>,
+    vk: ZkSyncRecursionLayerVerificationKey,
+    node_vk_commitment: [F; VK_COMMITMENT_LENGTH],
+    leaf_layer_params: &Vec<(u8, RecursionLeafParametersWitness<F>)>,
+    arity: RecursionTreeArity,
+) -> (
+    Vec<(
+        u64,
+        RecursionQueueSimulator<F>, // chunks
+    )>,
+    Vec<ZkSyncRecursiveLayerCircuit>, // proofs for chunks
+) {
+    arity
+        .validate()
+        .unwrap_or_else(|err| panic!("invalid recursion tree arity: {err}"));
     use crate::zkevm_circuits::recursion::NUM_BASE_LAYER_CIRCUITS;
     assert_eq!(leaf_layer_params.len(), NUM_BASE_LAYER_CIRCUITS);
 
@@ -265,14 +391,15 @@ pub fn create_node_witnesses(
     let mut results = vec![];
     let mut recursive_circuits = vec![];
 
-    for chunk in chunks.chunks(RECURSION_ARITY) {
+    for chunk in chunks.chunks(arity.node_layer_capacity) {
         let proofs_for_circuit = (&mut proofs_iter).take(chunk.len()).collect();
-        let (processed_circuit_type, circuit, queue) = create_node_witness(
+        let (processed_circuit_type, circuit, queue) = create_node_witness_for_arity(
             chunk,
             proofs_for_circuit,
             &vk,
             node_vk_commitment,
             &leaf_layer_params,
+            arity,
         );
 
         assert_eq!(circuit_type as u64, processed_circuit_type);
@@ -299,6 +426,31 @@ pub fn create_node_witness(
     u64,
     ZkSyncRecursiveLayerCircuit, // proof for the part of queue
     RecursionQueueSimulator<F>,
+) {
+    create_node_witness_for_arity(
+        chunks_of_queue,
+        proofs,
+        vk,
+        node_vk_commitment,
+        leaf_layer_params,
+        RecursionTreeArity::default(),
+    )
+}
+
+pub fn create_node_witness_for_arity(
+    chunks_of_queue: &[(
+        u64,                        // circuit type
+        RecursionQueueSimulator<F>, // part of queue
+    )],
+    proofs: Vec<ZkSyncRecursionLayerProof>,
+    vk: &ZkSyncRecursionLayerVerificationKey,
+    node_vk_commitment: [F; VK_COMMITMENT_LENGTH],
+    leaf_layer_params: &Vec<(u8, RecursionLeafParametersWitness<F>)>,
+    arity: RecursionTreeArity,
+) -> (
+    u64,
+    ZkSyncRecursiveLayerCircuit, // proof for the part of queue
+    RecursionQueueSimulator<F>,
 ) {
     use crate::zkevm_circuits::recursion::NUM_BASE_LAYER_CIRCUITS;
     assert_eq!(leaf_layer_params.len(), NUM_BASE_LAYER_CIRCUITS);
@@ -311,9 +463,11 @@ pub fn create_node_witness(
     let num_chunks = chunks_of_queue.len();
     assert_eq!(proofs.len(), num_chunks); // so we indeed taken exactly enough
 
+    let node_layer_capacity = arity.node_layer_capacity;
+
     // now even though we would have a chunk of len N, we should only create N-1 split points at the end
 
-    let mut split_points = Vec::with_capacity(RECURSION_ARITY);
+    let mut split_points = Vec::with_capacity(node_layer_capacity);
     let mut it = chunks_of_queue.into_iter();
 
     // Take the first chunk (guaranteed to exist)
@@ -343,9 +497,9 @@ pub fn create_node_witness(
     assert_eq!(acc, total_queue_len);
 
     // for N chunks we need N-1 split points, so either truncate, or pad
-    assert!(split_points.len() <= RECURSION_ARITY);
+    assert!(split_points.len() <= node_layer_capacity);
 
-    if split_points.len() == RECURSION_ARITY {
+    if split_points.len() == node_layer_capacity {
         let _ = split_points.pop().unwrap();
     } else {
         // pad it
@@ -353,10 +507,10 @@ pub fn create_node_witness(
             tail: subqueue.tail,
             length: 0,
         };
-        split_points.resize(RECURSION_ARITY - 1, padding);
+        split_points.resize(node_layer_capacity - 1, padding);
     }
 
-    assert_eq!(split_points.len() + 1, RECURSION_ARITY);
+    assert_eq!(split_points.len() + 1, node_layer_capacity);
 
     let leaf_layer_params = leaf_layer_params
         .iter()
@@ -394,8 +548,8 @@ pub fn create_node_witness(
     > {
         proof_config: recursion_layer_proof_config(),
         vk_fixed_parameters: vk.clone().into_inner().fixed_parameters,
-        leaf_layer_capacity: RECURSION_ARITY,
-        node_layer_capacity: RECURSION_ARITY,
+        leaf_layer_capacity: arity.leaf_layer_capacity,
+        node_layer_capacity: arity.node_layer_capacity,
         _marker: std::marker::PhantomData,
     };
 
@@ -410,3 +564,51 @@ pub fn create_node_witness(
 
     (circuit_type, circuit, subqueue)
 }
+
+/// Creates a circuit that aggregates several scheduler proofs (one per batch) into one.
+/// Empty list of proofs gives a circuit suitable for the setup generation.
+pub fn create_interblock_aggregation_circuit(
+    scheduler_proofs: Vec<ZkSyncRecursionLayerProof>,
+    scheduler_vk: ZkSyncRecursionLayerVerificationKey,
+) -> ZkSyncInterblockAggregationCircuit {
+    use crate::zkevm_circuits::recursion::interblock::*;
+    use circuit_definitions::circuit_definitions::recursion_layer::interblock::*;
+
+    assert_eq!(
+        scheduler_vk.numeric_circuit_type(),
+        ZkSyncRecursionLayerStorageType::SchedulerCircuit as u8
+    );
+    assert!(
+        scheduler_proofs.len() <= INTERBLOCK_AGGREGATION_CAPACITY,
+        "can aggregate at most {} scheduler proofs, got {}",
+        INTERBLOCK_AGGREGATION_CAPACITY,
+        scheduler_proofs.len()
+    );
+
+    let proof_witnesses: VecDeque<_> = scheduler_proofs
+        .into_iter()
+        .map(|proof| {
+            assert_eq!(
+                proof.numeric_circuit_type(),
+                ZkSyncRecursionLayerStorageType::SchedulerCircuit as u8
+            );
+            proof.into_inner()
+        })
+        .collect();
+
+    let witness = InterblockRecursionCircuitInstanceWitness { proof_witnesses };
+
+    let config = InterblockRecursionConfig {
+        proof_config: recursion_layer_proof_config(),
+        verification_key: scheduler_vk.into_inner(),
+        capacity: INTERBLOCK_AGGREGATION_CAPACITY,
+        _marker: std::marker::PhantomData,
+    };
+
+    ZkSyncInterblockAggregationCircuit {
+        witness,
+        config,
+        transcript_params: (),
+        _marker: std::marker::PhantomData,
+    }
+}